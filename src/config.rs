@@ -1,32 +1,246 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use notify::{RecommendedWatcher, DebouncedEvent, RecursiveMode, Watcher};
-use std::sync::mpsc::channel;
-use std::time::Duration;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
 use serde_json;
 use log::{info, error, debug}; // Import logging macros
 
+use crate::file_trust::check_file_trust;
+
+/// Quiet period after the last file event before the configuration is reloaded.
+/// Editors often emit several writes per save; they are coalesced into one reload.
+const CONFIG_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Upper bound on how long a burst may postpone the reload, so a directory that is
+/// written to continuously still gets its configuration reloaded.
+const CONFIG_DEBOUNCE_MAX: Duration = Duration::from_secs(5);
+
 /// Application configuration structure.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppConfig {
     pub aliases: Vec<AliasConfig>,
     pub language: String,
     pub notification_enable: bool,
     pub antiflood: bool,
     pub notification_delay: u32, // Задержка для уведомлений
+    #[serde(default)]
+    pub disabled_intents: Vec<String>, // Интенты, отключённые оператором
+    #[serde(default = "default_min_confidence")]
+    pub min_confidence: f32, // Ниже этого порога команда требует подтверждения
+    #[serde(default)]
+    pub task_history_path: Option<String>, // JSON-lines журнал переходов задач
+    #[serde(default)]
+    pub allow_close_all: bool, // Разрешить "закрыть все окна"
+    #[serde(default)]
+    pub allow_raw_messages: bool, // Разрешить send_message: отправку произвольных оконных сообщений
+    #[serde(default)]
+    pub language_dir: Option<String>, // Каталог с языковыми файлами, ищется первым
+    #[serde(default = "default_notification_backend")]
+    pub notification_backend: String, // "log", "toast" или "none"
+    #[serde(default)]
+    pub api_keys: Vec<ApiKeyConfig>, // Пусто — API открыт без ключа
+    #[serde(default)]
+    pub action_timeout_ms: u64, // Сколько ждать появления окна; 0 — одна попытка
+    #[serde(default = "default_history_limit")]
+    pub history_limit: usize, // Сколько последних команд хранить для /history
+    #[serde(default)]
+    pub layouts_path: Option<String>, // Файл сохранённых раскладок окон; по умолчанию layouts.json
+    #[serde(default)]
+    pub file_root: Option<String>, // Каталог, в котором ищет выбор файлов; по умолчанию текущий
+    #[serde(default = "default_antiflood_window_secs")]
+    pub antiflood_window_secs: u64, // Окно антифлуда, секунд
+    #[serde(default = "default_antiflood_max_requests")]
+    pub antiflood_max_requests: usize, // Сколько команд клиент может прислать за окно
+    #[serde(default = "default_shutdown_grace_secs")]
+    pub shutdown_grace_secs: u64, // Сколько ждать завершения задач при остановке сервера
+    #[serde(default = "default_idempotency_ttl_secs")]
+    pub idempotency_ttl_secs: u64, // Сколько помнить Idempotency-Key, секунд
+    #[serde(default)]
+    pub allowed_applications: Option<Vec<String>>, // Какие программы можно запускать (имя exe); не задано — любые
+    #[serde(default)]
+    pub keystroke_delay_ms: u64, // Пауза между символами при наборе текста; 0 — без пауз
+    #[serde(default)]
+    pub require_focus_lock: bool, // Прерывать задачу, если активное окно сменилось перед вводом с клавиатуры или мыши
+    #[serde(default)]
+    pub safe_mode: bool, // Запретить операции с файлами и запуск программ, оставив только управление окнами
+    #[serde(default)]
+    pub coalesce_enable: bool, // Объединять одинаковые команды, пока задача ещё в очереди (прокрутка, сворачивание окон и т.п.)
+    #[serde(default = "default_coalesce_window_ms")]
+    pub coalesce_window_ms: u64, // Сколько ждать повтора команды для объединения, мс
+    #[serde(default = "default_max_queue_depth")]
+    pub max_queue_depth: usize, // Сколько задач может ждать в очереди; сверх этого команды отклоняются, 0 — без ограничения
+    #[serde(default = "default_task_max_attempts")]
+    pub task_max_attempts: u32, // Сколько раз запускать неудавшуюся команду, считая первый запуск; запрос может задать attempts
+    #[serde(default = "default_task_retry_delay_ms")]
+    pub task_retry_delay_ms: u64, // Пауза перед повторной попыткой, мс
+    #[serde(default)]
+    pub logging: Option<LoggingConfig>, // Запись журнала в файл с ротацией; не задано — вывод в stderr
+}
+
+fn default_notification_backend() -> String {
+    "log".to_string()
+}
+
+fn default_min_confidence() -> f32 {
+    0.8
+}
+
+fn default_history_limit() -> usize {
+    100
+}
+
+fn default_antiflood_window_secs() -> u64 {
+    5
+}
+
+fn default_antiflood_max_requests() -> usize {
+    1
+}
+
+fn default_shutdown_grace_secs() -> u64 {
+    10
+}
+
+fn default_idempotency_ttl_secs() -> u64 {
+    300
+}
+
+fn default_coalesce_window_ms() -> u64 {
+    200
+}
+
+fn default_max_queue_depth() -> usize {
+    1000
+}
+
+fn default_task_max_attempts() -> u32 {
+    1
+}
+
+fn default_task_retry_delay_ms() -> u64 {
+    1000
+}
+
+impl Default for AppConfig {
+    /// The configuration used when the file cannot be loaded.
+    fn default() -> Self {
+        AppConfig {
+            aliases: Vec::new(),
+            language: "en".to_string(),
+            notification_enable: true, // default value
+            antiflood: false, // default value
+            notification_delay: 500,
+            disabled_intents: Vec::new(),
+            min_confidence: default_min_confidence(),
+            task_history_path: None,
+            allow_close_all: false,
+            allow_raw_messages: false,
+            language_dir: None,
+            notification_backend: default_notification_backend(),
+            api_keys: Vec::new(),
+            action_timeout_ms: 0,
+            history_limit: default_history_limit(),
+            layouts_path: None,
+            file_root: None,
+            antiflood_window_secs: default_antiflood_window_secs(),
+            antiflood_max_requests: default_antiflood_max_requests(),
+            shutdown_grace_secs: default_shutdown_grace_secs(),
+            idempotency_ttl_secs: default_idempotency_ttl_secs(),
+            allowed_applications: None,
+            keystroke_delay_ms: 0,
+            require_focus_lock: false,
+            safe_mode: false,
+            coalesce_enable: false,
+            coalesce_window_ms: default_coalesce_window_ms(),
+            max_queue_depth: default_max_queue_depth(),
+            task_max_attempts: default_task_max_attempts(),
+            task_retry_delay_ms: default_task_retry_delay_ms(),
+            logging: None,
+        }
+    }
+}
+
+/// File logging with size-based rotation. Read once at startup; changes take effect after a restart.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LoggingConfig {
+    pub path: String,
+    /// Default filter (`error`, `warn`, `info`, `debug`, `trace`); `RUST_LOG` still overrides it.
+    #[serde(default = "default_log_level")]
+    pub level: String,
+    #[serde(default = "default_log_max_size_bytes")]
+    pub max_size_bytes: u64, // Размер файла, после которого он переименовывается в .1, .2 и т.д.
+    #[serde(default = "default_log_max_files")]
+    pub max_files: usize, // Сколько старых файлов хранить помимо текущего
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+fn default_log_max_size_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+fn default_log_max_files() -> usize {
+    5
 }
 
 /// Alias configuration definition.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AliasConfig {
     pub alias: String,
     pub intent: String,
     pub parameters: Option<HashMap<String, String>>,
     pub command_type: Option<String>,
     pub steps: Option<Vec<AliasConfig>>,
+    #[serde(default)]
+    pub continue_on_error: bool, // Для "multi": выполнить все шаги и вернуть сводку вместо остановки на ошибке
+}
+
+/// API key with the intents it may run.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApiKeyConfig {
+    pub key: String,
+    /// Intent names, `prefix_*` patterns for a whole category, or `*` for everything.
+    pub allowed_intents: Vec<String>,
+}
+
+impl ApiKeyConfig {
+    /// Returns true if this key may run the given intent.
+    pub fn allows(&self, intent: &str) -> bool {
+        self.allowed_intents.iter().any(|allowed| match allowed.strip_suffix('*') {
+            Some(prefix) => intent.to_lowercase().starts_with(&prefix.to_lowercase()),
+            None => allowed.eq_ignore_ascii_case(intent),
+        })
+    }
+}
+
+/// The intents named by an alias and its steps, nested steps included.
+fn alias_intents(alias: &AliasConfig) -> Vec<String> {
+    std::iter::once(alias.intent.clone())
+        .chain(alias.steps.iter().flatten().flat_map(alias_intents))
+        .collect()
+}
+
+/// Endpoints that are not commands but still need permission. An API key must list them in
+/// `allowed_intents` like an intent (or allow `*`), so a key restricted to screenshots cannot
+/// stop tasks, undo actions or edit the configuration.
+pub const OPERATION_STOP_TASK: &str = "stop_task";
+pub const OPERATION_UNDO: &str = "undo";
+pub const OPERATION_EDIT_ALIASES: &str = "edit_aliases";
+pub const OPERATION_EDIT_SETTINGS: &str = "edit_settings";
+
+/// Why a request was refused by the API key check.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiKeyRejection {
+    /// Keys are configured but the request carried none, or an unknown one.
+    Unauthorized,
+    /// The key is valid but may not run this intent.
+    IntentNotAllowed(String),
 }
 
 impl AppConfig {
@@ -52,23 +266,54 @@ impl AppConfig {
             return Err(format!("Configuration file '{}' does not exist", config_path.display()));
         }
 
-        let metadata = fs::metadata(&config_path)
-            .map_err(|e| format!("Failed to retrieve metadata for '{}': {}", config_path.display(), e))?;
-        let permissions = metadata.permissions();
-        let mode = permissions.mode();
-
-        if mode & 0o022 != 0 {
-            return Err(format!(
-                "Configuration file '{}' is writable by group or others (mode {:o}). Please secure the file.",
-                config_path.display(), mode
-            ));
-        }
+        check_file_trust(&config_path)?;
 
         let json_str = fs::read_to_string(&config_path)
             .map_err(|e| format!("Error reading config file '{}': {}", config_path.display(), e))?;
 
-        serde_json::from_str(&json_str)
-            .map_err(|e| format!("Error parsing config file '{}': {}", config_path.display(), e))
+        let config: AppConfig = serde_json::from_str(&json_str)
+            .map_err(|e| format!("Error parsing config file '{}': {}", config_path.display(), e))?;
+        config.validate()
+            .map_err(|e| format!("Invalid config file '{}': {}", config_path.display(), e))?;
+        Ok(config)
+    }
+
+    /// Reads only the `logging` section, before the logger exists and without logging anything,
+    /// so the logger can be set up before the rest of the configuration is loaded.
+    pub fn read_logging_config<P: AsRef<Path>>(path: P) -> Result<Option<LoggingConfig>, String> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let json_str = fs::read_to_string(path)
+            .map_err(|e| format!("Error reading config file '{}': {}", path.display(), e))?;
+        let value: serde_json::Value = serde_json::from_str(&json_str)
+            .map_err(|e| format!("Error parsing config file '{}': {}", path.display(), e))?;
+        match value.get("logging") {
+            None | Some(serde_json::Value::Null) => Ok(None),
+            Some(section) => serde_json::from_value(section.clone())
+                .map(Some)
+                .map_err(|e| format!("Invalid logging section in '{}': {}", path.display(), e)),
+        }
+    }
+
+    /// Checks value ranges that serde cannot express.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.language.trim().is_empty() {
+            return Err("language must not be empty".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.min_confidence) {
+            return Err(format!("min_confidence must be between 0 and 1, got {}", self.min_confidence));
+        }
+        if let Some(logging) = &self.logging {
+            if logging.path.trim().is_empty() {
+                return Err("logging.path must not be empty".to_string());
+            }
+            if logging.max_size_bytes == 0 {
+                return Err("logging.max_size_bytes must be greater than 0".to_string());
+            }
+        }
+        Ok(())
     }
 
     // Getters for config values
@@ -87,6 +332,119 @@ impl AppConfig {
     pub fn get_antiflood(&self) -> bool {
         self.antiflood
     }
+
+    /// Checks that `api_key` may run `intent`. Aliases are checked against the intents
+    /// they expand to, so an allowed alias cannot smuggle in a disallowed step.
+    /// With no keys configured every request is allowed.
+    pub fn check_api_key(&self, api_key: Option<&str>, intent: &str) -> Result<(), ApiKeyRejection> {
+        if self.api_keys.is_empty() {
+            return Ok(());
+        }
+        let key = self.find_api_key(api_key)?;
+        // Unrecognised commands run nothing; let them through to get their hint.
+        if intent.eq_ignore_ascii_case("unknown") {
+            return Ok(());
+        }
+        match self.resolved_intents(intent).into_iter().find(|resolved| !key.allows(resolved)) {
+            Some(denied) => Err(ApiKeyRejection::IntentNotAllowed(denied)),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that `api_key` may use an endpoint that is not a command, one of the `OPERATION_*` names.
+    /// With no keys configured every request is allowed.
+    pub fn check_api_key_operation(&self, api_key: Option<&str>, operation: &str) -> Result<(), ApiKeyRejection> {
+        if self.api_keys.is_empty() {
+            return Ok(());
+        }
+        if self.find_api_key(api_key)?.allows(operation) {
+            Ok(())
+        } else {
+            Err(ApiKeyRejection::IntentNotAllowed(operation.to_string()))
+        }
+    }
+
+    /// Looks up the configured entry for the key a request carried.
+    fn find_api_key(&self, api_key: Option<&str>) -> Result<&ApiKeyConfig, ApiKeyRejection> {
+        api_key
+            .and_then(|key| self.api_keys.iter().find(|candidate| candidate.key == key))
+            .ok_or(ApiKeyRejection::Unauthorized)
+    }
+
+    /// Returns the intents an intent runs: for an alias its target and all its steps, nested
+    /// steps included, or the intent itself. Each alias is expanded once, so cycles end.
+    fn resolved_intents(&self, intent: &str) -> Vec<String> {
+        let mut resolved = Vec::new();
+        let mut expanded: Vec<String> = Vec::new();
+        let mut pending = vec![intent.to_string()];
+        while let Some(intent) = pending.pop() {
+            let already_expanded = expanded.iter().any(|seen| seen.eq_ignore_ascii_case(&intent));
+            match self.aliases.iter().find(|alias| alias.alias.eq_ignore_ascii_case(&intent)) {
+                Some(alias) if !already_expanded => {
+                    expanded.push(intent);
+                    pending.extend(alias_intents(alias));
+                }
+                // An alias naming itself as its intent runs that intent.
+                _ => resolved.push(intent),
+            }
+        }
+        resolved
+    }
+
+    /// Returns true if the given intent has been disabled by the operator.
+    /// `window_close_all` is also disabled unless `allow_close_all` is set,
+    /// and `send_message` unless `allow_raw_messages` is set.
+    pub fn is_intent_disabled(&self, intent: &str) -> bool {
+        if !self.allow_close_all && intent.eq_ignore_ascii_case("window_close_all") {
+            return true;
+        }
+        if !self.allow_raw_messages && intent.eq_ignore_ascii_case("send_message") {
+            return true;
+        }
+        self.disabled_intents
+            .iter()
+            .any(|disabled| disabled.eq_ignore_ascii_case(intent))
+    }
+
+    /// Returns true if `target` may be launched: always when `allowed_applications` is unset,
+    /// otherwise only when its executable base name is on the list, ignoring case.
+    pub fn is_application_allowed(&self, target: &str) -> bool {
+        match &self.allowed_applications {
+            Some(allowed) => {
+                let target = Path::new(target.trim().trim_matches('"'));
+                allowed.iter().any(|entry| executable_names_match(Path::new(entry.trim()), target))
+            }
+            None => true,
+        }
+    }
+}
+
+/// Compares executable base names, ignoring case. When either side has no extension only the
+/// stems are compared, so "notepad" and "notepad.exe" match.
+fn executable_names_match(entry: &Path, target: &Path) -> bool {
+    let lower = |name: Option<&std::ffi::OsStr>| name.map(|name| name.to_string_lossy().to_lowercase());
+    let (entry_name, target_name) = match (entry.extension(), target.extension()) {
+        (Some(_), Some(_)) => (lower(entry.file_name()), lower(target.file_name())),
+        _ => (lower(entry.file_stem()), lower(target.file_stem())),
+    };
+    entry_name.is_some() && entry_name == target_name
+}
+
+/// Returns true for create/modify events that touch the watched config file.
+fn is_config_change(event: &Event, config_file: &Path) -> bool {
+    let relevant_kind = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_));
+    relevant_kind && event.paths.iter().any(|path| path.file_name() == config_file.file_name())
+}
+
+/// Waits out a burst of file events: returns once no event arrives for `quiet`, or after
+/// `max` at the latest. Returns how many further events of the burst were consumed.
+fn drain_burst<T>(rx: &Receiver<T>, quiet: Duration, max: Duration) -> usize {
+    let burst_start = Instant::now();
+    let mut drained = 0;
+    while burst_start.elapsed() < max && rx.recv_timeout(quiet).is_ok() {
+        drained += 1;
+    }
+    drained
 }
 
 /// Shared configuration type used application-wide.
@@ -104,47 +462,55 @@ pub fn init_shared_config<P: AsRef<Path>>(config_path: P, on_config_change: Opti
         },
         Err(e) => {
             error!("Failed to load initial config: {}, use default values", e);
-             Some(AppConfig {
-                aliases: Vec::new(),
-                language: "en".to_string(),
-                notification_enable: true, // default value
-                antiflood: false, // default value
-                notification_delay: 500,
-             })
+            Some(AppConfig::default())
         }
     };
 
     let shared_config: SharedConfig = Arc::new(Mutex::new(config));
     let shared_config_clone = Arc::clone(&shared_config);
-    let config_path_str = config_path.as_ref().to_string_lossy().into_owned();
-    let (tx, rx) = channel();
+    let config_file = config_path.as_ref().to_path_buf();
+    let (tx, rx) = channel::<notify::Result<Event>>();
 
-    let mut watcher: RecommendedWatcher = RecommendedWatcher::new(tx, Duration::from_secs(2))
+    let mut watcher: RecommendedWatcher = RecommendedWatcher::new(tx, notify::Config::default())
         .expect("Failed to create file watcher");
-    watcher.watch(Path::new(&config_path_str), RecursiveMode::NonRecursive)
-        .expect("Failed to watch config file");
+    // Watch the parent directory: editors that save by replacing the file would otherwise drop the watch.
+    let watch_dir = config_file.parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf();
+    watcher.watch(&watch_dir, RecursiveMode::NonRecursive)
+        .expect("Failed to watch config directory");
 
     std::thread::spawn(move || {
+        // The watcher stops delivering events once dropped, so it lives as long as this thread.
+        let _watcher = watcher;
         loop {
             match rx.recv() {
-                Ok(DebouncedEvent::Write(_)) | Ok(DebouncedEvent::Create(_)) => {
-                    match AppConfig::load_from_file(&config_path_str) {
+                Ok(Ok(event)) if is_config_change(&event, &config_file) => {
+                    // Drain the rest of the burst before reloading once.
+                    drain_burst(&rx, CONFIG_DEBOUNCE, CONFIG_DEBOUNCE_MAX);
+                    match AppConfig::load_from_file(&config_file) {
                         Ok(new_config) => {
                             let mut config_lock = shared_config_clone.lock().unwrap();
                             *config_lock = Some(new_config);
+                            drop(config_lock);
                             info!("[CONFIG] Secure configuration updated.");
                             if let Some(ref callback) = on_config_change {
                                 callback();
                             }
                         },
                         Err(e) => {
-                            error!("[CONFIG] Secure configuration update failed: {}", e);
+                            // Keep serving the previous configuration.
+                            error!("[CONFIG] Secure configuration update failed, keeping previous config: {}", e);
                         }
                     }
                 },
-                Ok(_) => {},
-                Err(e) => {
+                Ok(Ok(_)) => {},
+                Ok(Err(e)) => {
                     error!("[CONFIG] Watcher error: {}", e);
+                },
+                Err(e) => {
+                    error!("[CONFIG] Watcher channel closed: {}", e);
                     break;
                 }
             }
@@ -152,3 +518,127 @@ pub fn init_shared_config<P: AsRef<Path>>(config_path: P, on_config_change: Opti
     });
     shared_config
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn a_burst_of_events_yields_one_reload() {
+        let (tx, rx) = channel();
+        let writer = thread::spawn(move || {
+            for i in 0..5 {
+                tx.send(i).unwrap();
+                thread::sleep(Duration::from_millis(10));
+            }
+        });
+        let mut reloads = 0;
+        let mut drained = 0;
+        while rx.recv_timeout(Duration::from_millis(500)).is_ok() {
+            drained += drain_burst(&rx, Duration::from_millis(100), Duration::from_secs(5));
+            reloads += 1;
+        }
+        writer.join().unwrap();
+        assert_eq!(reloads, 1);
+        assert_eq!(drained, 4);
+    }
+
+    #[test]
+    fn a_continuous_burst_is_cut_off_at_the_maximum() {
+        let (tx, rx) = channel();
+        let writer = thread::spawn(move || {
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_millis(400) && tx.send(()).is_ok() {
+                thread::sleep(Duration::from_millis(5));
+            }
+        });
+        let start = Instant::now();
+        drain_burst(&rx, Duration::from_millis(100), Duration::from_millis(100));
+        assert!(start.elapsed() < Duration::from_millis(300));
+        drop(rx);
+        writer.join().unwrap();
+    }
+
+    fn api_key(key: &str, allowed_intents: &[&str]) -> ApiKeyConfig {
+        ApiKeyConfig { key: key.to_string(), allowed_intents: allowed_intents.iter().map(|intent| intent.to_string()).collect() }
+    }
+
+    fn alias(name: &str, intent: &str, steps: Vec<AliasConfig>) -> AliasConfig {
+        AliasConfig {
+            alias: name.to_string(),
+            intent: intent.to_string(),
+            parameters: None,
+            command_type: (!steps.is_empty()).then(|| "multi".to_string()),
+            steps: (!steps.is_empty()).then_some(steps),
+            continue_on_error: false,
+        }
+    }
+
+    #[test]
+    fn api_key_allows_names_categories_and_everything() {
+        let restricted = api_key("shots", &["screenshot", "Window_*"]);
+        assert!(restricted.allows("screenshot"));
+        assert!(restricted.allows("SCREENSHOT"));
+        assert!(restricted.allows("window_minimize"));
+        assert!(!restricted.allows("type_text"));
+        assert!(!restricted.allows(OPERATION_EDIT_SETTINGS));
+
+        let admin = api_key("admin", &["*"]);
+        assert!(admin.allows("type_text"));
+        assert!(admin.allows(OPERATION_STOP_TASK));
+    }
+
+    #[test]
+    fn restricted_key_is_refused_a_disallowed_intent() {
+        let cfg = AppConfig {
+            api_keys: vec![api_key("shots", &["screenshot"]), api_key("admin", &["*"])],
+            ..AppConfig::default()
+        };
+        assert_eq!(cfg.check_api_key(Some("shots"), "screenshot"), Ok(()));
+        assert_eq!(
+            cfg.check_api_key(Some("shots"), "window_close"),
+            Err(ApiKeyRejection::IntentNotAllowed("window_close".to_string()))
+        );
+        assert_eq!(cfg.check_api_key(Some("admin"), "window_close"), Ok(()));
+        assert_eq!(cfg.check_api_key(None, "screenshot"), Err(ApiKeyRejection::Unauthorized));
+        assert_eq!(cfg.check_api_key(Some("wrong"), "screenshot"), Err(ApiKeyRejection::Unauthorized));
+
+        assert_eq!(
+            cfg.check_api_key_operation(Some("shots"), OPERATION_UNDO),
+            Err(ApiKeyRejection::IntentNotAllowed(OPERATION_UNDO.to_string()))
+        );
+        assert_eq!(cfg.check_api_key_operation(Some("admin"), OPERATION_UNDO), Ok(()));
+        assert_eq!(cfg.check_api_key_operation(None, OPERATION_UNDO), Err(ApiKeyRejection::Unauthorized));
+    }
+
+    #[test]
+    fn without_keys_everything_is_allowed() {
+        let cfg = AppConfig::default();
+        assert_eq!(cfg.check_api_key(None, "window_close"), Ok(()));
+        assert_eq!(cfg.check_api_key_operation(None, OPERATION_EDIT_ALIASES), Ok(()));
+    }
+
+    #[test]
+    fn alias_steps_are_checked_down_to_nested_steps() {
+        let nested = alias("", "screenshot", vec![alias("", "screenshot", Vec::new()), alias("", "window_close", Vec::new())]);
+        let cfg = AppConfig {
+            api_keys: vec![api_key("shots", &["screenshot"])],
+            aliases: vec![
+                alias("снимок", "screenshot", Vec::new()),
+                alias("тайком", "screenshot", vec![alias("", "screenshot", Vec::new()), nested]),
+                alias("через_алиас", "тайком", Vec::new()),
+                alias("screenshot", "screenshot", Vec::new()),
+                alias("петля", "петля2", Vec::new()),
+                alias("петля2", "петля", Vec::new()),
+            ],
+            ..AppConfig::default()
+        };
+        let check = |intent: &str| cfg.check_api_key(Some("shots"), intent);
+        assert_eq!(check("снимок"), Ok(()));
+        assert_eq!(check("screenshot"), Ok(()));
+        assert_eq!(check("тайком"), Err(ApiKeyRejection::IntentNotAllowed("window_close".to_string())));
+        assert_eq!(check("через_алиас"), Err(ApiKeyRejection::IntentNotAllowed("window_close".to_string())));
+        assert!(check("петля").is_err());
+    }
+}
@@ -1,11 +1,68 @@
 use std::collections::HashMap;
 
+/// Placeholder name a multi-step alias uses for the output of the step before.
+pub const PREVIOUS_STEP_PLACEHOLDER: &str = "prev";
+
+/// A screen rectangle in pixels, used to capture part of the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Whether pasting the selected files copies them or moves them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasteMode {
+    Copy,
+    Move,
+}
+
+/// The state of a checkbox; three-state boxes can also be `Indeterminate` (partly checked).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckState {
+    Checked,
+    Unchecked,
+    Indeterminate,
+}
+
+impl CheckState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CheckState::Checked => "checked",
+            CheckState::Unchecked => "unchecked",
+            CheckState::Indeterminate => "indeterminate",
+        }
+    }
+}
+
+/// What an `Open` target is, which decides how it is opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenKind {
+    Url,
+    Folder,
+    File,
+    App,
+}
+
+impl OpenKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OpenKind::Url => "url",
+            OpenKind::Folder => "folder",
+            OpenKind::File => "file",
+            OpenKind::App => "app",
+        }
+    }
+}
+
 /// Represents an action derived from the natural language input.
 #[derive(Debug, Clone)]
 pub enum Action {
-    ButtonClick { label: String },
-    ButtonDoubleClick { label: String },
-    EditEnterText { label: String, text: String },
+    ButtonClick { label: String, hwnd: Option<isize> },
+    ButtonDoubleClick { label: String, use_mouse: bool, hwnd: Option<isize> },
+    EditEnterText { label: String, text: String, hwnd: Option<isize> },
     EditSelectText { label: String, start: Option<u32>, end: Option<u32> },
     EditCopyText { label: String },
     EditCutText { label: String },
@@ -13,40 +70,102 @@ pub enum Action {
     EditDeleteText { label: String },
     EditPasteText { label: String, text: Option<String> },
     StaticGetText { label: String },
-    SetText { label: String, text: String },
+    SetText { label: String, text: String, hwnd: Option<isize> },
     SetFocus { label: String },
-    CheckboxSetState { label: String, state: bool },
+    CheckboxSetState { label: String, state: CheckState },
     RadioSelect { label: String, variant: Option<String> },
     TreeViewSelect { label: String, node: Option<String> },
     TreeViewExpand { label: String, node: Option<String> },
     ListViewSelectItem { label: String, item: String },
+    ListViewGetItems { label: String },
     TabControlSelectTab { label: String, tab: String },
+    SetSlider { label: String, percent: u32 },
+    GetCheckboxState { label: String },
+    GetSelectedText { label: Option<String> },
+    GetSelectedTab { label: String },
     WindowResize { width: u32, height: u32 },
-    WindowMinimize { label: String },
-    WindowMaximize { label: String },
-    WindowClose { label: String },
+    WindowResizeRelative { dw: i32, dh: i32 },
+    WindowMinimize { label: String, process: Option<String> },
+    WindowMaximize { label: String, process: Option<String> },
+    WindowClose { label: String, process: Option<String> },
+    WindowActivate { label: String, process: Option<String> },
+    WindowSetTopmost { label: String, topmost: bool },
+    WindowHide { label: String },
+    WindowShow { label: String },
+    SendMessage { label: String, msg: u32, wparam: usize, lparam: isize },
+    SaveLayout { name: String },
+    RestoreLayout { name: String },
+    SystemMenu { label: String, command: String },
+    MenuSelect { window: String, menu_path: String },
+    WaitForAnyWindow { labels: Vec<String>, timeout_ms: u64 },
+    WaitForTitle { title: String, timeout_ms: u64 },
     WindowMove { label: String, x: u32, y: u32 },
+    WindowMoveNamed { label: String, position: String },
     LaunchApplication { app: String },
     FocusApplication { app: String },
     GroupWindows { group: String, windows: String },
+    ArrangeWindows { layout: String },
     LaunchObject { object: String },
+    Open { target: String, kind: OpenKind },
     FocusObject { object: String },
     WindowMinimizeAll,
     WindowMaximizeAll,
     WindowCloseAll,
+    ListWindows,
+    WindowExists { label: String, class: Option<String> },
+    GetWindowDpi { label: Option<String> },
+    GetPixelColor { x: i32, y: i32 },
+    SendToBack { label: Option<String> },
+    BringToFront { label: Option<String> },
+    DragResize { label: Option<String>, edge: String, delta: i32 },
+    Drag { from_label: String, to_label: String },
     OpenFileProperties { file: String },
     ListSelect { label: String, item: String },
     KeyPress { key: String },
+    TypeText { text: String, delay_ms: Option<u64> },
+    KeyChord { modifiers: Vec<String>, key: String },
+    HoldKey { key: String, duration_ms: u64 },
+    TabTo { count: u32, reverse: bool },
+    SubmitDialog,
+    CancelDialog,
+    ActivateFocused,
     Scroll { direction: String, amount: Option<u32> },
-    Screenshot,
+    MouseDoubleClick { x: i32, y: i32 },
+    ClickText { text: String },
+    MouseWheel { direction: String, amount: u32 },
+    Screenshot { region: Option<Rect>, target_window: Option<String>, path: Option<String> },
+    GetClipboardImage { path: Option<String> },
+    SetClipboardImage { path: String },
     SpinnerAdjust { label: String, operation: String, value: u32 },
-    SelectFiles { criteria: String },
+    SelectFiles { criteria: String, root: Option<String>, recursive: bool },
     FileOperation { operation: String },
-    PasteFiles { destination: String },
+    PasteFiles { destination: String, mode: PasteMode },
     CreateDirectory { name: String },
     DeleteDirectory { name: String },
     CreateFile { name: String },
     DeleteFile { name: String },
-    MultiStep { steps: Vec<Action> },
+    MultiStep { steps: Vec<Action>, continue_on_error: bool },
+    Refused { intent: String, reason: String },
     Unknown { hint: String },
 }
+
+impl Action {
+    /// The action that reverts this one, for actions that can be undone without knowing the state
+    /// before them. Replacing text and setting a checkbox can be undone too, but their inverses need
+    /// the old text or state, which the executor reads before running the action.
+    pub fn inverse(&self) -> Option<Action> {
+        match self {
+            // SystemMenu finds windows by title only, so a process-scoped window cannot be restored by it.
+            Action::WindowMinimize { label, process: None } | Action::WindowMaximize { label, process: None } => Some(Action::SystemMenu {
+                label: label.clone(),
+                command: "restore".to_string(),
+            }),
+            Action::WindowHide { label } => Some(Action::WindowShow { label: label.clone() }),
+            Action::WindowSetTopmost { label, topmost } => Some(Action::WindowSetTopmost {
+                label: label.clone(),
+                topmost: !topmost,
+            }),
+            _ => None,
+        }
+    }
+}
@@ -2,11 +2,20 @@ use regex::Regex;
 use lazy_static::lazy_static;
 use std::collections::HashMap;
 use std::fs;
-use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 use serde::Deserialize;
 use log::error;
 
+use crate::file_trust::check_file_trust;
+
+/// Default Russian language data, compiled into the binary.
+const EMBEDDED_LANGUAGE_DATA: &str = include_str!("../../lang/ru.json");
+
+/// Pattern compiled in place of an empty regex string. An empty regex matches
+/// every command, so a missing pattern must never match instead. `$^` is not enough:
+/// it still matches an empty command, such as one made only of stop words.
+const NEVER_MATCH_RE: &str = r"[^\s\S]";
+
 /// Represents both regular expression patterns and various messages loaded from a language file.
 #[derive(Debug, Deserialize, Clone)]
 pub struct LanguageData {
@@ -53,39 +62,32 @@ pub struct LanguageData {
 
 impl LanguageData {
     /// Securely loads language data from a specified JSON file.
+    /// The file must live in one of the directories returned by [`language_dir_candidates`].
     pub fn load_from_file(lang_file: &str) -> Result<Self, String> {
-        // Define the trusted base directory for language files.
-        let base_dir = std::env::current_dir()
-            .map_err(|e| format!("Failed to retrieve current directory: {}", e))?
-            .join("assets")
-            .join("lang")
-            .canonicalize()
-            .map_err(|e| format!("Failed to canonicalize language base directory: {}", e))?;
+        // Define the trusted base directories for language files.
+        let base_dirs: Vec<PathBuf> = language_dir_candidates()
+            .into_iter()
+            .filter_map(|dir| dir.canonicalize().ok())
+            .collect();
+        if base_dirs.is_empty() {
+            return Err("No language directory found".to_string());
+        }
 
         // Canonicalize the provided language file path.
         let input_path = Path::new(lang_file)
             .canonicalize()
             .map_err(|e| format!("Failed to canonicalize language file path '{}': {}", lang_file, e))?;
 
-        // Ensure that the language file is within the trusted base directory.
-        if !input_path.starts_with(&base_dir) {
+        // Ensure that the language file is within a trusted base directory.
+        if !base_dirs.iter().any(|base_dir| input_path.starts_with(base_dir)) {
             return Err(format!(
-                "Path injection detected: '{}' is not within trusted base '{}'",
-                input_path.display(),
-                base_dir.display()
+                "Path injection detected: '{}' is not within a trusted language directory",
+                input_path.display()
             ));
         }
 
-        // Check file permissions: ensure that the file is not writable by group or others.
-        let metadata = fs::metadata(&input_path)
-            .map_err(|e| format!("Unable to read metadata for '{}': {}", input_path.display(), e))?;
-        let mode = metadata.permissions().mode();
-        if mode & 0o022 != 0 {
-            return Err(format!(
-                "Language file '{}' is writable by group or others (mode {:o}). Please secure the file.",
-                input_path.display(), mode
-            ));
-        }
+        // Check file permissions: ensure that the file is not writable by unprivileged users.
+        check_file_trust(&input_path)?;
 
         // Read the file contents.
         let contents = fs::read_to_string(&input_path)
@@ -97,6 +99,26 @@ impl LanguageData {
 
         Ok(data)
     }
+
+    /// Language data compiled into the binary from `lang/ru.json`, used when
+    /// no language file can be found or loaded.
+    pub fn builtin() -> Self {
+        serde_json::from_str(EMBEDDED_LANGUAGE_DATA)
+            .expect("Embedded language data must be valid")
+    }
+}
+
+/// Directories searched for JSON language files: the shared candidates
+/// (configured directory, next to the executable, current directory) in both
+/// the `lang` and the older `assets/lang` layouts.
+pub fn language_dir_candidates() -> Vec<PathBuf> {
+    let mut candidates = crate::language::language_dir_candidates();
+    let assets: Vec<PathBuf> = candidates
+        .iter()
+        .filter_map(|dir| dir.parent().map(|parent| parent.join("assets").join("lang")))
+        .collect();
+    candidates.extend(assets);
+    candidates
 }
 
 /// Структура для хранения скомпилированных Regex и данных языка.
@@ -145,7 +167,10 @@ impl Patterns {
     pub fn new(language_data: LanguageData) -> Result<Self, String> {
         macro_rules! compile_regex {
             ($field:ident) => {
-                Regex::new(&language_data.$field)
+                Regex::new(match language_data.$field.trim() {
+                    "" => NEVER_MATCH_RE,
+                    pattern => pattern,
+                })
                     .map_err(|e| format!("Failed to compile regex for {}: {}", stringify!($field), e))?
             };
         }
@@ -192,55 +217,32 @@ impl Patterns {
     }
 }
 
+/// Loads and compiles `<lang>.json` from the language directories, falling back
+/// to the built-in Russian data when the file is missing or invalid.
+pub fn load_patterns(lang: &str) -> Patterns {
+    let file_name = format!("{}.json", lang);
+    crate::language::find_language_file(&language_dir_candidates(), &file_name)
+        .ok_or_else(|| format!("'{}' not found in any language directory", file_name))
+        .and_then(|path| LanguageData::load_from_file(&path.to_string_lossy()))
+        .and_then(Patterns::new)
+        .unwrap_or_else(|e| {
+            error!("Failed to load language '{}', falling back to built-in defaults: {}", lang, e);
+            Patterns::new(LanguageData::builtin()).expect("Failed to create Patterns")
+        })
+}
+
 lazy_static::lazy_static! {
-    pub static ref PATTERNS: Patterns = {
-        let lang = "ru";
-        let lang_file = format!("lang/{}.json", lang);
-        let language_data = LanguageData::load_from_file(&lang_file)
-            .unwrap_or_else(|e| {
-                error!("Failed to load language data: {}", e);
-                // Provide a default or fallback LanguageData here
-                LanguageData {
-                    click_re: "".to_string(),
-                    double_click_re: "".to_string(),
-                     menu_re: "".to_string(),
-                    navigation_re: "".to_string(),
-                    window_resize_re: "".to_string(),
-                    window_minimize_re: "".to_string(),
-                    window_maximize_re: "".to_string(),
-                    window_close_re: "".to_string(),
-                    window_move_re: "".to_string(),
-                    group_windows_re: "".to_string(),
-                    tabcontrol_re: "".to_string(),
-                    listview_re: "".to_string(),
-                    radio_re: "".to_string(),
-                    checkbox_re: "".to_string(),
-                    file_open_re: "".to_string(),
-                    file_copy_re: "".to_string(),
-                    file_move_re: "".to_string(),
-                    file_rename_re: "".to_string(),
-                    file_delete_re: "".to_string(),
-                    enter_text_re: "".to_string(),
-                    get_text_re: "".to_string(),
-                    set_text_re: "".to_string(),
-                    select_text_re: "".to_string(),
-                    copy_text_re: "".to_string(),
-                    cut_text_re: "".to_string(),
-                    delete_text_re: "".to_string(),
-                    paste_text_re: "".to_string(),
-                    universal_open_re: "".to_string(),
-                    universal_focus_re: "".to_string(),
-                    msg_hint: "Command not recognized. Please try again.".to_string(),
-                    msg_action_executed: "Action executed: {}".to_string(),
-                    msg_task_queued: "Task queued".to_string(),
-                    msg_task_processing: "Task processing".to_string(),
-                    msg_task_success: "Task succeeded".to_string(),
-                    msg_task_failure: "Task failed".to_string(),
-                    msg_execution_result: "Execution result: {}".to_string(),
-                    msg_error: "Error: {}".to_string(),
-                }
-            });
-        Patterns::new(language_data)
-            .expect("Failed to create Patterns")
-    };
+    pub static ref PATTERNS: Patterns = load_patterns("ru");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn english_language_file_compiles() {
+        let data: LanguageData = serde_json::from_str(include_str!("../../lang/en.json")).unwrap();
+        let patterns = Patterns::new(data).unwrap();
+        assert!(patterns.click_re.is_match("click on the button ok"));
+    }
 }
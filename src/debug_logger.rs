@@ -1,4 +1,94 @@
-use log::{debug, info, warn, error};
+use env_logger::{Env, Target, WriteStyle};
+use log::{debug, info, warn, error, log_enabled, Level};
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use uuid::Uuid;
+
+use crate::config::LoggingConfig;
+
+/// Appends to a log file and, once it would grow past `max_size` bytes, shifts it to
+/// `<path>.1` (older files to `.2`, `.3`, ...) keeping at most `max_files` of them.
+pub struct RotatingFileWriter {
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    file: File,
+    written: u64,
+}
+
+impl RotatingFileWriter {
+    pub fn open(path: &Path, max_size: u64, max_files: usize) -> io::Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let written = file.metadata()?.len();
+        Ok(RotatingFileWriter { path: path.to_path_buf(), max_size, max_files, file, written })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.max_files == 0 {
+            self.file = OpenOptions::new().create(true).write(true).truncate(true).open(&self.path)?;
+        } else {
+            let _ = fs::remove_file(self.rotated_path(self.max_files));
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+            self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        }
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // A record is never split: the file may exceed the limit by one oversized record.
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Installs the global logger: a rotating file when `logging` is configured, stderr otherwise.
+/// `RUST_LOG` overrides the configured level in both cases. If the file cannot be opened the
+/// logger falls back to stderr and the error is returned so the caller can report it.
+pub fn init_logging(logging: Option<&LoggingConfig>) -> Result<(), String> {
+    let level = logging.map_or("info", |cfg| cfg.level.as_str());
+    let mut builder = env_logger::Builder::from_env(Env::default().default_filter_or(level));
+    let mut result = Ok(());
+    if let Some(cfg) = logging {
+        match RotatingFileWriter::open(Path::new(&cfg.path), cfg.max_size_bytes, cfg.max_files) {
+            Ok(writer) => {
+                builder.target(Target::Pipe(Box::new(writer))).write_style(WriteStyle::Never);
+            }
+            Err(e) => result = Err(format!("Failed to open log file '{}': {}", cfg.path, e)),
+        }
+    }
+    builder.try_init().map_err(|e| e.to_string())?;
+    result
+}
 
 /// Logs a debug message.
 #[inline]
@@ -23,3 +113,55 @@ pub fn log_warn(message: &str) {
 pub fn log_error(message: &str) {
     error!("{}", message);
 }
+
+/// One command's way through the pipeline, from the raw text to the execution result,
+/// logged as a single JSON line so parsing and outcome can be correlated by `trace_id`.
+#[derive(Debug, Serialize)]
+pub struct PipelineTrace {
+    pub trace_id: Uuid,
+    pub command: String,
+    pub normalized: String,
+    pub intent: String,
+    pub confidence: f32,
+    pub action: String,
+    pub duration_ms: u64,
+    pub result: String,
+    #[serde(skip)]
+    started: Instant,
+}
+
+impl PipelineTrace {
+    /// Starts a trace once the command has been parsed and mapped; the clock runs from here.
+    /// `trace_id` is the id the command got on arrival, which its task takes if one is scheduled.
+    pub fn new(trace_id: Uuid, command: &str, normalized: &str, intent: &str, confidence: f32, action: String) -> Self {
+        PipelineTrace {
+            trace_id,
+            command: command.to_string(),
+            normalized: normalized.to_string(),
+            intent: intent.to_string(),
+            confidence,
+            action,
+            duration_ms: 0,
+            result: String::new(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Completes the trace with the command's outcome and logs it.
+    pub fn finish(mut self, result: &str) {
+        self.duration_ms = self.started.elapsed().as_millis() as u64;
+        self.result = result.to_string();
+        log_pipeline(&self);
+    }
+}
+
+/// Logs a pipeline trace as JSON at debug level, so it stays off under the default `RUST_LOG=info`.
+pub fn log_pipeline(trace: &PipelineTrace) {
+    if !log_enabled!(target: "pipeline", Level::Debug) {
+        return;
+    }
+    match serde_json::to_string(trace) {
+        Ok(json) => debug!(target: "pipeline", "{}", json),
+        Err(e) => warn!("Failed to serialize pipeline trace: {}", e),
+    }
+}
@@ -0,0 +1,100 @@
+use std::path::Path;
+
+/// Verifies that a file we load code-like data from (config, language patterns)
+/// cannot be modified by unprivileged users.
+///
+/// On Unix the file must not be writable by group or others.
+/// On Windows the DACL must not grant write access to `Everyone` or `BUILTIN\Users`.
+/// Path-injection checks are the caller's job; this only inspects permissions.
+pub fn check_file_trust(path: &Path) -> Result<(), String> {
+    platform_check(path)
+}
+
+#[cfg(unix)]
+fn platform_check(path: &Path) -> Result<(), String> {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    let metadata = fs::metadata(path)
+        .map_err(|e| format!("Unable to read metadata for '{}': {}", path.display(), e))?;
+    let mode = metadata.permissions().mode();
+    if mode & 0o022 != 0 {
+        return Err(format!(
+            "File '{}' is writable by group or others (mode {:o}). Please secure the file.",
+            path.display(), mode
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(all(windows, feature = "enable_win32"))]
+fn platform_check(path: &Path) -> Result<(), String> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::{LocalFree, HLOCAL, PSID};
+    use windows::Win32::Security::Authorization::{
+        BuildTrusteeWithSidW, GetEffectiveRightsFromAclW, GetNamedSecurityInfoW, SE_FILE_OBJECT, TRUSTEE_W,
+    };
+    use windows::Win32::Security::{
+        CreateWellKnownSid, WinBuiltinUsersSid, WinWorldSid, ACL, DACL_SECURITY_INFORMATION,
+        PSECURITY_DESCRIPTOR, SECURITY_MAX_SID_SIZE, WELL_KNOWN_SID_TYPE,
+    };
+
+    // FILE_WRITE_DATA | FILE_APPEND_DATA | FILE_WRITE_EA | FILE_WRITE_ATTRIBUTES
+    const WRITE_RIGHTS: u32 = 0x0002 | 0x0004 | 0x0010 | 0x0100;
+
+    let wide_path: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    unsafe {
+        let mut dacl: *mut ACL = std::ptr::null_mut();
+        let mut descriptor = PSECURITY_DESCRIPTOR::default();
+        let status = GetNamedSecurityInfoW(
+            PCWSTR(wide_path.as_ptr()),
+            SE_FILE_OBJECT,
+            DACL_SECURITY_INFORMATION,
+            None,
+            None,
+            Some(&mut dacl),
+            None,
+            &mut descriptor,
+        );
+        if status.is_err() {
+            return Err(format!("Unable to read security info for '{}': {:?}", path.display(), status));
+        }
+
+        let check = |sid_type: WELL_KNOWN_SID_TYPE, name: &str| -> Result<(), String> {
+            // A NULL DACL grants everyone full access.
+            if dacl.is_null() {
+                return Err(format!("File '{}' has no DACL and is writable by everyone", path.display()));
+            }
+            let mut sid_buffer = vec![0u8; SECURITY_MAX_SID_SIZE as usize];
+            let mut sid_size = sid_buffer.len() as u32;
+            let sid = PSID(sid_buffer.as_mut_ptr() as *mut _);
+            CreateWellKnownSid(sid_type, None, sid, &mut sid_size)
+                .map_err(|e| format!("Failed to build SID for {}: {}", name, e))?;
+            let mut trustee = TRUSTEE_W::default();
+            BuildTrusteeWithSidW(&mut trustee, sid);
+            let mut rights: u32 = 0;
+            let status = GetEffectiveRightsFromAclW(dacl, &trustee, &mut rights);
+            if status.is_err() {
+                return Err(format!("Unable to evaluate ACL of '{}': {:?}", path.display(), status));
+            }
+            if rights & WRITE_RIGHTS != 0 {
+                return Err(format!(
+                    "File '{}' is writable by {}. Please secure the file.",
+                    path.display(), name
+                ));
+            }
+            Ok(())
+        };
+
+        let result = check(WinWorldSid, "Everyone").and_then(|_| check(WinBuiltinUsersSid, "Users"));
+        LocalFree(HLOCAL(descriptor.0));
+        result
+    }
+}
+
+#[cfg(not(any(unix, all(windows, feature = "enable_win32"))))]
+fn platform_check(_path: &Path) -> Result<(), String> {
+    // No permission model available to inspect; rely on the path checks alone.
+    Ok(())
+}
@@ -2,13 +2,120 @@ use crate::nlp::NLPResult;
 use crate::config::SharedConfig;
 use crate::config::AppConfig;
 use std::collections::HashMap;
+use std::path::Path;
+use lazy_static::lazy_static;
+use log::debug;
+use regex::{Captures, Regex};
+
+/// Longest a `hold_key` command may keep a key pressed.
+const MAX_HOLD_MS: u64 = 60_000;
+
+/// Longest pause a `type_text` command may put between keystrokes.
+const MAX_KEYSTROKE_DELAY_MS: u64 = 1_000;
+
+/// Longest a `wait_for_any_window` or `wait_for_title` command may wait, and its default.
+const MAX_WAIT_MS: u64 = 300_000;
+const DEFAULT_WAIT_MS: u64 = 10_000;
+
+/// Layouts `arrange_windows` understands; the first is the default.
+pub const WINDOW_LAYOUTS: &[&str] = &["grid", "cascade", "tile_vertical", "tile_horizontal", "snap_left", "snap_right"];
+
+/// Named spots in the work area `WindowMoveNamed` can put a window.
+pub const WINDOW_POSITIONS: &[&str] = &["top_left", "top_right", "bottom_left", "bottom_right", "center"];
+
+/// Placeholder name a multi-step alias uses for the output of the step before.
+pub const PREVIOUS_STEP_PLACEHOLDER: &str = "prev";
+
+lazy_static! {
+    // `${name}` placeholders in alias parameter values.
+    static ref PLACEHOLDER_RE: Regex = Regex::new(r"\$\{([^}]+)\}").unwrap();
+}
+
+/// A screen rectangle in pixels, used to capture part of the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Whether pasting the selected files copies them or moves them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasteMode {
+    Copy,
+    Move,
+}
+
+/// The state of a checkbox; three-state boxes can also be `Indeterminate` (partly checked).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckState {
+    Checked,
+    Unchecked,
+    Indeterminate,
+}
+
+impl CheckState {
+    /// Parses the `state` parameter; `true`/`false` still mean checked/unchecked.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "true" | "checked" => Some(CheckState::Checked),
+            "false" | "unchecked" => Some(CheckState::Unchecked),
+            "indeterminate" | "partial" | "частично" => Some(CheckState::Indeterminate),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CheckState::Checked => "checked",
+            CheckState::Unchecked => "unchecked",
+            CheckState::Indeterminate => "indeterminate",
+        }
+    }
+}
+
+/// What an `Open` target is, which decides how it is opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenKind {
+    Url,
+    Folder,
+    File,
+    App,
+}
+
+impl OpenKind {
+    /// Detects the kind of `target`: http(s) links are URLs, existing directories folders,
+    /// existing files files, and anything else an application to launch.
+    pub fn detect(target: &str) -> Self {
+        let lower = target.trim().to_lowercase();
+        if lower.starts_with("http://") || lower.starts_with("https://") {
+            OpenKind::Url
+        } else if Path::new(target).is_dir() {
+            OpenKind::Folder
+        } else if Path::new(target).is_file() {
+            OpenKind::File
+        } else {
+            OpenKind::App
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OpenKind::Url => "url",
+            OpenKind::Folder => "folder",
+            OpenKind::File => "file",
+            OpenKind::App => "app",
+        }
+    }
+}
 
 /// Represents an action derived from the natural language input.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Action {
-    ButtonClick { label: String },
-    ButtonDoubleClick { label: String },
-    EditEnterText { label: String, text: String },
+    ButtonClick { label: String, hwnd: Option<isize> },
+    ButtonDoubleClick { label: String, use_mouse: bool, hwnd: Option<isize> },
+    EditEnterText { label: String, text: String, hwnd: Option<isize> },
     EditSelectText { label: String, start: Option<u32>, end: Option<u32> },
     EditCopyText { label: String },
     EditCutText { label: String },
@@ -16,44 +123,284 @@ pub enum Action {
     EditDeleteText { label: String },
     EditPasteText { label: String, text: Option<String> },
     StaticGetText { label: String },
-    SetText { label: String, text: String },
+    SetText { label: String, text: String, hwnd: Option<isize> },
     SetFocus { label: String },
-    CheckboxSetState { label: String, state: bool },
+    CheckboxSetState { label: String, state: CheckState },
     RadioSelect { label: String, variant: Option<String> },
     TreeViewSelect { label: String, node: Option<String> },
     TreeViewExpand { label: String, node: Option<String> },
     ListViewSelectItem { label: String, item: String },
+    ListViewGetItems { label: String },
     TabControlSelectTab { label: String, tab: String },
+    SetSlider { label: String, percent: u32 },
+    GetCheckboxState { label: String },
+    GetSelectedText { label: Option<String> },
+    GetSelectedTab { label: String },
     WindowResize { width: u32, height: u32 },
-    WindowMinimize { label: String },
-    WindowMaximize { label: String },
-    WindowClose { label: String },
+    WindowResizeRelative { dw: i32, dh: i32 },
+    WindowMinimize { label: String, process: Option<String> },
+    WindowMaximize { label: String, process: Option<String> },
+    WindowClose { label: String, process: Option<String> },
+    WindowActivate { label: String, process: Option<String> },
+    WindowSetTopmost { label: String, topmost: bool },
+    WindowHide { label: String },
+    WindowShow { label: String },
+    SendMessage { label: String, msg: u32, wparam: usize, lparam: isize },
+    SaveLayout { name: String },
+    RestoreLayout { name: String },
+    SystemMenu { label: String, command: String },
+    MenuSelect { window: String, menu_path: String },
+    WaitForAnyWindow { labels: Vec<String>, timeout_ms: u64 },
+    WaitForTitle { title: String, timeout_ms: u64 },
     WindowMove { label: String, x: u32, y: u32 },
+    WindowMoveNamed { label: String, position: String },
     LaunchApplication { app: String },
     FocusApplication { app: String },
     GroupWindows { group: String, windows: String },
+    ArrangeWindows { layout: String },
     LaunchObject { object: String },
+    Open { target: String, kind: OpenKind },
     FocusObject { object: String },
     WindowMinimizeAll,
     WindowMaximizeAll,
     WindowCloseAll,
+    ListWindows,
+    WindowExists { label: String, class: Option<String> },
+    GetWindowDpi { label: Option<String> },
+    GetPixelColor { x: i32, y: i32 },
+    SendToBack { label: Option<String> },
+    BringToFront { label: Option<String> },
+    DragResize { label: Option<String>, edge: String, delta: i32 },
+    Drag { from_label: String, to_label: String },
     OpenFileProperties { file: String },
     ListSelect { label: String, item: String },
     KeyPress { key: String },
+    TypeText { text: String, delay_ms: Option<u64> },
+    KeyChord { modifiers: Vec<String>, key: String },
+    HoldKey { key: String, duration_ms: u64 },
+    TabTo { count: u32, reverse: bool },
+    SubmitDialog,
+    CancelDialog,
+    ActivateFocused,
     Scroll { direction: String, amount: Option<u32> },
-    Screenshot,
+    MouseDoubleClick { x: i32, y: i32 },
+    ClickText { text: String },
+    MouseWheel { direction: String, amount: u32 },
+    Screenshot { region: Option<Rect>, target_window: Option<String>, path: Option<String> },
+    GetClipboardImage { path: Option<String> },
+    SetClipboardImage { path: String },
     SpinnerAdjust { label: String, operation: String, value: u32 },
-    SelectFiles { criteria: String },
+    SelectFiles { criteria: String, root: Option<String>, recursive: bool },
     FileOperation { operation: String },
-    PasteFiles { destination: String },
+    PasteFiles { destination: String, mode: PasteMode },
     CreateDirectory { name: String },
     DeleteDirectory { name: String },
     CreateFile { name: String },
     DeleteFile { name: String },
-    MultiStep { steps: Vec<Action> },
+    MultiStep { steps: Vec<Action>, continue_on_error: bool },
+    Refused { intent: String, reason: String },
     Unknown { hint: String },
 }
 
+impl Action {
+    /// The action that reverts this one, for actions that can be undone without knowing the state
+    /// before them. Replacing text, setting a checkbox and pinning a window can be undone too, but their
+    /// inverses need the old text or state, which the controller reads before running the action.
+    pub fn inverse(&self) -> Option<Action> {
+        match self {
+            // SystemMenu finds windows by title only, so a process-scoped window cannot be restored by it.
+            Action::WindowMinimize { label, process: None } | Action::WindowMaximize { label, process: None } => Some(Action::SystemMenu {
+                label: label.clone(),
+                command: "restore".to_string(),
+            }),
+            Action::WindowHide { label } => Some(Action::WindowShow { label: label.clone() }),
+            _ => None,
+        }
+    }
+}
+
+/// Checks that an action carries the parameters it needs to run, so a command that is
+/// bound to fail is rejected before a task is scheduled. The error names the bad parameter.
+pub fn validate(action: &Action) -> Result<(), String> {
+    fn require_label(label: &str) -> Result<(), String> {
+        if label.trim().is_empty() {
+            Err("missing required parameter 'label'".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    match action {
+        // A window handle replaces the label as the way to find the control.
+        Action::ButtonClick { hwnd: Some(_), .. }
+        | Action::ButtonDoubleClick { hwnd: Some(_), .. }
+        | Action::EditEnterText { hwnd: Some(_), .. }
+        | Action::SetText { hwnd: Some(_), .. } => Ok(()),
+        // A process narrows the search to its windows; without a label its first window is used.
+        Action::WindowActivate { process: Some(process), .. } => {
+            if process.trim().is_empty() {
+                Err("parameter 'process' must not be empty".to_string())
+            } else {
+                Ok(())
+            }
+        }
+        Action::ButtonClick { label, .. }
+        | Action::ButtonDoubleClick { label, .. }
+        | Action::EditEnterText { label, .. }
+        | Action::SetText { label, .. }
+        | Action::SetFocus { label }
+        | Action::CheckboxSetState { label, .. }
+        | Action::WindowActivate { label, .. }
+        | Action::SystemMenu { label, .. }
+        | Action::WindowExists { label, .. } => require_label(label),
+        Action::SendMessage { label, msg, .. } => {
+            require_label(label)?;
+            if *msg == 0 {
+                Err("missing required parameter 'msg' (a message number, decimal or 0x hex)".to_string())
+            } else {
+                Ok(())
+            }
+        }
+        Action::TabControlSelectTab { label, tab } => {
+            require_label(label)?;
+            if tab.trim().is_empty() {
+                Err("missing required parameter 'tab'".to_string())
+            } else {
+                Ok(())
+            }
+        }
+        Action::Drag { from_label, to_label } => {
+            if from_label.trim().is_empty() {
+                Err("missing required parameter 'from_label'".to_string())
+            } else if to_label.trim().is_empty() {
+                Err("missing required parameter 'to_label'".to_string())
+            } else {
+                Ok(())
+            }
+        }
+        Action::TypeText { text, .. } if text.is_empty() => Err("missing required parameter 'text'".to_string()),
+        Action::WaitForTitle { title, .. } if title.is_empty() => Err("missing required parameter 'title'".to_string()),
+        Action::ArrangeWindows { layout } if !WINDOW_LAYOUTS.contains(&layout.as_str()) => {
+            Err(format!("unknown layout '{}', expected one of: {}", layout, WINDOW_LAYOUTS.join(", ")))
+        }
+        Action::WindowMoveNamed { position, .. } if !WINDOW_POSITIONS.contains(&position.as_str()) => {
+            Err(format!("unknown position '{}', expected one of: {}", position, WINDOW_POSITIONS.join(", ")))
+        }
+        Action::ClickText { text } if text.trim().is_empty() => Err("missing required parameter 'text'".to_string()),
+        Action::Open { target, .. } if target.trim().is_empty() => Err("missing required parameter 'target'".to_string()),
+        Action::Open { target, kind: OpenKind::Url } if OpenKind::detect(target) != OpenKind::Url => {
+            Err(format!("parameter 'target' must be an http or https link, got '{}'", target))
+        }
+        Action::MenuSelect { menu_path, .. } if menu_path.trim().is_empty() => Err("missing required parameter 'menu_path'".to_string()),
+        Action::WindowResizeRelative { dw: 0, dh: 0 } => Err("parameters 'dw' and 'dh' are both 0".to_string()),
+        Action::WindowResize { width, height } => {
+            if *width == 0 {
+                Err("parameter 'width' must be greater than 0".to_string())
+            } else if *height == 0 {
+                Err("parameter 'height' must be greater than 0".to_string())
+            } else {
+                Ok(())
+            }
+        }
+        Action::Scroll { direction, .. } => match direction.to_lowercase().as_str() {
+            "up" | "down" | "page_up" | "page_down" | "top" | "bottom" => Ok(()),
+            other => Err(format!(
+                "parameter 'direction' must be one of up, down, page_up, page_down, top, bottom, got '{}'", other)),
+        },
+        Action::MouseWheel { direction, .. } => match direction.to_lowercase().as_str() {
+            "up" | "down" => Ok(()),
+            other => Err(format!("parameter 'direction' must be 'up' or 'down', got '{}'", other)),
+        },
+        Action::WaitForAnyWindow { labels, .. } if labels.is_empty() => {
+            Err("missing required parameter 'labels'".to_string())
+        }
+        Action::MultiStep { steps, .. } => steps
+            .iter()
+            .enumerate()
+            .try_for_each(|(index, step)| validate(step).map_err(|e| format!("step #{}: {}", index, e))),
+        _ => Ok(()),
+    }
+}
+
+/// Lists the windows and controls an action has to find on screen before it can run.
+/// Empty for actions that work on the foreground window or need nothing on screen.
+pub fn required_targets(action: &Action) -> Vec<String> {
+    let control = |label: &str| format!("control '{}'", label);
+    let window = |label: &str| format!("window '{}'", label);
+    match action {
+        Action::ButtonClick { hwnd: Some(hwnd), .. }
+        | Action::ButtonDoubleClick { hwnd: Some(hwnd), .. }
+        | Action::EditEnterText { hwnd: Some(hwnd), .. }
+        | Action::SetText { hwnd: Some(hwnd), .. } => vec![format!("window handle {:#x}", hwnd)],
+        Action::ButtonClick { label, .. }
+        | Action::ButtonDoubleClick { label, .. }
+        | Action::EditEnterText { label, .. }
+        | Action::EditSelectText { label, .. }
+        | Action::EditCopyText { label }
+        | Action::EditCutText { label }
+        | Action::EditClearField { label }
+        | Action::EditDeleteText { label }
+        | Action::EditPasteText { label, .. }
+        | Action::StaticGetText { label }
+        | Action::SetText { label, .. }
+        | Action::SetFocus { label }
+        | Action::CheckboxSetState { label, .. }
+        | Action::RadioSelect { label, .. }
+        | Action::TreeViewSelect { label, .. }
+        | Action::TreeViewExpand { label, .. }
+        | Action::ListViewSelectItem { label, .. }
+        | Action::ListViewGetItems { label }
+        | Action::TabControlSelectTab { label, .. }
+        | Action::SetSlider { label, .. }
+        | Action::GetCheckboxState { label }
+        | Action::GetSelectedTab { label }
+        | Action::ListSelect { label, .. }
+        | Action::SpinnerAdjust { label, .. }
+        | Action::GetSelectedText { label: Some(label) }
+        | Action::SendMessage { label, .. } => vec![control(label)],
+        Action::WindowMinimize { label, process: Some(process) }
+        | Action::WindowMaximize { label, process: Some(process) }
+        | Action::WindowClose { label, process: Some(process) }
+        | Action::WindowActivate { label, process: Some(process) } => {
+            let process = format!("process '{}'", process);
+            if label.is_empty() { vec![process] } else { vec![window(label), process] }
+        }
+        Action::WindowMinimize { label, .. }
+        | Action::WindowMaximize { label, .. }
+        | Action::WindowClose { label, .. }
+        | Action::WindowActivate { label, .. }
+        | Action::SystemMenu { label, .. }
+        | Action::WindowMove { label, .. }
+        | Action::WindowExists { label, .. }
+        | Action::GetWindowDpi { label: Some(label) }
+        | Action::SendToBack { label: Some(label) }
+        | Action::BringToFront { label: Some(label) }
+        | Action::DragResize { label: Some(label), .. }
+        | Action::Screenshot { target_window: Some(label), .. } => vec![window(label)],
+        Action::MenuSelect { window: label, .. } if !label.is_empty() => vec![window(label)],
+        Action::WindowMoveNamed { label, .. }
+        | Action::WindowSetTopmost { label, .. }
+        | Action::WindowHide { label } if !label.is_empty() => vec![window(label)],
+        Action::WaitForAnyWindow { labels, .. } => labels.iter().map(|label| window(label)).collect(),
+        Action::Drag { from_label, to_label } => vec![control(from_label), control(to_label)],
+        Action::MultiStep { steps, .. } => {
+            let mut targets: Vec<String> = Vec::new();
+            for target in steps.iter().flat_map(required_targets) {
+                if !targets.contains(&target) {
+                    targets.push(target);
+                }
+            }
+            targets
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Returns true if `intent` maps to an action, i.e. it is a name aliases may point at.
+pub fn is_known_intent(intent: &str) -> bool {
+    intent_spec(intent).is_some()
+}
+
 /// Attempts to apply an alias to the NLP result using the current configuration.
 /// If an alias is found matching the NLP intent, it replaces the intent and parameters accordingly.
 fn try_apply_alias(nlp_result: &NLPResult, shared_config: &SharedConfig) -> Option<Action> {
@@ -61,16 +408,23 @@ fn try_apply_alias(nlp_result: &NLPResult, shared_config: &SharedConfig) -> Opti
     let config = config_lock.as_ref()?;
     for alias in config.aliases.iter() {
         if alias.alias.to_lowercase() == nlp_result.intent.to_lowercase() {
+            if config.is_intent_disabled(&alias.intent) {
+                return Some(refuse_intent(&alias.intent));
+            }
             let mut new_result = nlp_result.clone();
             new_result.intent = alias.intent.clone();
             if let Some(ref alias_params) = alias.parameters {
                 for (k, v) in alias_params {
-                    new_result.parameters.entry(k.clone()).or_insert(v.clone());
+                    let value = substitute_placeholders(v, &nlp_result.parameters);
+                    new_result.parameters.entry(k.clone()).or_insert(value);
                 }
             }
             if let Some(cmd_type) = &alias.command_type {
                 if cmd_type.to_lowercase() == "multi" {
                     if let Some(steps) = &alias.steps {
+                        if let Some(step) = steps.iter().find(|step| config.is_intent_disabled(&step.intent)) {
+                            return Some(refuse_intent(&step.intent));
+                        }
                         let mapped_steps = steps
                             .iter()
                             .map(|step_alias| {
@@ -78,13 +432,14 @@ fn try_apply_alias(nlp_result: &NLPResult, shared_config: &SharedConfig) -> Opti
                                 step_result.intent = step_alias.intent.clone();
                                 if let Some(ref step_params) = step_alias.parameters {
                                     for (k, v) in step_params {
-                                        step_result.parameters.entry(k.clone()).or_insert(v.clone());
+                                        let value = substitute_placeholders(v, &nlp_result.parameters);
+                                        step_result.parameters.entry(k.clone()).or_insert(value);
                                     }
                                 }
                                 map_intent_impl(&step_result)
                             })
                             .collect();
-                        return Some(Action::MultiStep { steps: mapped_steps });
+                        return Some(Action::MultiStep { steps: mapped_steps, continue_on_error: alias.continue_on_error });
                     }
                 }
             }
@@ -94,172 +449,996 @@ fn try_apply_alias(nlp_result: &NLPResult, shared_config: &SharedConfig) -> Opti
     None
 }
 
+/// Replaces `${name}` placeholders in an alias parameter with values captured from the original command.
+/// Literal values pass through unchanged; unresolved placeholders become empty strings, except
+/// `${prev}`, which a multi-step alias resolves at execution time from the previous step's output.
+fn substitute_placeholders(value: &str, captured: &HashMap<String, String>) -> String {
+    PLACEHOLDER_RE
+        .replace_all(value, |caps: &Captures| {
+            let name = &caps[1];
+            if name == PREVIOUS_STEP_PLACEHOLDER && !captured.contains_key(name) {
+                return caps[0].to_string();
+            }
+            captured.get(name).cloned().unwrap_or_else(|| {
+                debug!("Alias placeholder '${{{}}}' has no captured value, using empty string", name);
+                String::new()
+            })
+        })
+        .into_owned()
+}
+
+/// Builds a capture region from the `x`, `y`, `width` and `height` parameters.
+/// Returns None unless all four are present and the size is non-zero.
+fn parse_region(parameters: &HashMap<String, String>) -> Option<Rect> {
+    let get = |key: &str| parameters.get(key).and_then(|s| s.trim().parse::<i64>().ok());
+    let (x, y, width, height) = (get("x")?, get("y")?, get("width")?, get("height")?);
+    if width <= 0 || height <= 0 {
+        return None;
+    }
+    Some(Rect {
+        x: x as i32,
+        y: y as i32,
+        width: width as u32,
+        height: height as u32,
+    })
+}
+
+/// Reads the optional `hwnd` parameter, as listed by `/windows`, in decimal or `0x` hex.
+fn parse_hwnd(parameters: &HashMap<String, String>) -> Option<isize> {
+    let value = parameters.get("hwnd")?.trim();
+    match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => isize::from_str_radix(hex, 16).ok(),
+        None => value.parse::<isize>().ok(),
+    }
+}
+
+/// Reads an integer parameter in decimal or `0x` hex, as window messages are usually written.
+/// Hex values keep their bit pattern, so `0xFFFFFFFF` style masks fit wide parameters too.
+fn parse_integer(parameters: &HashMap<String, String>, name: &str) -> Option<i64> {
+    let value = parameters.get(name)?.trim();
+    match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok().map(|bits| bits as i64),
+        None => value.parse::<i64>().ok(),
+    }
+}
+
+/// Builds the refusal returned in place of an intent listed in `disabled_intents`.
+fn refuse_intent(intent: &str) -> Action {
+    Action::Refused {
+        intent: intent.to_string(),
+        reason: format!("Intent '{}' is disabled by configuration", intent),
+    }
+}
+
+/// Returns true if the intent is listed in the configured `disabled_intents`.
+fn is_intent_disabled(intent: &str, shared_config: &SharedConfig) -> bool {
+    shared_config
+        .lock()
+        .ok()
+        .and_then(|config_lock| config_lock.as_ref().map(|cfg| cfg.is_intent_disabled(intent)))
+        .unwrap_or(false)
+}
+
 /// Public API for mapping an NLP result to an Action, potentially utilizing alias configuration.
+/// Intents disabled in the configuration, and launches of applications outside `allowed_applications`,
+/// are mapped to `Action::Refused` and never executed.
 pub fn map_intent(nlp_result: &NLPResult, shared_config: &SharedConfig) -> Action {
-    if let Some(alias_action) = try_apply_alias(nlp_result, shared_config) {
-        return alias_action;
+    if is_intent_disabled(&nlp_result.intent, shared_config) {
+        return refuse_intent(&nlp_result.intent);
+    }
+    let action = match try_apply_alias(nlp_result, shared_config) {
+        Some(alias_action) => alias_action,
+        None => map_intent_impl(nlp_result),
+    };
+    match unpermitted_launch(&action, shared_config) {
+        Some(target) => Action::Refused {
+            intent: nlp_result.intent.clone(),
+            reason: format!("application not permitted: '{}'", target),
+        },
+        None => action,
     }
-    map_intent_impl(nlp_result)
 }
 
-/// Internal implementation of intent mapping based on the NLP result.
-/// If the intent is not recognized, returns an Unknown action with a hint message based on language settings.
-fn map_intent_impl(nlp_result: &NLPResult) -> Action {
-    match nlp_result.intent.as_str() {
-        "button_click" => Action::ButtonClick {
+/// Failure message for actions refused because `safe_mode` is on.
+pub const SAFE_MODE_MESSAGE: &str = "operation disabled in safe mode";
+
+/// Returns true if `action` touches files or starts a process, which `safe_mode` disables.
+/// A `MultiStep` is blocked as a whole when any of its steps is.
+pub fn is_blocked_in_safe_mode(action: &Action) -> bool {
+    match action {
+        Action::DeleteFile { .. }
+        | Action::DeleteDirectory { .. }
+        | Action::CreateFile { .. }
+        | Action::CreateDirectory { .. }
+        | Action::FileOperation { .. }
+        | Action::PasteFiles { .. }
+        | Action::LaunchApplication { .. }
+        | Action::LaunchObject { .. }
+        | Action::Open { .. } => true,
+        Action::MultiStep { steps, .. } => steps.iter().any(is_blocked_in_safe_mode),
+        _ => false,
+    }
+}
+
+/// Returns the first application or object `action` would launch, alias steps included,
+/// that is not on the configured `allowed_applications` list.
+fn unpermitted_launch(action: &Action, shared_config: &SharedConfig) -> Option<String> {
+    let target = match action {
+        Action::LaunchApplication { app } => app,
+        Action::LaunchObject { object } => object,
+        Action::Open { target, kind: OpenKind::App } => target,
+        // Opening an executable by path launches it just the same.
+        Action::Open { target, kind: OpenKind::File } if is_executable_file(target) => target,
+        Action::MultiStep { steps, .. } => return steps.iter().find_map(|step| unpermitted_launch(step, shared_config)),
+        _ => return None,
+    };
+    let allowed = shared_config
+        .lock()
+        .ok()
+        .and_then(|config_lock| config_lock.as_ref().map(|cfg| cfg.is_application_allowed(target)))
+        .unwrap_or(true);
+    if allowed { None } else { Some(target.clone()) }
+}
+
+/// File extensions the shell runs rather than hands to a viewer.
+const EXECUTABLE_EXTENSIONS: &[&str] = &["exe", "com", "bat", "cmd", "lnk", "ps1", "vbs", "vbe", "js", "jse", "wsf", "wsh", "msi", "msc", "scr", "pif", "cpl", "hta", "url", "appref-ms"];
+
+/// Returns true if opening `target` would run a program, judged by its extension.
+fn is_executable_file(target: &str) -> bool {
+    Path::new(target.trim().trim_matches('"'))
+        .extension()
+        .is_some_and(|ext| EXECUTABLE_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)))
+}
+
+/// A parameter an intent reads from the NLP result. Values always arrive as strings;
+/// `kind` says how the mapper interprets them.
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub kind: &'static str, // "string", "integer", "boolean" or "list"
+    pub required: bool,
+}
+
+const fn required(name: &'static str, kind: &'static str) -> ParamSpec {
+    ParamSpec { name, kind, required: true }
+}
+
+const fn optional(name: &'static str, kind: &'static str) -> ParamSpec {
+    ParamSpec { name, kind, required: false }
+}
+
+/// One intent: the parameters it reads, the action variant it maps to and how that action is built.
+/// `map_intent_impl` and the `/intents` endpoint both read [`INTENT_SPECS`], so they cannot drift apart.
+pub struct IntentSpec {
+    pub intent: &'static str,
+    pub action: &'static str,
+    pub params: &'static [ParamSpec],
+    build: fn(&NLPResult) -> Action,
+}
+
+/// Every intent the mapper understands, in lookup order.
+pub static INTENT_SPECS: &[IntentSpec] = &[
+    IntentSpec {
+        intent: "button_click",
+        action: "ButtonClick",
+        params: &[required("label", "string"), optional("hwnd", "integer")],
+        build: |nlp_result| Action::ButtonClick {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
+            hwnd: parse_hwnd(&nlp_result.parameters),
         },
-        "button_double_click" => Action::ButtonDoubleClick {
+    },
+    IntentSpec {
+        intent: "button_double_click",
+        action: "ButtonDoubleClick",
+        params: &[required("label", "string"), optional("use_mouse", "boolean"), optional("hwnd", "integer")],
+        build: |nlp_result| Action::ButtonDoubleClick {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
+            use_mouse: nlp_result.parameters.get("use_mouse").is_some_and(|s| s == "true"),
+            hwnd: parse_hwnd(&nlp_result.parameters),
         },
-        "edit_enter_text" => Action::EditEnterText {
+    },
+    IntentSpec {
+        intent: "edit_enter_text",
+        action: "EditEnterText",
+        params: &[required("label", "string"), optional("text", "string"), optional("hwnd", "integer")],
+        build: |nlp_result| Action::EditEnterText {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
             text: nlp_result.parameters.get("text").cloned().unwrap_or_default(),
+            hwnd: parse_hwnd(&nlp_result.parameters),
         },
-        "edit_select_text" => Action::EditSelectText {
+    },
+    IntentSpec {
+        intent: "edit_select_text",
+        action: "EditSelectText",
+        params: &[optional("label", "string"), optional("start", "integer"), optional("end", "integer")],
+        build: |nlp_result| Action::EditSelectText {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
             start: nlp_result.parameters.get("start").and_then(|s| s.parse::<u32>().ok()),
             end: nlp_result.parameters.get("end").and_then(|s| s.parse::<u32>().ok()),
         },
-        "edit_copy_text" => Action::EditCopyText {
+    },
+    IntentSpec {
+        intent: "edit_copy_text",
+        action: "EditCopyText",
+        params: &[optional("label", "string")],
+        build: |nlp_result| Action::EditCopyText {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
         },
-        "edit_cut_text" => Action::EditCutText {
+    },
+    IntentSpec {
+        intent: "edit_cut_text",
+        action: "EditCutText",
+        params: &[optional("label", "string")],
+        build: |nlp_result| Action::EditCutText {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
         },
-        "edit_clear_field" => Action::EditClearField {
+    },
+    IntentSpec {
+        intent: "edit_clear_field",
+        action: "EditClearField",
+        params: &[optional("label", "string")],
+        build: |nlp_result| Action::EditClearField {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
         },
-        "edit_delete_text" => Action::EditDeleteText {
+    },
+    IntentSpec {
+        intent: "edit_delete_text",
+        action: "EditDeleteText",
+        params: &[optional("label", "string")],
+        build: |nlp_result| Action::EditDeleteText {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
         },
-        "edit_paste_text" => Action::EditPasteText {
+    },
+    IntentSpec {
+        intent: "edit_paste_text",
+        action: "EditPasteText",
+        params: &[optional("label", "string"), optional("text", "string")],
+        build: |nlp_result| Action::EditPasteText {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
             text: nlp_result.parameters.get("text").cloned(),
         },
-        "static_get_text" => Action::StaticGetText {
+    },
+    IntentSpec {
+        intent: "static_get_text",
+        action: "StaticGetText",
+        params: &[optional("label", "string")],
+        build: |nlp_result| Action::StaticGetText {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
         },
-        "set_text" => Action::SetText {
+    },
+    IntentSpec {
+        intent: "set_text",
+        action: "SetText",
+        params: &[required("label", "string"), optional("text", "string"), optional("hwnd", "integer")],
+        build: |nlp_result| Action::SetText {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
             text: nlp_result.parameters.get("text").cloned().unwrap_or_default(),
+            hwnd: parse_hwnd(&nlp_result.parameters),
         },
-        "set_focus" => Action::SetFocus {
+    },
+    IntentSpec {
+        intent: "set_focus",
+        action: "SetFocus",
+        params: &[required("label", "string")],
+        build: |nlp_result| Action::SetFocus {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
         },
-        "checkbox_set_state" => {
-            let state_str = nlp_result.parameters.get("state").cloned().unwrap_or_else(|| "false".to_string());
-            Action::CheckboxSetState {
-                label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
-                state: state_str == "true",
-            }
+    },
+    IntentSpec {
+        intent: "checkbox_set_state",
+        action: "CheckboxSetState",
+        params: &[required("label", "string"), optional("state", "string")],
+        build: |nlp_result| Action::CheckboxSetState {
+            label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
+            state: nlp_result.parameters.get("state")
+                .and_then(|state| CheckState::parse(state))
+                .unwrap_or(CheckState::Unchecked),
         },
-        "radio_select" => Action::RadioSelect {
+    },
+    IntentSpec {
+        intent: "radio_select",
+        action: "RadioSelect",
+        params: &[optional("label", "string"), optional("variant", "string")],
+        build: |nlp_result| Action::RadioSelect {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
             variant: nlp_result.parameters.get("variant").cloned(),
         },
-        "treeview_select" => Action::TreeViewSelect {
+    },
+    IntentSpec {
+        intent: "treeview_select",
+        action: "TreeViewSelect",
+        params: &[optional("label", "string"), optional("node", "string")],
+        build: |nlp_result| Action::TreeViewSelect {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
             node: nlp_result.parameters.get("node").cloned(),
         },
-        "treeview_expand" => Action::TreeViewExpand {
+    },
+    IntentSpec {
+        intent: "treeview_expand",
+        action: "TreeViewExpand",
+        params: &[optional("label", "string"), optional("node", "string")],
+        build: |nlp_result| Action::TreeViewExpand {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
             node: nlp_result.parameters.get("node").cloned(),
         },
-        "listview_select_item" => Action::ListViewSelectItem {
+    },
+    IntentSpec {
+        intent: "listview_select_item",
+        action: "ListViewSelectItem",
+        params: &[optional("label", "string"), optional("item", "string")],
+        build: |nlp_result| Action::ListViewSelectItem {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
             item: nlp_result.parameters.get("item").cloned().unwrap_or_default(),
         },
-        "tabcontrol_select_tab" => Action::TabControlSelectTab {
+    },
+    IntentSpec {
+        intent: "set_slider",
+        action: "SetSlider",
+        params: &[optional("label", "string"), optional("percent", "integer")],
+        build: |nlp_result| Action::SetSlider {
+            label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
+            percent: nlp_result.parameters.get("percent").and_then(|s| s.parse::<u32>().ok()).unwrap_or(0).min(100),
+        },
+    },
+    IntentSpec {
+        intent: "get_checkbox_state",
+        action: "GetCheckboxState",
+        params: &[optional("label", "string")],
+        build: |nlp_result| Action::GetCheckboxState {
+            label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
+        },
+    },
+    IntentSpec {
+        intent: "get_selected_text",
+        action: "GetSelectedText",
+        params: &[optional("label", "string")],
+        build: |nlp_result| Action::GetSelectedText {
+            label: nlp_result.parameters.get("label").cloned(),
+        },
+    },
+    IntentSpec {
+        intent: "listview_get_items",
+        action: "ListViewGetItems",
+        params: &[required("label", "string")],
+        build: |nlp_result| Action::ListViewGetItems {
+            label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
+        },
+    },
+    IntentSpec {
+        intent: "get_selected_tab",
+        action: "GetSelectedTab",
+        params: &[optional("label", "string")],
+        build: |nlp_result| Action::GetSelectedTab {
+            label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
+        },
+    },
+    IntentSpec {
+        intent: "tabcontrol_select_tab",
+        action: "TabControlSelectTab",
+        params: &[required("label", "string"), required("tab", "string")],
+        build: |nlp_result| Action::TabControlSelectTab {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
             tab: nlp_result.parameters.get("tab").cloned().unwrap_or_default(),
         },
-        "window_resize" => Action::WindowResize {
+    },
+    IntentSpec {
+        intent: "window_resize",
+        action: "WindowResize",
+        params: &[optional("width", "integer"), optional("height", "integer")],
+        build: |nlp_result| Action::WindowResize {
             width: nlp_result.parameters.get("width").and_then(|s| s.parse::<u32>().ok()).unwrap_or(800),
             height: nlp_result.parameters.get("height").and_then(|s| s.parse::<u32>().ok()).unwrap_or(600),
         },
-        "window_minimize" => Action::WindowMinimize {
+    },
+    IntentSpec {
+        intent: "window_resize_relative",
+        action: "WindowResizeRelative",
+        params: &[optional("dw", "integer"), optional("dh", "integer")],
+        build: |nlp_result| Action::WindowResizeRelative {
+            dw: nlp_result.parameters.get("dw").and_then(|s| s.parse::<i32>().ok()).unwrap_or(0),
+            dh: nlp_result.parameters.get("dh").and_then(|s| s.parse::<i32>().ok()).unwrap_or(0),
+        },
+    },
+    IntentSpec {
+        intent: "window_topmost",
+        action: "WindowSetTopmost",
+        params: &[optional("label", "string"), optional("topmost", "boolean")],
+        build: |nlp_result| Action::WindowSetTopmost {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
+            topmost: nlp_result.parameters.get("topmost").is_none_or(|s| s != "false"),
         },
-        "window_maximize" => Action::WindowMaximize {
+    },
+    IntentSpec {
+        intent: "window_hide",
+        action: "WindowHide",
+        params: &[optional("label", "string")],
+        build: |nlp_result| Action::WindowHide {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
         },
-        "window_close" => Action::WindowClose {
+    },
+    IntentSpec {
+        intent: "window_show",
+        action: "WindowShow",
+        params: &[optional("label", "string")],
+        build: |nlp_result| Action::WindowShow {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
         },
-        "window_move" => Action::WindowMove {
+    },
+    IntentSpec {
+        intent: "send_message",
+        action: "SendMessage",
+        params: &[
+            required("label", "string"),
+            required("msg", "integer"),
+            optional("wparam", "integer"),
+            optional("lparam", "integer"),
+        ],
+        build: |nlp_result| Action::SendMessage {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
-            x: nlp_result.parameters.get("x").and_then(|s| s.parse::<u32>().ok()).unwrap_or(0),
-            y: nlp_result.parameters.get("y").and_then(|s| s.parse::<u32>().ok()).unwrap_or(0),
+            msg: parse_integer(&nlp_result.parameters, "msg").and_then(|msg| u32::try_from(msg).ok()).unwrap_or(0),
+            wparam: parse_integer(&nlp_result.parameters, "wparam").map_or(0, |wparam| wparam as usize),
+            lparam: parse_integer(&nlp_result.parameters, "lparam").map_or(0, |lparam| lparam as isize),
         },
-        "launch_object" | "launch_application" => Action::LaunchApplication {
-            app: nlp_result.parameters.get("object")
-                .or_else(|| nlp_result.parameters.get("app"))
-                .cloned()
-                .unwrap_or_default(),
+    },
+    IntentSpec {
+        intent: "window_activate",
+        action: "WindowActivate",
+        params: &[required("label", "string"), optional("process", "string")],
+        build: |nlp_result| Action::WindowActivate {
+            label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
+            process: nlp_result.parameters.get("process").cloned(),
+        },
+    },
+    IntentSpec {
+        intent: "save_layout",
+        action: "SaveLayout",
+        params: &[optional("name", "string")],
+        build: |nlp_result| Action::SaveLayout {
+            name: nlp_result.parameters.get("name").cloned().unwrap_or_else(|| "default".to_string()),
+        },
+    },
+    IntentSpec {
+        intent: "restore_layout",
+        action: "RestoreLayout",
+        params: &[optional("name", "string")],
+        build: |nlp_result| Action::RestoreLayout {
+            name: nlp_result.parameters.get("name").cloned().unwrap_or_else(|| "default".to_string()),
+        },
+    },
+    IntentSpec {
+        intent: "drag",
+        action: "Drag",
+        params: &[required("from_label", "string"), required("to_label", "string")],
+        build: |nlp_result| Action::Drag {
+            from_label: nlp_result.parameters.get("from_label").cloned().unwrap_or_default(),
+            to_label: nlp_result.parameters.get("to_label").cloned().unwrap_or_default(),
+        },
+    },
+    IntentSpec {
+        intent: "type_text",
+        action: "TypeText",
+        params: &[required("text", "string"), optional("delay_ms", "integer")],
+        build: |nlp_result| Action::TypeText {
+            text: nlp_result.parameters.get("text").cloned().unwrap_or_default(),
+            delay_ms: nlp_result.parameters.get("delay_ms").and_then(|s| s.parse::<u64>().ok()).map(|ms| ms.min(MAX_KEYSTROKE_DELAY_MS)),
+        },
+    },
+    IntentSpec {
+        intent: "system_menu",
+        action: "SystemMenu",
+        params: &[required("label", "string"), optional("command", "string")],
+        build: |nlp_result| Action::SystemMenu {
+            label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
+            command: nlp_result.parameters.get("command").cloned().unwrap_or_default(),
         },
-        "focus_object" | "focus_application" => Action::FocusApplication {
-            app: nlp_result.parameters.get("object")
-                .or_else(|| nlp_result.parameters.get("app"))
-                .cloned()
+    },
+    IntentSpec {
+        intent: "menu_select",
+        action: "MenuSelect",
+        params: &[optional("window", "string"), required("menu_path", "string")],
+        build: |nlp_result| Action::MenuSelect {
+            // An empty window means the foreground window.
+            window: nlp_result.parameters.get("window").cloned().unwrap_or_default(),
+            menu_path: nlp_result.parameters.get("menu_path").cloned().unwrap_or_default(),
+        },
+    },
+    IntentSpec {
+        intent: "wait_for_any_window",
+        action: "WaitForAnyWindow",
+        params: &[required("labels", "list"), optional("timeout_ms", "integer")],
+        build: |nlp_result| Action::WaitForAnyWindow {
+            // Titles may contain commas, so the list is '|'-separated.
+            labels: nlp_result.parameters.get("labels")
+                .map(|s| s.split('|').filter(|l| !l.is_empty()).map(str::to_string).collect())
                 .unwrap_or_default(),
+            timeout_ms: nlp_result.parameters.get("timeout_ms").and_then(|s| s.parse::<u64>().ok()).unwrap_or(DEFAULT_WAIT_MS).min(MAX_WAIT_MS),
+        },
+    },
+    IntentSpec {
+        intent: "wait_for_title",
+        action: "WaitForTitle",
+        params: &[required("title", "string"), optional("timeout_ms", "integer")],
+        build: |nlp_result| Action::WaitForTitle {
+            title: nlp_result.parameters.get("title").cloned().unwrap_or_default(),
+            timeout_ms: nlp_result.parameters.get("timeout_ms").and_then(|s| s.parse::<u64>().ok()).unwrap_or(DEFAULT_WAIT_MS).min(MAX_WAIT_MS),
+        },
+    },
+    IntentSpec {
+        intent: "window_minimize",
+        action: "WindowMinimize",
+        params: &[optional("label", "string"), optional("process", "string")],
+        build: |nlp_result| Action::WindowMinimize {
+            label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
+            process: nlp_result.parameters.get("process").cloned(),
+        },
+    },
+    IntentSpec {
+        intent: "window_maximize",
+        action: "WindowMaximize",
+        params: &[optional("label", "string"), optional("process", "string")],
+        build: |nlp_result| Action::WindowMaximize {
+            label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
+            process: nlp_result.parameters.get("process").cloned(),
+        },
+    },
+    IntentSpec {
+        intent: "window_close",
+        action: "WindowClose",
+        params: &[optional("label", "string"), optional("process", "string")],
+        build: |nlp_result| Action::WindowClose {
+            label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
+            process: nlp_result.parameters.get("process").cloned(),
+        },
+    },
+    IntentSpec {
+        intent: "window_move",
+        action: "WindowMove",
+        params: &[optional("label", "string"), optional("x", "integer"), optional("y", "integer")],
+        build: |nlp_result| Action::WindowMove {
+            label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
+            x: nlp_result.parameters.get("x").and_then(|s| s.parse::<u32>().ok()).unwrap_or(0),
+            y: nlp_result.parameters.get("y").and_then(|s| s.parse::<u32>().ok()).unwrap_or(0),
+        },
+    },
+    IntentSpec {
+        intent: "window_move_named",
+        action: "WindowMoveNamed",
+        params: &[optional("label", "string"), required("position", "string")],
+        build: |nlp_result| Action::WindowMoveNamed {
+            label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
+            position: nlp_result.parameters.get("position").cloned().unwrap_or_default(),
         },
-        "group_windows" => Action::GroupWindows {
+    },
+    IntentSpec {
+        intent: "launch_object",
+        action: "LaunchApplication",
+        params: &[optional("object", "string"), optional("app", "string")],
+        build: build_launch_object,
+    },
+    IntentSpec {
+        intent: "launch_application",
+        action: "LaunchApplication",
+        params: &[optional("object", "string"), optional("app", "string")],
+        build: build_launch_application,
+    },
+    IntentSpec {
+        intent: "focus_object",
+        action: "FocusApplication",
+        params: &[optional("object", "string"), optional("app", "string")],
+        build: build_focus_application,
+    },
+    IntentSpec {
+        intent: "focus_application",
+        action: "FocusApplication",
+        params: &[optional("object", "string"), optional("app", "string")],
+        build: build_focus_application,
+    },
+    IntentSpec {
+        intent: "group_windows",
+        action: "GroupWindows",
+        params: &[optional("group", "string"), optional("windows", "string")],
+        build: |nlp_result| Action::GroupWindows {
             group: nlp_result.parameters.get("group").cloned().unwrap_or_default(),
             windows: nlp_result.parameters.get("windows").cloned().unwrap_or_default(),
         },
-        "window_minimize_all" => Action::WindowMinimizeAll,
-        "window_maximize_all" => Action::WindowMaximizeAll,
-        "window_close_all" => Action::WindowCloseAll,
-        "open_file" => Action::OpenFileProperties {
+    },
+    IntentSpec {
+        intent: "arrange_windows",
+        action: "ArrangeWindows",
+        params: &[optional("layout", "string")],
+        build: |nlp_result| Action::ArrangeWindows {
+            layout: nlp_result.parameters.get("layout").cloned().unwrap_or_else(|| WINDOW_LAYOUTS[0].to_string()),
+        },
+    },
+    IntentSpec {
+        intent: "window_minimize_all",
+        action: "WindowMinimizeAll",
+        params: &[],
+        build: |_| Action::WindowMinimizeAll,
+    },
+    IntentSpec {
+        intent: "window_maximize_all",
+        action: "WindowMaximizeAll",
+        params: &[],
+        build: |_| Action::WindowMaximizeAll,
+    },
+    IntentSpec {
+        intent: "window_close_all",
+        action: "WindowCloseAll",
+        params: &[],
+        build: |_| Action::WindowCloseAll,
+    },
+    IntentSpec {
+        intent: "list_windows",
+        action: "ListWindows",
+        params: &[],
+        build: |_| Action::ListWindows,
+    },
+    IntentSpec {
+        intent: "window_exists",
+        action: "WindowExists",
+        params: &[required("label", "string"), optional("class", "string")],
+        build: |nlp_result| Action::WindowExists {
+            label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
+            class: nlp_result.parameters.get("class").cloned(),
+        },
+    },
+    IntentSpec {
+        intent: "get_window_dpi",
+        action: "GetWindowDpi",
+        params: &[optional("label", "string")],
+        build: |nlp_result| Action::GetWindowDpi {
+            label: nlp_result.parameters.get("label").cloned(),
+        },
+    },
+    IntentSpec {
+        intent: "get_pixel",
+        action: "GetPixelColor",
+        params: &[required("x", "integer"), required("y", "integer")],
+        build: |nlp_result| Action::GetPixelColor {
+            x: nlp_result.parameters.get("x").and_then(|s| s.parse::<i32>().ok()).unwrap_or(0),
+            y: nlp_result.parameters.get("y").and_then(|s| s.parse::<i32>().ok()).unwrap_or(0),
+        },
+    },
+    IntentSpec {
+        intent: "send_to_back",
+        action: "SendToBack",
+        params: &[optional("label", "string")],
+        build: |nlp_result| Action::SendToBack {
+            label: nlp_result.parameters.get("label").cloned(),
+        },
+    },
+    IntentSpec {
+        intent: "bring_to_front",
+        action: "BringToFront",
+        params: &[optional("label", "string")],
+        build: |nlp_result| Action::BringToFront {
+            label: nlp_result.parameters.get("label").cloned(),
+        },
+    },
+    IntentSpec {
+        intent: "drag_resize",
+        action: "DragResize",
+        params: &[optional("label", "string"), optional("edge", "string"), optional("delta", "integer")],
+        build: |nlp_result| Action::DragResize {
+            label: nlp_result.parameters.get("label").cloned(),
+            edge: nlp_result.parameters.get("edge").cloned().unwrap_or_else(|| "right".to_string()),
+            delta: nlp_result.parameters.get("delta").and_then(|s| s.parse::<i32>().ok()).unwrap_or(0),
+        },
+    },
+    IntentSpec {
+        intent: "open_url",
+        action: "Open",
+        params: &[required("target", "string")],
+        build: |nlp_result| build_open(nlp_result, OpenKind::Url),
+    },
+    IntentSpec {
+        intent: "open_folder",
+        action: "Open",
+        params: &[required("target", "string")],
+        build: |nlp_result| build_open(nlp_result, OpenKind::Folder),
+    },
+    IntentSpec {
+        intent: "open_file",
+        action: "Open",
+        params: &[required("target", "string")],
+        build: |nlp_result| build_open(nlp_result, OpenKind::File),
+    },
+    IntentSpec {
+        intent: "file_properties",
+        action: "OpenFileProperties",
+        params: &[optional("file", "string")],
+        build: |nlp_result| Action::OpenFileProperties {
             file: nlp_result.parameters.get("file").cloned().unwrap_or_default(),
         },
-        "list_select" => Action::ListSelect {
+    },
+    IntentSpec {
+        intent: "list_select",
+        action: "ListSelect",
+        params: &[optional("label", "string"), optional("item", "string")],
+        build: |nlp_result| Action::ListSelect {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
             item: nlp_result.parameters.get("item").cloned().unwrap_or_default(),
         },
-        "key_press" => Action::KeyPress {
+    },
+    IntentSpec {
+        intent: "key_press",
+        action: "KeyPress",
+        params: &[optional("key", "string")],
+        build: |nlp_result| Action::KeyPress {
+            key: nlp_result.parameters.get("key").cloned().unwrap_or_default(),
+        },
+    },
+    IntentSpec {
+        intent: "key_chord",
+        action: "KeyChord",
+        params: &[optional("modifiers", "list"), optional("key", "string")],
+        build: |nlp_result| Action::KeyChord {
+            modifiers: nlp_result.parameters.get("modifiers")
+                .map(|s| s.split(',').filter(|m| !m.is_empty()).map(str::to_string).collect())
+                .unwrap_or_default(),
             key: nlp_result.parameters.get("key").cloned().unwrap_or_default(),
         },
-        "scroll" => Action::Scroll {
+    },
+    IntentSpec {
+        intent: "hold_key",
+        action: "HoldKey",
+        params: &[optional("key", "string"), optional("duration_ms", "integer")],
+        build: |nlp_result| Action::HoldKey {
+            key: nlp_result.parameters.get("key").cloned().unwrap_or_default(),
+            // Capped so a typo cannot block the task worker for hours.
+            duration_ms: nlp_result.parameters.get("duration_ms").and_then(|s| s.parse::<u64>().ok()).unwrap_or(1000).min(MAX_HOLD_MS),
+        },
+    },
+    IntentSpec {
+        intent: "tab_to",
+        action: "TabTo",
+        params: &[optional("count", "integer"), optional("reverse", "boolean")],
+        build: |nlp_result| Action::TabTo {
+            count: nlp_result.parameters.get("count").and_then(|s| s.parse::<u32>().ok()).unwrap_or(1),
+            reverse: nlp_result.parameters.get("reverse").is_some_and(|s| s == "true"),
+        },
+    },
+    IntentSpec {
+        intent: "submit_dialog",
+        action: "SubmitDialog",
+        params: &[],
+        build: |_| Action::SubmitDialog,
+    },
+    IntentSpec {
+        intent: "cancel_dialog",
+        action: "CancelDialog",
+        params: &[],
+        build: |_| Action::CancelDialog,
+    },
+    IntentSpec {
+        intent: "activate_focused",
+        action: "ActivateFocused",
+        params: &[],
+        build: |_| Action::ActivateFocused,
+    },
+    IntentSpec {
+        intent: "scroll",
+        action: "Scroll",
+        params: &[optional("direction", "string"), optional("amount", "integer")],
+        build: |nlp_result| Action::Scroll {
             direction: nlp_result.parameters.get("direction").cloned().unwrap_or_else(|| "up".to_string()),
             amount: nlp_result.parameters.get("amount").and_then(|s| s.parse::<u32>().ok()),
         },
-        "screenshot" => Action::Screenshot,
-        "spinner_adjust" => Action::SpinnerAdjust {
+    },
+    IntentSpec {
+        intent: "mouse_double_click",
+        action: "MouseDoubleClick",
+        params: &[optional("x", "integer"), optional("y", "integer")],
+        build: |nlp_result| Action::MouseDoubleClick {
+            x: nlp_result.parameters.get("x").and_then(|s| s.parse::<i32>().ok()).unwrap_or(0),
+            y: nlp_result.parameters.get("y").and_then(|s| s.parse::<i32>().ok()).unwrap_or(0),
+        },
+    },
+    IntentSpec {
+        intent: "click_text",
+        action: "ClickText",
+        params: &[required("text", "string")],
+        build: |nlp_result| Action::ClickText {
+            text: nlp_result.parameters.get("text").cloned().unwrap_or_default(),
+        },
+    },
+    IntentSpec {
+        intent: "mouse_wheel",
+        action: "MouseWheel",
+        params: &[optional("direction", "string"), optional("amount", "integer")],
+        build: |nlp_result| Action::MouseWheel {
+            direction: nlp_result.parameters.get("direction").cloned().unwrap_or_else(|| "down".to_string()),
+            // Three notches matches the default Windows wheel scroll.
+            amount: nlp_result.parameters.get("amount").and_then(|s| s.parse::<u32>().ok()).unwrap_or(3),
+        },
+    },
+    IntentSpec {
+        intent: "screenshot",
+        action: "Screenshot",
+        params: &[optional("window", "string"), optional("path", "string"), optional("x", "integer"), optional("y", "integer"), optional("width", "integer"), optional("height", "integer")],
+        build: |nlp_result| Action::Screenshot {
+            region: parse_region(&nlp_result.parameters),
+            target_window: nlp_result.parameters.get("window").cloned(),
+            path: nlp_result.parameters.get("path").cloned(),
+        },
+    },
+    IntentSpec {
+        intent: "get_clipboard_image",
+        action: "GetClipboardImage",
+        params: &[optional("path", "string")],
+        build: |nlp_result| Action::GetClipboardImage {
+            path: nlp_result.parameters.get("path").cloned(),
+        },
+    },
+    IntentSpec {
+        intent: "set_clipboard_image",
+        action: "SetClipboardImage",
+        params: &[optional("path", "string")],
+        build: |nlp_result| Action::SetClipboardImage {
+            path: nlp_result.parameters.get("path").cloned().unwrap_or_default(),
+        },
+    },
+    IntentSpec {
+        intent: "spinner_adjust",
+        action: "SpinnerAdjust",
+        params: &[optional("label", "string"), optional("operation", "string"), optional("value", "integer")],
+        build: |nlp_result| Action::SpinnerAdjust {
             label: nlp_result.parameters.get("label").cloned().unwrap_or_default(),
             operation: nlp_result.parameters.get("operation").cloned().unwrap_or_default(),
             value: nlp_result.parameters.get("value").and_then(|s| s.parse::<u32>().ok()).unwrap_or(0),
         },
-        "select_files" => Action::SelectFiles {
+    },
+    IntentSpec {
+        intent: "select_files",
+        action: "SelectFiles",
+        params: &[optional("criteria", "string"), optional("root", "string"), optional("recursive", "boolean")],
+        build: |nlp_result| Action::SelectFiles {
             criteria: nlp_result.parameters.get("criteria").cloned().unwrap_or_default(),
+            root: nlp_result.parameters.get("root").cloned(),
+            recursive: nlp_result.parameters.get("recursive").is_some_and(|s| s == "true"),
         },
-        "copy_file" | "cut_file" | "delete_file" | "move_file" | "rename_file" => {
-            Action::FileOperation {
-                operation: nlp_result.intent.clone(),
-            }
-        },
-        "paste_files" => Action::PasteFiles {
+    },
+    IntentSpec {
+        intent: "copy_file",
+        action: "FileOperation",
+        params: &[],
+        build: build_file_operation,
+    },
+    IntentSpec {
+        intent: "cut_file",
+        action: "FileOperation",
+        params: &[],
+        build: build_file_operation,
+    },
+    IntentSpec {
+        intent: "delete_file",
+        action: "FileOperation",
+        params: &[],
+        build: build_file_operation,
+    },
+    IntentSpec {
+        intent: "move_file",
+        action: "FileOperation",
+        params: &[],
+        build: build_file_operation,
+    },
+    IntentSpec {
+        intent: "rename_file",
+        action: "FileOperation",
+        params: &[],
+        build: build_file_operation,
+    },
+    IntentSpec {
+        intent: "paste_files",
+        action: "PasteFiles",
+        params: &[optional("destination", "string"), optional("mode", "string")],
+        build: |nlp_result| Action::PasteFiles {
             destination: nlp_result.parameters.get("destination").cloned().unwrap_or_default(),
+            mode: match nlp_result.parameters.get("mode").map(String::as_str) {
+                Some("move") => PasteMode::Move,
+                _ => PasteMode::Copy,
+            },
         },
-        "create_directory" => Action::CreateDirectory {
-            name: nlp_result.parameters.get("name").cloned().unwrap_or_default(),
-        },
-        "delete_directory" => Action::DeleteDirectory {
+    },
+    IntentSpec {
+        intent: "create_directory",
+        action: "CreateDirectory",
+        params: &[optional("name", "string")],
+        build: |nlp_result| Action::CreateDirectory {
             name: nlp_result.parameters.get("name").cloned().unwrap_or_default(),
         },
-        "create_file" => Action::CreateFile {
+    },
+    IntentSpec {
+        intent: "delete_directory",
+        action: "DeleteDirectory",
+        params: &[optional("name", "string")],
+        build: |nlp_result| Action::DeleteDirectory {
             name: nlp_result.parameters.get("name").cloned().unwrap_or_default(),
         },
-        "delete_file" => Action::DeleteFile {
+    },
+    IntentSpec {
+        intent: "create_file",
+        action: "CreateFile",
+        params: &[optional("name", "string")],
+        build: |nlp_result| Action::CreateFile {
             name: nlp_result.parameters.get("name").cloned().unwrap_or_default(),
         },
-        "multi_step" => {
+    },
+    IntentSpec {
+        intent: "multi_step",
+        action: "MultiStep",
+        params: &[],
+        build: |_| {
             // This should be handled by an alias.
-            Action::MultiStep { steps: vec![] }
-        }
+            Action::MultiStep { steps: vec![], continue_on_error: false }
+        },
+    },
+];
+
+/// Builds the action for the `launch_application` intent.
+fn build_launch_application(nlp_result: &NLPResult) -> Action {
+    Action::LaunchApplication {
+        app: nlp_result.parameters.get("object")
+            .or_else(|| nlp_result.parameters.get("app"))
+            .cloned()
+            .unwrap_or_default(),
+    }
+}
+
+/// Builds the action for `launch_object`: links, folders and files that exist are opened with
+/// `Open` of the detected kind, anything else is launched as an application.
+fn build_launch_object(nlp_result: &NLPResult) -> Action {
+    let target = nlp_result.parameters.get("object")
+        .or_else(|| nlp_result.parameters.get("app"))
+        .cloned()
+        .unwrap_or_default();
+    match OpenKind::detect(&target) {
+        OpenKind::App => Action::LaunchApplication { app: target },
+        kind => Action::Open { target, kind },
+    }
+}
+
+/// Builds the action shared by the `open_url`, `open_folder`, `open_file` intents.
+/// `open_file` also accepts the `file` parameter it took before it opened files directly.
+fn build_open(nlp_result: &NLPResult, kind: OpenKind) -> Action {
+    Action::Open {
+        target: nlp_result.parameters.get("target")
+            .or_else(|| nlp_result.parameters.get("file"))
+            .cloned()
+            .unwrap_or_default(),
+        kind,
+    }
+}
+
+/// Builds the action shared by the `focus_object`, `focus_application` intents.
+fn build_focus_application(nlp_result: &NLPResult) -> Action {
+    Action::FocusApplication {
+        app: nlp_result.parameters.get("object")
+            .or_else(|| nlp_result.parameters.get("app"))
+            .cloned()
+            .unwrap_or_default(),
+    }
+}
+
+/// Builds the action shared by the `copy_file`, `cut_file`, `delete_file`, `move_file`, `rename_file` intents.
+fn build_file_operation(nlp_result: &NLPResult) -> Action {
+    Action::FileOperation {
+        operation: nlp_result.intent.clone(),
+    }
+}
+
+/// Looks up the spec for `intent`.
+pub fn intent_spec(intent: &str) -> Option<&'static IntentSpec> {
+    INTENT_SPECS.iter().find(|spec| spec.intent == intent)
+}
+
+/// Internal implementation of intent mapping based on the NLP result.
+/// If the intent is not recognized, returns an Unknown action with a hint message based on language settings.
+fn map_intent_impl(nlp_result: &NLPResult) -> Action {
+    match intent_spec(&nlp_result.intent) {
+        Some(spec) => (spec.build)(nlp_result),
         // Fallback for unknown intent.
-        _ => Action::Unknown {
+        None => Action::Unknown {
             hint: nlp_result.parameters.get("hint").cloned().unwrap_or_else(|| {
                 // Default to a hint message from language messages.
                 // Note: This usage assumes that the language module has already provided a message hint.
@@ -268,4 +1447,161 @@ fn map_intent_impl(nlp_result: &NLPResult) -> Action {
             }),
         },
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AliasConfig;
+    use std::sync::{Arc, Mutex};
+
+    fn nlp_result(intent: &str, parameters: &[(&str, &str)]) -> NLPResult {
+        NLPResult {
+            intent: intent.to_string(),
+            parameters: parameters.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            confidence: 1.0,
+            normalized: String::new(),
+            tokens: Vec::new(),
+        }
+    }
+
+    fn shared(config: AppConfig) -> SharedConfig {
+        Arc::new(Mutex::new(Some(config)))
+    }
+
+    #[test]
+    fn disabled_intent_is_refused_while_others_run() {
+        let config = shared(AppConfig { disabled_intents: vec!["Window_Close".to_string()], ..AppConfig::default() });
+
+        match map_intent(&nlp_result("window_close", &[("label", "Блокнот")]), &config) {
+            Action::Refused { intent, .. } => assert_eq!(intent, "window_close"),
+            other => panic!("expected a refusal, got {:?}", other),
+        }
+        match map_intent(&nlp_result("window_minimize", &[("label", "Блокнот")]), &config) {
+            Action::WindowMinimize { label, .. } => assert_eq!(label, "Блокнот"),
+            other => panic!("expected WindowMinimize, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn alias_to_a_disabled_intent_is_refused() {
+        let alias = AliasConfig {
+            alias: "спрячь".to_string(),
+            intent: "window_close".to_string(),
+            parameters: None,
+            command_type: None,
+            steps: None,
+            continue_on_error: false,
+        };
+        let config = shared(AppConfig {
+            aliases: vec![alias],
+            disabled_intents: vec!["window_close".to_string()],
+            ..AppConfig::default()
+        });
+        assert!(matches!(map_intent(&nlp_result("спрячь", &[]), &config), Action::Refused { .. }));
+    }
+
+    #[test]
+    fn dialog_intents_map_to_their_keys() {
+        let config = shared(AppConfig::default());
+        assert!(matches!(map_intent(&nlp_result("submit_dialog", &[]), &config), Action::SubmitDialog));
+        assert!(matches!(map_intent(&nlp_result("cancel_dialog", &[]), &config), Action::CancelDialog));
+        assert!(matches!(map_intent(&nlp_result("activate_focused", &[]), &config), Action::ActivateFocused));
+    }
+
+    #[test]
+    fn use_mouse_flag_reaches_the_double_click_action() {
+        let config = shared(AppConfig::default());
+        let with_mouse = nlp_result("button_double_click", &[("label", "item"), ("use_mouse", "true")]);
+        assert!(matches!(map_intent(&with_mouse, &config), Action::ButtonDoubleClick { use_mouse: true, .. }));
+        let without = nlp_result("button_double_click", &[("label", "item")]);
+        assert!(matches!(map_intent(&without, &config), Action::ButtonDoubleClick { use_mouse: false, .. }));
+    }
+
+    #[test]
+    fn validation_names_the_missing_parameter() {
+        assert_eq!(validate(&Action::SetFocus { label: " ".to_string() }), Err("missing required parameter 'label'".to_string()));
+        assert_eq!(validate(&Action::SetFocus { label: "Имя".to_string() }), Ok(()));
+        let steps = vec![
+            Action::SetFocus { label: "Имя".to_string() },
+            Action::WaitForAnyWindow { labels: Vec::new(), timeout_ms: 1000 },
+        ];
+        assert_eq!(
+            validate(&Action::MultiStep { steps, continue_on_error: false }),
+            Err("step #1: missing required parameter 'labels'".to_string())
+        );
+    }
+
+    #[test]
+    fn hwnd_parses_in_decimal_and_hex() {
+        let params = |value: &str| HashMap::from([("hwnd".to_string(), value.to_string())]);
+        assert_eq!(parse_hwnd(&params("132456")), Some(132456));
+        assert_eq!(parse_hwnd(&params(" 0x2058C ")), Some(0x2058C));
+        assert_eq!(parse_hwnd(&params("0Xff")), Some(255));
+        assert_eq!(parse_hwnd(&params("окно")), None);
+        assert_eq!(parse_hwnd(&HashMap::new()), None);
+    }
+
+    #[test]
+    fn describe_lists_each_target_of_an_alias_once() {
+        let step = |intent: &str, label: &str| AliasConfig {
+            alias: String::new(),
+            intent: intent.to_string(),
+            parameters: Some(HashMap::from([("label".to_string(), label.to_string())])),
+            command_type: None,
+            steps: None,
+            continue_on_error: false,
+        };
+        let alias = AliasConfig {
+            alias: "подготовь".to_string(),
+            intent: "multi".to_string(),
+            parameters: None,
+            command_type: Some("multi".to_string()),
+            steps: Some(vec![step("set_focus", "Имя"), step("window_close", "Блокнот"), step("set_focus", "Имя")]),
+            continue_on_error: false,
+        };
+        let config = shared(AppConfig { aliases: vec![alias], ..AppConfig::default() });
+
+        let action = map_intent(&nlp_result("подготовь", &[]), &config);
+        assert_eq!(required_targets(&action), vec!["control 'Имя'".to_string(), "window 'Блокнот'".to_string()]);
+        assert!(required_targets(&Action::WindowCloseAll).is_empty());
+    }
+
+    #[test]
+    fn only_reversible_actions_have_an_inverse() {
+        let minimize = Action::WindowMinimize { label: "Блокнот".to_string(), process: None };
+        assert!(matches!(minimize.inverse(), Some(Action::SystemMenu { ref command, .. }) if command == "restore"));
+        let topmost = Action::WindowSetTopmost { label: "Блокнот".to_string(), topmost: true };
+        // Unpinning is only right if the window was not pinned before; the controller checks that.
+        assert!(topmost.inverse().is_none());
+        let scoped = Action::WindowMinimize { label: String::new(), process: Some("notepad.exe".to_string()) };
+        assert!(scoped.inverse().is_none());
+        assert!(Action::DeleteFile { name: "a.txt".to_string() }.inverse().is_none());
+    }
+
+    #[test]
+    fn executables_opened_by_path_need_the_allow_list() {
+        let dir = std::env::temp_dir().join(format!("launch-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (program, script, document) = (dir.join("calc.exe"), dir.join("Run.CMD"), dir.join("notes.txt"));
+        for file in [&program, &script, &document] {
+            std::fs::write(file, b"").unwrap();
+        }
+        let config = shared(AppConfig { allowed_applications: Some(vec!["notepad.exe".to_string()]), ..AppConfig::default() });
+
+        for file in [&program, &script] {
+            let target = file.to_string_lossy().to_string();
+            match map_intent(&nlp_result("launch_object", &[("object", &target)]), &config) {
+                Action::Refused { .. } => {}
+                other => panic!("expected {} to be refused, got {:?}", target, other),
+            }
+        }
+        let target = document.to_string_lossy().to_string();
+        match map_intent(&nlp_result("launch_object", &[("object", &target)]), &config) {
+            Action::Open { kind: OpenKind::File, .. } => {}
+            other => panic!("expected the document to open, got {:?}", other),
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,8 +1,13 @@
 use regex::Regex;
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
-use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use log::{error, info, warn};
+
+use crate::file_trust::check_file_trust;
 
 /// Represents both regular expression patterns and various messages loaded from a language file.
 pub struct Patterns {
@@ -12,11 +17,14 @@ pub struct Patterns {
     pub menu_re: Regex,
     pub navigation_re: Regex,
     pub window_resize_re: Regex,
+    pub window_resize_relative_re: Regex,
     pub window_minimize_re: Regex,
     pub window_maximize_re: Regex,
     pub window_close_re: Regex,
     pub window_move_re: Regex,
+    pub window_move_named_re: Regex,
     pub group_windows_re: Regex,
+    pub arrange_windows_re: Regex,
     pub tabcontrol_re: Regex,
     pub listview_re: Regex,
     pub radio_re: Regex,
@@ -27,6 +35,7 @@ pub struct Patterns {
     pub file_rename_re: Regex,
     pub file_delete_re: Regex,
     pub enter_text_re: Regex,
+    pub type_text_re: Regex,
     pub get_text_re: Regex,
     pub set_text_re: Regex,
     pub select_text_re: Regex,
@@ -35,7 +44,43 @@ pub struct Patterns {
     pub delete_text_re: Regex,
     pub paste_text_re: Regex,
     pub universal_open_re: Regex,
+    pub open_url_re: Regex,
+    pub open_folder_re: Regex,
     pub universal_focus_re: Regex,
+    pub tab_to_re: Regex,
+    pub key_chord_re: Regex,
+    pub hold_key_re: Regex,
+    pub submit_dialog_re: Regex,
+    pub cancel_dialog_re: Regex,
+    pub activate_focused_re: Regex,
+    pub window_activate_re: Regex,
+    pub window_topmost_re: Regex,
+    pub window_hide_re: Regex,
+    pub window_show_re: Regex,
+    pub save_layout_re: Regex,
+    pub restore_layout_re: Regex,
+    pub system_menu_re: Regex,
+    pub wait_for_any_window_re: Regex,
+    pub wait_for_title_re: Regex,
+    pub mouse_double_click_re: Regex,
+    pub click_text_re: Regex,
+    pub mouse_wheel_re: Regex,
+    pub scroll_re: Regex,
+    pub drag_resize_re: Regex,
+    pub drag_re: Regex,
+    pub send_to_back_re: Regex,
+    pub bring_to_front_re: Regex,
+    pub clipboard_image_re: Regex,
+    pub set_clipboard_image_re: Regex,
+    pub set_slider_re: Regex,
+    pub get_checkbox_state_re: Regex,
+    pub get_selected_tab_re: Regex,
+    pub listview_get_items_re: Regex,
+    pub get_selected_text_re: Regex,
+    pub list_windows_re: Regex,
+    pub window_exists_re: Regex,
+    pub get_window_dpi_re: Regex,
+    pub get_pixel_re: Regex,
     // Message strings
     pub msg_hint: String,
     pub msg_action_executed: String,
@@ -45,50 +90,102 @@ pub struct Patterns {
     pub msg_task_failure: String,
     pub msg_execution_result: String,
     pub msg_error: String,
+
+    // Example phrasing per intent, from the optional EXAMPLE_<INTENT> keys
+    pub examples: HashMap<String, String>,
+
+    // Every MSG_<KEY> message by lowercase key, including ones newer than the fields above
+    messages: HashMap<String, String>,
+
+    // Lowercase words dropped before stemming, from the optional STOP_WORDS key
+    pub stop_words: Vec<String>,
+}
+
+/// Stop words for language files without a `STOP_WORDS` key; the patterns of `ru.lng` assume them.
+const DEFAULT_STOP_WORDS: &str = "и в на с к по за для также не но а то же";
+
+/// Pattern compiled in place of an empty regex string. An empty regex matches
+/// every command, so a language file that blanks a key would otherwise route
+/// everything to that intent; this class matches nothing, not even an empty command.
+const NEVER_MATCH_RE: &str = r"[^\s\S]";
+
+/// Compiles the pattern of a language file key, treating an empty pattern as never matching.
+fn compile_pattern(key: &str, pattern: &str) -> Result<Regex, String> {
+    let pattern = match pattern.trim() {
+        "" => NEVER_MATCH_RE,
+        pattern => pattern,
+    };
+    Regex::new(pattern).map_err(|e| format!("Error compiling regex for key '{}': {}", key, e))
 }
 
 impl Patterns {
     /// Loads regex patterns and messages from a specified language file.
     ///
-    /// The function ensures that the language file is located within the trusted "lang"
-    /// directory in the project root and checks file permissions to mitigate path injection vulnerabilities.
+    /// The function ensures that the language file is located within one of the trusted
+    /// language directories (see [`language_dir_candidates`]) and checks file permissions
+    /// to mitigate path injection vulnerabilities.
     pub fn new(lang_file: &str) -> Result<Self, String> {
-        // Define the trusted base directory for language files.
-        let base_dir = std::env::current_dir()
-            .map_err(|e| format!("Failed to retrieve current directory: {}", e))?
-            .join("lang")
-            .canonicalize()
-            .map_err(|e| format!("Failed to canonicalize language base directory: {}", e))?;
+        // Define the trusted base directories for language files.
+        let base_dirs: Vec<PathBuf> = language_dir_candidates()
+            .into_iter()
+            .filter_map(|dir| dir.canonicalize().ok())
+            .collect();
+        if base_dirs.is_empty() {
+            return Err("No language directory found".to_string());
+        }
         
         // Canonicalize the provided language file path.
         let input_path = Path::new(lang_file)
             .canonicalize()
             .map_err(|e| format!("Failed to canonicalize language file path '{}': {}", lang_file, e))?;
         
-        // Ensure that the language file is within the trusted base directory.
-        if !input_path.starts_with(&base_dir) {
+        // Ensure that the language file is within a trusted base directory.
+        if !base_dirs.iter().any(|base_dir| input_path.starts_with(base_dir)) {
             return Err(format!(
-                "Path injection detected: '{}' is not within trusted base '{}'",
-                input_path.display(),
-                base_dir.display()
+                "Path injection detected: '{}' is not within a trusted language directory",
+                input_path.display()
             ));
         }
         
-        // Check file permissions: ensure that the file is not writable by group or others.
-        let metadata = fs::metadata(&input_path)
-            .map_err(|e| format!("Unable to read metadata for '{}': {}", input_path.display(), e))?;
-        let mode = metadata.permissions().mode();
-        if mode & 0o022 != 0 {
-            return Err(format!(
-                "Language file '{}' is writable by group or others (mode {:o}). Please secure the file.",
-                input_path.display(), mode
-            ));
-        }
+        // Check file permissions: ensure that the file is not writable by unprivileged users.
+        check_file_trust(&input_path)?;
         
         // Read the file contents.
         let contents = fs::read_to_string(&input_path)
             .map_err(|e| format!("Error reading language file '{}': {}", input_path.display(), e))?;
         
+        Self::from_contents(&contents)
+    }
+
+    /// The `MSG_<KEY>` message for `key` (e.g. "task_not_found"), or `fallback` when the language
+    /// file does not define it. Each `{}` in the text takes the next of `args`; arguments left over
+    /// are appended after a colon, so "Результат выполнения" still shows the result.
+    pub fn message(&self, key: &str, fallback: &str, args: &[&dyn fmt::Display]) -> String {
+        let template = self.messages.get(&key.to_lowercase()).map_or(fallback, String::as_str);
+        let mut args = args.iter();
+        let mut pieces = template.split("{}");
+        let mut text = pieces.next().unwrap_or_default().to_string();
+        for piece in pieces {
+            if let Some(arg) = args.next() {
+                text.push_str(&arg.to_string());
+            }
+            text.push_str(piece);
+        }
+        let rest: Vec<String> = args.map(|arg| arg.to_string()).collect();
+        if !rest.is_empty() {
+            text = format!("{}: {}", text, rest.join(", "));
+        }
+        text
+    }
+
+    /// Patterns compiled from the Russian language file embedded at build time.
+    /// Used when no language file can be found or loaded, so the server still starts.
+    pub fn builtin() -> Self {
+        Self::from_contents(EMBEDDED_LANGUAGE).expect("Embedded language file must be valid")
+    }
+
+    /// Parses the `KEY = value` lines of a language file and compiles the patterns.
+    fn from_contents(contents: &str) -> Result<Self, String> {
         // Parse the file lines into a map.
         let mut map = HashMap::new();
         for line in contents.lines() {
@@ -106,11 +203,19 @@ impl Patterns {
         // Helper macro to compile a regex for a given key.
         macro_rules! get_regex {
             ($key:expr) => {
-                Regex::new(
+                compile_pattern(
+                    $key,
                     map.get($key)
-                        .ok_or_else(|| format!("Missing regex for key '{}'", $key))?
-                )
-                .map_err(|e| format!("Error compiling regex for key '{}': {}", $key, e))?
+                        .ok_or_else(|| format!("Missing regex for key '{}'", $key))?,
+                )?
+            };
+        }
+
+        // Helper macro to compile a regex for a key newer than the shipped language files,
+        // falling back to a built-in pattern when the file doesn't define it.
+        macro_rules! get_regex_or {
+            ($key:expr, $default:expr) => {
+                compile_pattern($key, map.get($key).map(|s| s.as_str()).unwrap_or($default))?
             };
         }
 
@@ -129,11 +234,14 @@ impl Patterns {
             menu_re: get_regex!("MENU_RE"),
             navigation_re: get_regex!("NAVIGATION_RE"),
             window_resize_re: get_regex!("WINDOW_RESIZE_RE"),
+            window_resize_relative_re: get_regex_or!("WINDOW_RESIZE_RELATIVE_RE", r"(\d+)\s+(?:(?:пиксел|точ(?:е)?к|px|pixel)\w*\s+)?(шир|уж|выш|ниж|wider|narrower|taller|shorter)\w*"),
             window_minimize_re: get_regex!("WINDOW_MINIMIZE_RE"),
             window_maximize_re: get_regex!("WINDOW_MAXIMIZE_RE"),
             window_close_re: get_regex!("WINDOW_CLOSE_RE"),
             window_move_re: get_regex!("WINDOW_MOVE_RE"),
+            window_move_named_re: get_regex_or!("WINDOW_MOVE_NAMED_RE", r"(?:перемест|передвин|сдвин|move)\w*\s+(?:\w+\s+)*?(?:уг[оа]?л|центр|середин|corner|cent(?:er|re)|middle)\w*"),
             group_windows_re: get_regex!("GROUP_WINDOWS_RE"),
+            arrange_windows_re: get_regex_or!("ARRANGE_WINDOWS_RE", r"(?:располож|расстав|arrange|cascade|tile|snap)\w*\s+(?:\w+\s+)*?(?:окн|window)\w*"),
             tabcontrol_re: get_regex!("TABCONTROL_RE"),
             listview_re: get_regex!("LISTVIEW_RE"),
             radio_re: get_regex!("RADIO_RE"),
@@ -144,6 +252,7 @@ impl Patterns {
             file_rename_re: get_regex!("FILE_RENAME_RE"),
             file_delete_re: get_regex!("FILE_DELETE_RE"),
             enter_text_re: get_regex!("ENTER_TEXT_RE"),
+            type_text_re: get_regex_or!("TYPE_TEXT_RE", r"(?:напечата\w*|набер\w*|набира\w*|type)\b"),
             get_text_re: get_regex!("GET_TEXT_RE"),
             set_text_re: get_regex!("SET_TEXT_RE"),
             select_text_re: get_regex!("SELECT_TEXT_RE"),
@@ -152,7 +261,43 @@ impl Patterns {
             delete_text_re: get_regex!("DELETE_TEXT_RE"),
             paste_text_re: get_regex!("PASTE_TEXT_RE"),
             universal_open_re: get_regex!("UNIVERSAL_OPEN_RE"),
+            open_url_re: get_regex_or!("OPEN_URL_RE", r"^(?:откр|перейд|зайд|запуст|open|go|visit)\w*"),
+            open_folder_re: get_regex_or!("OPEN_FOLDER_RE", r"(?:откр\w*|open)\s+(?:\w+\s+)*?(?:папк|каталог|директор|folder|director)\w*"),
             universal_focus_re: get_regex!("UNIVERSAL_FOCUS_RE"),
+            submit_dialog_re: get_regex_or!("SUBMIT_DIALOG_RE", r"^(?:нажм\w*\s+|press\s+)?(?:ok|ок|enter|ввод|подтверд\w*|submit)$"),
+            cancel_dialog_re: get_regex_or!("CANCEL_DIALOG_RE", r"^(?:нажм\w*\s+|press\s+)?(?:отм\w*|cancel|escape|esc|эскейп)$"),
+            wait_for_title_re: get_regex_or!("WAIT_FOR_TITLE_RE", r"(?:(?:дожд|подожд|жд)\w*\s+(?:\w+\s+)*?заголов\w*|wait\s+for\s+(?:the\s+)?title)"),
+            wait_for_any_window_re: get_regex_or!("WAIT_FOR_ANY_WINDOW_RE", r"(?:(?:дожд|подожд|жд)\w*\s+(?:появл\w*\s+)?(?:люб\w*\s+)?(?:из\s+)?ок(?:о)?н\w*|wait\s+for\s+(?:any\s+)?(?:of\s+)?(?:the\s+)?windows?)"),
+            system_menu_re: get_regex_or!("SYSTEM_MENU_RE", r"(?:системн\w*\s+мен\w*|system\s+menu)\s+(?:\w+\s+)*?(восстанов|сверн|разверн|закр|перемест|размер|restore|minimi[sz]e|maximi[sz]e|close|move|size)"),
+            save_layout_re: get_regex_or!("SAVE_LAYOUT_RE", r"(?:сохран\w*|запомн\w*|save)\s+(?:\w+\s+)*?(?:раскладк\w*|расположен\w*|layout)"),
+            restore_layout_re: get_regex_or!("RESTORE_LAYOUT_RE", r"(?:восстанов\w*|верн\w*|restore)\s+(?:\w+\s+)*?(?:раскладк\w*|расположен\w*|layout)"),
+            window_topmost_re: get_regex_or!("WINDOW_TOPMOST_RE", r"(?:(закреп|откреп)\w*\s+(?:\w+\s+)*?(?:поверх|окн|окон)\w*|(un)?pin\s+(?:the\s+)?window|(?:keep\s+)?window\s+(?:\S+\s+)*?on\s+top)"),
+            window_hide_re: get_regex_or!("WINDOW_HIDE_RE", r"(?:спряч\w*|скр(?:о\w*|ы\w*)?|hide)\s+(?:\w+\s+)*?(?:окн|window)\w*"),
+            window_show_re: get_regex_or!("WINDOW_SHOW_RE", r"(?:(?:покаж|верн|восстанов|отобраз)\w*\s+(?:\w+\s+)*?скрыт\w*\s+(?:\w+\s+)*?окн\w*|(?:show|unhide)\s+(?:the\s+)?(?:hidden\s+)?window)"),
+            window_activate_re: get_regex_or!("WINDOW_ACTIVATE_RE", r"(?:активир\w*\s+окн\w*|activate\s+(?:the\s+)?window|bring\s+(?:the\s+)?window\s+forward)"),
+            activate_focused_re: get_regex_or!("ACTIVATE_FOCUSED_RE", r"^(?:нажм\w*\s+|press\s+)?(?:пробел|space|activate|актив\w*)$"),
+            mouse_double_click_re: get_regex_or!("MOUSE_DOUBLE_CLICK_RE", r"(?:двойн\w*\s+клик\w*|double\s+click)\s+(\d+)\s+(\d+)"),
+            click_text_re: get_regex_or!("CLICK_TEXT_RE", r"(?:(?:клик|нажм|щ[её]лкн)\w*\s+(?:\w+\s+)*?текст\w*|click\s+(?:on\s+)?text)"),
+            mouse_wheel_re: get_regex_or!("MOUSE_WHEEL_RE", r"(?:колес\w*|wheel)\s+(вверх|вниз|up|down)(?:\s+(\d+))?"),
+            tab_to_re: get_regex_or!("TAB_TO_RE", r"\b(shift\s+)?(?:tab|таб)\b\s*(вперед|вперёд|назад|forward|backward|back)?\s*(\d+)?"),
+            key_chord_re: get_regex_or!("KEY_CHORD_RE", r"(?:нажм\w*|press)\s+((?:(?:ctrl|control|ктрл|alt|альт|shift|шифт|win|вин)\s*\+\s*)+[^\s+]+)"),
+            scroll_re: get_regex_or!("SCROLL_RE", r"(?:прокрут\w*|пролист\w*|scroll)\s+(?:\w+\s+)*?(нача\w*|конец|конц\w*|top|bottom|страниц\w*\s+(?:вверх|вниз)|page\s+(?:up|down)|вверх|вниз|up|down)(?:\s+(\d+))?"),
+            hold_key_re: get_regex_or!("HOLD_KEY_RE", r"(?:удерж\w*|зажм\w*|hold)\s+(?:клавиш\w*\s+|key\s+)?(.+?)\s+(?:на|for)\s+(\d+)\s*(мс|ms|миллисекунд\w*|milliseconds?|с|s|сек\w*|sec\w*)?\s*$"),
+            drag_resize_re: get_regex_or!("DRAG_RESIZE_RE", r"(растян|расшир|увелич|сожм|суз|уменьш|grow|extend|shrink)\w*\s+(?:\w+\s+)*?(лев|прав|верхн|нижн|left|right|top|bottom)\w*\s+(?:кра|границ|edge|border)\w*\D*?(\d+)"),
+            drag_re: get_regex_or!("DRAG_RE", r#"(?i)(?:перетащ\w*|перетян\w*|перенес\w*|drag)\s+(?:\S+\s+)*?"([^"]+)"\s+(?:в|во|на|к|to|onto|into)\s+(?:\S+\s+)*?"([^"]+)""#),
+            send_to_back_re: get_regex_or!("SEND_TO_BACK_RE", r"(?:задн\w*\s+план\w*|под\s+остальн\w*|send\s+(?:\S+\s+)*?to\s+back|behind\s+(?:the\s+)?others)"),
+            bring_to_front_re: get_regex_or!("BRING_TO_FRONT_RE", r"(?:передн\w*\s+план\w*|поверх\s+остальн\w*|bring\s+(?:\S+\s+)*?to\s+front)"),
+            clipboard_image_re: get_regex_or!("CLIPBOARD_IMAGE_RE", r"(?:сохран\w*|get|save)\s+(?:изображ\w*|картинк\w*|image|picture)\s+(?:из\s+буфер\w*|from\s+(?:the\s+)?clipboard)"),
+            set_clipboard_image_re: get_regex_or!("SET_CLIPBOARD_IMAGE_RE", r"(?:скопир\w*|помест\w*|copy|put)\s+(?:изображ\w*|картинк\w*|image|picture)\s+.*(?:буфер\w*|to\s+(?:the\s+)?clipboard)"),
+            set_slider_re: get_regex_or!("SET_SLIDER_RE", r"(?:слайдер\w*|ползун\w*|slider)\D*?(\d+)"),
+            get_checkbox_state_re: get_regex_or!("GET_CHECKBOX_STATE_RE", r"(?:состоян\w*\s+(?:чекбокс\w*|флажк\w*)|(?:отмеч\w*|установл\w*)\s+ли|is\s+(?:the\s+)?checkbox\s+checked|checkbox\s+state)"),
+            listview_get_items_re: get_regex_or!("LISTVIEW_GET_ITEMS_RE", r"(?:(?:покаж|прочита|прочт|перечисл|получ|вывед|вывест)\w*\s+(?:\w+\s+)*?(?:элемент|строк|содержим)\w*\s+(?:\w+\s+)*?списк\w*|list\s*view\s+items|list\s+items)"),
+            get_selected_tab_re: get_regex_or!("GET_SELECTED_TAB_RE", r"(?:как\w*\s+вкладк\w*\s+(?:выбра\w*|открыт\w*)|текущ\w*\s+вкладк\w*|selected\s+tab|current\s+tab)"),
+            get_selected_text_re: get_regex_or!("GET_SELECTED_TEXT_RE", r"(?:(?:выделен\w*|выбра\w*)\s+текст\w*|selected\s+text|selection\s+text)"),
+            get_window_dpi_re: get_regex_or!("GET_WINDOW_DPI_RE", r"(?:\bdpi\b|масштаб\w*\s+(?:\w+\s+)*?окн\w*|scale\s+factor|display\s+scal\w*)"),
+            get_pixel_re: get_regex_or!("GET_PIXEL_RE", r"(?:цвет\w*\s+(?:\w+\s+)*?(?:пиксел|точк)\w*|pixel\s+colou?r|get\s+pixel)\s+(\d+)\s+(\d+)"),
+            window_exists_re: get_regex_or!("WINDOW_EXISTS_RE", r"(?:(?:существ\w*|ест\w*|открыт\w*)\s+ли\s+окн\w*|(?:does|is)\s+(?:the\s+)?window\s+(?:\S+\s+)*?(?:exist|open)\w*)"),
+            list_windows_re: get_regex_or!("LIST_WINDOWS_RE", r"(?:(?:список|покаж\w*|перечисл\w*)\s+(?:все\w*\s+|открыт\w*\s+)*ок(?:о)?н\w*|list\s+(?:all\s+|open\s+)*windows)"),
             // Messages
             msg_hint: get_msg!("MSG_HINT"),
             msg_action_executed: get_msg!("MSG_ACTION_EXECUTED"),
@@ -162,16 +307,238 @@ impl Patterns {
             msg_task_failure: get_msg!("MSG_TASK_FAILURE"),
             msg_execution_result: get_msg!("MSG_EXECUTION_RESULT"),
             msg_error: get_msg!("MSG_ERROR"),
+            examples: map
+                .iter()
+                .filter_map(|(key, value)| key.strip_prefix("EXAMPLE_").map(|intent| (intent.to_lowercase(), value.clone())))
+                .collect(),
+            messages: map
+                .iter()
+                .filter_map(|(key, value)| key.strip_prefix("MSG_").map(|name| (name.to_lowercase(), value.clone())))
+                .collect(),
+            stop_words: map
+                .get("STOP_WORDS")
+                .map_or(DEFAULT_STOP_WORDS, |words| words.as_str())
+                .split_whitespace()
+                .map(str::to_lowercase)
+                .collect(),
         })
     }
 }
 
+/// The default language file, compiled in as the last-resort fallback.
+const EMBEDDED_LANGUAGE: &str = include_str!("../lang/ru.lng");
+
+lazy_static::lazy_static! {
+    // Directory set from the `language_dir` config option; searched before the built-in locations.
+    static ref CONFIGURED_LANGUAGE_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+}
+
+/// Sets the configured language directory. Call before the first use of [`current_patterns`].
+pub fn set_language_dir(dir: Option<PathBuf>) {
+    *CONFIGURED_LANGUAGE_DIR.lock().unwrap() = dir;
+}
+
+/// Directories searched for language files, in order: the configured directory,
+/// `lang` next to the executable, and `lang` in the current directory.
+pub fn language_dir_candidates() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(dir) = CONFIGURED_LANGUAGE_DIR.lock().unwrap().clone() {
+        candidates.push(dir);
+    }
+    if let Some(exe_dir) = std::env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf)) {
+        candidates.push(exe_dir.join("lang"));
+    }
+    if let Ok(cwd) = std::env::current_dir() {
+        candidates.push(cwd.join("lang"));
+    }
+    candidates
+}
+
+/// Returns the first `candidate/file_name` that exists.
+pub fn find_language_file(candidates: &[PathBuf], file_name: &str) -> Option<PathBuf> {
+    candidates
+        .iter()
+        .map(|dir| dir.join(file_name))
+        .find(|path| path.is_file())
+}
+
+/// Language whose file backs the default patterns.
+const DEFAULT_LANGUAGE: &str = "ru";
+
+/// Accepts language codes only; anything else could point outside the lang directory.
+fn check_language_code(lang: &str) -> Result<(), String> {
+    if lang.is_empty() || !lang.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        return Err(format!("Invalid language code '{}'", lang));
+    }
+    Ok(())
+}
+
+/// Finds and compiles the language file for `lang`.
+fn load_language_file(lang: &str) -> Result<(Patterns, PathBuf), String> {
+    check_language_code(lang)?;
+    let file_name = format!("{}.lng", lang);
+    let path = find_language_file(&language_dir_candidates(), &file_name)
+        .ok_or_else(|| format!("'{}' not found in any language directory", file_name))?;
+    let patterns = Patterns::new(&path.to_string_lossy())?;
+    Ok((patterns, path))
+}
+
+/// Set while the default patterns are the embedded copy because no language file could be loaded.
+static USING_EMBEDDED_PATTERNS: AtomicBool = AtomicBool::new(false);
+
 lazy_static::lazy_static! {
-    // Load the patterns and messages using the language specified by configuration.
-    // For demonstration, default to Russian ("ru") with language file "lang/ru.lng".
-    pub static ref PATTERNS: Patterns = {
-        let lang = "ru";
-        let lang_file = format!("lang/{}.lng", lang);
-        Patterns::new(&lang_file).expect("Failed to load language regex patterns and messages")
+    // Language set from the `language` config option; its file backs the default patterns.
+    static ref CONFIGURED_LANGUAGE: Mutex<String> = Mutex::new(DEFAULT_LANGUAGE.to_string());
+    // Language the default patterns were loaded for; differs from the configured one after a fallback.
+    static ref PATTERNS_LANGUAGE: Mutex<String> = Mutex::new(DEFAULT_LANGUAGE.to_string());
+}
+
+/// Sets the language whose file backs the default patterns. Call before the first use of [`current_patterns`].
+pub fn set_default_language(lang: &str) {
+    *CONFIGURED_LANGUAGE.lock().unwrap() = lang.to_string();
+}
+
+/// The language the default patterns were loaded for: the configured one, or ru when its file could not be loaded.
+pub fn patterns_language() -> String {
+    lazy_static::initialize(&PATTERNS);
+    PATTERNS_LANGUAGE.lock().unwrap().clone()
+}
+
+lazy_static::lazy_static! {
+    // Load the patterns and messages for the configured language, falling back to Russian ("ru")
+    // and then to the embedded copy of "ru.lng".
+    // Swapped by `reload_patterns`; readers take a cheap `Arc` clone and never hold the lock while matching.
+    static ref PATTERNS: RwLock<Arc<Patterns>> = {
+        let mut lang = CONFIGURED_LANGUAGE.lock().unwrap().clone();
+        let mut loaded = load_language_file(&lang);
+        if let (Err(e), false) = (&loaded, lang == DEFAULT_LANGUAGE) {
+            warn!("Failed to load language '{}', falling back to '{}': {}", lang, DEFAULT_LANGUAGE, e);
+            lang = DEFAULT_LANGUAGE.to_string();
+            loaded = load_language_file(&lang);
+        }
+        let patterns = match loaded {
+            Ok((patterns, _)) => {
+                *PATTERNS_LANGUAGE.lock().unwrap() = lang;
+                patterns
+            }
+            Err(e) => {
+                error!("Failed to load language file, using embedded '{}' patterns: {}", DEFAULT_LANGUAGE, e);
+                USING_EMBEDDED_PATTERNS.store(true, Ordering::SeqCst);
+                Patterns::builtin()
+            }
+        };
+        RwLock::new(Arc::new(patterns))
     };
-}
\ No newline at end of file
+}
+
+/// The default language's patterns, as last loaded.
+pub fn current_patterns() -> Arc<Patterns> {
+    PATTERNS.read().unwrap().clone()
+}
+
+/// Whether the default patterns fell back to the embedded copy instead of a language file on disk.
+pub fn using_embedded_patterns() -> bool {
+    lazy_static::initialize(&PATTERNS);
+    USING_EMBEDDED_PATTERNS.load(Ordering::SeqCst)
+}
+
+/// Reads the language file for `lang` and swaps its patterns in as the default ones, returning the file's path.
+/// On failure the current patterns stay in place. Languages cached by [`load_patterns`] are dropped
+/// so they are re-read on their next use.
+pub fn reload_patterns(lang: &str) -> Result<PathBuf, String> {
+    let (patterns, path) = load_language_file(lang)?;
+    *PATTERNS.write().unwrap() = Arc::new(patterns);
+    *PATTERNS_LANGUAGE.lock().unwrap() = lang.to_string();
+    USING_EMBEDDED_PATTERNS.store(false, Ordering::SeqCst);
+    PATTERNS_CACHE.lock().unwrap().clear();
+    info!("Language patterns reloaded from '{}'", path.display());
+    Ok(path)
+}
+
+lazy_static::lazy_static! {
+    // Patterns for languages requested per command, loaded on first use.
+    static ref PATTERNS_CACHE: Mutex<HashMap<String, Arc<Patterns>>> = Mutex::new(HashMap::new());
+}
+
+/// Returns the patterns for `lang`, loading `{lang}.lng` from the language directories the first time it is requested.
+pub fn load_patterns(lang: &str) -> Result<Arc<Patterns>, String> {
+    check_language_code(lang)?;
+    let mut cache = PATTERNS_CACHE.lock().unwrap();
+    if let Some(patterns) = cache.get(lang) {
+        return Ok(patterns.clone());
+    }
+    let path = find_language_file(&language_dir_candidates(), &format!("{}.lng", lang))
+        .ok_or_else(|| format!("Language file for '{}' not found", lang))?;
+    let patterns = Arc::new(Patterns::new(&path.to_string_lossy())?);
+    cache.insert(lang.to_string(), patterns.clone());
+    Ok(patterns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the system temp dir, removed by the caller.
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lang-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn first_candidate_holding_the_file_wins() {
+        let (empty, first, second) = (temp_dir(), temp_dir(), temp_dir());
+        fs::write(first.join("ru.lng"), "").unwrap();
+        fs::write(second.join("ru.lng"), "").unwrap();
+        let missing = empty.join("missing");
+
+        let candidates = vec![missing, empty.clone(), first.clone(), second.clone()];
+        assert_eq!(find_language_file(&candidates, "ru.lng"), Some(first.join("ru.lng")));
+        assert_eq!(find_language_file(&candidates, "en.lng"), None);
+        assert_eq!(find_language_file(&[], "ru.lng"), None);
+
+        for dir in [empty, first, second] {
+            fs::remove_dir_all(dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn configured_directory_is_searched_first() {
+        let configured = std::env::temp_dir().join("lang-test-configured");
+        set_language_dir(Some(configured.clone()));
+        let candidates = language_dir_candidates();
+        set_language_dir(None);
+
+        assert_eq!(candidates.first(), Some(&configured));
+        assert_eq!(candidates.last(), Some(&std::env::current_dir().unwrap().join("lang")));
+    }
+
+    #[test]
+    fn embedded_patterns_are_valid() {
+        let patterns = Patterns::builtin();
+        assert!(patterns.cancel_dialog_re.is_match("отм"));
+        assert!(patterns.click_re.is_match("нажм кнопк ok"));
+        assert!(!patterns.msg_hint.is_empty());
+    }
+
+    #[test]
+    fn empty_patterns_never_match() {
+        let blanked: String = EMBEDDED_LANGUAGE
+            .lines()
+            .map(|line| match line.split_once('=') {
+                Some((key, _)) if key.trim().ends_with("_RE") => format!("{} =", key.trim()),
+                _ => line.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let patterns = Patterns::from_contents(&blanked).unwrap();
+        for command in ["нажм кнопк ok", "click button ok", ""] {
+            assert!(!patterns.click_re.is_match(command));
+            assert!(!patterns.double_click_re.is_match(command));
+            assert!(!patterns.menu_re.is_match(command));
+            assert!(!patterns.navigation_re.is_match(command));
+            assert!(!patterns.universal_open_re.is_match(command));
+            assert!(!patterns.universal_focus_re.is_match(command));
+        }
+    }
+}
@@ -1,9 +1,15 @@
 mod config;
+mod file_trust;
+mod notification;
 mod language;
 mod intent_mapper;
 mod nlp;
 mod task_scheduler;
 mod winui_controller;
+mod task {
+    pub mod history;
+    pub mod model;
+}
 //mod debug_logger;
 
 pub mod prelude {
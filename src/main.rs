@@ -1,105 +1,709 @@
-use actix_web::{get, put, App, HttpResponse, HttpServer, Responder, web, Result};
-use serde::{Deserialize, Serialize};
+use actix_web::{delete, get, post, put, App, HttpRequest, HttpResponse, HttpServer, Responder, web, Result};
+use serde::Serialize;
 use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::{HashMap, VecDeque};
 use tokio::sync::oneshot; // For task cancellation
+use tokio::sync::broadcast; // For task status events
+use futures_util::{stream, StreamExt};
 use tokio::task::JoinHandle;
 use uuid::Uuid; // For generating unique task IDs
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use actix_web::http::header::ContentType;
 use std::fs;
-use log::{info, error, debug}; // Import logging macros
-use env_logger::Env;
+use log::{info, warn, error, debug}; // Import logging macros
+use regex::Regex;
 
 // Добавьте ваши модули:
 mod config;
+mod file_trust;
+mod notification;
 mod language;
 mod intent_mapper;
 mod nlp;
 mod task_scheduler;
 mod winui_controller;
 mod debug_logger;
+mod task {
+    pub mod history;
+    pub mod model;
+}
+mod webapi {
+    pub mod models;
+}
 
-use crate::config::{AppConfig, SharedConfig, init_shared_config};
-use crate::nlp::parse_command;
-use crate::intent_mapper::map_intent;
-use crate::winui_controller::execute_action;
+use crate::config::{AliasConfig, AppConfig, ApiKeyRejection, SharedConfig, init_shared_config};
+use crate::config::{OPERATION_EDIT_ALIASES, OPERATION_EDIT_SETTINGS, OPERATION_STOP_TASK, OPERATION_UNDO};
+use crate::nlp::{normalize_command, parse_command, parse_command_with, pattern_captures, NLPResult};
+use crate::intent_mapper::{is_blocked_in_safe_mode, is_known_intent, map_intent, required_targets, validate, Action, INTENT_SPECS, SAFE_MODE_MESSAGE};
+use crate::winui_controller::{execute_action_timed, undo_last, ExecutionResult};
 use crate::task_scheduler::{Task, TaskScheduler};
-use crate::language::PATTERNS; // Import PATTERNS
-
-// Task structure (replace with your actual Task structure)
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct TaskInfo {
-    id: Uuid, // Уникальный идентификатор задачи
-    name: String,
-    status: String, // e.g., "queued", "running", "completed", "error"
-    // Optional: Add more fields to describe the task
-}
+use crate::debug_logger::PipelineTrace;
+use crate::language::{current_patterns, load_patterns, reload_patterns, using_embedded_patterns};
+use crate::task::history::TaskHistory;
+use crate::task::model::{TaskInfo, TaskStatus};
+use crate::webapi::models::{CapabilitiesResponse, ConfirmationResponse, DescribeQuery, DescribeResponse, DryRunResponse};
+use crate::webapi::models::{ErrorCode, ErrorResponse, ExecuteBatchRequest, ExecuteCommandRequest, IntentDescription, IntentParameter};
+use crate::webapi::models::{LangTestRequest, LangTestResponse};
+use crate::webapi::models::{ReadinessCheck, ReadinessResponse, ReloadLanguageResponse, SocketCommand, SocketEvent, TaskEvent};
+use crate::webapi::models::{TaskRunningQuery, TaskRunningResponse, WindowExistsQuery, WindowExistsResponse};
 
 // State to hold tasks
 struct AppState {
-    tasks: Arc<Mutex<HashMap<Uuid, (TaskInfo, Option<oneshot::Sender<()>>, Option<JoinHandle<()>>> >>,
+    tasks: Arc<Mutex<HashMap<Uuid, (TaskInfo, Option<oneshot::Sender<()>>, Option<JoinHandle<()>>)>>>,
     config: SharedConfig,  // Shared configuration
     scheduler: Arc<TaskScheduler>,   // Your TaskScheduler
     config_path: String, // Store the config file path
+    pending_confirmations: Arc<Mutex<HashMap<Uuid, (String, Action, Instant)>>>, // Commands awaiting confirmation, and when they were held back
+    task_events: Arc<Mutex<HashMap<Uuid, broadcast::Sender<TaskStatus>>>>, // Status transitions per task, until it finishes
+    command_history: Arc<Mutex<VecDeque<(Uuid, String, Instant)>>>, // Received commands, newest last
+    last_task: Arc<Mutex<Option<Uuid>>>, // Most recently scheduled task, served by /last
+    intent_metrics: Arc<Mutex<HashMap<String, (u64, u64)>>>, // (succeeded, failed) executions per intent
+    counters: Arc<CommandCounters>,
+    idempotency_keys: Arc<Mutex<HashMap<String, (Uuid, String, Instant)>>>, // Idempotency-Key -> task and command scheduled for it, and when
+    coalescing: Arc<Mutex<HashMap<String, (Uuid, Instant, Arc<Mutex<Vec<Action>>>)>>>, // Normalized command -> its queued task, last arrival and actions
+}
+
+/// Counters served by `/metrics`, updated without taking any lock.
+#[derive(Default)]
+struct CommandCounters {
+    commands_received: AtomicU64, // Single and batch commands, throttled ones included
+    tasks_completed: AtomicU64,
+    tasks_failed: AtomicU64,
+    tasks_cancelled: AtomicU64,
+    execution_ms_total: AtomicU64, // Summed over completed and failed tasks
+    parse_ema_us: AtomicU64, // Moving average of parse_command + map_intent time, microseconds
+}
+
+/// Weight of the newest command in the parse-time moving average.
+const PARSE_EMA_ALPHA: f64 = 0.1;
+
+/// A command parsing this many times slower than the average is logged as slow.
+const SLOW_PARSE_FACTOR: f64 = 5.0;
+
+/// Parses faster than this are never reported as slow, however they compare to the average.
+const SLOW_PARSE_MIN: Duration = Duration::from_millis(5);
+
+/// Folds the time `command` took to parse and map into the moving average, and warns when it was far
+/// above it: that usually means a language-file pattern backtracks badly on this input.
+fn record_parse_time(data: &AppState, command: &str, elapsed: Duration) {
+    let sample = elapsed.as_micros() as u64;
+    let average = data.counters.parse_ema_us
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |average| {
+            if average == 0 {
+                Some(sample)
+            } else {
+                Some((average as f64 + PARSE_EMA_ALPHA * (sample as f64 - average as f64)).round() as u64)
+            }
+        })
+        .unwrap_or(0);
+    if average > 0 && elapsed >= SLOW_PARSE_MIN && sample as f64 > average as f64 * SLOW_PARSE_FACTOR {
+        warn!("Slow command parse: {} us against an average of {} us for '{}'", sample, average, command);
+    }
+}
+
+/// A command received by the server, as served by `GET /history`.
+#[derive(Debug, Serialize)]
+struct CommandHistoryEntry {
+    id: Uuid, // Также id задачи команды, если она была запланирована
+    command: String,
+    received_secs_ago: f64,
+}
+
+/// Capacity of each task's status channel; a task has only a handful of transitions.
+const TASK_EVENTS_CAPACITY: usize = 16;
+
+/// How long a client is told to wait before retrying when the task queue is full.
+const QUEUE_FULL_RETRY_AFTER_SECS: u64 = 5;
+
+/// Most runs a command's action may get, however many a request or the configuration asks for.
+const MAX_TASK_ATTEMPTS: u32 = 10;
+
+/// Runs of a command's action in all: the `attempts` the request asked for, else the configured
+/// `task_max_attempts`, kept between 1 and `MAX_TASK_ATTEMPTS`.
+fn task_attempts(requested: Option<u32>, configured: u32) -> u32 {
+    requested.unwrap_or(configured).clamp(1, MAX_TASK_ATTEMPTS)
+}
+
+/// Header carrying the caller's API key.
+const API_KEY_HEADER: &str = "X-Api-Key";
+
+/// Reads the API key sent with the request, if any.
+fn api_key(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// A user-facing message in the server's language: the `MSG_<KEY>` entry of the language file,
+/// or the English `fallback` when the file has none. See [`crate::language::Patterns::message`].
+fn localized(key: &str, fallback: &str, args: &[&dyn std::fmt::Display]) -> String {
+    current_patterns().message(key, fallback, args)
+}
+
+/// Header a client sets so that retrying the same command does not run it twice.
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// Reads the idempotency key sent with the request, if any.
+fn idempotency_key(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Returns the task id and command already scheduled under `key` within `ttl`.
+/// Expired keys are dropped on the way.
+fn idempotent_task(keys: &mut HashMap<String, (Uuid, String, Instant)>, key: &str, ttl: Duration) -> Option<(Uuid, String)> {
+    keys.retain(|_, (_, _, created)| created.elapsed() < ttl);
+    keys.get(key).map(|(task_id, command, _)| (*task_id, command.clone()))
+}
+
+/// Runs an API key check against the current configuration. Returns the 401/403 response when the key is refused.
+fn check_api_key(config: &SharedConfig, check: impl FnOnce(&AppConfig) -> Result<(), ApiKeyRejection>) -> Option<HttpResponse> {
+    let rejection = config.lock().unwrap().as_ref().map_or(Ok(()), check).err()?;
+    Some(api_key_rejected(rejection))
+}
+
+/// Builds the 401/403 response for a request refused by the API key check.
+fn api_key_rejected(rejection: ApiKeyRejection) -> HttpResponse {
+    match rejection {
+        ApiKeyRejection::Unauthorized => {
+            let message = format!("A valid {} header is required", API_KEY_HEADER);
+            let error_response = ErrorResponse { error_code: ErrorCode::Unauthorized, message };
+            HttpResponse::Unauthorized().json(&error_response)
+        }
+        ApiKeyRejection::IntentNotAllowed(denied) => {
+            info!("API key refused intent '{}'", denied);
+            let message = format!("Intent '{}' is not allowed for this API key", denied);
+            let error_response = ErrorResponse { error_code: ErrorCode::IntentNotAllowed, message };
+            HttpResponse::Forbidden().json(&error_response)
+        }
+    }
+}
+
+/// Why a mapped command cannot run: not recognised, refused by the configuration, or missing parameters.
+fn command_rejection(action: &Action) -> Option<(ErrorCode, String)> {
+    match action {
+        Action::Unknown { hint } => Some((ErrorCode::ParseFailed, hint.clone())),
+        Action::Refused { reason, .. } => Some((ErrorCode::IntentDisabled, reason.clone())),
+        _ => validate(action).err().map(|message| (ErrorCode::InvalidParameters, message)),
+    }
+}
+
+/// Error code for a failure reported by the controller; windows that cannot be found get their own code.
+fn controller_error_code(message: &str) -> ErrorCode {
+    if message.starts_with("Window") && message.contains("not found") {
+        ErrorCode::WindowNotFound
+    } else {
+        ErrorCode::ActionFailed
+    }
+}
+
+/// Response for a failure reported by the controller: 404 when the window is missing, 500 otherwise.
+fn controller_error(message: String) -> HttpResponse {
+    let error_code = controller_error_code(&message);
+    let error_response = ErrorResponse { error_code, message };
+    match error_code {
+        ErrorCode::WindowNotFound => HttpResponse::NotFound().json(&error_response),
+        _ => HttpResponse::InternalServerError().json(&error_response),
+    }
+}
+
+/// Appends a command to the bounded history, dropping the oldest entries beyond `limit`.
+fn push_history(history: &mut VecDeque<(Uuid, String, Instant)>, id: Uuid, command: &str, limit: usize) {
+    history.push_back((id, command.to_string(), Instant::now()));
+    while history.len() > limit {
+        history.pop_front();
+    }
+}
+
+/// Records a received command for `/history`. Returns the entry's id, which the command's task takes if one is scheduled.
+fn record_command(data: &AppState, command: &str) -> Uuid {
+    let limit = data.config.lock().unwrap().as_ref().map_or(100, |cfg| cfg.history_limit);
+    let id = Uuid::new_v4();
+    push_history(&mut data.command_history.lock().unwrap(), id, command, limit);
+    id
+}
+
+/// Rebuilds the task list from the persisted history on startup.
+/// Only finished tasks are restored, and they come back without cancel channels or JoinHandles.
+fn restore_tasks(history: &TaskHistory) -> HashMap<Uuid, (TaskInfo, Option<oneshot::Sender<()>>, Option<JoinHandle<()>>)> {
+    let restored: HashMap<_, _> = history
+        .load_finished()
+        .into_iter()
+        .map(|task_info| (task_info.id, (task_info, None, None)))
+        .collect();
+    info!("Restored {} finished tasks from history", restored.len());
+    restored
 }
 
 // 1. Handler for command processing
 #[get("/")]
-async fn execute_command(data: web::Data<AppState>, query: web::Query<HashMap<String, String>>) -> impl Responder {
-    let command = query.get("query").cloned().unwrap_or_else(|| "help".to_string());
-    info!("Received command: {}", command);
+async fn execute_command(req: HttpRequest, data: web::Data<AppState>, query: web::Query<HashMap<String, String>>) -> impl Responder {
+    let request = ExecuteCommandRequest {
+        query: query.get("query").cloned().unwrap_or_else(|| "help".to_string()),
+        lang: query.get("lang").cloned(),
+        dry_run: query.get("dry_run").is_some_and(|dry_run| dry_run == "true"),
+    };
+    let attempts = match query.get("attempts").map(|attempts| attempts.parse::<u32>()) {
+        Some(Ok(attempts)) => Some(attempts),
+        Some(Err(_)) => {
+            let message = "attempts must be a whole number".to_string();
+            let error_response = ErrorResponse { error_code: ErrorCode::InvalidParameters, message };
+            return HttpResponse::BadRequest().json(&error_response);
+        }
+        None => None,
+    };
+    run_command(&req, &data, &request, attempts)
+}
+
+// Same as `GET /`, but the command comes in a JSON body, so quotes, newlines and Cyrillic need no URL-encoding
+#[post("/")]
+async fn execute_command_body(req: HttpRequest, data: web::Data<AppState>, body: web::Json<ExecuteCommandRequest>) -> impl Responder {
+    run_command(&req, &data, &body, None)
+}
+
+/// Parses `command` with the patterns of `lang`, or the default ones when no language is given.
+fn parse_in_language(command: &str, lang: Option<&str>) -> Result<NLPResult, HttpResponse> {
+    match lang {
+        Some(lang) => match load_patterns(lang) {
+            Ok(patterns) => Ok(parse_command_with(command, &patterns)),
+            Err(e) => {
+                let error_response = ErrorResponse { error_code: ErrorCode::LanguageNotFound, message: e };
+                Err(HttpResponse::BadRequest().json(&error_response))
+            }
+        },
+        None => Ok(parse_command(command)),
+    }
+}
+
+/// Identifies the client for anti-flood accounting by its (proxy-reported) address.
+fn client_address(req: &HttpRequest) -> String {
+    req.connection_info()
+        .realip_remote_addr()
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+/// Header telling the client how many commands it has left in the anti-flood window.
+/// Lowercase, as `HeaderName::from_static` requires.
+const RATE_LIMIT_REMAINING_HEADER: &str = "x-ratelimit-remaining";
+
+lazy_static::lazy_static! {
+    // Recent command times per client address, for the anti-flood sliding window.
+    static ref CLIENT_REQUESTS: Mutex<HashMap<String, VecDeque<Instant>>> = Mutex::new(HashMap::new());
+}
+
+/// Applies the anti-flood limit: at most `antiflood_max_requests` commands per client within
+/// the last `antiflood_window_secs`. Returns how many commands the client has left in the window
+/// (`None` when antiflood is off), or the 429 response when the command must be rejected.
+fn check_antiflood(data: &AppState, client: &str) -> Result<Option<usize>, HttpResponse> {
+    let (antiflood, window_secs, max_requests) = data.config.lock().unwrap()
+        .as_ref()
+        .map_or((false, 5, 1), |cfg| (cfg.antiflood, cfg.antiflood_window_secs, cfg.antiflood_max_requests));
+    if !antiflood {
+        return Ok(None);
+    }
+    let window = Duration::from_secs(window_secs);
+    let max_requests = max_requests.max(1);
+    let now = Instant::now();
+
+    let mut clients = CLIENT_REQUESTS.lock().unwrap();
+    // Forget clients whose whole window has expired so the map does not grow without bound.
+    clients.retain(|_, times| times.back().is_some_and(|last| now.duration_since(*last) < window));
+    let times = clients.entry(client.to_string()).or_default();
+    while times.front().is_some_and(|first| now.duration_since(*first) >= window) {
+        times.pop_front();
+    }
+
+    if times.len() >= max_requests {
+        let retry_after = times.front().map_or(window, |first| window - now.duration_since(*first));
+        let retry_after_secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+        let message = format!(
+            "Too many requests: at most {} commands per {} s. Retry in {} s",
+            max_requests, window_secs, retry_after_secs
+        );
+        let error_response = ErrorResponse { error_code: ErrorCode::RateLimited, message };
+        return Err(HttpResponse::TooManyRequests()
+            .insert_header(("Retry-After", retry_after_secs.to_string()))
+            .insert_header((RATE_LIMIT_REMAINING_HEADER, "0"))
+            .json(&error_response));
+    }
+    times.push_back(now);
+    Ok(Some(max_requests - times.len()))
+}
+
+fn insert_rate_limit_remaining(response: &mut HttpResponse, remaining: usize) {
+    if let Ok(value) = actix_web::http::header::HeaderValue::from_str(&remaining.to_string()) {
+        response.headers_mut().insert(
+            actix_web::http::header::HeaderName::from_static(RATE_LIMIT_REMAINING_HEADER),
+            value,
+        );
+    }
+}
+
+/// Records, parses, maps and schedules a command for `GET /`, `POST /` and `/history/{id}/replay`.
+/// A failed action is run again, up to `attempts` runs in all or the configured `task_max_attempts`.
+/// A `dry_run` request gets the parsed and mapped command back instead of a task.
+/// With antiflood on, a client over its limit gets a 429 and the command is neither recorded nor run.
+/// A repeated `Idempotency-Key` gets the response of the task scheduled the first time instead of running the command again.
+fn run_command(req: &HttpRequest, data: &AppState, request: &ExecuteCommandRequest, attempts: Option<u32>) -> HttpResponse {
+    let client = client_address(req);
+    info!("Received command from {}: {}", client, request.query);
+    data.counters.commands_received.fetch_add(1, Ordering::Relaxed);
 
-    let nlp_result = parse_command(&command);
+    if let Some(key) = idempotency_key(req) {
+        let ttl_secs = data.config.lock().unwrap().as_ref().map_or(300, |cfg| cfg.idempotency_ttl_secs);
+        let scheduled = idempotent_task(&mut data.idempotency_keys.lock().unwrap(), &key, Duration::from_secs(ttl_secs));
+        if let Some((task_id, command)) = scheduled {
+            info!("Idempotency key '{}' already scheduled task {}; not running '{}' again", key, task_id, request.query);
+            return task_scheduled(&command, task_id);
+        }
+    }
+
+    match check_antiflood(data, &client) {
+        Ok(remaining) => {
+            let mut response = dispatch_command(req, data, request, attempts);
+            if let Some(remaining) = remaining {
+                insert_rate_limit_remaining(&mut response, remaining);
+            }
+            response
+        }
+        Err(response) => response,
+    }
+}
+
+/// The part of [`run_command`] after the anti-flood check.
+fn dispatch_command(req: &HttpRequest, data: &AppState, request: &ExecuteCommandRequest, attempts: Option<u32>) -> HttpResponse {
+    let command = &request.query;
+    // The history entry and the task share this id.
+    let task_id = record_command(data, command);
+    // Input goes to the window that was active when the command arrived, not when the task runs.
+    let focus_target = crate::winui_controller::capture_focus_target();
+
+    let parse_started = Instant::now();
+    let nlp_result = match parse_in_language(command, request.lang.as_deref()) {
+        Ok(nlp_result) => nlp_result,
+        Err(response) => return response,
+    };
+    let parse_elapsed = parse_started.elapsed();
     debug!("NLP Result: {:?}", nlp_result);
 
+    let key = api_key(req);
+    if let Some(response) = check_api_key(&data.config, |cfg| cfg.check_api_key(key.as_deref(), &nlp_result.intent)) {
+        return response;
+    }
+
+    let map_started = Instant::now();
     let action = map_intent(&nlp_result, &data.config);
+    record_parse_time(data, command, parse_elapsed + map_started.elapsed());
     debug!("Mapped Action: {:?}", action);
+    let trace = PipelineTrace::new(task_id, command, &nlp_result.normalized, &nlp_result.intent, nlp_result.confidence, format!("{:?}", action));
+
+    if request.dry_run {
+        let dry_run = DryRunResponse {
+            query: command.clone(),
+            intent: nlp_result.intent.clone(),
+            parameters: nlp_result.parameters.clone(),
+            confidence: nlp_result.confidence,
+            action: format!("{:?}", action),
+        };
+        return HttpResponse::Ok().json(&dry_run);
+    }
+
+    if let Some((error_code, message)) = command_rejection(&action) {
+        info!("Command '{}' rejected: {}", command, message);
+        trace.finish(&format!("rejected: {}", message));
+        let error_response = ErrorResponse { error_code, message };
+        return match error_code {
+            ErrorCode::IntentDisabled => HttpResponse::Forbidden().json(&error_response),
+            _ => HttpResponse::BadRequest().json(&error_response),
+        };
+    }
+
+    if let Some(response) = require_confirmation(data, command, &nlp_result, &action) {
+        trace.finish("held for confirmation");
+        return response;
+    }
+
+    if !data.scheduler.admit() {
+        trace.finish("rejected: queue full");
+        return queue_full(&data.scheduler);
+    }
 
-    let task_name = format!("Task: {}", command);
-    let task_id = Uuid::new_v4(); // Generate a unique task ID
+    let coalesce = coalescing(&action);
+    if coalesce.is_some() {
+        if let Some(task_info) = coalesce_command(data, &nlp_result.normalized, &action) {
+            info!("Command '{}' coalesced into queued task {}", command, task_info.id);
+            return task_scheduled(command, task_info.id);
+        }
+    }
+
+    let configured_attempts = data.config.lock().unwrap().as_ref().map_or(1, |cfg| cfg.task_max_attempts);
+    let actions = Arc::new(Mutex::new(vec![action]));
+    let max_attempts = task_attempts(attempts, configured_attempts);
+    schedule_shared_actions(data, task_id, format!("Task: {}", command), vec![nlp_result.intent.clone()], actions.clone(), Some(trace), focus_target, max_attempts);
+    if let Some(key) = idempotency_key(req) {
+        data.idempotency_keys.lock().unwrap().insert(key, (task_id, command.clone(), Instant::now()));
+    }
+    if coalesce.is_some() && coalesce_window(data).is_some() {
+        data.coalescing.lock().unwrap().insert(nlp_result.normalized.clone(), (task_id, Instant::now(), actions));
+    }
+    task_scheduled(command, task_id)
+}
+
+/// How a command is folded into an identical one whose task is still queued.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Coalesce {
+    /// Add the new command's amount to the queued one.
+    AddAmount,
+    /// Drop the new command; running it twice changes nothing.
+    Dedupe,
+}
+
+/// Which commands `coalesce_enable` may merge: scrolling by lines or pages adds up the amounts, while
+/// scrolling to the top or bottom, minimizing, maximizing, activating, pinning and arranging windows
+/// are deduplicated. Every other command runs as many times as it is sent.
+fn coalescing(action: &Action) -> Option<Coalesce> {
+    match action {
+        Action::Scroll { direction, .. } if matches!(direction.as_str(), "top" | "bottom") => Some(Coalesce::Dedupe),
+        Action::Scroll { .. } => Some(Coalesce::AddAmount),
+        Action::WindowMinimize { .. }
+        | Action::WindowMaximize { .. }
+        | Action::WindowActivate { .. }
+        | Action::WindowSetTopmost { .. }
+        | Action::WindowMinimizeAll
+        | Action::WindowMaximizeAll
+        | Action::ArrangeWindows { .. }
+        | Action::WindowMoveNamed { .. } => Some(Coalesce::Dedupe),
+        _ => None,
+    }
+}
+
+/// The `coalesce_window_ms` window, or `None` while coalescing is off.
+fn coalesce_window(data: &AppState) -> Option<Duration> {
+    data.config.lock().unwrap()
+        .as_ref()
+        .filter(|cfg| cfg.coalesce_enable)
+        .map(|cfg| Duration::from_millis(cfg.coalesce_window_ms))
+}
+
+/// Folds `action` into the task of the same normalized command if that arrived within
+/// `coalesce_window_ms` and has not started yet. Returns the task it was folded into.
+fn coalesce_command(data: &AppState, normalized: &str, action: &Action) -> Option<TaskInfo> {
+    let window = coalesce_window(data)?;
+    let mut pending = data.coalescing.lock().unwrap();
+    pending.retain(|_, (_, last_arrival, _)| last_arrival.elapsed() < window);
+    let (task_id, last_arrival, actions) = pending.get_mut(normalized)?;
+
+    // The worker marks the task running under the tasks lock before it reads its actions,
+    // so while this lock is held a queued task still runs whatever is changed here.
+    let tasks_lock = data.tasks.lock().unwrap();
+    let task_info = tasks_lock.get(task_id).map(|(task_info, _, _)| task_info.clone())?;
+    if task_info.status != TaskStatus::Queued {
+        return None;
+    }
+    if coalescing(action) == Some(Coalesce::AddAmount) {
+        let mut actions = actions.lock().unwrap();
+        if let (Some(Action::Scroll { amount, .. }), Action::Scroll { amount: extra, .. }) = (actions.first_mut(), action) {
+            *amount = Some(amount.unwrap_or(1) + extra.unwrap_or(1));
+        }
+    }
+    *last_arrival = Instant::now();
+    Some(task_info)
+}
+
+/// The response for a command whose task was scheduled.
+fn task_scheduled(command: &str, task_id: Uuid) -> HttpResponse {
+    let message = localized("task_queued", "Task '{}' scheduled with id {}.", &[&command, &task_id]);
+    HttpResponse::Ok().content_type(ContentType::plaintext()).body(message)
+}
+
+/// The 503 response for a command refused because `max_queue_depth` tasks are already waiting.
+fn queue_full(scheduler: &TaskScheduler) -> HttpResponse {
+    let message = format!(
+        "Task queue is full ({} tasks waiting). Retry in {} s",
+        scheduler.queue_depth(), QUEUE_FULL_RETRY_AFTER_SECS
+    );
+    let error_response = ErrorResponse { error_code: ErrorCode::QueueFull, message };
+    HttpResponse::ServiceUnavailable()
+        .insert_header(("Retry-After", QUEUE_FULL_RETRY_AFTER_SECS.to_string()))
+        .json(&error_response)
+}
+
+/// How long a confirmation token stays valid; unconfirmed commands are forgotten afterwards.
+const CONFIRMATION_TTL: Duration = Duration::from_secs(300);
+
+/// Returns true if `action`, parsed with `confidence`, must be confirmed before it runs.
+/// Unrecognised commands execute nothing, so there is nothing to confirm.
+fn needs_confirmation(action: &Action, confidence: f32, min_confidence: f32) -> bool {
+    !matches!(action, Action::Unknown { .. }) && confidence < min_confidence
+}
+
+/// Drops confirmations held back for longer than `ttl`.
+fn sweep_confirmations(pending: &mut HashMap<Uuid, (String, Action, Instant)>, ttl: Duration) {
+    pending.retain(|_, (_, _, created)| created.elapsed() < ttl);
+}
+
+/// Holds back commands parsed below `min_confidence` until the client confirms them.
+/// Returns the 202 "did you mean" response carrying the confirmation token when confirmation is needed.
+fn require_confirmation(data: &AppState, command: &str, nlp_result: &NLPResult, action: &Action) -> Option<HttpResponse> {
+    let min_confidence = data.config.lock().unwrap().as_ref().map_or(0.0, |cfg| cfg.min_confidence);
+    if !needs_confirmation(action, nlp_result.confidence, min_confidence) {
+        return None;
+    }
+
+    let token = Uuid::new_v4();
+    info!("Command '{}' parsed with low confidence {:.2}, confirmation token {}", command, nlp_result.confidence, token);
+    let mut pending = data.pending_confirmations.lock().unwrap();
+    sweep_confirmations(&mut pending, CONFIRMATION_TTL);
+    pending.insert(token, (command.to_string(), action.clone(), Instant::now()));
+    drop(pending);
+
+    let confirmation = ConfirmationResponse {
+        message: format!("Did you mean '{}' ({})? Confirm with /confirm={}", command, nlp_result.intent, token),
+        token,
+        intent: nlp_result.intent.clone(),
+        confidence: nlp_result.confidence,
+    };
+    Some(HttpResponse::Accepted().json(&confirmation))
+}
+
+/// Counts one execution of a command parsed as `intent` as succeeded or failed.
+fn record_action_metrics(metrics: &Mutex<HashMap<String, (u64, u64)>>, intent: &str, succeeded: bool) {
+    let mut metrics = metrics.lock().unwrap();
+    let counts = metrics.entry(intent.to_string()).or_insert((0, 0));
+    if succeeded {
+        counts.0 += 1;
+    } else {
+        counts.1 += 1;
+    }
+}
+
+/// Creates the task `task_id` for a mapped action, registers it in the task list and hands it to the scheduler.
+/// A failed action is run again, up to `max_attempts` runs in all; `trace`, if given, is finished by the last attempt.
+fn schedule_action(data: &AppState, task_id: Uuid, command: &str, intent: &str, action: Action, trace: Option<PipelineTrace>, focus_target: Option<isize>, max_attempts: u32) -> TaskInfo {
+    schedule_actions(data, task_id, format!("Task: {}", command), vec![intent.to_string()], vec![action], trace, focus_target, max_attempts)
+}
+
+/// Creates one task that runs `actions` in order and stops on the first failure; a retry starts over from the first action.
+/// `intents` holds the parsed intent of each action, for the per-intent metrics. Returns the task as queued.
+fn schedule_actions(data: &AppState, task_id: Uuid, task_name: String, intents: Vec<String>, actions: Vec<Action>, trace: Option<PipelineTrace>, focus_target: Option<isize>, max_attempts: u32) -> TaskInfo {
+    schedule_shared_actions(data, task_id, task_name, intents, Arc::new(Mutex::new(actions)), trace, focus_target, max_attempts)
+}
+
+/// Like [`schedule_actions`], but the actions stay shared, so coalescing can change them until the task starts.
+fn schedule_shared_actions(data: &AppState, task_id: Uuid, task_name: String, intents: Vec<String>, actions: Arc<Mutex<Vec<Action>>>, trace: Option<PipelineTrace>, focus_target: Option<isize>, max_attempts: u32) -> TaskInfo {
+    let retry_delay = data.config.lock().unwrap().as_ref().map_or(0, |cfg| cfg.task_retry_delay_ms);
 
     // Create a channel for task cancellation
     let (cancel_tx, cancel_rx) = oneshot::channel::<()>();
+    // The scheduler publishes Running and Retrying here; the task publishes how it ended
+    let (events_tx, _) = broadcast::channel::<TaskStatus>(TASK_EVENTS_CAPACITY);
+    data.task_events.lock().unwrap().insert(task_id, events_tx.clone());
 
     let task_action = {
-        let config = data.config.clone();
+        let task_name = task_name.clone(); // The task itself is named below
         let task_id = task_id.clone(); // Capture the task ID
         let tasks_clone = data.tasks.clone(); // Capture the task list
+        let scheduler = data.scheduler.clone(); // For the history log
+        let task_events = data.task_events.clone(); // Closed once the task ends
+        let intent_metrics = data.intent_metrics.clone(); // Counts every execution, retries included
+        let counters = data.counters.clone(); // Counts the task once, by how it ended
+        let mut trace = trace; // Finished by the last attempt
+        let mut attempt = 0;
         move || {
              info!("Executing task: {}", task_name);
-            let action_result = execute_action(&action);
+            attempt += 1;
+            if attempt == 1 {
+                if let Some((task_info, _, _)) = tasks_clone.lock().unwrap().get_mut(&task_id) {
+                    task_info.status = TaskStatus::Running;
+                    scheduler.record(task_info.clone());
+                }
+            }
+            let actions = actions.lock().unwrap().clone();
+            crate::winui_controller::begin_focus_lock(focus_target);
+            let mut step_durations_ms = Vec::new();
+            // Values read by a query action are kept; a later read replaces an earlier one.
+            let action_result = actions.iter().zip(&intents).try_fold(None, |details, (action, intent)| {
+                let execution = execute_action_timed(action);
+                record_action_metrics(&intent_metrics, intent, !matches!(execution.result, ExecutionResult::Failure(_)));
+                step_durations_ms.push(execution.duration_ms);
+                match execution.result {
+                    ExecutionResult::Success(_) => Ok(details),
+                    ExecutionResult::Read(_, read) => Ok(Some(read)),
+                    ExecutionResult::Failure(e) => Err(e),
+                }
+            });
+            crate::winui_controller::end_focus_lock();
+            let duration_ms: u64 = step_durations_ms.iter().sum();
 
             // Log or handle action_result within the task if needed
-             info!("Task completed with result: {:?}", action_result);
+             info!("Task completed in {} ms with result: {:?}", duration_ms, action_result);
+            let mut tasks_lock = tasks_clone.lock().unwrap();
+            if let Err(ref e) = action_result {
+                if attempt < max_attempts {
+                    // The scheduler runs the action again; show which attempt is next.
+                    if let Some((task_info, _, _)) = tasks_lock.get_mut(&task_id) {
+                        task_info.status = TaskStatus::Retrying { attempt: attempt + 1, max: max_attempts, last_error: e.clone() };
+                        scheduler.record(task_info.clone());
+                    }
+                    return Err(e.clone());
+                }
+            }
+            counters.execution_ms_total.fetch_add(duration_ms, Ordering::Relaxed);
+            match action_result {
+                Ok(_) => counters.tasks_completed.fetch_add(1, Ordering::Relaxed),
+                Err(_) => counters.tasks_failed.fetch_add(1, Ordering::Relaxed),
+            };
+            if let Some(trace) = trace.take() {
+                trace.finish(&match &action_result {
+                    Err(e) => format!("failed: {}", e),
+                    Ok(_) => "completed".to_string(),
+                });
+            }
 
             // Update the task status
-            let mut tasks_lock = tasks_clone.lock().unwrap();
             if let Some((task_info, _, _)) = tasks_lock.get_mut(&task_id) {
-                task_info.status = format!("{:?}", action_result); // Update with actual result
+                task_info.duration_ms = Some(duration_ms);
+                // A batch reports each of its commands.
+                if actions.len() > 1 {
+                    task_info.step_durations_ms = step_durations_ms;
+                }
+                task_info.status = match action_result {
+                    Ok(ref details) => {
+                        task_info.details = details.clone();
+                        TaskStatus::Completed
+                    }
+                    Err(ref e) => TaskStatus::Failed(e.clone()),
+                };
+                scheduler.record(task_info.clone());
+                finish_task_events(&task_events, &task_id, task_info.status.clone());
             }
+            action_result.map(|_| ())
         }
     };
 
-    let task = Task::new(&task_name, task_action);
+    let task = Task::retrying(&task_name, max_attempts, Duration::from_millis(retry_delay), task_action)
+        .with_id(task_id)
+        .with_status_events(events_tx);
 
     // Create TaskInfo
-    let task_info = TaskInfo {
-        id: task_id,
-        name: task_name.clone(),
-        status: "queued".to_string(), // Initial status
-    };
+    let task_info = TaskInfo { id: task_id, ..TaskInfo::new(task_name.clone()) };
 
-    // Add task to the list
-    {
-        let mut tasks_lock = data.tasks.lock().unwrap();
-        tasks_lock.insert(task_id, (task_info.clone(), Some(cancel_tx), None));
-    }
+    // Add task to the list; the JoinHandle is added once the task is spawned
+    data.tasks.lock().unwrap().insert(task_id, (task_info.clone(), Some(cancel_tx), None));
+    *data.last_task.lock().unwrap() = Some(task_id);
+    data.scheduler.record(task_info.clone());
 
     // Spawn the task using Tokio
     let scheduler_clone = data.scheduler.clone(); // Clone the scheduler
     let task_id_clone = task_id.clone(); // Clone the task ID for the spawned task
     let tasks_clone_2 = data.tasks.clone(); // Clone task
+    let task_events = data.task_events.clone();
+    let counters = data.counters.clone();
     let handle: JoinHandle<()> = tokio::spawn(async move {
             // Schedule task
             scheduler_clone.schedule(task);
@@ -108,64 +712,780 @@ async fn execute_command(data: web::Data<AppState>, query: web::Query<HashMap<St
             tokio::select! {
                 _ = cancel_rx => {
                     info!("Task {} cancelled.", task_id_clone);
+                    counters.tasks_cancelled.fetch_add(1, Ordering::Relaxed);
                       let mut tasks_lock = tasks_clone_2.lock().unwrap();
                     if let Some((task_info, _, _)) = tasks_lock.get_mut(&task_id_clone) {
-                        task_info.status = "cancelled".to_string(); // Update with actual result
+                        task_info.status = TaskStatus::Cancelled;
+                        scheduler_clone.record(task_info.clone());
                     }
+                    // A stopped task has already left the task list; its event streams still need to end.
+                    finish_task_events(&task_events, &task_id_clone, TaskStatus::Cancelled);
                 }
             }
            
         });
 
      // Update task list with JoinHandle
-        {
-            let mut tasks_lock = data.tasks.lock().unwrap();
-            if let Some((task_info, _, _)) = tasks_lock.get_mut(&task_id) {
-                tasks_lock.insert(task_id, (task_info.clone(), Some(cancel_tx), Some(handle)));
+    if let Some((_, _, join_handle)) = data.tasks.lock().unwrap().get_mut(&task_id) {
+        *join_handle = Some(handle);
+    }
+    task_info
+}
+
+/// Publishes a task's terminal status and drops its channel, which ends any open event streams.
+fn finish_task_events(task_events: &Mutex<HashMap<Uuid, broadcast::Sender<TaskStatus>>>, task_id: &Uuid, status: TaskStatus) {
+    if let Some(events) = task_events.lock().unwrap().remove(task_id) {
+        let _ = events.send(status);
+    }
+}
+
+/// Returns a task's current status and a receiver for its later transitions, or `None` for an
+/// unknown task. The receiver is `None` once the task has finished and its channel is gone.
+fn subscribe_task_events(data: &AppState, id: &Uuid) -> Option<(TaskStatus, Option<broadcast::Receiver<TaskStatus>>)> {
+    // Subscribe under the tasks lock so no transition slips in between reading the status and subscribing.
+    let tasks_lock = data.tasks.lock().unwrap();
+    let current = tasks_lock.get(id).map(|(task_info, _, _)| task_info.status.clone())?;
+    let receiver = data.task_events.lock().unwrap().get(id).map(|events| events.subscribe());
+    Some((current, receiver))
+}
+
+// Handler streaming a task's status changes as Server-Sent Events
+#[get("/tasks/{id}/events")]
+async fn stream_task_events(data: web::Data<AppState>, task_id: web::Path<Uuid>) -> impl Responder {
+    let id = task_id.into_inner();
+    let Some((current, receiver)) = subscribe_task_events(&data, &id) else {
+        let message = localized("task_not_found", "Task with id {} not found", &[&id]);
+        let error_response = ErrorResponse { error_code: ErrorCode::TaskNotFound, message };
+        return HttpResponse::NotFound().json(&error_response);
+    };
+
+    // State: the status to emit next (the current one first), then whatever the channel delivers.
+    let events = stream::unfold((Some(current), receiver, false), move |(pending, mut receiver, done)| async move {
+        if done {
+            return None;
+        }
+        let status = match pending {
+            Some(status) => status,
+            None => loop {
+                match receiver.as_mut()?.recv().await {
+                    Ok(status) => break status,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            },
+        };
+        // Close after the terminal state, or right away for finished tasks without a channel.
+        let done = status.is_terminal() || receiver.is_none();
+        let event = TaskEvent { id, status };
+        let payload = serde_json::to_string(&event).unwrap_or_default();
+        let chunk = web::Bytes::from(format!("data: {}\n\n", payload));
+        Some((Ok::<_, actix_web::Error>(chunk), (None, receiver, done)))
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(events)
+}
+
+/// Events queued for a `/ws` client before reading further frames waits for it to catch up.
+const SOCKET_OUTBOX_CAPACITY: usize = 64;
+
+// Handler for a persistent command channel: JSON command frames in, parse, scheduling,
+// status and result events out on the same socket
+#[get("/ws")]
+async fn command_socket(req: HttpRequest, body: web::Payload, data: web::Data<AppState>) -> Result<HttpResponse> {
+    let (response, session, messages) = actix_ws::handle(&req, body)?;
+    let client = client_address(&req);
+    info!("WebSocket client {} connected", client);
+    actix_web::rt::spawn(serve_command_socket(data, session, messages, api_key(&req), client));
+    Ok(response)
+}
+
+/// Runs one `/ws` connection until the client disconnects. Every event goes through a bounded
+/// outbox, so a client that stops reading stalls its own commands rather than growing a queue.
+async fn serve_command_socket(
+    data: web::Data<AppState>,
+    mut session: actix_ws::Session,
+    mut messages: actix_ws::MessageStream,
+    api_key: Option<String>,
+    client: String,
+) {
+    let (outbox, mut events) = tokio::sync::mpsc::channel::<SocketEvent>(SOCKET_OUTBOX_CAPACITY);
+    let mut writer_session = session.clone();
+    let writer = actix_web::rt::spawn(async move {
+        while let Some(event) = events.recv().await {
+            let payload = serde_json::to_string(&event).unwrap_or_default();
+            if writer_session.text(payload).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut cancel_on_disconnect = Vec::new();
+    while let Some(message) = messages.next().await {
+        match message {
+            Ok(actix_ws::Message::Text(text)) => {
+                let command = match serde_json::from_str::<SocketCommand>(&text) {
+                    Ok(command) => command,
+                    Err(e) => {
+                        let message = format!("Invalid command frame: {}", e);
+                        let rejected = SocketEvent::Rejected { id: None, error_code: ErrorCode::InvalidParameters, message };
+                        if outbox.send(rejected).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                };
+                if let Some(task_info) = socket_command(&data, &command, api_key.as_deref(), &client, &outbox).await {
+                    if command.cancel_on_disconnect {
+                        cancel_on_disconnect.push(task_info.id);
+                    }
+                    actix_web::rt::spawn(forward_task_events(data.clone(), task_info.id, outbox.clone()));
+                }
+            }
+            Ok(actix_ws::Message::Ping(bytes)) => {
+                if session.pong(&bytes).await.is_err() {
+                    break;
+                }
+            }
+            Ok(actix_ws::Message::Close(reason)) => {
+                debug!("WebSocket client {} closed the socket: {:?}", client, reason);
+                break;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                error!("WebSocket protocol error from {}: {}", client, e);
+                break;
+            }
+        }
+    }
+
+    info!("WebSocket client {} disconnected", client);
+    for task_id in cancel_on_disconnect {
+        let pending = data.tasks.lock().unwrap()
+            .get(&task_id)
+            .is_some_and(|(task_info, _, _)| !task_info.status.is_terminal());
+        if pending && cancel_task(&data, &task_id).is_some() {
+            info!("Cancelled task {} of disconnected WebSocket client {}", task_id, client);
+        }
+    }
+    writer.abort();
+    let _ = session.close(None).await;
+}
+
+/// Parses, maps and schedules one `/ws` command, sending `parsed` and then `scheduled` or `rejected`.
+/// Applies the same anti-flood, API key, policy and validation checks as `GET /`; a command that would
+/// need confirmation is rejected, since there is no token to confirm it with on the socket.
+async fn socket_command(
+    data: &AppState,
+    command: &SocketCommand,
+    api_key: Option<&str>,
+    client: &str,
+    outbox: &tokio::sync::mpsc::Sender<SocketEvent>,
+) -> Option<TaskInfo> {
+    let query = &command.query;
+    info!("Received WebSocket command from {}: {}", client, query);
+    data.counters.commands_received.fetch_add(1, Ordering::Relaxed);
+    let reject = |error_code: ErrorCode, message: String| SocketEvent::Rejected { id: command.id.clone(), error_code, message };
+
+    if check_antiflood(data, client).is_err() {
+        let message = "Too many requests, retry later".to_string();
+        let _ = outbox.send(reject(ErrorCode::RateLimited, message)).await;
+        return None;
+    }
+    let task_id = record_command(data, query);
+    let focus_target = crate::winui_controller::capture_focus_target();
+
+    let parse_started = Instant::now();
+    let nlp_result = parse_command(query);
+    let parse_elapsed = parse_started.elapsed();
+    debug!("NLP Result: {:?}", nlp_result);
+    let parsed = SocketEvent::Parsed {
+        id: command.id.clone(),
+        query: query.clone(),
+        intent: nlp_result.intent.clone(),
+        parameters: nlp_result.parameters.clone(),
+        confidence: nlp_result.confidence,
+    };
+    outbox.send(parsed).await.ok()?;
+
+    let (key_check, min_confidence, configured_attempts) = data.config.lock().unwrap()
+        .as_ref()
+        .map_or((Ok(()), 0.0, 1), |cfg| (cfg.check_api_key(api_key, &nlp_result.intent), cfg.min_confidence, cfg.task_max_attempts));
+    let map_started = Instant::now();
+    let action = map_intent(&nlp_result, &data.config);
+    record_parse_time(data, query, parse_elapsed + map_started.elapsed());
+    debug!("Mapped Action: {:?}", action);
+
+    let rejection = match key_check {
+        Err(ApiKeyRejection::Unauthorized) => Some((ErrorCode::Unauthorized, format!("A valid {} header is required", API_KEY_HEADER))),
+        Err(ApiKeyRejection::IntentNotAllowed(denied)) => {
+            Some((ErrorCode::IntentNotAllowed, format!("Intent '{}' is not allowed for this API key", denied)))
+        }
+        Ok(()) => command_rejection(&action).or_else(|| {
+            needs_confirmation(&action, nlp_result.confidence, min_confidence).then(|| (
+                ErrorCode::ParseFailed,
+                format!("Command parsed with low confidence {:.2}; send it to GET / to confirm", nlp_result.confidence),
+            ))
+        }),
+    };
+    if let Some((error_code, message)) = rejection {
+        info!("WebSocket command '{}' rejected: {}", query, message);
+        let _ = outbox.send(reject(error_code, message)).await;
+        return None;
+    }
+
+    if !data.scheduler.admit() {
+        let message = format!("Task queue is full, retry in {} s", QUEUE_FULL_RETRY_AFTER_SECS);
+        let _ = outbox.send(reject(ErrorCode::QueueFull, message)).await;
+        return None;
+    }
+
+    let trace = PipelineTrace::new(task_id, query, &nlp_result.normalized, &nlp_result.intent, nlp_result.confidence, format!("{:?}", action));
+    let max_attempts = task_attempts(None, configured_attempts);
+    let task_info = schedule_action(data, task_id, query, &nlp_result.intent, action, Some(trace), focus_target, max_attempts);
+    outbox.send(SocketEvent::Scheduled { id: command.id.clone(), task: task_info.clone() }).await.ok()?;
+    Some(task_info)
+}
+
+/// Sends a task's status transitions to a `/ws` client, then its final state as a `result` event.
+async fn forward_task_events(data: web::Data<AppState>, task_id: Uuid, outbox: tokio::sync::mpsc::Sender<SocketEvent>) {
+    if let Some((_, Some(mut receiver))) = subscribe_task_events(&data, &task_id) {
+        loop {
+            match receiver.recv().await {
+                Ok(status) => {
+                    let terminal = status.is_terminal();
+                    if outbox.send(SocketEvent::Status { task_id, status }).await.is_err() {
+                        return;
+                    }
+                    if terminal {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
             }
         }
+    }
+    // A cancelled task is gone from the task list, but the scheduler still knows its status.
+    let task_info = data.tasks.lock().unwrap()
+        .get(&task_id)
+        .map(|(task_info, _, _)| task_info.clone())
+        .or_else(|| data.scheduler.status(&task_id));
+    if let Some(task) = task_info {
+        let _ = outbox.send(SocketEvent::Result { task }).await;
+    }
+}
+
+// Handler for a batch of commands sent as a JSON body
+#[post("/execute")]
+async fn execute_batch(req: HttpRequest, data: web::Data<AppState>, body: web::Json<ExecuteBatchRequest>) -> impl Responder {
+    info!("Received batch of {} commands (sequential: {})", body.commands.len(), body.sequential);
+    data.counters.commands_received.fetch_add(body.commands.len() as u64, Ordering::Relaxed);
+    let remaining = match check_antiflood(&data, &client_address(&req)) {
+        Ok(remaining) => remaining,
+        Err(response) => return response,
+    };
+    let focus_target = crate::winui_controller::capture_focus_target();
+    let key = api_key(&req);
+    let (min_confidence, configured_attempts) = data.config.lock().unwrap()
+        .as_ref()
+        .map_or((0.0, 1), |cfg| (cfg.min_confidence, cfg.task_max_attempts));
 
-     HttpResponse::Ok().content_type(ContentType::plaintext()).body(format!("Task '{}' scheduled with id {}.", command, task_id))
+    // Map the whole batch up front so a bad command rejects it before anything runs.
+    let mut actions = Vec::with_capacity(body.commands.len());
+    for (index, command) in body.commands.iter().enumerate() {
+        let parse_started = Instant::now();
+        let nlp_result = parse_command(command);
+        let parse_elapsed = parse_started.elapsed();
+        if let Some(response) = check_api_key(&data.config, |cfg| cfg.check_api_key(key.as_deref(), &nlp_result.intent)) {
+            return response;
+        }
+        let map_started = Instant::now();
+        let action = map_intent(&nlp_result, &data.config);
+        record_parse_time(&data, command, parse_elapsed + map_started.elapsed());
+        debug!("Batch command #{} '{}' mapped to {:?}", index, command, action);
+
+        let rejection = command_rejection(&action).or_else(|| {
+            needs_confirmation(&action, nlp_result.confidence, min_confidence).then(|| (
+                ErrorCode::ParseFailed,
+                format!("Command parsed with low confidence {:.2}; send it alone to confirm", nlp_result.confidence),
+            ))
+        });
+        if let Some((error_code, reason)) = rejection {
+            let message = format!("Command #{} '{}' rejected: {}", index, command, reason);
+            let error_response = ErrorResponse { error_code, message };
+            return HttpResponse::UnprocessableEntity().json(&error_response);
+        }
+        let trace = PipelineTrace::new(Uuid::new_v4(), command, &nlp_result.normalized, &nlp_result.intent, nlp_result.confidence, format!("{:?}", action));
+        actions.push((command.clone(), nlp_result.intent, action, trace));
+    }
+
+    if !data.scheduler.admit() {
+        return queue_full(&data.scheduler);
+    }
+
+    let task_infos: Vec<TaskInfo> = if body.sequential {
+        // Runs once: a retry would repeat the commands that had already succeeded.
+        let task_name = format!("Batch: {}", body.commands.join("; "));
+        let (intents, actions) = actions.into_iter().map(|(_, intent, action, _)| (intent, action)).unzip();
+        vec![schedule_actions(&data, Uuid::new_v4(), task_name, intents, actions, None, focus_target, 1)]
+    } else {
+        let max_attempts = task_attempts(None, configured_attempts);
+        actions
+            .into_iter()
+            .map(|(command, intent, action, trace)| schedule_action(&data, trace.trace_id, &command, &intent, action, Some(trace), focus_target, max_attempts))
+            .collect()
+    };
+    let mut response = HttpResponse::Ok().json(&task_infos);
+    if let Some(remaining) = remaining {
+        insert_rate_limit_remaining(&mut response, remaining);
+    }
+    response
+}
+
+// Handler to confirm a low-confidence command
+#[get("/confirm={token}")]
+async fn confirm_command(req: HttpRequest, data: web::Data<AppState>, token: web::Path<Uuid>) -> impl Responder {
+    let token = token.into_inner();
+    let pending = {
+        let mut pending = data.pending_confirmations.lock().unwrap();
+        sweep_confirmations(&mut pending, CONFIRMATION_TTL);
+        pending.remove(&token)
+    };
+    let Some((command, action, created)) = pending else {
+        let message = format!("Confirmation token {} not found", token);
+        let error_response = ErrorResponse { error_code: ErrorCode::ConfirmationNotFound, message };
+        return HttpResponse::NotFound().json(&error_response);
+    };
+
+    // The token may reach another client than the one that sent the command, so its key is checked again.
+    let intent = parse_command(&command).intent;
+    let key = api_key(&req);
+    if let Some(response) = check_api_key(&data.config, |cfg| cfg.check_api_key(key.as_deref(), &intent)) {
+        data.pending_confirmations.lock().unwrap().insert(token, (command, action, created));
+        return response;
+    }
+    if !data.scheduler.admit() {
+        // Keep the token so the client can confirm again once the queue drains; it still expires on time.
+        data.pending_confirmations.lock().unwrap().insert(token, (command, action, created));
+        return queue_full(&data.scheduler);
+    }
+    info!("Command '{}' confirmed with token {}", command, token);
+
+    let task_id = Uuid::new_v4();
+    let focus_target = crate::winui_controller::capture_focus_target();
+    let configured_attempts = data.config.lock().unwrap().as_ref().map_or(1, |cfg| cfg.task_max_attempts);
+    schedule_action(&data, task_id, &command, &intent, action, None, focus_target, task_attempts(None, configured_attempts));
+    task_scheduled(&command, task_id)
+}
+
+// Handler listing the most recent commands received, newest first
+#[get("/history")]
+async fn get_command_history(data: web::Data<AppState>, query: web::Query<HashMap<String, String>>) -> impl Responder {
+    let limit = query.get("limit").and_then(|limit| limit.parse::<usize>().ok()).unwrap_or(50);
+    let history = data.command_history.lock().unwrap();
+    let entries: Vec<CommandHistoryEntry> = history
+        .iter()
+        .rev()
+        .take(limit)
+        .map(|(id, command, received)| CommandHistoryEntry {
+            id: *id,
+            command: command.clone(),
+            received_secs_ago: received.elapsed().as_secs_f64(),
+        })
+        .collect();
+    HttpResponse::Ok().json(entries)
+}
+
+// Handler re-running a recorded command as a new task
+#[post("/history/{id}/replay")]
+async fn replay_command(req: HttpRequest, data: web::Data<AppState>, id: web::Path<Uuid>) -> impl Responder {
+    let id = id.into_inner();
+    let command = data.command_history.lock().unwrap()
+        .iter()
+        .find(|(entry_id, ..)| *entry_id == id)
+        .map(|(_, command, _)| command.clone());
+    match command {
+        Some(command) => {
+            info!("Replaying command {}: {}", id, command);
+            let request = ExecuteCommandRequest { query: command, lang: None, dry_run: false };
+            run_command(&req, &data, &request, None)
+        }
+        None => {
+            let message = localized("command_not_found", "Command with id {} not found in history", &[&id]);
+            let error_response = ErrorResponse { error_code: ErrorCode::CommandNotFound, message };
+            HttpResponse::NotFound().json(&error_response)
+        }
+    }
+}
+
+/// Looks up the task served by `/last`: the most recently scheduled one, while it is still tracked.
+fn last_task_info<A, B>(last_task: Option<Uuid>, tasks: &HashMap<Uuid, (TaskInfo, A, B)>) -> Option<&TaskInfo> {
+    last_task.and_then(|id| tasks.get(&id)).map(|(task_info, _, _)| task_info)
+}
+
+// Handler returning the most recently scheduled task, for clients that do not track task ids
+#[get("/last")]
+async fn get_last_task(data: web::Data<AppState>) -> impl Responder {
+    let last_task = *data.last_task.lock().unwrap();
+    let tasks_lock = data.tasks.lock().unwrap();
+    match last_task_info(last_task, &tasks_lock) {
+        Some(task_info) => HttpResponse::Ok().json(task_info),
+        None => {
+            let message = localized("no_task_scheduled", "No command has been scheduled yet", &[]);
+            let error_response = ErrorResponse { error_code: ErrorCode::TaskNotFound, message };
+            HttpResponse::NotFound().json(&error_response)
+        }
+    }
 }
 
 // 2. Handler to get the task list
 #[get("/get=tasksall")]
 async fn get_all_tasks(data: web::Data<AppState>) -> impl Responder {
     let tasks_lock = data.tasks.lock().unwrap();
-    let task_list: Vec<TaskInfo> = tasks_lock.iter().map(|(_, (task_info, _, _))| task_info.clone()).collect();
+    let task_list: Vec<TaskInfo> = tasks_lock.iter().map(|(_, (task_info, _, _))| {
+        let mut task_info = task_info.clone();
+        // Long file operations report their progress to the scheduler while they run;
+        // the scheduler also measures how long the task waited in the queue.
+        let reported = data.scheduler.status(&task_info.id);
+        task_info.progress = reported.as_ref().and_then(|reported| reported.progress);
+        task_info.queue_ms = reported.and_then(|reported| reported.queue_ms);
+        task_info
+    }).collect();
     HttpResponse::Ok().json(task_list)
 }
 
+// Handler to check whether a task with the given name is still running
+#[get("/task/running")]
+async fn is_task_running(data: web::Data<AppState>, query: web::Query<TaskRunningQuery>) -> impl Responder {
+    let tasks_lock = data.tasks.lock().unwrap();
+    let task_ids = running_task_ids(tasks_lock.values().map(|(task_info, _, _)| task_info), &query.name);
+    HttpResponse::Ok().json(TaskRunningResponse {
+        name: query.name.clone(),
+        running: !task_ids.is_empty(),
+        task_ids,
+    })
+}
+
+/// Returns the ids of non-terminal tasks whose name is `name` or `Task: {name}`,
+/// so callers can pass either the task name or the original command.
+fn running_task_ids<'a>(tasks: impl Iterator<Item = &'a TaskInfo>, name: &str) -> Vec<Uuid> {
+    let task_name = format!("Task: {}", name);
+    tasks
+        .filter(|task_info| !task_info.status.is_terminal())
+        .filter(|task_info| task_info.name == name || task_info.name == task_name)
+        .map(|task_info| task_info.id)
+        .collect()
+}
+
+// Handler listing the visible top-level windows, for picking a command target.
+// Read-only, so it answers directly instead of going through the scheduler
+#[get("/windows")]
+async fn get_windows() -> impl Responder {
+    HttpResponse::Ok().json(crate::winui_controller::list_windows())
+}
+
+// Handler checking whether a window exists. Read-only, so it answers directly instead of going through the scheduler
+#[get("/exists")]
+async fn window_exists(query: web::Query<WindowExistsQuery>) -> impl Responder {
+    HttpResponse::Ok().json(WindowExistsResponse {
+        label: query.label.clone(),
+        class: query.class.clone(),
+        exists: crate::winui_controller::window_is_open(&query.label, query.class.as_deref()),
+    })
+}
+
+// Handler reporting what this build can do, so clients can adapt their UI
+#[get("/capabilities")]
+async fn get_capabilities() -> impl Responder {
+    HttpResponse::Ok().json(capabilities())
+}
+
+/// Collects the compiled-in backend and features and the language files present on disk.
+fn capabilities() -> CapabilitiesResponse {
+    let backend = if cfg!(feature = "enable_win32") { "win32" } else { "none" };
+    let mut features = Vec::new();
+    if cfg!(feature = "enable_win32") {
+        features.push("enable_win32".to_string());
+    }
+    if cfg!(feature = "ocr") {
+        features.push("ocr".to_string());
+    }
+    let endpoints = [
+        "GET /", "POST /", "POST /execute", "GET /ws", "GET /confirm={token}", "GET /get=tasksall", "GET /last", "GET /tasks/{id}/events", "GET /task/running",
+        "GET /stop={task_id}", "GET /status", "GET /ready", "GET /metrics", "GET /history", "POST /history/{id}/replay", "GET /capabilities", "GET /intents", "GET /describe",
+        "GET /windows", "GET /exists", "POST /undo", "POST /reload-language", "POST /lang/test", "GET /get=settings", "GET /config", "POST /aliases", "DELETE /aliases/{alias}", "GET /get=settings.{setting_name}", "PUT /put=settings.{setting_name}",
+    ];
+    CapabilitiesResponse {
+        backend: backend.to_string(),
+        platform: std::env::consts::OS.to_string(),
+        features,
+        endpoints: endpoints.iter().map(|e| e.to_string()).collect(),
+        languages: available_languages(),
+    }
+}
+
+/// Lists the language codes that have a `{code}.lng` file in any language directory.
+fn available_languages() -> Vec<String> {
+    let mut languages: Vec<String> = crate::language::language_dir_candidates()
+        .into_iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lng"))
+        .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+        .collect();
+    languages.sort();
+    languages.dedup();
+    languages
+}
+
 // 3. Handler to stop a task
 #[get("/stop={task_id}")]
-async fn stop_task(data: web::Data<AppState>, task_id: web::Path<Uuid>) -> impl Responder {
+async fn stop_task(req: HttpRequest, data: web::Data<AppState>, task_id: web::Path<Uuid>) -> impl Responder {
+    let key = api_key(&req);
+    if let Some(response) = check_api_key(&data.config, |cfg| cfg.check_api_key_operation(key.as_deref(), OPERATION_STOP_TASK)) {
+        return response;
+    }
     let id = task_id.into_inner();
     info!("Stopping task with id: {}", id);
 
-    let mut tasks_lock = data.tasks.lock().unwrap();
+    if cancel_task(&data, &id).is_some() {
+        HttpResponse::Ok().content_type(ContentType::plaintext()).body(format!("Stopping task with id: {}", id))
+    } else {
+        let message = localized("task_not_found", "Task with id {} not found", &[&id]);
+        let error_response = ErrorResponse { error_code: ErrorCode::TaskNotFound, message };
+        HttpResponse::NotFound().json(&error_response)
+    }
+}
 
-    if let Some((task_info, cancel_tx_opt, join_handle_opt)) = tasks_lock.remove(&id) {
-        task_info.status = "stopping".to_string(); // Set status to "stopping"
+/// Removes a task from the task list, signals its cancellation and aborts it. Returns `None` for an unknown task.
+fn cancel_task(data: &AppState, id: &Uuid) -> Option<TaskInfo> {
+    let (mut task_info, cancel_tx_opt, join_handle_opt) = data.tasks.lock().unwrap().remove(id)?;
+    task_info.status = TaskStatus::Stopping; // Set status to "stopping"
+    if let Some(cancel_tx) = cancel_tx_opt {
+        let _ = cancel_tx.send(()); // Signal cancellation
+        info!("Sent cancellation signal for task {}", id);
+    }
+    if let Some(join_handle) = join_handle_opt {
+        info!("Aborting tokio handle for task {}", id);
+        join_handle.abort(); // Abort the spawned task
+    }
+    Some(task_info)
+}
 
-        if let Some(cancel_tx) = cancel_tx_opt {
-            let _ = cancel_tx.send(()); // Signal cancellation
-            info!("Sent cancellation signal for task {}", id);
+// Handler reverting the most recent reversible action
+#[post("/undo")]
+async fn undo(req: HttpRequest, data: web::Data<AppState>) -> impl Responder {
+    let key = api_key(&req);
+    if let Some(response) = check_api_key(&data.config, |cfg| cfg.check_api_key_operation(key.as_deref(), OPERATION_UNDO)) {
+        return response;
+    }
+    match undo_last() {
+        Some((inverse, ExecutionResult::Failure(e))) => {
+            info!("Undo with {:?} failed: {}", inverse, e);
+            controller_error(e)
         }
-
-        if let Some(join_handle) = join_handle_opt {
-             info!("Aborting tokio handle for task {}", id);
-            join_handle.abort(); // Abort the spawned task
+        Some((inverse, _)) => HttpResponse::Ok().content_type(ContentType::plaintext()).body(format!("Undone with {:?}", inverse)),
+        None => {
+            let message = "Nothing to undo: only checkbox changes, window minimize/maximize/hide/topmost and text replacement can be undone".to_string();
+            let error_response = ErrorResponse { error_code: ErrorCode::NothingToUndo, message };
+            HttpResponse::Conflict().json(&error_response)
         }
-         HttpResponse::Ok().content_type(ContentType::plaintext()).body(format!("Stopping task with id: {}", id))
+    }
+}
+
+// Handler serving command, task and per-intent counters in the Prometheus text format
+#[get("/metrics")]
+async fn get_metrics(data: web::Data<AppState>) -> impl Responder {
+    let counters = &data.counters;
+    let completed = counters.tasks_completed.load(Ordering::Relaxed);
+    let failed = counters.tasks_failed.load(Ordering::Relaxed);
+    let finished = completed + failed;
+    let average_ms = if finished == 0 {
+        0.0
     } else {
-        HttpResponse::NotFound().body(format!("Task with id {} not found", id))
+        counters.execution_ms_total.load(Ordering::Relaxed) as f64 / finished as f64
+    };
+
+    let mut body = String::new();
+    body.push_str("# HELP winnlp_commands_received_total Commands received, throttled ones included.\n");
+    body.push_str("# TYPE winnlp_commands_received_total counter\n");
+    body.push_str(&format!("winnlp_commands_received_total {}\n", counters.commands_received.load(Ordering::Relaxed)));
+    body.push_str("# HELP winnlp_tasks_total Tasks by terminal status.\n");
+    body.push_str("# TYPE winnlp_tasks_total counter\n");
+    body.push_str(&format!("winnlp_tasks_total{{status=\"completed\"}} {}\n", completed));
+    body.push_str(&format!("winnlp_tasks_total{{status=\"failed\"}} {}\n", failed));
+    body.push_str(&format!("winnlp_tasks_total{{status=\"cancelled\"}} {}\n", counters.tasks_cancelled.load(Ordering::Relaxed)));
+    body.push_str("# HELP winnlp_task_duration_average_ms Average execution time of completed and failed tasks.\n");
+    body.push_str("# TYPE winnlp_task_duration_average_ms gauge\n");
+    body.push_str(&format!("winnlp_task_duration_average_ms {:.3}\n", average_ms));
+    body.push_str("# HELP winnlp_queue_depth Scheduled tasks that have not started yet.\n");
+    body.push_str("# TYPE winnlp_queue_depth gauge\n");
+    body.push_str(&format!("winnlp_queue_depth {}\n", data.scheduler.queue_depth()));
+    body.push_str("# HELP winnlp_tasks_rejected_total Tasks refused because the queue was at max_queue_depth.\n");
+    body.push_str("# TYPE winnlp_tasks_rejected_total counter\n");
+    body.push_str(&format!("winnlp_tasks_rejected_total {}\n", data.scheduler.rejected_count()));
+    body.push_str("# HELP winnlp_parse_duration_ema_ms Moving average of the time to parse a command and map its intent.\n");
+    body.push_str("# TYPE winnlp_parse_duration_ema_ms gauge\n");
+    body.push_str(&format!("winnlp_parse_duration_ema_ms {:.3}\n", counters.parse_ema_us.load(Ordering::Relaxed) as f64 / 1000.0));
+
+    let mut intents: Vec<(String, u64, u64)> = data.intent_metrics.lock().unwrap()
+        .iter()
+        .map(|(intent, (succeeded, failed))| (intent.clone(), *succeeded, *failed))
+        .collect();
+    intents.sort();
+    body.push_str("# HELP winnlp_action_executions_total Action executions by intent and outcome.\n");
+    body.push_str("# TYPE winnlp_action_executions_total counter\n");
+    for (intent, succeeded, failed) in intents {
+        body.push_str(&format!("winnlp_action_executions_total{{intent=\"{}\",result=\"succeeded\"}} {}\n", intent, succeeded));
+        body.push_str(&format!("winnlp_action_executions_total{{intent=\"{}\",result=\"failed\"}} {}\n", intent, failed));
+    }
+
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(body)
+}
+
+// Handler listing every intent with its parameters, for clients building commands or forms
+#[get("/intents")]
+async fn list_intents() -> impl Responder {
+    let patterns = current_patterns();
+    let intents: Vec<IntentDescription> = INTENT_SPECS
+        .iter()
+        .map(|spec| IntentDescription {
+            intent: spec.intent.to_string(),
+            action: spec.action.to_string(),
+            parameters: spec.params
+                .iter()
+                .map(|param| IntentParameter {
+                    name: param.name.to_string(),
+                    kind: param.kind.to_string(),
+                    required: param.required,
+                })
+                .collect(),
+            example: patterns.examples.get(spec.intent).cloned(),
+        })
+        .collect();
+    HttpResponse::Ok().json(intents)
+}
+
+// Handler explaining how a command is parsed and mapped, without executing it
+#[get("/describe")]
+async fn describe_command(data: web::Data<AppState>, query: web::Query<DescribeQuery>) -> HttpResponse {
+    let nlp_result = match parse_in_language(&query.query, query.lang.as_deref()) {
+        Ok(nlp_result) => nlp_result,
+        Err(response) => return response,
+    };
+    // Mapped through the configured aliases, like an executed command.
+    let action = map_intent(&nlp_result, &data.config);
+    let safe_mode = data.config.lock().unwrap().as_ref().is_some_and(|cfg| cfg.safe_mode);
+    HttpResponse::Ok().json(DescribeResponse {
+        would_require: required_targets(&action),
+        blocked: (safe_mode && is_blocked_in_safe_mode(&action)).then(|| SAFE_MODE_MESSAGE.to_string()),
+        resolved_action: format!("{:?}", action),
+        normalized: nlp_result.normalized,
+        tokens: nlp_result.tokens,
+        intent: nlp_result.intent,
+        confidence: nlp_result.confidence,
+        parameters: nlp_result.parameters,
+    })
+}
+
+// Handler re-reading the configured language file, so pattern edits apply without a restart
+#[post("/reload-language")]
+async fn reload_language(req: HttpRequest, data: web::Data<AppState>) -> HttpResponse {
+    let key = api_key(&req);
+    if let Some(response) = check_api_key(&data.config, |cfg| cfg.check_api_key_operation(key.as_deref(), OPERATION_EDIT_SETTINGS)) {
+        return response;
     }
+    let Some(lang) = data.config.lock().unwrap().as_ref().map(|cfg| cfg.language.clone()) else {
+        return settings_not_initialized();
+    };
+    match reload_patterns(&lang) {
+        Ok(path) => {
+            crate::nlp::set_stemmer_language(&lang);
+            HttpResponse::Ok().json(ReloadLanguageResponse { path: path.display().to_string() })
+        }
+        Err(e) => {
+            error!("Failed to reload language patterns, keeping the current ones: {}", e);
+            let error_response = ErrorResponse { error_code: ErrorCode::LanguageInvalid, message: e };
+            HttpResponse::UnprocessableEntity().json(&error_response)
+        }
+    }
+}
+
+// Handler trying a regex against a command normalized the way the parser sees it, for language authors
+#[post("/lang/test")]
+async fn test_language_pattern(body: web::Json<LangTestRequest>) -> HttpResponse {
+    let request = body.into_inner();
+    if let Some(which) = request.which.as_deref().filter(|which| !is_known_intent(which)) {
+        let error_response = ErrorResponse { error_code: ErrorCode::InvalidParameters, message: format!("Unknown intent '{}'", which) };
+        return HttpResponse::BadRequest().json(&error_response);
+    }
+    let patterns = match request.lang.as_deref() {
+        Some(lang) => match load_patterns(lang) {
+            Ok(patterns) => patterns,
+            Err(e) => {
+                let error_response = ErrorResponse { error_code: ErrorCode::LanguageNotFound, message: e };
+                return HttpResponse::BadRequest().json(&error_response);
+            }
+        },
+        None => current_patterns(),
+    };
+    // Compiled exactly as the language loader does, so the same errors surface here.
+    let regex = match Regex::new(&request.pattern) {
+        Ok(regex) => regex,
+        Err(e) => {
+            let error_response = ErrorResponse { error_code: ErrorCode::PatternInvalid, message: e.to_string() };
+            return HttpResponse::UnprocessableEntity().json(&error_response);
+        }
+    };
+
+    let (tokens, normalized) = normalize_command(&request.text, &patterns);
+    let (captures, named_captures) = pattern_captures(&regex, &normalized);
+    let parsed_intent = parse_command_with(&request.text, &patterns).intent;
+    HttpResponse::Ok().json(LangTestResponse {
+        which_matched: request.which.as_ref().map(|which| which.eq_ignore_ascii_case(&parsed_intent)),
+        matched: !captures.is_empty(),
+        normalized,
+        tokens,
+        captures,
+        named_captures,
+        parsed_intent,
+    })
 }
 
 // 4. Handler to get the status
 #[get("/status")]
 async fn get_status() -> impl Responder {
-    HttpResponse::Ok().content_type(ContentType::plaintext()).body("Status: Running")
+    HttpResponse::Ok().content_type(ContentType::plaintext()).body(localized("status_running", "Status: Running", &[]))
+}
+
+// Readiness: unlike `/status`, fails with 503 until the configuration, language patterns and scheduler are usable
+#[get("/ready")]
+async fn get_ready(data: web::Data<AppState>) -> impl Responder {
+    let check = |name: &str, healthy: bool, detail: &str| ReadinessCheck {
+        name: name.to_string(),
+        healthy,
+        detail: detail.to_string(),
+    };
+    let config_loaded = data.config.lock().unwrap().is_some();
+    let embedded_patterns = using_embedded_patterns();
+    let worker_alive = data.scheduler.is_worker_alive();
+    let checks = vec![
+        check("config", config_loaded, if config_loaded { "loaded" } else { "configuration failed to load" }),
+        check("language", !embedded_patterns, if embedded_patterns { "language file not loaded; using the embedded fallback" } else { "loaded" }),
+        check("scheduler", worker_alive, if worker_alive { "worker running" } else { "worker thread has stopped" }),
+    ];
+    let ready = checks.iter().all(|check| check.healthy);
+    let response = ReadinessResponse { ready, checks };
+    if ready {
+        HttpResponse::Ok().json(response)
+    } else {
+        HttpResponse::ServiceUnavailable().json(response)
+    }
 }
 
 // 5. Handler to get settings
@@ -175,7 +1495,112 @@ async fn get_settings(data: web::Data<AppState>) -> impl Responder {
     if let Some(ref cfg) = *config_lock {
         HttpResponse::Ok().json(&cfg)
     } else {
-        HttpResponse::NotFound().body("Settings not initialized")
+        settings_not_initialized()
+    }
+}
+
+// Handler returning the whole configuration, aliases with their steps included.
+// API key values are masked; their allowed intents are shown.
+#[get("/config")]
+async fn get_config(data: web::Data<AppState>) -> impl Responder {
+    let config_lock = data.config.lock().unwrap();
+    if let Some(ref cfg) = *config_lock {
+        let mut config = cfg.clone();
+        for api_key in config.api_keys.iter_mut() {
+            api_key.key = "***".to_string();
+        }
+        HttpResponse::Ok().json(&config)
+    } else {
+        settings_not_initialized()
+    }
+}
+
+// Handler adding an alias at runtime; the config file is rewritten so the alias survives restarts
+#[post("/aliases")]
+async fn create_alias(req: HttpRequest, data: web::Data<AppState>, body: web::Json<AliasConfig>) -> HttpResponse {
+    let key = api_key(&req);
+    if let Some(response) = check_api_key(&data.config, |cfg| cfg.check_api_key_operation(key.as_deref(), OPERATION_EDIT_ALIASES)) {
+        return response;
+    }
+    let alias = body.into_inner();
+    if let Err(message) = validate_alias(&alias) {
+        let error_response = ErrorResponse { error_code: ErrorCode::AliasInvalid, message };
+        return HttpResponse::BadRequest().json(&error_response);
+    }
+
+    {
+        let mut config_lock = data.config.lock().unwrap();
+        let Some(cfg) = config_lock.as_mut() else {
+            return settings_not_initialized();
+        };
+        if cfg.aliases.iter().any(|existing| existing.alias.eq_ignore_ascii_case(&alias.alias)) {
+            let message = localized("alias_exists", "Alias '{}' already exists", &[&alias.alias]);
+            let error_response = ErrorResponse { error_code: ErrorCode::AliasExists, message };
+            return HttpResponse::Conflict().json(&error_response);
+        }
+        cfg.aliases.push(alias.clone());
+    } // save_config_to_file locks the config again
+
+    if let Err(e) = save_config_to_file(data.config.clone(), &data.config_path) {
+        error!("Failed to save config to file: {}", e);
+    }
+    info!("Alias '{}' -> '{}' created", alias.alias, alias.intent);
+    HttpResponse::Created().json(&alias)
+}
+
+// Handler removing an alias at runtime
+#[delete("/aliases/{alias}")]
+async fn delete_alias(req: HttpRequest, data: web::Data<AppState>, path: web::Path<String>) -> HttpResponse {
+    let key = api_key(&req);
+    if let Some(response) = check_api_key(&data.config, |cfg| cfg.check_api_key_operation(key.as_deref(), OPERATION_EDIT_ALIASES)) {
+        return response;
+    }
+    let name = path.into_inner();
+    let removed = {
+        let mut config_lock = data.config.lock().unwrap();
+        let Some(cfg) = config_lock.as_mut() else {
+            return settings_not_initialized();
+        };
+        let position = cfg.aliases.iter().position(|existing| existing.alias.eq_ignore_ascii_case(&name));
+        position.map(|index| cfg.aliases.remove(index))
+    };
+
+    match removed {
+        Some(alias) => {
+            if let Err(e) = save_config_to_file(data.config.clone(), &data.config_path) {
+                error!("Failed to save config to file: {}", e);
+            }
+            info!("Alias '{}' deleted", alias.alias);
+            HttpResponse::Ok().json(&alias)
+        }
+        None => {
+            let message = localized("alias_not_found", "Alias '{}' not found", &[&name]);
+            let error_response = ErrorResponse { error_code: ErrorCode::AliasNotFound, message };
+            HttpResponse::NotFound().json(&error_response)
+        }
+    }
+}
+
+/// Checks that an alias points at known intents; a `multi` alias needs at least one step.
+fn validate_alias(alias: &AliasConfig) -> Result<(), String> {
+    if alias.alias.trim().is_empty() {
+        return Err("Alias name must not be empty".to_string());
+    }
+    let is_multi = alias.command_type.as_deref().is_some_and(|t| t.eq_ignore_ascii_case("multi"));
+    if !is_multi {
+        return if is_known_intent(&alias.intent) {
+            Ok(())
+        } else {
+            Err(format!("Unknown intent '{}'", alias.intent))
+        };
+    }
+    let steps = alias.steps.as_deref().unwrap_or_default();
+    if steps.is_empty() {
+        return Err(format!("Alias '{}' is 'multi' but has no steps", alias.alias));
+    }
+    match steps.iter().position(|step| !is_known_intent(&step.intent)) {
+        Some(index) => Err(format!("Step #{} has unknown intent '{}'", index, steps[index].intent)),
+        None => Ok(()),
     }
 }
 
@@ -186,54 +1611,70 @@ async fn get_setting_by_name(data: web::Data<AppState>, setting_name: web::Path<
     let config_lock = data.config.lock().unwrap();
     if let Some(ref cfg) = *config_lock {
         match name.as_str() {
-            "notifications_delay" => HttpResponse::Ok().content_type(ContentType::plaintext()).body(cfg.notifications_delay.to_string()),
+            "notifications_delay" => HttpResponse::Ok().content_type(ContentType::plaintext()).body(cfg.notification_delay.to_string()),
             "language" => HttpResponse::Ok().content_type(ContentType::plaintext()).body(cfg.language.clone()),
-            _ => HttpResponse::NotFound().body("Setting not found"),
+            _ => {
+                let message = localized("setting_not_found", "Setting not found", &[]);
+                let error_response = ErrorResponse { error_code: ErrorCode::SettingNotFound, message };
+                HttpResponse::NotFound().json(&error_response)
+            }
         }
     } else {
-        HttpResponse::NotFound().body("Settings not initialized")
+        settings_not_initialized()
     }
 }
 
+/// The 404 response for a request made while the configuration failed to load.
+fn settings_not_initialized() -> HttpResponse {
+    let message = localized("settings_not_initialized", "Settings not initialized", &[]);
+    let error_response = ErrorResponse { error_code: ErrorCode::SettingsNotInitialized, message };
+    HttpResponse::NotFound().json(&error_response)
+}
+
 // 7. Handler to update settings
 #[put("/put=settings.{setting_name}")]
-async fn update_setting(data: web::Data<AppState>, path: web::Path<String>, query: web::Query<HashMap<String, String>>) -> impl Responder {
+async fn update_setting(req: HttpRequest, data: web::Data<AppState>, path: web::Path<String>, query: web::Query<HashMap<String, String>>) -> impl Responder {
+    let key = api_key(&req);
+    if let Some(response) = check_api_key(&data.config, |cfg| cfg.check_api_key_operation(key.as_deref(), OPERATION_EDIT_SETTINGS)) {
+        return response;
+    }
     let setting_path = path.into_inner();
-    let app_state = data.clone();
-    if let Some((config_lock, mut json_result)) = update_config(&data.config, &data.config_path, &setting_path, query).await {
-       
-        if json_result.is_ok() {
-             HttpResponse::Ok().content_type(ContentType::plaintext()).body(format!("{}", json_result.unwrap()))
-        } else {
-              HttpResponse::BadRequest().body(json_result.unwrap_err().to_string())
+    match update_config(&data.config, &data.config_path, &setting_path, query).await {
+        Some((_, Ok(result))) => {
+            let message = localized("execution_result", "Execution result: {}", &[&result]);
+            HttpResponse::Ok().content_type(ContentType::plaintext()).body(message)
         }
-    } else {
-         HttpResponse::NotFound().body("Settings not initialized")
+        Some((_, Err((error_code, message)))) => HttpResponse::BadRequest().json(&ErrorResponse { error_code, message }),
+        None => settings_not_initialized(),
     }
 }
 
 //Helper to perform safe config update
-async fn update_config(config: &SharedConfig, config_path: &str, setting_path: &str, query: web::Query<HashMap<String, String>>) -> Option<(SharedConfig,  Result<String, Box<dyn std::error::Error>>>) {
+async fn update_config(config: &SharedConfig, config_path: &str, setting_path: &str, query: web::Query<HashMap<String, String>>) -> Option<(SharedConfig,  Result<String, (ErrorCode, String)>)> {
      let mut config_lock = config.lock().unwrap();
     if let Some(ref mut cfg) = *config_lock {
         if let Some(value) = query.get("value") {
-            let result: Result<String, Box<dyn std::error::Error>> = match setting_path {
+            let result: Result<String, (ErrorCode, String)> = match setting_path {
                 "notifications_delay" => {
                      match value.parse::<u32>() {
                          Ok(new_delay) => {
-                              cfg.notifications_delay = new_delay;
+                              cfg.notification_delay = new_delay;
                                Ok(format!("Notification delay updated to {}", new_delay))
                          },
-                         Err(e) => {
-                              Err(From::from("value is not in the right type, please try again"))
+                         Err(_) => {
+                              Err((ErrorCode::SettingInvalid, "value is not in the right type, please try again".to_string()))
                          }
                      }
                 },
-                "language" => {
-                    cfg.language = value.clone();
-                    Ok(format!("Language updated to {}", value))
+                "language" => match reload_patterns(value) {
+                    Ok(_) => {
+                        cfg.language = value.clone();
+                        crate::nlp::set_stemmer_language(&cfg.language);
+                        Ok(format!("Language updated to {}", value))
+                    }
+                    Err(e) => Err((ErrorCode::SettingInvalid, e)),
                 },
-                _ =>  Err(From::from("Setting not found"))
+                _ =>  Err((ErrorCode::SettingNotFound, "Setting not found".to_string()))
             };
 
            if result.is_ok() {
@@ -245,7 +1686,7 @@ async fn update_config(config: &SharedConfig, config_path: &str, setting_path: &
 
            Some((config.clone(), result))
         } else {
-             Some((config.clone(), Err(From::from("Missing 'value' parameter"))))
+             Some((config.clone(), Err((ErrorCode::SettingInvalid, "Missing 'value' parameter".to_string()))))
         }
     } else {
         None
@@ -268,36 +1709,302 @@ fn save_config_to_file(config: SharedConfig, file_path: &str) -> Result<(), Stri
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    // Initialize logging
-    env_logger::init_from_env(Env::default().default_filter_or("info"));
+    let config_path = "natural.config"; // Путь к вашему файлу конфигурации
+
+    // Initialize logging first, so everything below (config loading included) is captured
+    let logging_config = AppConfig::read_logging_config(config_path);
+    let logger_result = crate::debug_logger::init_logging(logging_config.as_ref().ok().and_then(|cfg| cfg.as_ref()));
+    if let Err(e) = logging_config.and(logger_result) {
+        error!("{}; logging to stderr", e);
+    }
 
      // Initialize configuration
-    let config_path = "natural.config"; // Путь к вашему файлу конфигурации
-    let shared_config: SharedConfig = init_shared_config(config_path);
-    let scheduler = Arc::new(TaskScheduler::new(shared_config.clone()));
+    let shared_config: SharedConfig = init_shared_config(config_path, None);
+    // Settings read once at startup; the guard is dropped before anything below runs.
+    let startup_config = shared_config.lock().unwrap().clone().unwrap_or_default();
+    // Must run before the language patterns are first used.
+    crate::language::set_language_dir(startup_config.language_dir.clone().map(std::path::PathBuf::from));
+    crate::winui_controller::set_action_timeout(Duration::from_millis(startup_config.action_timeout_ms));
+    crate::winui_controller::set_keystroke_delay(Duration::from_millis(startup_config.keystroke_delay_ms));
+    crate::language::set_default_language(&startup_config.language);
+    // Stem in the language the patterns were actually loaded for, which is ru after a fallback.
+    crate::nlp::set_stemmer_language(&crate::language::patterns_language());
+    crate::winui_controller::set_require_focus_lock(startup_config.require_focus_lock);
+    if startup_config.safe_mode {
+        info!("Safe mode is on: file and process operations are disabled");
+    }
+    crate::winui_controller::set_safe_mode(startup_config.safe_mode);
+    if let Some(path) = &startup_config.layouts_path {
+        crate::winui_controller::set_layouts_path(std::path::PathBuf::from(path));
+    }
+    if let Some(root) = &startup_config.file_root {
+        crate::winui_controller::set_file_root(std::path::PathBuf::from(root));
+    }
+    let notifier = crate::notification::sink_from_config(&startup_config);
+    let history = startup_config.task_history_path.as_ref().map(TaskHistory::new);
+    let restored = history.as_ref().map(restore_tasks).unwrap_or_default();
+    let scheduler = Arc::new(TaskScheduler::new(shared_config.clone(), notifier, history));
 
-    // Example task list (replace with your actual task management)
-    let tasks = Arc::new(Mutex::new(HashMap::new())); // Use a HashMap for task management
+    // Finished tasks from earlier runs, then every task scheduled from now on
+    let tasks = Arc::new(Mutex::new(restored)); // Use a HashMap for task management
 
     let app_state = web::Data::new(AppState {
         tasks: tasks.clone(),
         config: shared_config.clone(),
         scheduler: scheduler.clone(),
         config_path: config_path.to_string(),
+        pending_confirmations: Arc::new(Mutex::new(HashMap::new())),
+        task_events: Arc::new(Mutex::new(HashMap::new())),
+        command_history: Arc::new(Mutex::new(VecDeque::new())),
+        last_task: Arc::new(Mutex::new(None)),
+        intent_metrics: Arc::new(Mutex::new(HashMap::new())),
+        counters: Arc::new(CommandCounters::default()),
+        idempotency_keys: Arc::new(Mutex::new(HashMap::new())),
+        coalescing: Arc::new(Mutex::new(HashMap::new())),
     });
 
-    HttpServer::new(move || {
+    let (http_grace_secs, task_grace_secs) = split_shutdown_grace(startup_config.shutdown_grace_secs);
+
+    // actix stops accepting connections on SIGINT/SIGTERM and lets in-flight requests finish.
+    let server_result = HttpServer::new(move || {
         App::new()
             .app_data(app_state.clone()) // Pass the shared state
             .service(execute_command)
+            .service(execute_command_body)
+            .service(execute_batch)
+            .service(command_socket)
+            .service(confirm_command)
             .service(get_all_tasks)
+            .service(get_last_task)
+            .service(stream_task_events)
+            .service(is_task_running)
             .service(stop_task)
             .service(get_status)
+            .service(get_ready)
+            .service(get_metrics)
+            .service(get_capabilities)
+            .service(list_intents)
+            .service(describe_command)
+            .service(get_windows)
+            .service(window_exists)
             .service(get_settings)
+            .service(get_config)
+            .service(create_alias)
+            .service(delete_alias)
             .service(get_setting_by_name)
             .service(update_setting)
+            .service(get_command_history)
+            .service(replay_command)
+            .service(undo)
+            .service(reload_language)
+            .service(test_language_pattern)
     })
+    .shutdown_timeout(http_grace_secs)
     .bind("127.0.0.1:8080")?
     .run()
-    .await
+    .await;
+
+    // No new commands can arrive now; give the queued tasks the rest of the grace period to finish.
+    let (drained, aborted) = scheduler.shutdown(Duration::from_secs(task_grace_secs));
+    info!("Shutdown complete: {} tasks drained, {} force-aborted", drained, aborted);
+    server_result
+}
+
+/// Splits the configured shutdown grace period between the in-flight HTTP requests and the
+/// queued tasks, so a full stop never takes longer than `shutdown_grace_secs` altogether.
+fn split_shutdown_grace(total_secs: u64) -> (u64, u64) {
+    let http_secs = total_secs / 2;
+    (http_secs, total_secs - http_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_keeps_the_newest_commands_up_to_the_limit() {
+        let mut history = VecDeque::new();
+        let ids: Vec<Uuid> = (0..4).map(|_| Uuid::new_v4()).collect();
+        for (index, id) in ids.iter().enumerate() {
+            push_history(&mut history, *id, &format!("команда {}", index), 3);
+        }
+        let kept: Vec<Uuid> = history.iter().map(|(id, ..)| *id).collect();
+        assert_eq!(kept, ids[1..]);
+        assert_eq!(history.back().map(|(_, command, _)| command.as_str()), Some("команда 3"));
+    }
+
+    #[test]
+    fn shutdown_grace_is_split_without_exceeding_the_total() {
+        assert_eq!(split_shutdown_grace(10), (5, 5));
+        assert_eq!(split_shutdown_grace(7), (3, 4));
+        assert_eq!(split_shutdown_grace(0), (0, 0));
+    }
+
+    #[test]
+    fn requested_attempts_override_the_configured_ones_within_bounds() {
+        assert_eq!(task_attempts(None, 3), 3);
+        assert_eq!(task_attempts(Some(2), 3), 2);
+        assert_eq!(task_attempts(Some(0), 3), 1);
+        assert_eq!(task_attempts(None, 0), 1);
+        assert_eq!(task_attempts(Some(1000), 3), MAX_TASK_ATTEMPTS);
+    }
+
+    #[test]
+    fn low_confidence_command_requires_confirmation() {
+        let action = Action::WindowMinimize { label: "Блокнот".to_string(), process: None };
+        assert!(needs_confirmation(&action, 0.4, 0.6));
+        assert!(!needs_confirmation(&action, 0.8, 0.6));
+        // With a threshold of 0 nothing is held back.
+        assert!(!needs_confirmation(&action, 0.0, 0.0));
+        let unknown = Action::Unknown { hint: String::new() };
+        assert!(!needs_confirmation(&unknown, 0.0, 0.6));
+    }
+
+    #[test]
+    fn expired_confirmations_are_swept() {
+        let action = Action::WindowMinimize { label: String::new(), process: None };
+        let mut pending = HashMap::new();
+        let fresh = Uuid::new_v4();
+        let stale = Uuid::new_v4();
+        pending.insert(fresh, ("fresh".to_string(), action.clone(), Instant::now()));
+        pending.insert(stale, ("stale".to_string(), action, Instant::now() - Duration::from_secs(600)));
+
+        sweep_confirmations(&mut pending, CONFIRMATION_TTL);
+
+        assert!(pending.contains_key(&fresh));
+        assert!(!pending.contains_key(&stale));
+    }
+
+    #[test]
+    fn repeated_idempotency_keys_return_the_first_task() {
+        let mut keys = HashMap::new();
+        let ttl = Duration::from_secs(300);
+        assert_eq!(idempotent_task(&mut keys, "retry-1", ttl), None);
+
+        let task_id = Uuid::new_v4();
+        keys.insert("retry-1".to_string(), (task_id, "сверни окно".to_string(), Instant::now()));
+        keys.insert("old".to_string(), (Uuid::new_v4(), "сверни окно".to_string(), Instant::now() - Duration::from_secs(600)));
+
+        assert_eq!(idempotent_task(&mut keys, "retry-1", ttl), Some((task_id, "сверни окно".to_string())));
+        assert_eq!(idempotent_task(&mut keys, "old", ttl), None);
+        assert!(!keys.contains_key("old"));
+    }
+
+    #[test]
+    fn last_reflects_the_most_recent_command() {
+        let mut tasks = HashMap::new();
+        assert!(last_task_info(None, &tasks).is_none());
+
+        let mut last_task = None;
+        for name in ["Task: первая", "Task: вторая"] {
+            let task_info = TaskInfo::new(name.to_string());
+            last_task = Some(task_info.id);
+            tasks.insert(task_info.id, (task_info, (), ()));
+        }
+        assert_eq!(last_task_info(last_task, &tasks).map(|t| t.name.as_str()), Some("Task: вторая"));
+
+        tasks.clear();
+        assert!(last_task_info(last_task, &tasks).is_none());
+    }
+
+    #[test]
+    fn executions_are_counted_per_intent() {
+        let metrics = Mutex::new(HashMap::new());
+        record_action_metrics(&metrics, "button_click", true);
+        record_action_metrics(&metrics, "button_click", true);
+        record_action_metrics(&metrics, "button_click", false);
+        // Both run a FileOperation, but are counted apart.
+        record_action_metrics(&metrics, "copy_file", true);
+        record_action_metrics(&metrics, "delete_file", false);
+
+        let metrics = metrics.into_inner().unwrap();
+        assert_eq!(metrics["button_click"], (2, 1));
+        assert_eq!(metrics["copy_file"], (1, 0));
+        assert_eq!(metrics["delete_file"], (0, 1));
+    }
+
+    #[test]
+    fn aliases_must_point_at_known_intents() {
+        let alias: AliasConfig = serde_json::from_str(r#"{"alias": "save", "intent": "button_click"}"#).unwrap();
+        assert!(validate_alias(&alias).is_ok());
+
+        let unknown: AliasConfig = serde_json::from_str(r#"{"alias": "save", "intent": "no_such_intent"}"#).unwrap();
+        assert!(validate_alias(&unknown).is_err());
+
+        let empty_multi: AliasConfig = serde_json::from_str(r#"{"alias": "both", "intent": "", "command_type": "multi", "steps": []}"#).unwrap();
+        assert!(validate_alias(&empty_multi).is_err());
+    }
+
+    #[test]
+    fn only_unfinished_tasks_with_the_name_count_as_running() {
+        let mut running = TaskInfo::new("Task: backup".to_string());
+        running.status = TaskStatus::Running;
+        let mut completed = TaskInfo::new("Task: backup".to_string());
+        completed.status = TaskStatus::Completed;
+        let other = TaskInfo::new("Task: report".to_string());
+        let tasks = [running.clone(), completed, other];
+
+        assert_eq!(running_task_ids(tasks.iter(), "backup"), vec![running.id]);
+        assert_eq!(running_task_ids(tasks.iter(), "Task: backup"), vec![running.id]);
+        assert!(running_task_ids(tasks[1..2].iter(), "backup").is_empty());
+    }
+
+    #[test]
+    fn rejected_commands_carry_their_error_code() {
+        let unknown = Action::Unknown { hint: "Команда не распознана".to_string() };
+        assert!(matches!(command_rejection(&unknown), Some((ErrorCode::ParseFailed, _))));
+
+        let refused = Action::Refused { intent: "window_close".to_string(), reason: "disabled".to_string() };
+        assert!(matches!(command_rejection(&refused), Some((ErrorCode::IntentDisabled, _))));
+
+        let missing_label = Action::SetFocus { label: String::new() };
+        assert!(matches!(command_rejection(&missing_label), Some((ErrorCode::InvalidParameters, _))));
+
+        let valid = Action::SetFocus { label: "Имя".to_string() };
+        assert!(command_rejection(&valid).is_none());
+    }
+
+    #[test]
+    fn missing_windows_get_their_own_error_code() {
+        let message = "Window with label 'Блокнот' not found: The system cannot find the file specified.";
+        assert_eq!(controller_error_code(message), ErrorCode::WindowNotFound);
+        assert_eq!(controller_error_code("Button with label 'OK' not found: ..."), ErrorCode::ActionFailed);
+        assert_eq!(controller_error_code("Failed to enumerate windows"), ErrorCode::ActionFailed);
+        assert_eq!(serde_json::to_value(ErrorCode::WindowNotFound).unwrap(), "WINDOW_NOT_FOUND");
+    }
+
+    #[test]
+    fn posted_command_keeps_quotes_and_newlines() {
+        let body = r#"{"query": "напечатай \"Привет,\nмир\"", "dry_run": true}"#;
+        let request: ExecuteCommandRequest = serde_json::from_str(body).unwrap();
+        assert_eq!(request.query, "напечатай \"Привет,\nмир\"");
+        assert!(request.dry_run);
+        assert!(request.lang.is_none());
+
+        let nlp_result = parse_command(&request.query);
+        assert_eq!(nlp_result.intent, "type_text");
+        assert_eq!(nlp_result.parameters["text"], "Привет,\nмир");
+    }
+
+    #[test]
+    fn finishing_a_task_ends_its_event_stream() {
+        let task_id = Uuid::new_v4();
+        let (events, mut receiver) = broadcast::channel(TASK_EVENTS_CAPACITY);
+        let task_events = Mutex::new(HashMap::from([(task_id, events)]));
+
+        finish_task_events(&task_events, &task_id, TaskStatus::Completed);
+
+        assert_eq!(receiver.try_recv(), Ok(TaskStatus::Completed));
+        assert_eq!(receiver.try_recv(), Err(broadcast::error::TryRecvError::Closed));
+        assert!(task_events.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn default_build_reports_the_win32_backend_only() {
+        let capabilities = capabilities();
+        assert_eq!(capabilities.backend, "win32");
+        assert_eq!(capabilities.features, vec!["enable_win32".to_string()]);
+        assert_eq!(capabilities.platform, std::env::consts::OS);
+        assert!(capabilities.endpoints.iter().any(|endpoint| endpoint == "GET /capabilities"));
+    }
 }
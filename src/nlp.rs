@@ -2,48 +2,110 @@ use regex::Regex;
 use lazy_static::lazy_static;
 use std::collections::HashMap;
 use rust_stemmers::{Algorithm, Stemmer};
+use std::sync::RwLock;
+use log::{debug, warn};
 
 // Import language-specific regex patterns and messages.
-use crate::language::PATTERNS;
+use crate::language::{current_patterns, Patterns};
 
 /// The result of natural language processing analysis.
 #[derive(Debug, Clone)]
 pub struct NLPResult {
     pub intent: String,
     pub parameters: HashMap<String, String>,
+    pub confidence: f32, // 1.0 for an exact pattern match, 0.0 when nothing matched
+    pub normalized: String, // The stemmed command the patterns were matched against
+    pub tokens: Vec<String>, // The stemmed words of `normalized`, stop words already dropped
 }
 
 /// Analyze and normalize natural language commands using stemming and language-specific regex patterns.
 pub fn parse_command(command: &str) -> NLPResult {
-    let normalized_command = morphological_analyze(command);
-    let lower_command = normalized_command.to_lowercase();
+    parse_command_with(command, &current_patterns())
+}
+
+/// Same as [`parse_command`], but matches against the given language's patterns.
+pub fn parse_command_with(command: &str, patterns: &Patterns) -> NLPResult {
+    let stemmer = current_stemmer();
+    let (tokens, lower_command) = normalize_command(command, patterns);
+    let normalized_command = tokens.join(" ");
 
     let mut result = NLPResult {
         intent: "unknown".to_string(),
         parameters: HashMap::new(),
+        confidence: 1.0,
+        normalized: normalized_command.clone(),
+        tokens,
     };
 
     // Check commands using regex patterns loaded from the language file.
-    if let Some(caps) = PATTERNS.universal_open_re.captures(&lower_command) {
+    // Chords go first: "нажми ctrl+s" would otherwise be taken for a button click.
+    // They are matched on the raw command because normalization strips the '+' separators.
+    if let Some(caps) = patterns.hold_key_re.captures(&command.to_lowercase()) {
+        result.intent = "hold_key".to_string();
+        let key = caps.get(1).map_or("", |m| m.as_str())
+            .split_whitespace()
+            .filter(|word| !matches!(*word, "arrow" | "стрелка" | "стрелку"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        result.parameters.insert("key".to_string(), key);
+        let amount = caps.get(2).and_then(|m| m.as_str().parse::<u64>().ok()).unwrap_or(1);
+        let duration_ms = duration_to_ms(amount, caps.get(3).map(|m| m.as_str()));
+        result.parameters.insert("duration_ms".to_string(), duration_ms.to_string());
+        return result;
+    }
+    // Drag needs both quoted labels as typed and the joining "в"/"to", which normalization drops.
+    if let Some(caps) = patterns.drag_re.captures(command) {
+        result.intent = "drag".to_string();
+        result.parameters.insert("from_label".to_string(), caps[1].to_string());
+        result.parameters.insert("to_label".to_string(), caps[2].to_string());
+        return result;
+    }
+    if let Some(caps) = patterns.key_chord_re.captures(&command.to_lowercase()) {
+        result.intent = "key_chord".to_string();
+        let mut parts: Vec<&str> = caps.get(1).map_or("", |m| m.as_str()).split('+').map(str::trim).collect();
+        let key = parts.pop().unwrap_or_default().to_string();
+        result.parameters.insert("modifiers".to_string(), parts.join(","));
+        result.parameters.insert("key".to_string(), key);
+        return result;
+    }
+    // Stemming breaks links apart, so the link itself comes from the original command.
+    if let Some(url) = extract_url(command).filter(|_| patterns.open_url_re.is_match(&lower_command)) {
+        result.intent = "open_url".to_string();
+        result.parameters.insert("target".to_string(), url);
+        return result;
+    }
+    if patterns.open_folder_re.is_match(&lower_command) {
+        result.intent = "open_folder".to_string();
+        if let Some(path) = extract_quoted_text(command) {
+            result.parameters.insert("target".to_string(), path);
+        }
+        return result;
+    }
+    if let Some(caps) = patterns.universal_open_re.captures(&lower_command) {
         result.intent = "launch_object".to_string();
         let object = caps.get(2).map_or("default_object", |m| m.as_str()).to_string();
         result.parameters.insert("object".to_string(), object);
         return result;
     }
-    if let Some(caps) = PATTERNS.universal_focus_re.captures(&lower_command) {
+    if let Some(caps) = patterns.universal_focus_re.captures(&lower_command) {
         result.intent = "focus_object".to_string();
         let object = caps.get(2).map_or("default_object", |m| m.as_str()).to_string();
         result.parameters.insert("object".to_string(), object);
         return result;
     }
-    if let Some(caps) = PATTERNS.group_windows_re.captures(&lower_command) {
+    if patterns.arrange_windows_re.is_match(&lower_command) {
+        result.intent = "arrange_windows".to_string();
+        result.parameters.insert("layout".to_string(), window_layout(&lower_command).to_string());
+        return result;
+    }
+    if let Some(caps) = patterns.group_windows_re.captures(&lower_command) {
         result.intent = "group_windows".to_string();
         let group = caps.get(2).map_or("default_group", |m| m.as_str()).to_string();
         result.parameters.insert("group".to_string(), group);
         result.parameters.insert("windows".to_string(), "".to_string());
         return result;
     }
-    if let Some(caps) = PATTERNS.select_text_re.captures(&lower_command) {
+    if let Some(caps) = patterns.select_text_re.captures(&lower_command) {
         result.intent = "edit_select_text".to_string();
         if let (Some(start), Some(end)) = (caps.get(2), caps.get(3)) {
             result.parameters.insert("start".to_string(), start.as_str().to_string());
@@ -54,28 +116,28 @@ pub fn parse_command(command: &str) -> NLPResult {
         }
         return result;
     }
-    if PATTERNS.copy_text_re.is_match(&lower_command) {
+    if patterns.copy_text_re.is_match(&lower_command) {
         result.intent = "edit_copy_text".to_string();
         if let Some(label) = extract_label(&lower_command) {
             result.parameters.insert("label".to_string(), label);
         }
         return result;
     }
-    if PATTERNS.cut_text_re.is_match(&lower_command) {
+    if patterns.cut_text_re.is_match(&lower_command) {
         result.intent = "edit_cut_text".to_string();
         if let Some(label) = extract_label(&lower_command) {
             result.parameters.insert("label".to_string(), label);
         }
         return result;
     }
-    if PATTERNS.delete_text_re.is_match(&lower_command) {
+    if patterns.delete_text_re.is_match(&lower_command) {
         result.intent = "edit_delete_text".to_string();
         if let Some(label) = extract_label(&lower_command) {
             result.parameters.insert("label".to_string(), label);
         }
         return result;
     }
-    if PATTERNS.paste_text_re.is_match(&lower_command) {
+    if patterns.paste_text_re.is_match(&lower_command) {
         result.intent = "edit_paste_text".to_string();
         if let Some(label) = extract_label(&lower_command) {
             result.parameters.insert("label".to_string(), label);
@@ -85,7 +147,22 @@ pub fn parse_command(command: &str) -> NLPResult {
         }
         return result;
     }
-    if PATTERNS.enter_text_re.is_match(&lower_command) {
+    // Typing goes to whatever has focus, so it needs only the quoted text, taken as typed.
+    if patterns.type_text_re.is_match(&lower_command) {
+        if let Some(caps) = QUOTED_RE.captures(command) {
+            result.intent = "type_text".to_string();
+            result.parameters.insert("text".to_string(), caps[1].to_string());
+            let unquoted = QUOTED_RE.replace_all(command, "").to_lowercase();
+            if let Some(caps) = KEYSTROKE_DELAY_RE.captures(&unquoted) {
+                // A bare number is milliseconds here; keystroke pauses are never seconds long.
+                let amount = caps[1].parse::<u64>().unwrap_or(0);
+                let delay_ms = caps.get(2).map_or(amount, |unit| duration_to_ms(amount, Some(unit.as_str())));
+                result.parameters.insert("delay_ms".to_string(), delay_ms.to_string());
+            }
+            return result;
+        }
+    }
+    if patterns.enter_text_re.is_match(&lower_command) {
         result.intent = "edit_enter_text".to_string();
         let label = extract_label(&lower_command).unwrap_or_else(|| "default".to_string());
         result.parameters.insert("label".to_string(), label);
@@ -96,13 +173,13 @@ pub fn parse_command(command: &str) -> NLPResult {
         }
         return result;
     }
-    if PATTERNS.get_text_re.is_match(&lower_command) {
+    if patterns.get_text_re.is_match(&lower_command) {
         result.intent = "static_get_text".to_string();
         let label = extract_label(&lower_command).unwrap_or_else(|| "default".to_string());
         result.parameters.insert("label".to_string(), label);
         return result;
     }
-    if PATTERNS.set_text_re.is_match(&lower_command) {
+    if patterns.set_text_re.is_match(&lower_command) {
         result.intent = "set_text".to_string();
         let label = extract_label(&lower_command).unwrap_or_else(|| "default".to_string());
         result.parameters.insert("label".to_string(), label);
@@ -113,7 +190,24 @@ pub fn parse_command(command: &str) -> NLPResult {
         }
         return result;
     }
-    if PATTERNS.window_resize_re.is_match(&lower_command) {
+    if patterns.window_resize_relative_re.is_match(&lower_command) {
+        result.intent = "window_resize_relative".to_string();
+        // "на 200 пикселей шире и на 100 ниже": each delta adds to its dimension.
+        let (mut dw, mut dh) = (0i32, 0i32);
+        for caps in patterns.window_resize_relative_re.captures_iter(&lower_command) {
+            let amount = caps[1].parse::<i32>().unwrap_or(0);
+            match &caps[2] {
+                "шир" | "wider" => dw += amount,
+                "уж" | "narrower" => dw -= amount,
+                "выш" | "taller" => dh += amount,
+                _ => dh -= amount,
+            }
+        }
+        result.parameters.insert("dw".to_string(), dw.to_string());
+        result.parameters.insert("dh".to_string(), dh.to_string());
+        return result;
+    }
+    if patterns.window_resize_re.is_match(&lower_command) {
         result.intent = "window_resize".to_string();
         let nums = extract_numbers(&lower_command);
         if nums.len() >= 2 {
@@ -125,25 +219,130 @@ pub fn parse_command(command: &str) -> NLPResult {
         }
         return result;
     }
-    if PATTERNS.window_minimize_re.is_match(&lower_command) {
+    if patterns.wait_for_title_re.is_match(&lower_command) {
+        result.intent = "wait_for_title".to_string();
+        // The expected title is quoted in the original command; normalization would mangle it.
+        if let Some(caps) = QUOTED_RE.captures(command) {
+            result.parameters.insert("title".to_string(), caps[1].to_string());
+        }
+        let unquoted = QUOTED_RE.replace_all(command, "").to_lowercase();
+        if let Some(caps) = TIMEOUT_RE.captures(&unquoted) {
+            let amount = caps[1].parse::<u64>().unwrap_or(0);
+            let timeout_ms = duration_to_ms(amount, caps.get(2).map(|m| m.as_str()));
+            result.parameters.insert("timeout_ms".to_string(), timeout_ms.to_string());
+        }
+        return result;
+    }
+    if patterns.click_text_re.is_match(&lower_command) {
+        result.intent = "click_text".to_string();
+        // The text to look for is quoted in the original command, as it appears on screen.
+        if let Some(caps) = QUOTED_RE.captures(command) {
+            result.parameters.insert("text".to_string(), caps[1].to_string());
+        }
+        return result;
+    }
+    if patterns.wait_for_any_window_re.is_match(&lower_command) {
+        result.intent = "wait_for_any_window".to_string();
+        // Titles are quoted in the original command; normalization would mangle them.
+        let labels: Vec<String> = QUOTED_RE.captures_iter(command).map(|caps| caps[1].to_string()).collect();
+        result.parameters.insert("labels".to_string(), labels.join("|"));
+        let unquoted = QUOTED_RE.replace_all(command, "").to_lowercase();
+        if let Some(caps) = TIMEOUT_RE.captures(&unquoted) {
+            let amount = caps[1].parse::<u64>().unwrap_or(0);
+            let timeout_ms = duration_to_ms(amount, caps.get(2).map(|m| m.as_str()));
+            result.parameters.insert("timeout_ms".to_string(), timeout_ms.to_string());
+        }
+        return result;
+    }
+    if patterns.save_layout_re.is_match(&lower_command) || patterns.restore_layout_re.is_match(&lower_command) {
+        result.intent = if patterns.save_layout_re.is_match(&lower_command) { "save_layout" } else { "restore_layout" }.to_string();
+        // The layout name is quoted in the original command; normalization would mangle it.
+        let name = QUOTED_RE.captures(command).map_or_else(|| "default".to_string(), |caps| caps[1].to_string());
+        result.parameters.insert("name".to_string(), name);
+        return result;
+    }
+    let system_menu = patterns.system_menu_re.captures(&lower_command)
+        .and_then(|caps| caps.get(1).and_then(|m| system_menu_command(m.as_str())));
+    if let Some(command) = system_menu {
+        result.intent = "system_menu".to_string();
+        result.parameters.insert("command".to_string(), command.to_string());
+        let label = extract_label(&lower_command).unwrap_or_else(|| "default".to_string());
+        result.parameters.insert("label".to_string(), label);
+        return result;
+    }
+    if let Some(caps) = patterns.menu_re.captures(&lower_command) {
+        result.intent = "menu_select".to_string();
+        // The path keeps its case and '>' separators only in the original command.
+        let menu_path = QUOTED_RE.captures(command)
+            .or_else(|| MENU_PATH_RE.captures(command))
+            .map(|caps| caps[1].to_string())
+            .or_else(|| caps.get(1).map(|m| m.as_str().to_string()))
+            .unwrap_or_default();
+        result.parameters.insert("menu_path".to_string(), menu_path);
+        if let Some(window) = extract_label(&lower_command) {
+            result.parameters.insert("window".to_string(), window);
+        }
+        return result;
+    }
+    if let Some(caps) = patterns.window_topmost_re.captures(&lower_command) {
+        result.intent = "window_topmost".to_string();
+        // "открепи"/"unpin" turn the pin off; everything else turns it on.
+        let unpin = caps.get(1).is_some_and(|m| m.as_str() == "откреп") || caps.get(2).is_some();
+        result.parameters.insert("topmost".to_string(), (!unpin).to_string());
+        if let Some(label) = extract_label(&lower_command) {
+            result.parameters.insert("label".to_string(), label);
+        }
+        return result;
+    }
+    // Showing goes first: "покажи скрытое окно" would otherwise be taken for hiding one.
+    if patterns.window_show_re.is_match(&lower_command) {
+        result.intent = "window_show".to_string();
+        if let Some(label) = extract_label(&lower_command) {
+            result.parameters.insert("label".to_string(), label);
+        }
+        return result;
+    }
+    if patterns.window_hide_re.is_match(&lower_command) {
+        result.intent = "window_hide".to_string();
+        if let Some(label) = extract_label(&lower_command) {
+            result.parameters.insert("label".to_string(), label);
+        }
+        return result;
+    }
+    if patterns.window_activate_re.is_match(&lower_command) {
+        result.intent = "window_activate".to_string();
+        let label = extract_label(&lower_command).unwrap_or_else(|| "default".to_string());
+        result.parameters.insert("label".to_string(), label);
+        return result;
+    }
+    if patterns.window_minimize_re.is_match(&lower_command) {
         result.intent = "window_minimize".to_string();
         let label = extract_label(&lower_command).unwrap_or_else(|| "default".to_string());
         result.parameters.insert("label".to_string(), label);
         return result;
     }
-    if PATTERNS.window_maximize_re.is_match(&lower_command) {
+    if patterns.window_maximize_re.is_match(&lower_command) {
         result.intent = "window_maximize".to_string();
         let label = extract_label(&lower_command).unwrap_or_else(|| "default".to_string());
         result.parameters.insert("label".to_string(), label);
         return result;
     }
-    if PATTERNS.window_close_re.is_match(&lower_command) {
+    if patterns.window_close_re.is_match(&lower_command) {
         result.intent = "window_close".to_string();
         let label = extract_label(&lower_command).unwrap_or_else(|| "default".to_string());
         result.parameters.insert("label".to_string(), label);
         return result;
     }
-    if PATTERNS.window_move_re.is_match(&lower_command) {
+    // Named spots go before plain moves, which would also match "передвинь окно в угол".
+    if patterns.window_move_named_re.is_match(&lower_command) {
+        result.intent = "window_move_named".to_string();
+        result.parameters.insert("position".to_string(), window_position(&lower_command).to_string());
+        if let Some(label) = extract_label(&lower_command) {
+            result.parameters.insert("label".to_string(), label);
+        }
+        return result;
+    }
+    if patterns.window_move_re.is_match(&lower_command) {
         result.intent = "window_move".to_string();
         let nums = extract_numbers(&lower_command);
         if nums.len() >= 2 {
@@ -155,58 +354,583 @@ pub fn parse_command(command: &str) -> NLPResult {
         }
         return result;
     }
-    if PATTERNS.file_open_re.is_match(&lower_command) {
+    if patterns.file_open_re.is_match(&lower_command) {
         result.intent = "open_file".to_string();
-        if let Some(file) = extract_quoted_text(&lower_command) {
-            result.parameters.insert("file".to_string(), file);
+        if let Some(file) = extract_quoted_text(command) {
+            result.parameters.insert("target".to_string(), file);
         }
         return result;
     }
-    if PATTERNS.file_copy_re.is_match(&lower_command) {
+    if patterns.file_copy_re.is_match(&lower_command) {
         result.intent = "copy_file".to_string();
         if let Some(file) = extract_quoted_text(&lower_command) {
             result.parameters.insert("file".to_string(), file);
         }
         return result;
     }
-    if PATTERNS.file_move_re.is_match(&lower_command) {
+    if patterns.file_move_re.is_match(&lower_command) {
         result.intent = "move_file".to_string();
         if let Some(file) = extract_quoted_text(&lower_command) {
             result.parameters.insert("file".to_string(), file);
         }
         return result;
     }
-    if PATTERNS.file_rename_re.is_match(&lower_command) {
+    if patterns.file_rename_re.is_match(&lower_command) {
         result.intent = "rename_file".to_string();
         if let Some(file) = extract_quoted_text(&lower_command) {
             result.parameters.insert("file".to_string(), file);
         }
         return result;
     }
-    if PATTERNS.file_delete_re.is_match(&lower_command) {
+    if patterns.file_delete_re.is_match(&lower_command) {
         result.intent = "delete_file".to_string();
         if let Some(file) = extract_quoted_text(&lower_command) {
             result.parameters.insert("file".to_string(), file);
         }
         return result;
     }
+    if let Some(caps) = patterns.tab_to_re.captures(&lower_command) {
+        result.intent = "tab_to".to_string();
+        let backwards = caps.get(2).is_some_and(|m| matches!(m.as_str(), "назад" | "back" | "backward"));
+        let reverse = caps.get(1).is_some() || backwards;
+        result.parameters.insert("reverse".to_string(), reverse.to_string());
+        let count = caps.get(3).map_or("1", |m| m.as_str()).to_string();
+        result.parameters.insert("count".to_string(), count);
+        return result;
+    }
+    if let Some(caps) = patterns.mouse_double_click_re.captures(&lower_command) {
+        result.intent = "mouse_double_click".to_string();
+        if let (Some(x), Some(y)) = (caps.get(1), caps.get(2)) {
+            result.parameters.insert("x".to_string(), x.as_str().to_string());
+            result.parameters.insert("y".to_string(), y.as_str().to_string());
+        }
+        return result;
+    }
+    if let Some(caps) = patterns.mouse_wheel_re.captures(&lower_command) {
+        result.intent = "mouse_wheel".to_string();
+        let direction = match caps.get(1).map_or("down", |m| m.as_str()) {
+            "вверх" | "up" => "up",
+            _ => "down",
+        };
+        result.parameters.insert("direction".to_string(), direction.to_string());
+        if let Some(amount) = caps.get(2) {
+            result.parameters.insert("amount".to_string(), amount.as_str().to_string());
+        }
+        return result;
+    }
+    if let Some(caps) = patterns.scroll_re.captures(&lower_command) {
+        result.intent = "scroll".to_string();
+        let target = caps.get(1).map_or("down", |m| m.as_str());
+        let direction = if target.starts_with("нача") || target == "top" {
+            "top"
+        } else if target.starts_with("конц") || target == "конец" || target == "bottom" {
+            "bottom"
+        } else if target.starts_with("страниц") || target.starts_with("page") {
+            if target.ends_with("вверх") || target.ends_with("up") { "page_up" } else { "page_down" }
+        } else if target == "вверх" || target == "up" {
+            "up"
+        } else {
+            "down"
+        };
+        result.parameters.insert("direction".to_string(), direction.to_string());
+        if let Some(amount) = caps.get(2) {
+            result.parameters.insert("amount".to_string(), amount.as_str().to_string());
+        }
+        return result;
+    }
+    if let Some(caps) = patterns.set_slider_re.captures(&lower_command) {
+        result.intent = "set_slider".to_string();
+        let label = extract_label(&lower_command).unwrap_or_else(|| "default".to_string());
+        result.parameters.insert("label".to_string(), label);
+        let percent = caps.get(1).map_or("0", |m| m.as_str()).to_string();
+        result.parameters.insert("percent".to_string(), percent);
+        return result;
+    }
+    if patterns.get_checkbox_state_re.is_match(&lower_command) {
+        result.intent = "get_checkbox_state".to_string();
+        let label = extract_label(&lower_command).unwrap_or_else(|| "default".to_string());
+        result.parameters.insert("label".to_string(), label);
+        return result;
+    }
+    if patterns.checkbox_re.is_match(&lower_command) {
+        result.intent = "checkbox_set_state".to_string();
+        let label = extract_label(&lower_command).unwrap_or_else(|| "default".to_string());
+        result.parameters.insert("label".to_string(), label);
+        result.parameters.insert("state".to_string(), checkbox_state(&lower_command).to_string());
+        return result;
+    }
+    if patterns.get_selected_text_re.is_match(&lower_command) {
+        result.intent = "get_selected_text".to_string();
+        // Without a label the focused control of the foreground window is read.
+        if let Some(label) = extract_label(&lower_command) {
+            result.parameters.insert("label".to_string(), label);
+        }
+        return result;
+    }
+    if patterns.listview_get_items_re.is_match(&lower_command) {
+        result.intent = "listview_get_items".to_string();
+        let label = extract_label(&lower_command).unwrap_or_else(|| "default".to_string());
+        result.parameters.insert("label".to_string(), label);
+        return result;
+    }
+    if patterns.get_selected_tab_re.is_match(&lower_command) {
+        result.intent = "get_selected_tab".to_string();
+        let label = extract_label(&lower_command).unwrap_or_else(|| "default".to_string());
+        result.parameters.insert("label".to_string(), label);
+        return result;
+    }
+    if let Some(caps) = patterns.get_pixel_re.captures(&lower_command) {
+        result.intent = "get_pixel".to_string();
+        result.parameters.insert("x".to_string(), caps[1].to_string());
+        result.parameters.insert("y".to_string(), caps[2].to_string());
+        return result;
+    }
+    if patterns.get_window_dpi_re.is_match(&lower_command) {
+        result.intent = "get_window_dpi".to_string();
+        // Without a label the foreground window is measured.
+        if let Some(label) = extract_label(&lower_command) {
+            result.parameters.insert("label".to_string(), label);
+        }
+        return result;
+    }
+    if patterns.window_exists_re.is_match(&lower_command) {
+        result.intent = "window_exists".to_string();
+        let label = extract_label(&lower_command).unwrap_or_default();
+        result.parameters.insert("label".to_string(), label);
+        return result;
+    }
+    if patterns.list_windows_re.is_match(&lower_command) {
+        result.intent = "list_windows".to_string();
+        return result;
+    }
+    if let Some(caps) = patterns.drag_resize_re.captures(&lower_command) {
+        result.intent = "drag_resize".to_string();
+        let verb = caps.get(1).map_or("", |m| m.as_str());
+        let edge = match caps.get(2).map_or("", |m| m.as_str()) {
+            "лев" | "left" => "left",
+            "верхн" | "top" => "top",
+            "нижн" | "bottom" => "bottom",
+            _ => "right",
+        };
+        let amount = caps.get(3).map_or("0", |m| m.as_str());
+        let shrink = matches!(verb, "сожм" | "суз" | "уменьш" | "shrink");
+        let delta = if shrink { format!("-{}", amount) } else { amount.to_string() };
+        result.parameters.insert("edge".to_string(), edge.to_string());
+        result.parameters.insert("delta".to_string(), delta);
+        // Without a label the foreground window is resized.
+        if let Some(label) = extract_label(&lower_command) {
+            result.parameters.insert("label".to_string(), label);
+        }
+        return result;
+    }
+    if patterns.send_to_back_re.is_match(&lower_command) {
+        result.intent = "send_to_back".to_string();
+        // Without a label the foreground window is moved.
+        if let Some(label) = extract_label(&lower_command) {
+            result.parameters.insert("label".to_string(), label);
+        }
+        return result;
+    }
+    if patterns.bring_to_front_re.is_match(&lower_command) {
+        result.intent = "bring_to_front".to_string();
+        if let Some(label) = extract_label(&lower_command) {
+            result.parameters.insert("label".to_string(), label);
+        }
+        return result;
+    }
+    if patterns.set_clipboard_image_re.is_match(&lower_command) {
+        result.intent = "set_clipboard_image".to_string();
+        if let Some(path) = extract_quoted_text(command) {
+            result.parameters.insert("path".to_string(), path);
+        }
+        return result;
+    }
+    if patterns.clipboard_image_re.is_match(&lower_command) {
+        result.intent = "get_clipboard_image".to_string();
+        // Quoted text is the target file; the original command keeps the path's case.
+        if let Some(path) = extract_quoted_text(command) {
+            result.parameters.insert("path".to_string(), path);
+        }
+        return result;
+    }
+    if patterns.submit_dialog_re.is_match(&lower_command) {
+        result.intent = "submit_dialog".to_string();
+        return result;
+    }
+    if patterns.cancel_dialog_re.is_match(&lower_command) {
+        result.intent = "cancel_dialog".to_string();
+        return result;
+    }
+    if patterns.activate_focused_re.is_match(&lower_command) {
+        result.intent = "activate_focused".to_string();
+        return result;
+    }
+    // Control captions are matched on the command as typed: stemming would cut "настройки" to "настройк".
+    let typed_command = command.to_lowercase();
+    if let Some(caps) = patterns.double_click_re.captures(&typed_command) {
+        result.intent = "button_double_click".to_string();
+        result.parameters.insert("label".to_string(), quoted_or(command, caps.get(1).map(|m| m.as_str())));
+        return result;
+    }
+    if let Some(caps) = patterns.click_re.captures(&typed_command) {
+        result.intent = "button_click".to_string();
+        result.parameters.insert("label".to_string(), quoted_or(command, caps.get(1).map(|m| m.as_str())));
+        return result;
+    }
+    if let Some(caps) = patterns.tabcontrol_re.captures(&typed_command) {
+        result.intent = "tabcontrol_select_tab".to_string();
+        result.parameters.insert("tab".to_string(), quoted_or(command, caps.get(1).map(|m| m.as_str())));
+        let label = extract_label(&lower_command).unwrap_or_else(|| "default".to_string());
+        result.parameters.insert("label".to_string(), label);
+        return result;
+    }
+    // No pattern matched: tolerate small typos in the command verb before giving up.
+    let tokens: Vec<String> = lower_command.split_whitespace().map(str::to_string).collect();
+    if let Some((intent, confidence)) = fuzzy_intent(&tokens, &stemmer) {
+        result.intent = intent;
+        result.confidence = confidence;
+        if let Some(label) = extract_label(&lower_command) {
+            result.parameters.insert("label".to_string(), label);
+        }
+        return result;
+    }
     // Fallback: no known command detected.
     result.intent = "unknown".to_string();
-    result.parameters.insert("hint".to_string(), PATTERNS.msg_hint.clone());
+    result.confidence = 0.0;
+    result.parameters.insert("hint".to_string(), patterns.msg_hint.clone());
     result
 }
 
+/// The first quoted fragment of `command`, which keeps multi-word captions whole, else `word`, else "default".
+fn quoted_or(command: &str, word: Option<&str>) -> String {
+    QUOTED_RE.captures(command)
+        .map(|caps| caps[1].to_string())
+        .or_else(|| word.map(str::to_string))
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// Converts a spoken duration to milliseconds; a missing unit means seconds.
+fn duration_to_ms(amount: u64, unit: Option<&str>) -> u64 {
+    match unit {
+        Some(u) if u == "мс" || u == "ms" || u.starts_with("миллисекунд") || u.starts_with("millisecond") => amount,
+        _ => amount.saturating_mul(1000),
+    }
+}
+
+/// Maximum edit distance between a command token and a canonical verb stem.
+const FUZZY_MAX_DISTANCE: usize = 2;
+
+/// Fuzzy matches never score as high as an exact pattern match.
+const FUZZY_CONFIDENCE_SCALE: f32 = 0.8;
+
+lazy_static! {
+    // Double-quoted fragments of the raw command, such as window titles.
+    static ref QUOTED_RE: Regex = Regex::new(r#""([^"]+)""#).unwrap();
+    // An unquoted menu path such as "File>Save" or "Файл > Печать"; multi-word captions need quotes.
+    static ref MENU_PATH_RE: Regex = Regex::new(r"([^\s>]+(?:\s*>\s*[^\s>]+)+)").unwrap();
+    // A duration such as "5 секунд" or "500 ms"; a bare number means seconds.
+    static ref TIMEOUT_RE: Regex = Regex::new(r"(\d+)\s*(мс|ms|миллисекунд\w*|milliseconds?|с|s|сек\w*|sec\w*)?\b").unwrap();
+    // Per-command keystroke pause in a type_text command, e.g. "с задержкой 50 мс".
+    static ref KEYSTROKE_DELAY_RE: Regex = Regex::new(r"(?:задержк\w*|пауз\w*|delay)\D*?(\d+)\s*(мс|ms|миллисекунд\w*|milliseconds?|с|s|сек\w*|sec\w*)?\b").unwrap();
+}
+
+// Canonical command verbs per intent; stemmed the same way as incoming commands before comparing.
+// Destructive intents such as window_close are left out, and so are the dialog keys:
+// a typo must never close a window or answer a dialog.
+const FUZZY_VERBS: &[(&str, &str)] = &[
+    ("свернуть", "window_minimize"), ("minimize", "window_minimize"),
+    ("развернуть", "window_maximize"), ("maximize", "window_maximize"),
+    ("сгруппировать", "group_windows"), ("group", "group_windows"),
+    ("скриншот", "screenshot"), ("screenshot", "screenshot"),
+];
+
+/// Picks the intent whose canonical verb is closest to one of the tokens, within `FUZZY_MAX_DISTANCE` edits.
+/// Short verbs allow fewer edits so that unrelated short words do not match.
+/// Returns the intent with a confidence that drops with the edit distance.
+fn fuzzy_intent(tokens: &[String], stemmer: &Stemmer) -> Option<(String, f32)> {
+    let verbs: Vec<(String, &str)> = FUZZY_VERBS.iter().map(|(verb, intent)| (stemmer.stem(verb).to_string(), *intent)).collect();
+    let mut best: Option<(&str, usize, usize)> = None; // (intent, distance, verb length)
+    for token in tokens {
+        for (verb, intent) in verbs.iter() {
+            let verb_len = verb.chars().count();
+            let allowed = FUZZY_MAX_DISTANCE.min(verb_len / 3);
+            let distance = levenshtein(token, verb);
+            if distance <= allowed && best.is_none_or(|(_, best_distance, _)| distance < best_distance) {
+                best = Some((intent, distance, verb_len));
+            }
+        }
+    }
+    best.map(|(intent, distance, verb_len)| {
+        let confidence = (1.0 - distance as f32 / verb_len as f32) * FUZZY_CONFIDENCE_SCALE;
+        (intent.to_string(), confidence)
+    })
+}
+
+/// Picks the window layout named in a stemmed command by its keyword: cascade, vertical or
+/// horizontal tiling, or a left/right snap. Anything else is the grid.
+fn window_layout(command: &str) -> &'static str {
+    for token in command.split_whitespace() {
+        let starts = |prefixes: &[&str]| prefixes.iter().any(|prefix| token.starts_with(prefix));
+        if starts(&["каскад", "cascade"]) {
+            return "cascade";
+        } else if starts(&["вертикал", "vertical"]) {
+            return "tile_vertical";
+        } else if starts(&["горизонтал", "horizontal"]) {
+            return "tile_horizontal";
+        } else if starts(&["лев", "слев", "влев", "налев", "left"]) {
+            return "snap_left";
+        } else if starts(&["прав", "справ", "вправ", "направ", "right"]) {
+            return "snap_right";
+        }
+    }
+    "grid"
+}
+
+/// Picks the work-area spot a "move window to the ... corner" command names. A single side
+/// ("в правый угол") takes the top corner on that side; no side at all means "center".
+fn window_position(command: &str) -> &'static str {
+    let (mut vertical, mut horizontal) = (None, None);
+    for token in command.split_whitespace() {
+        let starts = |prefixes: &[&str]| prefixes.iter().any(|prefix| token.starts_with(prefix));
+        if starts(&["верх", "вверх", "top", "upper"]) {
+            vertical = vertical.or(Some("top"));
+        } else if starts(&["ниж", "низ", "вниз", "bottom", "lower"]) {
+            vertical = vertical.or(Some("bottom"));
+        } else if starts(&["лев", "слев", "влев", "налев", "left"]) {
+            horizontal = horizontal.or(Some("left"));
+        } else if starts(&["прав", "справ", "вправ", "направ", "right"]) {
+            horizontal = horizontal.or(Some("right"));
+        }
+    }
+    match (vertical.unwrap_or("top"), horizontal.unwrap_or("left")) {
+        _ if vertical.is_none() && horizontal.is_none() => "center",
+        ("top", "left") => "top_left",
+        ("top", _) => "top_right",
+        (_, "left") => "bottom_left",
+        _ => "bottom_right",
+    }
+}
+
+/// Picks the checkbox state from the command: "частично"/"indeterminate" for the third state,
+/// a removing verb for unchecked, otherwise checked. Two-state values stay `true`/`false`.
+fn checkbox_state(command: &str) -> &'static str {
+    let has = |prefixes: &[&str]| command.split_whitespace()
+        .any(|token| prefixes.iter().any(|prefix| token.starts_with(prefix)));
+    if has(&["частичн", "промежуточн", "indetermin", "partial"]) {
+        "indeterminate"
+    } else if has(&["сним", "убер", "uncheck", "clear"]) {
+        "false"
+    } else {
+        "true"
+    }
+}
+
+/// Maps the system menu verb captured by `SYSTEM_MENU_RE` to its command. Closing is
+/// destructive, so only an explicit close verb selects it; any other word is no command.
+fn system_menu_command(verb: &str) -> Option<&'static str> {
+    match verb {
+        "восстанов" | "restore" => Some("restore"),
+        "сверн" | "minimize" | "minimise" => Some("minimize"),
+        "разверн" | "maximize" | "maximise" => Some("maximize"),
+        "перемест" | "move" => Some("move"),
+        "размер" | "size" => Some("size"),
+        "закр" | "close" => Some("close"),
+        _ => None,
+    }
+}
+
+/// Character-level Levenshtein distance, so Cyrillic letters count as one edit each.
+pub(crate) fn levenshtein(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b_chars.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b_chars.len() + 1];
+        for (j, b_char) in b_chars.iter().enumerate() {
+            let substitution = previous[j] + if a_char == *b_char { 0 } else { 1 };
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b_chars.len()]
+}
+
 /// Applies stemming to the input command while removing punctuation and stop words.
-fn morphological_analyze(command: &str) -> String {
-    let stop_words = vec!["и", "в", "на", "с", "к", "по", "за", "для", "также", "не", "но", "а", "то", "же"];
-    let stemmer = Stemmer::create(Algorithm::Russian);
+/// Returns the stemmed words in order.
+fn morphological_analyze(command: &str, stop_words: &[String], stemmer: &Stemmer) -> Vec<String> {
     let cleaned = command.replace(|c: char| !c.is_alphanumeric() && !c.is_whitespace(), " ");
-    let words: Vec<String> = cleaned
+    let cleaned = words_to_numbers(&cleaned);
+    cleaned
         .split_whitespace()
-        .filter(|w| !stop_words.contains(&w.to_lowercase().as_str()))
+        .filter(|w| !stop_words.contains(&w.to_lowercase()))
         .map(|w| stemmer.stem(w).to_string())
-        .collect();
-    words.join(" ")
+        .collect()
+}
+
+lazy_static! {
+    // Language commands are stemmed in, from `cfg.language`.
+    static ref STEMMER_LANGUAGE: RwLock<String> = RwLock::new("ru".to_string());
+}
+
+/// Sets the language commands are stemmed in, from `cfg.language` ("ru", "en", ...).
+/// Codes without a Snowball stemmer fall back to Russian.
+pub fn set_stemmer_language(language: &str) {
+    if stemmer_algorithm(language).is_none() {
+        warn!("No stemmer for language '{}', stemming commands as Russian", language);
+    }
+    *STEMMER_LANGUAGE.write().unwrap() = language.to_string();
+}
+
+/// Stems `command` and drops its stop words, returning the tokens and the lowercased text
+/// the patterns are matched against. Shared by [`parse_command_with`] and `POST /lang/test`.
+pub fn normalize_command(command: &str, patterns: &Patterns) -> (Vec<String>, String) {
+    let tokens = morphological_analyze(command, &patterns.stop_words, &current_stemmer());
+    debug!("Stemmed tokens of '{}': {:?}", command, tokens);
+    let lower_command = tokens.join(" ").to_lowercase();
+    (tokens, lower_command)
+}
+
+/// Runs `regex` against a command already passed through [`normalize_command`], returning the
+/// positional groups (group 0 is the whole match, `None` for groups that did not take part) and
+/// the named ones. Both are empty when the pattern does not match. Used by `POST /lang/test`.
+pub fn pattern_captures(regex: &Regex, normalized: &str) -> (Vec<Option<String>>, HashMap<String, String>) {
+    match regex.captures(normalized) {
+        Some(caps) => (
+            caps.iter().map(|group| group.map(|m| m.as_str().to_string())).collect(),
+            regex.capture_names()
+                .flatten()
+                .filter_map(|name| caps.name(name).map(|m| (name.to_string(), m.as_str().to_string())))
+                .collect(),
+        ),
+        None => (Vec::new(), HashMap::new()),
+    }
+}
+
+fn current_stemmer() -> Stemmer {
+    let algorithm = stemmer_algorithm(&STEMMER_LANGUAGE.read().unwrap()).unwrap_or(Algorithm::Russian);
+    Stemmer::create(algorithm)
+}
+
+/// Snowball algorithm for a language code or English language name.
+fn stemmer_algorithm(language: &str) -> Option<Algorithm> {
+    let algorithm = match language.trim().to_lowercase().as_str() {
+        "ru" | "russian" => Algorithm::Russian,
+        "en" | "english" => Algorithm::English,
+        "de" | "german" => Algorithm::German,
+        "fr" | "french" => Algorithm::French,
+        "es" | "spanish" => Algorithm::Spanish,
+        "it" | "italian" => Algorithm::Italian,
+        "pt" | "portuguese" => Algorithm::Portuguese,
+        _ => return None,
+    };
+    Some(algorithm)
+}
+
+/// Value of a spelled-out number word below a hundred, or a Russian hundreds word.
+fn number_word_value(word: &str) -> Option<u64> {
+    let value = match word {
+        "ноль" | "zero" => 0,
+        "один" | "одна" | "одно" | "one" => 1,
+        "два" | "две" | "two" => 2,
+        "три" | "three" => 3,
+        "четыре" | "four" => 4,
+        "пять" | "five" => 5,
+        "шесть" | "six" => 6,
+        "семь" | "seven" => 7,
+        "восемь" | "eight" => 8,
+        "девять" | "nine" => 9,
+        "десять" | "ten" => 10,
+        "одиннадцать" | "eleven" => 11,
+        "двенадцать" | "twelve" => 12,
+        "тринадцать" | "thirteen" => 13,
+        "четырнадцать" | "fourteen" => 14,
+        "пятнадцать" | "fifteen" => 15,
+        "шестнадцать" | "sixteen" => 16,
+        "семнадцать" | "seventeen" => 17,
+        "восемнадцать" | "eighteen" => 18,
+        "девятнадцать" | "nineteen" => 19,
+        "двадцать" | "twenty" => 20,
+        "тридцать" | "thirty" => 30,
+        "сорок" | "forty" => 40,
+        "пятьдесят" | "fifty" => 50,
+        "шестьдесят" | "sixty" => 60,
+        "семьдесят" | "seventy" => 70,
+        "восемьдесят" | "eighty" => 80,
+        "девяносто" | "ninety" => 90,
+        "сто" => 100,
+        "двести" => 200,
+        "триста" => 300,
+        "четыреста" => 400,
+        "пятьсот" => 500,
+        "шестьсот" => 600,
+        "семьсот" => 700,
+        "восемьсот" => 800,
+        "девятьсот" => 900,
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Place value of a number word's contribution: hundreds, tens or units (teens count as units).
+fn number_place(value: u64) -> u64 {
+    match value {
+        100.. => 100,
+        20.. => 10,
+        _ => 1,
+    }
+}
+
+/// Replaces spelled-out Russian and English numbers with digits, so "восемьсот на шестьсот"
+/// becomes "800 на 600" and "one hundred twenty" becomes "120". Other words are kept as they are.
+/// Words only combine from a higher place value to a lower one, so "пять двадцать" stays two numbers.
+fn words_to_numbers(text: &str) -> String {
+    let mut output: Vec<String> = Vec::new();
+    let mut total: u64 = 0;      // Completed thousands
+    let mut current: u64 = 0;    // Value below the next thousand
+    let mut place: u64 = 0;      // Place value of the last word in the number; a next word must be below it
+    let mut in_number = false;
+    let mut pending_and = false; // "and" inside an English number ("one hundred and five")
+
+    fn flush(output: &mut Vec<String>, total: &mut u64, current: &mut u64, in_number: &mut bool, pending_and: &mut bool) {
+        if *in_number {
+            output.push((*total + *current).to_string());
+        }
+        if *pending_and {
+            output.push("and".to_string());
+        }
+        *total = 0;
+        *current = 0;
+        *in_number = false;
+        *pending_and = false;
+    }
+
+    for word in text.split_whitespace() {
+        let lower = word.to_lowercase();
+        let is_thousand = lower == "тысяча" || lower == "тысячи" || lower == "тысяч" || lower == "thousand";
+        if let Some(value) = number_word_value(&lower) {
+            if in_number && !(value > 0 && value < place) {
+                flush(&mut output, &mut total, &mut current, &mut in_number, &mut pending_and);
+            }
+            current += value;
+            place = number_place(value);
+            in_number = true;
+            pending_and = false;
+        } else if in_number && !pending_and && lower == "hundred" && place == 1 && (1..10).contains(&current) {
+            current *= 100;
+            place = 100;
+        } else if is_thousand && total == 0 && !pending_and {
+            total = current.max(1) * 1000;
+            current = 0;
+            place = 1000;
+            in_number = true;
+        } else if in_number && !pending_and && lower == "and" && place >= 100 {
+            pending_and = true;
+        } else {
+            flush(&mut output, &mut total, &mut current, &mut in_number, &mut pending_and);
+            output.push(word.to_string());
+        }
+    }
+    flush(&mut output, &mut total, &mut current, &mut in_number, &mut pending_and);
+    output.join(" ")
 }
 
 /// Extracts a label from the command using a simple inline regex.
@@ -225,10 +949,233 @@ fn extract_quoted_text(command: &str) -> Option<String> {
         .map(|m| m.as_str().to_string())
 }
 
+/// Extracts the first http or https link, without trailing punctuation.
+fn extract_url(command: &str) -> Option<String> {
+    let re = Regex::new(r#"(?i)https?://[^\s"']+"#).ok()?;
+    re.find(command)
+        .map(|m| m.as_str().trim_end_matches(['.', ',', ';', '!', '?', ')']).to_string())
+}
+
 /// Extracts all numbers present in the command.
 fn extract_numbers(command: &str) -> Vec<String> {
     let re = Regex::new(r"\b(\d+)\b").unwrap();
     re.captures_iter(command)
         .filter_map(|caps| caps.get(1).map(|m| m.as_str().to_string()))
         .collect()
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tab_to_parses_count_and_direction() {
+        let result = parse_command("tab forward 3 times");
+        assert_eq!(result.intent, "tab_to");
+        assert_eq!(result.parameters["count"], "3");
+        assert_eq!(result.parameters["reverse"], "false");
+
+        let result = parse_command("shift tab 2");
+        assert_eq!(result.intent, "tab_to");
+        assert_eq!(result.parameters["count"], "2");
+        assert_eq!(result.parameters["reverse"], "true");
+
+        let result = parse_command("таб назад");
+        assert_eq!(result.intent, "tab_to");
+        assert_eq!(result.parameters["count"], "1");
+        assert_eq!(result.parameters["reverse"], "true");
+    }
+
+    #[test]
+    fn dialog_keys_parse_from_their_words() {
+        // "отмена" stems to "отм", "отменить" to "отмен".
+        assert_eq!(parse_command("отмена").intent, "cancel_dialog");
+        assert_eq!(parse_command("отменить").intent, "cancel_dialog");
+        assert_eq!(parse_command("escape").intent, "cancel_dialog");
+        assert_eq!(parse_command("ок").intent, "submit_dialog");
+        assert_eq!(parse_command("submit").intent, "submit_dialog");
+        assert_eq!(parse_command("пробел").intent, "activate_focused");
+    }
+
+    #[test]
+    fn z_order_commands_parse_without_a_label() {
+        let result = parse_command("на задний план");
+        assert_eq!(result.intent, "send_to_back");
+        assert!(!result.parameters.contains_key("label"));
+        assert_eq!(parse_command("send to back").intent, "send_to_back");
+        assert_eq!(parse_command("поверх остальных").intent, "bring_to_front");
+        assert_eq!(parse_command("bring to front").intent, "bring_to_front");
+    }
+
+    #[test]
+    fn levenshtein_counts_single_character_edits() {
+        assert_eq!(levenshtein("свернут", "свернут"), 0);
+        assert_eq!(levenshtein("свернт", "свернут"), 1);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "окн"), 3);
+    }
+
+    #[test]
+    fn fuzzy_match_tolerates_typos_but_never_closes() {
+        let stemmer = Stemmer::create(Algorithm::Russian);
+        let tokens = |words: &[&str]| words.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+
+        let (intent, confidence) = fuzzy_intent(&tokens(&["свернт", "окн"]), &stemmer).unwrap();
+        assert_eq!(intent, "window_minimize");
+        assert!(confidence < crate::config::AppConfig::default().min_confidence);
+
+        assert_eq!(fuzzy_intent(&tokens(&["закрт", "окн"]), &stemmer), None);
+        assert_eq!(fuzzy_intent(&tokens(&["clse"]), &stemmer), None);
+        for verb in ["подтвердить", "submit", "отменить", "cancel"] {
+            assert_eq!(fuzzy_intent(&[stemmer.stem(verb).to_string()], &stemmer), None, "{}", verb);
+        }
+    }
+
+    #[test]
+    fn slider_command_parses_the_percent() {
+        let result = parse_command("установи слайдер на 75 процентов");
+        assert_eq!(result.intent, "set_slider");
+        assert_eq!(result.parameters["percent"], "75");
+        assert_eq!(parse_command("set slider to 30").parameters["percent"], "30");
+    }
+
+    #[test]
+    fn hold_key_parses_key_and_duration() {
+        let result = parse_command("hold right arrow for 2 seconds");
+        assert_eq!(result.intent, "hold_key");
+        assert_eq!(result.parameters["key"], "right");
+        assert_eq!(result.parameters["duration_ms"], "2000");
+
+        let result = parse_command("удерживай стрелку вправо на 500 мс");
+        assert_eq!(result.parameters["key"], "вправо");
+        assert_eq!(result.parameters["duration_ms"], "500");
+
+        assert_eq!(duration_to_ms(3, None), 3000);
+        assert_eq!(duration_to_ms(250, Some("milliseconds")), 250);
+    }
+
+    #[test]
+    fn window_list_parses_with_the_plural_stem() {
+        // "окон" stems to "окон", not "окн".
+        assert_eq!(parse_command("покажи список окон").intent, "list_windows");
+        assert_eq!(parse_command("покажи все открытые окна").intent, "list_windows");
+        assert_eq!(parse_command("list open windows").intent, "list_windows");
+    }
+
+    #[test]
+    fn wait_for_any_window_parses_titles_and_timeout() {
+        // "окон" stems to "окон", not "окн".
+        let result = parse_command("подожди любое из окон \"Блокнот\" \"Калькулятор\" 5 секунд");
+        assert_eq!(result.intent, "wait_for_any_window");
+        assert_eq!(result.parameters["labels"], "Блокнот|Калькулятор");
+        assert_eq!(result.parameters["timeout_ms"], "5000");
+        assert_eq!(parse_command("дождись появления окна \"Сохранение\"").intent, "wait_for_any_window");
+        assert_eq!(parse_command("wait for any of the windows \"Save\" \"Open\"").intent, "wait_for_any_window");
+    }
+
+    #[test]
+    fn window_exists_parses_from_the_stemmed_question() {
+        // "есть ли окно" stems to "ест ли окн".
+        assert_eq!(parse_command("есть ли окно Блокнот").intent, "window_exists");
+        assert_eq!(parse_command("открыто ли окно Блокнот").intent, "window_exists");
+        assert_eq!(parse_command("does the window Notepad exist").intent, "window_exists");
+    }
+
+    #[test]
+    fn only_an_explicit_close_verb_picks_the_system_menu_close() {
+        assert_eq!(system_menu_command("закр"), Some("close"));
+        assert_eq!(system_menu_command("close"), Some("close"));
+        assert_eq!(system_menu_command("сверн"), Some("minimize"));
+        assert_eq!(system_menu_command("minimise"), Some("minimize"));
+        assert_eq!(system_menu_command("размер"), Some("size"));
+        assert_eq!(system_menu_command("закреп"), None);
+        assert_eq!(system_menu_command(""), None);
+
+        let result = parse_command("system menu minimize");
+        assert_eq!(result.intent, "system_menu");
+        assert_eq!(result.parameters["command"], "minimize");
+    }
+
+    #[test]
+    fn scroll_parses_direction_and_amount() {
+        // "начало" stems to "нача".
+        let scroll = |command: &str| {
+            let result = parse_command(command);
+            assert_eq!(result.intent, "scroll", "{}", command);
+            (result.parameters["direction"].clone(), result.parameters.get("amount").cloned())
+        };
+        assert_eq!(scroll("прокрути в начало"), ("top".to_string(), None));
+        assert_eq!(scroll("пролистай к началу"), ("top".to_string(), None));
+        assert_eq!(scroll("прокрути в конец"), ("bottom".to_string(), None));
+        assert_eq!(scroll("прокрути страницу вниз"), ("page_down".to_string(), None));
+        assert_eq!(scroll("прокрути вниз 5"), ("down".to_string(), Some("5".to_string())));
+        assert_eq!(scroll("scroll to top"), ("top".to_string(), None));
+    }
+
+    #[test]
+    fn spelled_out_numbers_combine_only_from_higher_to_lower_places() {
+        assert_eq!(words_to_numbers("двадцать пять"), "25");
+        assert_eq!(words_to_numbers("one hundred twenty"), "120");
+        assert_eq!(words_to_numbers("one hundred and five"), "105");
+        assert_eq!(words_to_numbers("две тысячи триста сорок"), "2340");
+        assert_eq!(words_to_numbers("пять двадцать"), "5 20");
+        assert_eq!(words_to_numbers("сто сто"), "100 100");
+        assert_eq!(words_to_numbers("one and two"), "1 and 2");
+        assert_eq!(words_to_numbers("восемьсот на шестьсот"), "800 на 600");
+    }
+
+    #[test]
+    fn relative_resize_sums_the_deltas_per_dimension() {
+        // "точек" keeps its "е", unlike "точки".
+        let resize = |command: &str| {
+            let result = parse_command(command);
+            assert_eq!(result.intent, "window_resize_relative", "{}", command);
+            (result.parameters["dw"].clone(), result.parameters["dh"].clone())
+        };
+        assert_eq!(resize("сделай окно на 200 точек шире"), ("200".to_string(), "0".to_string()));
+        assert_eq!(resize("сделай окно на 100 пикселей уже и на 50 выше"), ("-100".to_string(), "50".to_string()));
+        assert_eq!(resize("make the window 40 pixels shorter"), ("0".to_string(), "-40".to_string()));
+    }
+
+    #[test]
+    fn every_example_in_the_russian_language_file_parses_to_its_intent() {
+        let patterns = Patterns::new("lang/ru.lng").unwrap();
+        assert!(!patterns.examples.is_empty());
+        for (intent, example) in patterns.examples.iter() {
+            assert_eq!(&parse_command_with(example, &patterns).intent, intent, "{}", example);
+        }
+    }
+
+    #[test]
+    fn clicks_and_tabs_keep_the_caption_as_typed() {
+        let click = parse_command("нажми на кнопку \"Сохранить как\"");
+        assert_eq!(click.intent, "button_click");
+        assert_eq!(click.parameters["label"], "Сохранить как");
+        let double_click = parse_command("двойной клик по файлу");
+        assert_eq!(double_click.intent, "button_double_click");
+        assert_eq!(double_click.parameters["label"], "файлу");
+        let tab = parse_command("выбери вкладку настройки");
+        assert_eq!(tab.intent, "tabcontrol_select_tab");
+        assert_eq!(tab.parameters["tab"], "настройки");
+    }
+
+    #[test]
+    fn tested_patterns_run_against_the_stemmed_command() {
+        let patterns = Patterns::new("lang/ru.lng").unwrap();
+        // The stem, not the typed word, is what a pattern sees.
+        let (_, normalized) = normalize_command("Прокрути в начало", &patterns);
+        assert_eq!(normalized, "прокрут нача");
+        let regex = Regex::new(r"прокрут\w*\s+(?P<where>нача\w*)").unwrap();
+        let (captures, named) = pattern_captures(&regex, &normalized);
+        assert_eq!(captures, vec![Some("прокрут нача".to_string()), Some("нача".to_string())]);
+        assert_eq!(named["where"], "нача");
+
+        let regex = Regex::new(r"начал(о)?|(конец)").unwrap();
+        let (captures, named) = pattern_captures(&regex, &normalized);
+        assert!(captures.is_empty());
+        assert!(named.is_empty());
+
+        let regex = Regex::new(r"(нача)|(кон)").unwrap();
+        let (captures, _) = pattern_captures(&regex, &normalized);
+        assert_eq!(captures, vec![Some("нача".to_string()), Some("нача".to_string()), None]);
+    }
+}
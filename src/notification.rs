@@ -0,0 +1,98 @@
+use std::sync::Arc;
+use log::{info, error};
+
+use crate::config::AppConfig;
+
+/// Delivers task notifications to the user.
+pub trait NotificationSink: Send + Sync {
+    fn notify(&self, message: &str);
+}
+
+/// Writes notifications to the application log.
+pub struct LogSink;
+
+impl NotificationSink for LogSink {
+    fn notify(&self, message: &str) {
+        info!("[NOTIFY] {}", message);
+    }
+}
+
+/// Drops every notification.
+pub struct NullSink;
+
+impl NotificationSink for NullSink {
+    fn notify(&self, _message: &str) {}
+}
+
+/// Shows notifications as Windows toasts.
+pub struct WindowsToastSink {
+    app_id: String,
+}
+
+impl WindowsToastSink {
+    /// `app_id` is the AppUserModelID the toasts are shown under.
+    pub fn new(app_id: &str) -> Self {
+        WindowsToastSink { app_id: app_id.to_string() }
+    }
+}
+
+impl NotificationSink for WindowsToastSink {
+    fn notify(&self, message: &str) {
+        if let Err(e) = show_toast(&self.app_id, message) {
+            error!("[NOTIFY] Failed to show toast, message was '{}': {}", message, e);
+        }
+    }
+}
+
+/// AppUserModelID used for toasts. PowerShell's ID is registered on every
+/// Windows install, so toasts show up without registering a shortcut.
+const TOAST_APP_ID: &str = "{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\\WindowsPowerShell\\v1.0\\powershell.exe";
+
+/// Builds the sink selected by `notification_backend` ("log", "toast" or "none").
+/// Returns the null sink when notifications are disabled; unknown backends fall back to the log.
+pub fn sink_from_config(config: &AppConfig) -> Arc<dyn NotificationSink> {
+    if !config.notification_enable {
+        return Arc::new(NullSink);
+    }
+    match config.notification_backend.to_lowercase().as_str() {
+        "none" => Arc::new(NullSink),
+        "toast" => Arc::new(WindowsToastSink::new(TOAST_APP_ID)),
+        "log" => Arc::new(LogSink),
+        other => {
+            error!("[NOTIFY] Unknown notification backend '{}', using log", other);
+            Arc::new(LogSink)
+        }
+    }
+}
+
+#[cfg(all(windows, feature = "enable_win32"))]
+fn show_toast(app_id: &str, message: &str) -> Result<(), String> {
+    use windows::core::HSTRING;
+    use windows::Data::Xml::Dom::XmlDocument;
+    use windows::UI::Notifications::{ToastNotification, ToastNotificationManager};
+
+    let xml = format!(
+        "<toast><visual><binding template=\"ToastGeneric\"><text>{}</text></binding></visual></toast>",
+        escape_xml(message)
+    );
+    let document = XmlDocument::new().map_err(|e| e.to_string())?;
+    document.LoadXml(&HSTRING::from(xml)).map_err(|e| e.to_string())?;
+    let toast = ToastNotification::CreateToastNotification(&document).map_err(|e| e.to_string())?;
+    ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(app_id))
+        .and_then(|notifier| notifier.Show(&toast))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(all(windows, feature = "enable_win32")))]
+fn show_toast(_app_id: &str, _message: &str) -> Result<(), String> {
+    Err("Toast notifications are only available on Windows".to_string())
+}
+
+/// Escapes the characters that are special in XML text content.
+#[cfg(all(windows, feature = "enable_win32"))]
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -1,78 +1,108 @@
 #![allow(non_snake_case, unused_unsafe)]
 
+use crate::core::intent::{CheckState, OpenKind};
 use crate::platform::windows::winapi::*;
 use log::{info, warn, error, debug};
 use windows_sys::Win32::Foundation::{HWND, LPARAM, WPARAM, RECT};
 use windows_sys::Win32::UI::WindowsAndMessaging::{
-    BM_CLICK, BM_GETCHECK, BM_SETCHECK, BST_CHECKED, BST_UNCHECKED, EM_SETSEL,
-    SB_LINEUP, SB_LINEDOWN, SW_MAXIMIZE, SW_MINIMIZE, SW_SHOWNORMAL,
-    TCM_SETCURSEL, TVM_EXPAND, TVM_SELECTITEM, WM_VSCROLL, WM_CLOSE, LVM_SETITEMSTATE,
-    MoveWindow, SetWindowPos, SWP_NOZORDER, SWP_NOACTIVATE, FindWindowW, GetWindowTextW,
+    BM_CLICK, BM_GETCHECK, BM_SETCHECK, BST_CHECKED, BST_INDETERMINATE, BST_UNCHECKED, EM_SETSEL,
+    SB_LINEUP, SB_LINEDOWN, SB_PAGEUP, SB_PAGEDOWN, SB_TOP, SB_BOTTOM, SW_MAXIMIZE, SW_MINIMIZE, SW_SHOWNORMAL,
+    TCM_SETCURSEL, TCM_GETCURSEL, EM_GETSEL, WM_GETTEXT, WM_GETTEXTLENGTH, GetGUIThreadInfo, GUITHREADINFO,
+    TVM_EXPAND, TVM_SELECTITEM, WM_VSCROLL, WM_CLOSE, LVM_SETITEMSTATE,
+    MoveWindow, SetWindowPos, SWP_NOZORDER, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, HWND_TOPMOST, HWND_NOTOPMOST, FindWindowW, GetWindowTextW,
     GetWindowTextLengthW, SendMessageW, ShowWindow, SetWindowTextW, EnumWindows, IsWindowVisible,
     GetForegroundWindow, SetFocus, EnumChildWindows, GetClassNameW, WM_COPY, WM_CUT, WM_CLEAR,
     WM_PASTE, GetClientRect, CB_SETCURSEL, CB_GETCOUNT, CBS_DROPDOWNLIST, IsWindowEnabled,
     GWL_STYLE, GetWindowLongW, SHELLEXECUTEINFOW, ShellExecuteExW, SEE_MASK_NOCLOSEPROCESS,
-    SEE_MASK_FLAG_DDE, SEE_MASK_INVOKEIDLIST, SEE_MASK_IDLIST, SEE_MASK_CLASSNAME, SW_SHOW
+    SEE_MASK_FLAG_DDE, SEE_MASK_INVOKEIDLIST, SEE_MASK_IDLIST, SEE_MASK_CLASSNAME, SW_SHOW, SW_HIDE,
+    IsIconic, SW_RESTORE, SetForegroundWindow, BringWindowToTop, GetWindowThreadProcessId,
+    PostMessageW, WM_SYSCOMMAND, IsWindow, GetParent, SC_RESTORE, SC_MINIMIZE, SC_MAXIMIZE, SC_CLOSE, SC_MOVE, SC_SIZE,
 };
+use windows_sys::Win32::System::Threading::{GetCurrentThreadId, AttachThreadInput};
 use windows_sys::Win32::Graphics::Gdi::{HORZRES, VERTRES, SRCCOPY};
 use windows_sys::Win32::UI::Shell::ShellExecuteW;
+use windows_sys::Win32::UI::HiDpi::GetDpiForWindow;
 use windows_sys::core::{PCWSTR, w, PSTR};
 use std::ffi::OsString;
 use std::os::windows::prelude::*;
 use std::mem;
 use std::ptr::null_mut;
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
+// Interval between window lookups while waiting for a window to appear.
+const WINDOW_POLL_INTERVAL_MS: u64 = 100;
+
+// DPI at 100% scaling.
+const USER_DEFAULT_SCREEN_DPI: u32 = 96;
+
+// `GetPixel` result for a point outside the device context.
+const CLR_INVALID: u32 = 0xFFFF_FFFF;
+
 // Generic Result type for platform-specific operations
 pub type PlatformResult<T> = Result<T, String>;
 
-pub struct WinUiController {}
+pub struct WinUiController {
+    hidden_windows: Mutex<Vec<(String, isize)>>, // Windows hidden by `hide_window`, newest last, with the label they were hidden under
+}
 
 impl WinUiController {
     pub fn new() -> Self {
-        WinUiController {}
+        WinUiController { hidden_windows: Mutex::new(Vec::new()) }
     }
 
     /// Clicks a button with the given label.
-    pub fn click_button(&self, label: &str) -> PlatformResult<()> {
+    /// When `hwnd` is given it is used directly instead of searching by label.
+    pub fn click_button(&self, label: &str, hwnd: Option<isize>) -> PlatformResult<()> {
         info!("Clicking button with label: {}", label);
         unsafe {
-            let hwnd = find_window(Some("Button"), Some(label));
+            let hwnd = match hwnd {
+                Some(handle) => window_from_handle(handle)?,
+                None => find_control(Some("Button"), label)?,
+            };
             if hwnd.0 == 0 {
+                let os_error = last_error_string();
                 error!("Button with label '{}' not found", label);
-                return Err(format!("Button with label '{}' not found", label));
+                return Err(format!("Button with label '{}' not found: {}", label, os_error));
             }
 
             let result = send_message(hwnd, BM_CLICK, WPARAM(0), LPARAM(0));
             if result == 0 {
+                 let os_error = last_error_string();
                  warn!("Click failed for button with label '{}'", label);
-                return Err(format!("Click failed for button with label '{}'", label));
+                return Err(format!("Click failed for button with label '{}': {}", label, os_error));
             }
             Ok(())
         }
     }
 
      /// Double-clicks a button with the given label.
-    pub fn double_click_button(&self, label: &str) -> PlatformResult<()> {
+    pub fn double_click_button(&self, label: &str, hwnd: Option<isize>) -> PlatformResult<()> {
         info!("Double-clicking button with label: {}", label);
-        self.click_button(label)?;
+        self.click_button(label, hwnd)?;
         std::thread::sleep(std::time::Duration::from_millis(100)); // Small delay
-        self.click_button(label)
+        self.click_button(label, hwnd)
     }
 
     /// Enters text into an edit control with the given label.
-    pub fn enter_text(&self, label: &str, text: &str) -> PlatformResult<()> {
+    /// When `hwnd` is given it is used directly instead of searching by label.
+    pub fn enter_text(&self, label: &str, text: &str, hwnd: Option<isize>) -> PlatformResult<()> {
         info!("Entering text '{}' into edit control with label: {}", text, label);
         unsafe {
-            let hwnd = find_window(Some("Edit"), Some(label));
+            let hwnd = match hwnd {
+                Some(handle) => window_from_handle(handle)?,
+                None => find_control(Some("Edit"), label)?,
+            };
             if hwnd.0 == 0 {
+                let os_error = last_error_string();
                 error!("Edit control with label '{}' not found", label);
-                return Err(format!("Edit control with label '{}' not found", label));
+                return Err(format!("Edit control with label '{}' not found: {}", label, os_error));
             }
             if !set_window_text(hwnd, text) {
+                let os_error = last_error_string();
                 error!("Failed to set text for edit control with label '{}'", label);
-                return Err(format!("Failed to set text for edit control with label '{}'", label));
+                return Err(format!("Failed to set text for edit control with label '{}': {}", label, os_error));
             }
             Ok(())
         }
@@ -82,10 +112,11 @@ impl WinUiController {
     pub fn select_text(&self, label: &str, start: Option<u32>, end: Option<u32>) -> PlatformResult<()> {
         info!("Selecting text in edit control '{}' from {:?} to {:?}", label, start, end);
         unsafe {
-            let hwnd = find_window(Some("Edit"), Some(label));
+            let hwnd = find_control(Some("Edit"), label)?;
             if hwnd.0 == 0 {
+                let os_error = last_error_string();
                 error!("Edit control with label '{}' not found", label);
-                return Err(format!("Edit control with label '{}' not found", label));
+                return Err(format!("Edit control with label '{}' not found: {}", label, os_error));
             }
 
             let sel_start = start.unwrap_or(0) as usize;
@@ -93,8 +124,9 @@ impl WinUiController {
 
             let result = send_message(hwnd, EM_SETSEL, WPARAM(sel_start), LPARAM(sel_end));
             if result == 0 {
+                let os_error = last_error_string();
                 warn!("Failed to select text in edit control '{}'", label);
-                return Err(format!("Failed to select text in edit control '{}'", label));
+                return Err(format!("Failed to select text in edit control '{}': {}", label, os_error));
             }
 
             Ok(())
@@ -105,10 +137,11 @@ impl WinUiController {
     pub fn copy_text(&self, label: &str) -> PlatformResult<()> {
         info!("Copying text from edit control: {}", label);
          unsafe {
-             let hwnd = find_window(Some("Edit"), Some(label));
+             let hwnd = find_control(Some("Edit"), label)?;
             if hwnd.0 == 0 {
+                let os_error = last_error_string();
                 error!("Edit control with label '{}' not found", label);
-                return Err(format!("Edit control with label '{}' not found", label));
+                return Err(format!("Edit control with label '{}' not found: {}", label, os_error));
             }
 
             send_message(hwnd, WM_COPY, WPARAM(0), LPARAM(0));
@@ -120,10 +153,11 @@ impl WinUiController {
     pub fn cut_text(&self, label: &str) -> PlatformResult<()> {
         info!("Cutting text from edit control: {}", label);
          unsafe {
-              let hwnd = find_window(Some("Edit"), Some(label));
+              let hwnd = find_control(Some("Edit"), label)?;
             if hwnd.0 == 0 {
+                let os_error = last_error_string();
                 error!("Edit control with label '{}' not found", label);
-                return Err(format!("Edit control with label '{}' not found", label));
+                return Err(format!("Edit control with label '{}' not found: {}", label, os_error));
             }
 
             send_message(hwnd, WM_CUT, WPARAM(0), LPARAM(0));
@@ -135,10 +169,11 @@ impl WinUiController {
      pub fn clear_field(&self, label: &str) -> PlatformResult<()> {
         info!("Clearing text from edit control: {}", label);
          unsafe {
-               let hwnd = find_window(Some("Edit"), Some(label));
+               let hwnd = find_control(Some("Edit"), label)?;
             if hwnd.0 == 0 {
+                let os_error = last_error_string();
                 error!("Edit control with label '{}' not found", label);
-                return Err(format!("Edit control with label '{}' not found", label));
+                return Err(format!("Edit control with label '{}' not found: {}", label, os_error));
             }
             send_message(hwnd, WM_CLEAR, WPARAM(0), LPARAM(0));
             Ok(())
@@ -149,10 +184,11 @@ impl WinUiController {
      pub fn paste_text(&self, label: &str) -> PlatformResult<()> {
         info!("Pasting text to edit control: {}", label);
          unsafe {
-              let hwnd = find_window(Some("Edit"), Some(label));
+              let hwnd = find_control(Some("Edit"), label)?;
             if hwnd.0 == 0 {
+                let os_error = last_error_string();
                 error!("Edit control with label '{}' not found", label);
-                return Err(format!("Edit control with label '{}' not found", label));
+                return Err(format!("Edit control with label '{}' not found: {}", label, os_error));
             }
               send_message(hwnd, WM_PASTE, WPARAM(0), LPARAM(0));
               Ok(())
@@ -163,10 +199,11 @@ impl WinUiController {
     pub fn get_static_text(&self, label: &str) -> PlatformResult<String> {
          info!("Getting text from static control: {}", label);
          unsafe {
-             let hwnd = find_window(Some("Static"), Some(label));
+             let hwnd = find_control(Some("Static"), label)?;
              if hwnd.0 == 0 {
+                 let os_error = last_error_string();
                  error!("Static control with label '{}' not found", label);
-                 return Err(format!("Static control with label '{}' not found", label));
+                 return Err(format!("Static control with label '{}' not found: {}", label, os_error));
              }
              let len = GetWindowTextLengthW(hwnd) as usize;
             if len == 0 {
@@ -185,46 +222,411 @@ impl WinUiController {
          }
     }
 
+    /// Reads the whole text of an edit control, found by label or by window handle
+    pub fn get_edit_text(&self, label: &str, hwnd: Option<isize>) -> PlatformResult<String> {
+        info!("Getting text from edit control: {}", label);
+        unsafe {
+            let hwnd = match hwnd {
+                Some(handle) => window_from_handle(handle)?,
+                None => find_control(Some("Edit"), label)?,
+            };
+            if hwnd.0 == 0 {
+                let os_error = last_error_string();
+                error!("Edit control with label '{}' not found", label);
+                return Err(format!("Edit control with label '{}' not found: {}", label, os_error));
+            }
+            let len = GetWindowTextLengthW(hwnd.0) as usize;
+            if len == 0 {
+                return Ok("".to_string());
+            }
+            let mut buffer: Vec<u16> = vec![0; len + 1];
+            if GetWindowTextW(hwnd.0, buffer.as_mut_ptr(), (len + 1) as i32) == 0 {
+                return Err("GetWindowTextW return 0".to_string());
+            }
+            String::from_utf16(&buffer[..len]).map_err(|e| format!("Failed to convert from UTF-16: {}", e))
+        }
+    }
+
+    /// Reads the selected text of an edit control, or of the focused control when no label is given
+    pub fn get_selected_text(&self, label: Option<&str>) -> PlatformResult<String> {
+        info!("Getting selected text from: {}", label.unwrap_or("focused control"));
+        unsafe {
+            let hwnd = match label {
+                Some(lbl) => find_control(Some("Edit"), lbl)?,
+                None => focused_control(),
+            };
+            if hwnd.0 == 0 {
+                let os_error = last_error_string();
+                error!("Text control '{}' not found", label.unwrap_or("focused control"));
+                return Err(format!("Text control '{}' not found: {}", label.unwrap_or("focused control"), os_error));
+            }
+
+            let mut start: u32 = 0;
+            let mut end: u32 = 0;
+            send_message(hwnd, EM_GETSEL, WPARAM(&mut start as *mut u32 as usize), LPARAM(&mut end as *mut u32 as isize));
+            if start == end {
+                return Ok(String::new());
+            }
+
+            let len = send_message(hwnd, WM_GETTEXTLENGTH, WPARAM(0), LPARAM(0)) as usize;
+            let mut buffer: Vec<u16> = vec![0; len + 1];
+            let copied = send_message(hwnd, WM_GETTEXT, WPARAM(len + 1), LPARAM(buffer.as_mut_ptr() as isize)) as usize;
+            Ok(slice_selection(&buffer[..copied.min(len)], start as usize, end as usize))
+        }
+    }
+
+    /// Checks whether a window exists without touching it: an exact title match first,
+    /// then a case-insensitive substring match over the visible top-level windows
+    pub fn window_exists(&self, label: &str, class: Option<&str>) -> PlatformResult<bool> {
+        debug!("Checking whether window '{}' (class {:?}) exists", label, class);
+        unsafe {
+            if find_window(class, Some(label)).0 != 0 {
+                return Ok(true);
+            }
+            let needle = label.to_lowercase();
+            if needle.is_empty() {
+                return Ok(false);
+            }
+            Ok(list_windows().iter().any(|window| {
+                class.is_none_or(|class| window.class_name.eq_ignore_ascii_case(class))
+                    && window.title.to_lowercase().contains(&needle)
+            }))
+        }
+    }
+
+    /// Lists the visible top-level windows a command can target
+    pub fn list_windows(&self) -> PlatformResult<Vec<WindowInfo>> {
+        info!("Listing top-level windows");
+        let windows = unsafe { list_windows() };
+        debug!("Found {} windows", windows.len());
+        Ok(windows)
+    }
+
+    /// Returns the DPI of the monitor the window (or the foreground window) is on
+    pub fn get_window_dpi(&self, label: Option<&str>) -> PlatformResult<u32> {
+        info!("Reading DPI of window {:?}", label);
+        unsafe {
+            let hwnd = match label {
+                Some(lbl) => find_control(None, lbl)?,
+                None => HWND(GetForegroundWindow()),
+            };
+            if hwnd.0 == 0 {
+                let os_error = last_error_string();
+                error!("Window with label '{:?}' not found", label);
+                return Err(format!("Window with label '{:?}' not found: {}", label, os_error));
+            }
+            let dpi = GetDpiForWindow(hwnd.0);
+            if dpi == 0 {
+                let os_error = last_error_string();
+                return Err(format!("Failed to read DPI of window '{:?}': {}", label, os_error));
+            }
+            Ok(dpi)
+        }
+    }
+
+    /// Reads the color of the screen pixel at (`x`, `y`) as red, green and blue
+    pub fn get_pixel_color(&self, x: i32, y: i32) -> PlatformResult<(u8, u8, u8)> {
+        info!("Reading pixel color at ({}, {})", x, y);
+        unsafe {
+            let hdc = get_dc(HWND(0));
+            if hdc == 0 {
+                let os_error = last_error_string();
+                return Err(format!("Failed to get the screen device context: {}", os_error));
+            }
+            let color = get_pixel(hdc, x, y);
+            release_dc(HWND(0), hdc);
+            if color == CLR_INVALID {
+                return Err(format!("Pixel ({}, {}) is outside the screen", x, y));
+            }
+            Ok(((color & 0xFF) as u8, ((color >> 8) & 0xFF) as u8, ((color >> 16) & 0xFF) as u8))
+        }
+    }
+
+    /// Brings a window to the foreground, restoring it first if it is minimized. With a `process` only
+    /// that executable's windows are searched, and an empty label means its first window.
+    pub fn activate_window(&self, label: &str, process: Option<&str>) -> PlatformResult<()> {
+        info!("Activating window '{}' (process {:?})", label, process);
+        unsafe {
+            let hwnd = find_scoped_window(label, process)?;
+            if hwnd.0 == 0 {
+                let os_error = last_error_string();
+                error!("Window with label '{}' not found in process {:?}", label, process);
+                return Err(format!("Window with label '{}' not found: {}", label, os_error));
+            }
+            if IsIconic(hwnd.0) != 0 {
+                ShowWindow(hwnd.0, SW_RESTORE);
+            }
+            // Only the thread owning the foreground window may hand it over, so borrow its input queue.
+            let foreground_thread = GetWindowThreadProcessId(GetForegroundWindow(), null_mut());
+            let current_thread = GetCurrentThreadId();
+            let attached = foreground_thread != 0
+                && foreground_thread != current_thread
+                && AttachThreadInput(current_thread, foreground_thread, 1) != 0;
+            SetForegroundWindow(hwnd.0);
+            BringWindowToTop(hwnd.0);
+            if attached {
+                AttachThreadInput(current_thread, foreground_thread, 0);
+            }
+            if GetForegroundWindow() != hwnd.0 {
+                let os_error = last_error_string();
+                warn!("Window '{}' did not come to the foreground", label);
+                return Err(format!("Failed to bring window '{}' to the foreground: {}", label, os_error));
+            }
+            Ok(())
+        }
+    }
+
+    /// Hides a window entirely and remembers its handle for [`Self::show_window`]. An empty label means
+    /// the foreground window. Returns the label it was remembered under.
+    pub fn hide_window(&self, label: &str) -> PlatformResult<String> {
+        info!("Hiding window '{}'", label);
+        unsafe {
+            let hwnd = if label.is_empty() { HWND(GetForegroundWindow()) } else { find_control(None, label)? };
+            if hwnd.0 == 0 {
+                let os_error = last_error_string();
+                error!("Window with label '{}' not found", label);
+                return Err(format!("Window with label '{}' not found: {}", label, os_error));
+            }
+            let title = if label.is_empty() { get_window_text(hwnd).unwrap_or_default() } else { label.to_string() };
+            warn!("Hiding window '{}'; without a tray icon it can only be brought back with WindowShow", title);
+            ShowWindow(hwnd.0, SW_HIDE);
+            self.hidden_windows.lock().unwrap().push((title.clone(), hwnd.0));
+            Ok(title)
+        }
+    }
+
+    /// Sends a raw window message to a window or control and returns what it answered.
+    pub fn send_raw_message(&self, label: &str, msg: u32, wparam: usize, lparam: isize) -> PlatformResult<isize> {
+        warn!("Sending message {:#x} to '{}' (wParam={:#x}, lParam={:#x})", msg, label, wparam, lparam);
+        unsafe {
+            let hwnd = find_control(None, label)?;
+            if hwnd.0 == 0 {
+                let os_error = last_error_string();
+                error!("Window or control with label '{}' not found", label);
+                return Err(format!("Window or control with label '{}' not found: {}", label, os_error));
+            }
+            Ok(send_message(hwnd, msg, WPARAM(wparam), LPARAM(lparam)))
+        }
+    }
+
+    /// Shows a window hidden by [`Self::hide_window`], found by the label it was hidden under even if
+    /// its title has changed since, or the most recently hidden one for an empty label.
+    pub fn show_window(&self, label: &str) -> PlatformResult<()> {
+        info!("Showing hidden window '{}'", label);
+        unsafe {
+            let hidden = {
+                let mut hidden = self.hidden_windows.lock().unwrap();
+                // Windows closed while hidden are forgotten.
+                hidden.retain(|(_, handle)| IsWindow(*handle) != 0);
+                let index = if label.is_empty() {
+                    hidden.len().checked_sub(1)
+                } else {
+                    let label = label.to_lowercase();
+                    hidden.iter().rposition(|(hidden_label, _)| hidden_label.to_lowercase() == label)
+                };
+                index.map(|index| HWND(hidden.remove(index).1))
+            };
+            let hwnd = match hidden {
+                Some(hwnd) => hwnd,
+                None if !label.is_empty() => find_control(None, label)?,
+                None => return Err("No hidden windows to show".to_string()),
+            };
+            if hwnd.0 == 0 {
+                let os_error = last_error_string();
+                error!("Window with label '{}' not found", label);
+                return Err(format!("Window with label '{}' not found: {}", label, os_error));
+            }
+            ShowWindow(hwnd.0, SW_SHOW);
+            Ok(())
+        }
+    }
+
+    /// Keeps a window above all non-topmost windows, or releases it. An empty label means the foreground window.
+    pub fn set_topmost(&self, label: &str, topmost: bool) -> PlatformResult<()> {
+        info!("Setting window '{}' topmost: {}", label, topmost);
+        unsafe {
+            let hwnd = if label.is_empty() { HWND(GetForegroundWindow()) } else { find_control(None, label)? };
+            if hwnd.0 == 0 {
+                let os_error = last_error_string();
+                error!("Window with label '{}' not found", label);
+                return Err(format!("Window with label '{}' not found: {}", label, os_error));
+            }
+            let insert_after = if topmost { HWND_TOPMOST } else { HWND_NOTOPMOST };
+            if !SetWindowPos(hwnd, HWND(insert_after), 0, 0, 0, 0, SWP_NOMOVE | SWP_NOSIZE).as_bool() {
+                let os_error = last_error_string();
+                error!("Failed to change topmost state of window '{}'", label);
+                return Err(format!("Failed to change topmost state of window '{}': {}", label, os_error));
+            }
+            Ok(())
+        }
+    }
+
+    /// Scrolls the foreground window: `up`/`down` by lines and `page_up`/`page_down` by pages,
+    /// `amount` times, or straight to the `top`/`bottom` with a single message
+    pub fn scroll_window(&self, direction: &str, amount: Option<u32>) -> PlatformResult<()> {
+        info!("Scrolling foreground window '{}' by {:?}", direction, amount);
+        let (code, repeats) = match direction.to_lowercase().as_str() {
+            "up" => (SB_LINEUP, true),
+            "down" => (SB_LINEDOWN, true),
+            "page_up" => (SB_PAGEUP, true),
+            "page_down" => (SB_PAGEDOWN, true),
+            "top" => (SB_TOP, false),
+            "bottom" => (SB_BOTTOM, false),
+            _ => return Err(format!("Invalid scroll direction '{}'", direction)),
+        };
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd == 0 {
+                let os_error = last_error_string();
+                return Err(format!("Foreground window not found for scrolling: {}", os_error));
+            }
+            let count = if repeats { amount.unwrap_or(1) } else { 1 };
+            for _ in 0..count {
+                send_message(HWND(hwnd), WM_VSCROLL, WPARAM(code as usize), LPARAM(0));
+                thread::sleep(Duration::from_millis(50));
+            }
+            Ok(())
+        }
+    }
+
+    /// Sends a system menu command (restore, minimize, maximize, close, move, size) to a window
+    pub fn system_menu(&self, label: &str, command: &str) -> PlatformResult<()> {
+        info!("Sending system menu command '{}' to window '{}'", command, label);
+        let code = match command.to_lowercase().as_str() {
+            "restore" => SC_RESTORE,
+            "minimize" => SC_MINIMIZE,
+            "maximize" => SC_MAXIMIZE,
+            "close" => SC_CLOSE,
+            "move" => SC_MOVE,
+            "size" => SC_SIZE,
+            _ => return Err(format!("Unknown system menu command '{}'", command)),
+        };
+        unsafe {
+            let hwnd = find_control(None, label)?;
+            if hwnd.0 == 0 {
+                let os_error = last_error_string();
+                error!("Window with label '{}' not found", label);
+                return Err(format!("Window with label '{}' not found: {}", label, os_error));
+            }
+            // Posted, because SC_MOVE/SC_SIZE enter a modal loop that would block a sent message.
+            if PostMessageW(hwnd.0, WM_SYSCOMMAND, code as usize, 0) == 0 {
+                let os_error = last_error_string();
+                return Err(format!("Failed to send '{}' to window '{}': {}", command, label, os_error));
+            }
+            Ok(())
+        }
+    }
+
+    /// Waits until one of the windows titled `labels` appears and returns that title
+    pub fn wait_for_any_window(&self, labels: &[String], timeout_ms: u64) -> PlatformResult<String> {
+        info!("Waiting up to {} ms for any of {:?}", timeout_ms, labels);
+        let timeout = Duration::from_millis(timeout_ms);
+        let start = std::time::Instant::now();
+        loop {
+            let found = labels.iter().find(|label| unsafe { find_window(None, Some(label)).0 != 0 });
+            if let Some(label) = found {
+                return Ok(label.clone());
+            }
+            if start.elapsed() >= timeout {
+                return Err(format!("timed out waiting for '{}'", labels.join("' | '")));
+            }
+            thread::sleep(Duration::from_millis(WINDOW_POLL_INTERVAL_MS));
+        }
+    }
+
+    /// Waits until the foreground window's title contains `expected` (ignoring case) and returns that title
+    pub fn wait_for_title(&self, expected: &str, timeout_ms: u64) -> PlatformResult<String> {
+        info!("Waiting up to {} ms for a foreground title containing '{}'", timeout_ms, expected);
+        let timeout = Duration::from_millis(timeout_ms);
+        let expected_lower = expected.to_lowercase();
+        let start = std::time::Instant::now();
+        loop {
+            let title = unsafe {
+                let hwnd = GetForegroundWindow();
+                let len = GetWindowTextLengthW(hwnd) as usize;
+                let mut buffer: Vec<u16> = vec![0; len + 1];
+                let copied = GetWindowTextW(hwnd, buffer.as_mut_ptr(), (len + 1) as i32).max(0) as usize;
+                String::from_utf16_lossy(&buffer[..copied])
+            };
+            if title.to_lowercase().contains(&expected_lower) {
+                return Ok(title);
+            }
+            if start.elapsed() >= timeout {
+                return Err(format!("timed out waiting for title '{}'", expected));
+            }
+            thread::sleep(Duration::from_millis(WINDOW_POLL_INTERVAL_MS));
+        }
+    }
+
     /// Sets focus
     pub fn set_focus(&self, label: &str) -> PlatformResult<()> {
          info!("Setting focus on {}", label);
          unsafe {
-             let hwnd = find_window(None, Some(label));
+             let hwnd = find_control(None, label)?;
             if hwnd.0 == 0 {
+                let os_error = last_error_string();
                 error!("Window with label '{}' not found", label);
-                return Err(format!("Window with label '{}' not found", label));
+                return Err(format!("Window with label '{}' not found: {}", label, os_error));
             }
            if SetFocus(hwnd).0 == 0 {
+                let os_error = last_error_string();
                 error!("Failed to set focus on window with label '{}'", label);
-                return Err(format!("Failed to set focus on window with label '{}'", label));
+                return Err(format!("Failed to set focus on window with label '{}': {}", label, os_error));
             }
             Ok(())
          }
     }
 
-    /// Sets the checked state of a checkbox
-    pub fn set_checkbox_state(&self, label: &str, checked: bool) -> PlatformResult<()> {
-        info!("Setting checkbox '{}' to state: {}", label, checked);
+    /// Sets the checked state of a checkbox; `Indeterminate` needs a three-state box
+    pub fn set_checkbox_state(&self, label: &str, state: CheckState) -> PlatformResult<()> {
+        info!("Setting checkbox '{}' to state: {}", label, state.as_str());
         unsafe {
-            let hwnd = find_window(Some("Button"), Some(label));
+            let hwnd = find_control(Some("Button"), label)?;
             if hwnd.0 == 0 {
+                let os_error = last_error_string();
                 error!("Checkbox with label '{}' not found", label);
-                return Err(format!("Checkbox with label '{}' not found", label));
+                return Err(format!("Checkbox with label '{}' not found: {}", label, os_error));
             }
-            let check_state = if checked { BST_CHECKED } else { BST_UNCHECKED };
+            let check_state = match state {
+                CheckState::Checked => BST_CHECKED,
+                CheckState::Unchecked => BST_UNCHECKED,
+                CheckState::Indeterminate => BST_INDETERMINATE,
+            };
             send_message(hwnd, BM_SETCHECK, WPARAM(check_state as usize), LPARAM(0));
              Ok(())
         }
     }
 
+    /// Reads the checked state of a checkbox
+    pub fn get_checkbox_state(&self, label: &str) -> PlatformResult<CheckState> {
+        info!("Reading checkbox '{}' state", label);
+        unsafe {
+            let hwnd = find_control(Some("Button"), label)?;
+            if hwnd.0 == 0 {
+                let os_error = last_error_string();
+                error!("Checkbox with label '{}' not found", label);
+                return Err(format!("Checkbox with label '{}' not found: {}", label, os_error));
+            }
+            let state = send_message(hwnd, BM_GETCHECK, WPARAM(0), LPARAM(0));
+            Ok(if state == BST_CHECKED as isize {
+                CheckState::Checked
+            } else if state == BST_INDETERMINATE as isize {
+                CheckState::Indeterminate
+            } else {
+                CheckState::Unchecked
+            })
+        }
+    }
+
     /// Selects a radio button
     pub fn select_radio_button(&self, label: &str) -> PlatformResult<()> {
         info!("Selecting radio button: {}", label);
         unsafe {
-            let hwnd = find_window(Some("Button"), Some(label));
+            let hwnd = find_control(Some("Button"), label)?;
             if hwnd.0 == 0 {
+                let os_error = last_error_string();
                 error!("Radio button with label '{}' not found", label);
-                return Err(format!("Radio button with label '{}' not found", label));
+                return Err(format!("Radio button with label '{}' not found: {}", label, os_error));
             }
              send_message(hwnd, BM_SETCHECK, WPARAM(BST_CHECKED as usize), LPARAM(0));
              Ok(())
@@ -235,10 +637,11 @@ impl WinUiController {
     pub fn select_treeview_item(&self, label: &str, node_id: i32) -> PlatformResult<()> {
         info!("Selecting TreeView item with node_id: {}", node_id);
         unsafe {
-            let hwnd = find_window(Some("SysTreeView32"), Some(label));
+            let hwnd = find_control(Some("SysTreeView32"), label)?;
             if hwnd.0 == 0 {
+                let os_error = last_error_string();
                 error!("TreeView with label '{}' not found", label);
-                return Err(format!("TreeView with label '{}' not found", label));
+                return Err(format!("TreeView with label '{}' not found: {}", label, os_error));
             }
              send_message(hwnd, TVM_SELECTITEM, WPARAM(0), LPARAM(node_id as isize));
             Ok(())
@@ -249,10 +652,11 @@ impl WinUiController {
     pub fn expand_treeview_item(&self, label: &str, node_id: i32) -> PlatformResult<()> {
         info!("Expanding TreeView item with node_id: {}", node_id);
         unsafe {
-            let hwnd = find_window(Some("SysTreeView32"), Some(label));
+            let hwnd = find_control(Some("SysTreeView32"), label)?;
             if hwnd.0 == 0 {
+                let os_error = last_error_string();
                 error!("TreeView with label '{}' not found", label);
-                return Err(format!("TreeView with label '{}' not found", label));
+                return Err(format!("TreeView with label '{}' not found: {}", label, os_error));
             }
             send_message(hwnd, TVM_EXPAND, WPARAM(1), LPARAM(node_id as isize));
             Ok(())
@@ -263,43 +667,99 @@ impl WinUiController {
     pub fn select_listview_item(&self, label: &str, index: usize) -> PlatformResult<()> {
         info!("Selecting ListView item at index: {}", index);
         unsafe {
-            let hwnd = find_window(Some("SysListView32"), Some(label));
+            let hwnd = find_control(Some("SysListView32"), label)?;
             if hwnd.0 == 0 {
+                let os_error = last_error_string();
                 error!("ListView with label '{}' not found", label);
-                return Err(format!("ListView with label '{}' not found", label));
+                return Err(format!("ListView with label '{}' not found: {}", label, os_error));
             }
            send_message(hwnd, LVM_SETITEMSTATE, WPARAM(index), LPARAM(0));
             Ok(())
         }
     }
 
+    /// Returns the text of every row of a ListView
+    pub fn get_listview_items(&self, label: &str) -> PlatformResult<Vec<String>> {
+        info!("Reading items of ListView '{}'", label);
+        unsafe {
+            let hwnd = find_control(Some("SysListView32"), label)?;
+            if hwnd.0 == 0 {
+                let os_error = last_error_string();
+                error!("ListView with label '{}' not found", label);
+                return Err(format!("ListView with label '{}' not found: {}", label, os_error));
+            }
+            get_listview_items(hwnd)
+        }
+    }
+
     /// Selects a tab in a TabControl
     pub fn select_tabcontrol_tab(&self, label: &str, index: usize) -> PlatformResult<()> {
         info!("Selecting TabControl tab at index: {}", index);
         unsafe {
-            let hwnd = find_window(Some("SysTabControl32"), Some(label));
+            let hwnd = find_control(Some("SysTabControl32"), label)?;
             if hwnd.0 == 0 {
+                let os_error = last_error_string();
                 error!("TabControl with label '{}' not found", label);
-                return Err(format!("TabControl with label '{}' not found", label));
+                return Err(format!("TabControl with label '{}' not found: {}", label, os_error));
             }
             send_message(hwnd, TCM_SETCURSEL, WPARAM(index), LPARAM(0));
             Ok(())
         }
     }
 
+    /// Selects the TabControl tab whose caption matches `name`, exactly or else as a substring
+    pub fn select_tabcontrol_tab_by_name(&self, label: &str, name: &str) -> PlatformResult<usize> {
+        info!("Selecting TabControl tab named '{}'", name);
+        unsafe {
+            let hwnd = find_control(Some("SysTabControl32"), label)?;
+            if hwnd.0 == 0 {
+                let os_error = last_error_string();
+                error!("TabControl with label '{}' not found", label);
+                return Err(format!("TabControl with label '{}' not found: {}", label, os_error));
+            }
+            let captions = get_tab_captions(hwnd)?;
+            let index = match resolve_tab_index(&captions, name) {
+                Some(index) => index,
+                None => return Err(format!("Tab '{}' not found in TabControl '{}'. Available tabs: {}", name, label, captions.join(", "))),
+            };
+            send_message(hwnd, TCM_SETCURSEL, WPARAM(index), LPARAM(0));
+            Ok(index)
+        }
+    }
+
+    /// Returns the index of the selected TabControl tab
+    pub fn get_selected_tab(&self, label: &str) -> PlatformResult<usize> {
+        info!("Reading selected tab of TabControl '{}'", label);
+        unsafe {
+            let hwnd = find_control(Some("SysTabControl32"), label)?;
+            if hwnd.0 == 0 {
+                let os_error = last_error_string();
+                error!("TabControl with label '{}' not found", label);
+                return Err(format!("TabControl with label '{}' not found: {}", label, os_error));
+            }
+            let index = send_message(hwnd, TCM_GETCURSEL, WPARAM(0), LPARAM(0));
+            if index < 0 {
+                return Err(format!("TabControl '{}' has no selected tab", label));
+            }
+            Ok(index as usize)
+        }
+    }
+
     /// Resizes a window
     pub fn resize_window(&self, label: &str, width: i32, height: i32) -> PlatformResult<()> {
          info!("Resizing window '{}' to {}x{}", label, width, height);
 
         unsafe {
-            let hwnd = find_window(None, Some(label));
+            let hwnd = find_control(None, label)?;
             if hwnd.0 == 0 {
+                let os_error = last_error_string();
                 error!("Window with label '{}' not found", label);
-                return Err(format!("Window with label '{}' not found", label));
+                return Err(format!("Window with label '{}' not found: {}", label, os_error));
             }
             if !SetWindowPos(hwnd, HWND(0), 0, 0, width, height, SWP_NOZORDER | SWP_NOACTIVATE).as_bool() {
+               let os_error = last_error_string();
                error!("Failed to resize window with label '{}'", label);
-               return Err(format!("Failed to resize window with label '{}'", label));
+               return Err(format!("Failed to resize window with label '{}': {}", label, os_error));
             }
              Ok(())
         }
@@ -310,14 +770,16 @@ impl WinUiController {
         info!("Moving window '{}' to {}, {}", label, x, y);
 
         unsafe {
-           let hwnd = find_window(None, Some(label));
+           let hwnd = find_control(None, label)?;
            if hwnd.0 == 0 {
+                let os_error = last_error_string();
                 error!("Window with label '{}' not found", label);
-                return Err(format!("Window with label '{}' not found", label));
+                return Err(format!("Window with label '{}' not found: {}", label, os_error));
             }
             if !SetWindowPos(hwnd, HWND(0), x, y, 0, 0, SWP_NOZORDER | SWP_NOACTIVATE | windows_sys::Win32::UI::WindowsAndMessaging::SWP_NOSIZE).as_bool() {
+                let os_error = last_error_string();
                 error!("Failed to move window with label '{}'", label);
-                return Err(format!("Failed to move window with label '{}'", label));
+                return Err(format!("Failed to move window with label '{}': {}", label, os_error));
             }
              Ok(())
         }
@@ -327,22 +789,60 @@ impl WinUiController {
      pub fn key_press(&self, key: &str) -> PlatformResult<()> {
         info!("Sending key press: {}", key);
          unsafe {
-                let wide_key: Vec<u16> = key.encode_utf16().collect();
-                for &code_point in &wide_key {
-                        let mut input: INPUT = mem::zeroed();
-                        input.r#type = windows_sys::Win32::UI::Input::KeyboardAndMouse::INPUT_KEYBOARD as u32;
-                        input.Anonymous.ki.wVk = 0;
-                        input.Anonymous.ki.wScan = code_point; // Unicode code point
-                        input.Anonymous.ki.dwFlags = KEYEVENTF_UNICODE;
-                        SendInput(1, &mut input, std::mem::size_of::<INPUT>() as i32);
-
-                        input.Anonymous.ki.dwFlags = KEYEVENTF_UNICODE | KEYEVENTF_KEYUP;
-                        SendInput(1, &mut input, std::mem::size_of::<INPUT>() as i32);
-                }
+              send_unicode_text(key, Duration::ZERO);
               Ok(())
          }
     }
 
+    /// Types text into whatever control has keyboard focus, without looking up a window.
+    /// A `delay_ms` pause between characters helps applications that drop instant input,
+    /// at the cost of typing taking that much longer per character.
+    pub fn type_text(&self, text: &str, delay_ms: Option<u64>) -> PlatformResult<()> {
+        info!("Typing '{}' into the focused control", text);
+        unsafe {
+            let expected = text.encode_utf16().count() * 2;
+            let sent = send_unicode_text(text, Duration::from_millis(delay_ms.unwrap_or(0)));
+            if sent != expected {
+                let os_error = last_error_string();
+                return Err(format!("Typed only {} of {} key events for '{}': {}", sent, expected, text, os_error));
+            }
+            Ok(())
+        }
+    }
+
+    /// Opens a link in the default browser, a folder in Explorer, a file in its associated program,
+    /// or launches an application. Missing files and folders are reported before anything runs.
+    pub fn open_target(&self, target: &str, kind: OpenKind) -> PlatformResult<()> {
+        info!("Opening {} '{}'", kind.as_str(), target);
+        let verb = match kind {
+            OpenKind::Folder if !std::path::Path::new(target).is_dir() => {
+                return Err(format!("Folder '{}' does not exist", target));
+            }
+            OpenKind::File if !std::path::Path::new(target).is_file() => {
+                return Err(format!("File '{}' does not exist", target));
+            }
+            OpenKind::Folder => "explore",
+            OpenKind::Url | OpenKind::File | OpenKind::App => "open",
+        };
+        unsafe {
+            let wide_target = to_wide(target);
+            let operation = to_wide(verb);
+            let result = ShellExecuteW(
+                HWND(0),
+                operation.as_ptr(),
+                wide_target.as_ptr(),
+                std::ptr::null(),
+                std::ptr::null(),
+                SW_SHOWNORMAL as i32,
+            );
+            if result.0 <= 32 {
+                error!("Failed to open {} '{}'", kind.as_str(), target);
+                return Err(format!("Failed to open {} '{}' with error code {}", kind.as_str(), target, result.0));
+            }
+            Ok(())
+        }
+    }
+
     /// Launches an application using ShellExecuteW
     pub fn launch_application(&self, app: &str) -> PlatformResult<()> {
         info!("Launching application: {}", app);
@@ -365,3 +865,152 @@ impl WinUiController {
         }
     }
 }
+
+/// Converts a DPI value to the scale factor relative to the 96 DPI baseline (1.0 = 100%).
+pub fn dpi_scale_factor(dpi: u32) -> f64 {
+    dpi as f64 / USER_DEFAULT_SCREEN_DPI as f64
+}
+
+/// Resolves a `/`-delimited control path such as "MainWindow/ToolbarPanel/SaveButton".
+/// The first segment names a top-level window and each following one a direct child of the
+/// previous match. A segment matches a window's text or class name, ignoring case, and a
+/// `[n]` suffix picks the n-th match counting from 0. The error names the segment that failed.
+pub fn resolve_control_path(path: &str) -> PlatformResult<HWND> {
+    let segments: Vec<&str> = path.split('/').map(str::trim).collect();
+    let mut current: Option<HWND> = None;
+    for (depth, segment) in segments.iter().enumerate() {
+        let (name, index) = split_segment_index(segment);
+        match unsafe { matching_windows(current, name) }.get(index) {
+            Some(hwnd) => current = Some(*hwnd),
+            None => {
+                let parent = if depth == 0 {
+                    "the desktop".to_string()
+                } else {
+                    format!("'{}'", segments[..depth].join("/"))
+                };
+                return Err(format!("Control path '{}': segment {} '{}' not found under {}", path, depth + 1, segment, parent));
+            }
+        }
+    }
+    current.ok_or_else(|| format!("Control path '{}' is empty", path))
+}
+
+/// Splits a trailing `[n]` index off a path segment; segments without one select the first match.
+fn split_segment_index(segment: &str) -> (&str, usize) {
+    if let Some(open) = segment.rfind('[') {
+        if let Some(index) = segment[open + 1..].strip_suffix(']').and_then(|n| n.trim().parse().ok()) {
+            return (segment[..open].trim(), index);
+        }
+    }
+    (segment, 0)
+}
+
+/// Lists the windows whose text or class name is `name`, ignoring case and mnemonic ampersands:
+/// top-level windows when `parent` is `None`, otherwise the direct children of `parent`, in Z order.
+unsafe fn matching_windows(parent: Option<HWND>, name: &str) -> Vec<HWND> {
+    let name = name.to_lowercase();
+    if name.is_empty() {
+        return Vec::new();
+    }
+    let found = Arc::new(Mutex::new(Vec::new()));
+    let collected = found.clone();
+    let callback: EnumWindowsCallback = Box::new(move |hwnd| {
+        if parent.is_some_and(|parent| GetParent(hwnd.0) != parent.0) {
+            return true; // A grandchild; it is matched by a later segment
+        }
+        let text = get_window_text(hwnd).unwrap_or_default().replace('&', "").to_lowercase();
+        if text == name || get_class_name(hwnd).to_lowercase() == name {
+            collected.lock().unwrap().push(hwnd);
+        }
+        true
+    });
+    match parent {
+        Some(parent) => enum_child_windows(parent, callback),
+        None => enum_windows(callback),
+    };
+    let windows = found.lock().unwrap().clone();
+    windows
+}
+
+/// Finds a control by label. A window titled exactly `label` wins; otherwise a label containing
+/// `/` is resolved as a control path. Only an unresolved path is an error: a plain label that
+/// matches nothing yields `HWND(0)`, as `find_window` does.
+unsafe fn find_control(class_name: Option<&str>, label: &str) -> PlatformResult<HWND> {
+    let hwnd = find_window(class_name, Some(label));
+    if hwnd.0 != 0 || !label.contains('/') {
+        return Ok(hwnd);
+    }
+    resolve_control_path(label)
+}
+
+/// Finds the window titled `label` (case-insensitive) among the windows of the executable `process`,
+/// or its first window for an empty label. Without a process this is [`find_control`].
+unsafe fn find_scoped_window(label: &str, process: Option<&str>) -> PlatformResult<HWND> {
+    let process = match process {
+        Some(process) => process,
+        None => return find_control(None, label),
+    };
+    let label = label.to_lowercase();
+    Ok(list_process_windows(process)
+        .into_iter()
+        .find(|window| label.is_empty() || window.title.to_lowercase() == label)
+        .map_or(HWND(0), |window| HWND(window.hwnd)))
+}
+
+/// Returns the control that has keyboard focus in the foreground thread.
+unsafe fn focused_control() -> HWND {
+    let mut info: GUITHREADINFO = mem::zeroed();
+    info.cbSize = mem::size_of::<GUITHREADINFO>() as u32;
+    if GetGUIThreadInfo(0, &mut info) == 0 {
+        return HWND(0);
+    }
+    HWND(info.hwndFocus)
+}
+
+/// Slices the `start..end` selection (UTF-16 indices, as reported by `EM_GETSEL`) out of the control text.
+/// Out-of-range or reversed bounds are clamped instead of failing.
+fn slice_selection(text: &[u16], start: usize, end: usize) -> String {
+    let (start, end) = (start.min(end).min(text.len()), start.max(end).min(text.len()));
+    String::from_utf16_lossy(&text[start..end])
+}
+
+/// Finds the tab whose caption matches `name`: an exact (case-insensitive) match wins,
+/// otherwise the first caption containing `name`. Mnemonic ampersands are ignored.
+fn resolve_tab_index(captions: &[String], name: &str) -> Option<usize> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() {
+        return None;
+    }
+    let captions: Vec<String> = captions.iter().map(|c| c.replace('&', "").trim().to_lowercase()).collect();
+    captions.iter().position(|c| *c == name)
+        .or_else(|| captions.iter().position(|c| c.contains(&name)))
+}
+
+/// Wraps a raw handle from `/windows`, failing if it no longer names a window.
+unsafe fn window_from_handle(handle: isize) -> PlatformResult<HWND> {
+    if IsWindow(handle) == 0 {
+        return Err(format!("Window with handle {:#x} no longer exists", handle));
+    }
+    Ok(HWND(handle))
+}
+
+/// Sends `text` as `KEYEVENTF_UNICODE` key down/up pairs, sleeping `delay` between pairs.
+/// Returns how many events were sent.
+unsafe fn send_unicode_text(text: &str, delay: Duration) -> usize {
+    let mut sent = 0;
+    for (index, code_point) in text.encode_utf16().enumerate() {
+        if index > 0 && !delay.is_zero() {
+            thread::sleep(delay);
+        }
+        let mut input: INPUT = mem::zeroed();
+        input.r#type = windows_sys::Win32::UI::Input::KeyboardAndMouse::INPUT_KEYBOARD as u32;
+        input.Anonymous.ki.wVk = 0;
+        input.Anonymous.ki.wScan = code_point; // Unicode code point
+        input.Anonymous.ki.dwFlags = KEYEVENTF_UNICODE;
+        sent += SendInput(1, &mut input, std::mem::size_of::<INPUT>() as i32) as usize;
+
+        input.Anonymous.ki.dwFlags = KEYEVENTF_UNICODE | KEYEVENTF_KEYUP;
+        sent += SendInput(1, &mut input, std::mem::size_of::<INPUT>() as i32) as usize;
+    }
+    sent
+}
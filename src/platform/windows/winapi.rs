@@ -9,24 +9,35 @@ use windows_sys::Win32::UI::WindowsAndMessaging::{
 };
 use windows_sys::Win32::UI::Input::KeyboardAndMouse::{INPUT, SendInput, INPUT_0, KEYBDINPUT, KEYEVENTF_KEYUP, KEYEVENTF_UNICODE, VIRTUAL_KEY};
 use windows_sys::Win32::System::Threading::{
-    OpenProcess, TerminateProcess, PROCESS_TERMINATE, GetWindowThreadProcessId
+    OpenProcess, TerminateProcess, PROCESS_TERMINATE, GetWindowThreadProcessId,
+    PROCESS_VM_OPERATION, PROCESS_VM_READ, PROCESS_VM_WRITE, PROCESS_QUERY_LIMITED_INFORMATION,
+    QueryFullProcessImageNameW, PROCESS_NAME_WIN32
 };
 use windows_sys::Win32::System::Memory::{
-     GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE
+     GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE,
+     VirtualAllocEx, VirtualFreeEx, MEM_COMMIT, MEM_RESERVE, MEM_RELEASE, PAGE_READWRITE
 };
+use windows_sys::Win32::Foundation::CloseHandle;
 use windows_sys::Win32::Graphics::Gdi::{
     GetDC, CreateCompatibleDC, CreateCompatibleBitmap, SelectObject, BitBlt, DeleteDC, DeleteObject,
-    SRCCOPY, GetDeviceCaps, HORZRES, VERTRES
+    SRCCOPY, GetDeviceCaps, HORZRES, VERTRES, ReleaseDC, GetPixel
 };
 use windows_sys::Win32::System::Clipboard::{
     OpenClipboard, EmptyClipboard, SetClipboardData, CloseClipboard, CF_UNICODETEXT
 };
+use windows_sys::Win32::Foundation::GetLastError;
+use windows_sys::Win32::System::Diagnostics::Debug::{
+    ReadProcessMemory, WriteProcessMemory, FormatMessageW, FORMAT_MESSAGE_FROM_SYSTEM, FORMAT_MESSAGE_IGNORE_INSERTS
+};
 use windows_sys::core::{PCWSTR, w, PSTR};
 use std::ffi::OsString;
 use std::os::windows::prelude::*;
 use std::mem;
 
 use log::{warn};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
 // --- Helper functions to reduce boilerplate and improve safety ---
 
@@ -47,6 +58,24 @@ pub unsafe fn find_window(class_name: Option<&str>, window_name: Option<&str>) -
     FindWindowW(class_name_ptr as PCWSTR, window_name_ptr as PCWSTR)
 }
 
+/// Describes the calling thread's last Win32 error as "Win32 error <code>: <system message>".
+/// Call it right after the failing API call, before anything else can overwrite the code.
+pub unsafe fn last_error_string() -> String {
+    let code = GetLastError();
+    let mut buffer = [0u16; 512];
+    let len = FormatMessageW(
+        FORMAT_MESSAGE_FROM_SYSTEM | FORMAT_MESSAGE_IGNORE_INSERTS,
+        std::ptr::null(),
+        code,
+        0,
+        buffer.as_mut_ptr(),
+        buffer.len() as u32,
+        std::ptr::null(),
+    ) as usize;
+    let message = String::from_utf16_lossy(&buffer[..len.min(buffer.len())]);
+    format!("Win32 error {}: {}", code, message.trim_end())
+}
+
 /// Gets the text of a window.
 pub unsafe fn get_window_text(hwnd: HWND) -> Option<String> {
     let len = GetWindowTextLengthW(hwnd) as usize;
@@ -171,6 +200,70 @@ pub unsafe fn enum_child_windows(hwnd: HWND, callback: EnumWindowsCallback) -> b
     EnumChildWindows(hwnd, Some(enum_child_proc), LPARAM(0)).as_bool()
 }
 
+/// A visible top-level window, as reported by [`list_windows`].
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowInfo {
+    pub hwnd: isize,
+    pub title: String,
+    pub class_name: String,
+    pub pid: u32,
+}
+
+/// Gets the class name of a window.
+pub unsafe fn get_class_name(hwnd: HWND) -> String {
+    let mut buffer: Vec<u16> = vec![0; 256]; // Class names are limited to 256 characters
+    let len = GetClassNameW(hwnd, buffer.as_mut_ptr(), buffer.len() as i32);
+    String::from_utf16_lossy(&buffer[..len.max(0) as usize])
+}
+
+/// Lists visible top-level windows that have a title, skipping the desktop's Program Manager.
+pub unsafe fn list_windows() -> Vec<WindowInfo> {
+    let windows = Arc::new(Mutex::new(Vec::new()));
+    let collected = windows.clone();
+    enum_windows(Box::new(move |hwnd| {
+        if IsWindowVisible(hwnd) == 0 {
+            return true;
+        }
+        let title = match get_window_text(hwnd) {
+            Some(title) if !title.is_empty() => title,
+            _ => return true,
+        };
+        let class_name = get_class_name(hwnd);
+        if class_name == "Progman" {
+            return true;
+        }
+        collected.lock().unwrap().push(WindowInfo {
+            hwnd: hwnd.0,
+            title,
+            class_name,
+            pid: get_window_thread_process_id(hwnd),
+        });
+        true
+    }));
+    let windows = windows.lock().unwrap().clone();
+    windows
+}
+
+/// Lists the windows [`list_windows`] reports whose process runs the executable `process`, matched by
+/// file name case-insensitively, with or without `.exe`.
+pub unsafe fn list_process_windows(process: &str) -> Vec<WindowInfo> {
+    let process = process.trim().to_lowercase();
+    let mut images: HashMap<u32, Option<String>> = HashMap::new();
+    list_windows()
+        .into_iter()
+        .filter(|window| {
+            let image = images.entry(window.pid).or_insert_with(|| process_image_name(window.pid));
+            match image {
+                Some(image) => {
+                    let image = image.to_lowercase();
+                    image == process || image.strip_suffix(".exe") == Some(process.as_str())
+                }
+                None => false,
+            }
+        })
+        .collect()
+}
+
 // --- Process Management Functions ---
 
 /// Opens a process by its ID.  Requires PROCESS_TERMINATE rights for TerminateProcess.
@@ -178,6 +271,24 @@ pub unsafe fn open_process(process_id: u32) -> windows_sys::Win32::Foundation::H
     OpenProcess(PROCESS_TERMINATE, 0, process_id)
 }
 
+/// Gets the file name of the executable a process runs (e.g. `notepad.exe`), or `None` if the process
+/// cannot be opened.
+pub unsafe fn process_image_name(process_id: u32) -> Option<String> {
+    let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, process_id);
+    if process == 0 {
+        return None;
+    }
+    let mut buffer = vec![0u16; 1024];
+    let mut size = buffer.len() as u32;
+    let ok = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, buffer.as_mut_ptr(), &mut size) != 0;
+    CloseHandle(process);
+    if !ok {
+        return None;
+    }
+    let path = String::from_utf16_lossy(&buffer[..size as usize]);
+    std::path::Path::new(&path).file_name().map(|name| name.to_string_lossy().into_owned())
+}
+
 /// Terminates a process.
 pub unsafe fn terminate_process(process_handle: windows_sys::Win32::Foundation::HANDLE, exit_code: u32) -> bool {
     TerminateProcess(process_handle, exit_code).as_bool()
@@ -190,6 +301,166 @@ pub unsafe fn get_window_thread_process_id(hwnd: HWND) -> u32 {
     process_id
 }
 
+/// `TCITEMW` as laid out by comctl32.
+#[repr(C)]
+struct TabItem {
+    mask: u32,
+    state: u32,
+    state_mask: u32,
+    text: *mut u16,
+    text_max: i32,
+    image: i32,
+    lparam: isize,
+}
+
+const TCM_GETITEMCOUNT: u32 = 0x1300 + 4;
+const TCM_GETITEMW: u32 = 0x1300 + 60;
+const TCIF_TEXT: u32 = 0x0001;
+const TAB_TEXT_MAX: usize = 256;
+
+/// Reads the caption of every tab in a `SysTabControl32`. The item and text buffer passed
+/// to `TCM_GETITEMW` are allocated in the control's process, since it is usually not ours.
+pub unsafe fn get_tab_captions(hwnd: HWND) -> Result<Vec<String>, String> {
+    let count = send_message(hwnd, TCM_GETITEMCOUNT, WPARAM(0), LPARAM(0));
+    if count <= 0 {
+        return Ok(Vec::new());
+    }
+
+    let process_id = get_window_thread_process_id(hwnd);
+    let process = OpenProcess(PROCESS_VM_OPERATION | PROCESS_VM_READ | PROCESS_VM_WRITE, 0, process_id);
+    if process == 0 {
+        return Err(format!("Failed to open process {}: {}", process_id, last_error_string()));
+    }
+
+    let item_size = mem::size_of::<TabItem>();
+    let remote = VirtualAllocEx(process, std::ptr::null(), item_size + TAB_TEXT_MAX * 2, MEM_COMMIT | MEM_RESERVE, PAGE_READWRITE);
+    if remote.is_null() {
+        let os_error = last_error_string();
+        CloseHandle(process);
+        return Err(format!("Failed to allocate memory in process {}: {}", process_id, os_error));
+    }
+    let remote_text = (remote as *mut u8).add(item_size) as *mut u16;
+
+    let mut captions = Vec::with_capacity(count as usize);
+    let mut result = Ok(());
+    for index in 0..count as usize {
+        let item = TabItem {
+            mask: TCIF_TEXT,
+            state: 0,
+            state_mask: 0,
+            text: remote_text,
+            text_max: TAB_TEXT_MAX as i32,
+            image: 0,
+            lparam: 0,
+        };
+        if WriteProcessMemory(process, remote, &item as *const TabItem as *const _, item_size, std::ptr::null_mut()) == 0 {
+            result = Err(format!("Failed to write tab item {}: {}", index, last_error_string()));
+            break;
+        }
+        if send_message(hwnd, TCM_GETITEMW, WPARAM(index), LPARAM(remote as isize)) == 0 {
+            result = Err(format!("TCM_GETITEM failed for tab {}", index));
+            break;
+        }
+        let mut text = [0u16; TAB_TEXT_MAX];
+        if ReadProcessMemory(process, remote_text as *const _, text.as_mut_ptr() as *mut _, TAB_TEXT_MAX * 2, std::ptr::null_mut()) == 0 {
+            result = Err(format!("Failed to read tab text {}: {}", index, last_error_string()));
+            break;
+        }
+        let len = text.iter().position(|&c| c == 0).unwrap_or(TAB_TEXT_MAX);
+        captions.push(String::from_utf16_lossy(&text[..len]));
+    }
+
+    VirtualFreeEx(process, remote, 0, MEM_RELEASE);
+    CloseHandle(process);
+    result.map(|_| captions)
+}
+
+/// `LVITEMW` as laid out by comctl32.
+#[repr(C)]
+struct ListViewItem {
+    mask: u32,
+    item: i32,
+    sub_item: i32,
+    state: u32,
+    state_mask: u32,
+    text: *mut u16,
+    text_max: i32,
+    image: i32,
+    lparam: isize,
+    indent: i32,
+    group_id: i32,
+    columns: u32,
+    column_ids: *mut u32,
+    column_formats: *mut i32,
+    group: i32,
+}
+
+const LVM_GETITEMCOUNT: u32 = 0x1000 + 4;
+const LVM_GETITEMTEXTW: u32 = 0x1000 + 115;
+const LIST_TEXT_MAX: usize = 512;
+
+/// Reads the first-column text of every row in a `SysListView32`. Like [`get_tab_captions`],
+/// the item and text buffer passed to `LVM_GETITEMTEXTW` live in the control's process.
+pub unsafe fn get_listview_items(hwnd: HWND) -> Result<Vec<String>, String> {
+    let count = send_message(hwnd, LVM_GETITEMCOUNT, WPARAM(0), LPARAM(0));
+    if count <= 0 {
+        return Ok(Vec::new());
+    }
+
+    let process_id = get_window_thread_process_id(hwnd);
+    let process = OpenProcess(PROCESS_VM_OPERATION | PROCESS_VM_READ | PROCESS_VM_WRITE, 0, process_id);
+    if process == 0 {
+        return Err(format!("Failed to open process {}: {}", process_id, last_error_string()));
+    }
+
+    let item_size = mem::size_of::<ListViewItem>();
+    let remote = VirtualAllocEx(process, std::ptr::null(), item_size + LIST_TEXT_MAX * 2, MEM_COMMIT | MEM_RESERVE, PAGE_READWRITE);
+    if remote.is_null() {
+        let os_error = last_error_string();
+        CloseHandle(process);
+        return Err(format!("Failed to allocate memory in process {}: {}", process_id, os_error));
+    }
+    let remote_text = (remote as *mut u8).add(item_size) as *mut u16;
+
+    let mut rows = Vec::with_capacity(count as usize);
+    let mut result = Ok(());
+    for index in 0..count as usize {
+        let item = ListViewItem {
+            mask: 0,
+            item: index as i32,
+            sub_item: 0,
+            state: 0,
+            state_mask: 0,
+            text: remote_text,
+            text_max: LIST_TEXT_MAX as i32,
+            image: 0,
+            lparam: 0,
+            indent: 0,
+            group_id: 0,
+            columns: 0,
+            column_ids: std::ptr::null_mut(),
+            column_formats: std::ptr::null_mut(),
+            group: 0,
+        };
+        if WriteProcessMemory(process, remote, &item as *const ListViewItem as *const _, item_size, std::ptr::null_mut()) == 0 {
+            result = Err(format!("Failed to write list item {}: {}", index, last_error_string()));
+            break;
+        }
+        // Returns the text length; an empty cell is a valid row, not a failure.
+        let len = send_message(hwnd, LVM_GETITEMTEXTW, WPARAM(index), LPARAM(remote as isize)).clamp(0, LIST_TEXT_MAX as isize) as usize;
+        let mut text = [0u16; LIST_TEXT_MAX];
+        if len > 0 && ReadProcessMemory(process, remote_text as *const _, text.as_mut_ptr() as *mut _, len * 2, std::ptr::null_mut()) == 0 {
+            result = Err(format!("Failed to read list item text {}: {}", index, last_error_string()));
+            break;
+        }
+        rows.push(String::from_utf16_lossy(&text[..len]));
+    }
+
+    VirtualFreeEx(process, remote, 0, MEM_RELEASE);
+    CloseHandle(process);
+    result.map(|_| rows)
+}
+
 // --- GDI Functions (Basic, for Screenshot) ---
 
 /// Gets the device context for a window (or the entire screen if hWnd is NULL).
@@ -197,6 +468,16 @@ pub unsafe fn get_dc(hwnd: HWND) -> windows_sys::Win32::Graphics::Gdi::HDC {
     GetDC(hwnd)
 }
 
+/// Releases a device context obtained with [`get_dc`].
+pub unsafe fn release_dc(hwnd: HWND, hdc: windows_sys::Win32::Graphics::Gdi::HDC) -> bool {
+    ReleaseDC(hwnd, hdc) != 0
+}
+
+/// Gets the color of a pixel as a `COLORREF` (0x00BBGGRR), or `CLR_INVALID` outside the clipping region.
+pub unsafe fn get_pixel(hdc: windows_sys::Win32::Graphics::Gdi::HDC, x: i32, y: i32) -> u32 {
+    GetPixel(hdc, x, y)
+}
+
 /// Creates a compatible DC.
 pub unsafe fn create_compatible_dc(hdc: windows_sys::Win32::Graphics::Gdi::HDC) -> windows_sys::Win32::Graphics::Gdi::HDC {
     CreateCompatibleDC(hdc)
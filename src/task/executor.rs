@@ -1,31 +1,271 @@
-use crate::core::intent::Action;
-use crate::platform::windows::controller::{WinUiController, PlatformResult};
+use crate::core::intent::{Action, CheckState, PREVIOUS_STEP_PLACEHOLDER};
+use crate::platform::windows::controller::{dpi_scale_factor, WinUiController, PlatformResult};
 use log::{info, error};
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// How many undoable actions are remembered for `/undo`.
+const UNDO_LIMIT: usize = 50;
+
+lazy_static::lazy_static! {
+    // Inverses of the most recent reversible actions, newest last.
+    static ref UNDO_STACK: Mutex<VecDeque<Action>> = Mutex::new(VecDeque::new());
+}
+
+/// Runs `action` like [`execute_action_on_platform`] and, if it succeeds and can be reverted,
+/// remembers its inverse for [`undo_last`].
+pub fn execute_recording_undo(
+    action: &Action,
+    controller: &WinUiController,
+) -> PlatformResult<Option<serde_json::Value>> {
+    let inverse = capture_inverse(action, controller);
+    let details = execute_action_on_platform(action, controller)?;
+    if let Some(inverse) = inverse {
+        let mut stack = UNDO_STACK.lock().unwrap();
+        stack.push_back(inverse);
+        while stack.len() > UNDO_LIMIT {
+            stack.pop_front();
+        }
+    }
+    Ok(details)
+}
+
+/// Builds the inverse of `action` from the UI state before it runs.
+/// Returns `None` for actions that cannot be reverted or would change nothing.
+fn capture_inverse(action: &Action, controller: &WinUiController) -> Option<Action> {
+    match action {
+        Action::CheckboxSetState { label, state } => match controller.get_checkbox_state(label) {
+            // Setting a box to the state it already has leaves nothing to undo.
+            Ok(current) if current != *state => Some(Action::CheckboxSetState { label: label.clone(), state: current }),
+            _ => None,
+        },
+        Action::SetText { label, hwnd, .. } | Action::EditEnterText { label, hwnd, .. } => controller
+            .get_edit_text(label, *hwnd)
+            .ok()
+            .map(|text| Action::SetText { label: label.clone(), text, hwnd: *hwnd }),
+        _ => action.inverse(),
+    }
+}
+
+/// An action's outcome with how long it took to run.
+#[derive(Debug)]
+pub struct TimedExecution {
+    pub result: PlatformResult<Option<serde_json::Value>>,
+    pub duration_ms: u64,
+    pub step_durations_ms: Vec<u64>, // One entry per step for `MultiStep`, empty otherwise
+}
+
+/// Runs `action` like [`execute_recording_undo`], timing it and, for `MultiStep`, each of its steps.
+pub fn execute_timed(action: &Action, controller: &WinUiController) -> TimedExecution {
+    let started = Instant::now();
+    let mut step_durations_ms = Vec::new();
+    let result = match action {
+        Action::MultiStep { steps, continue_on_error } => {
+            execute_steps(steps, *continue_on_error, controller, &mut step_durations_ms)
+        }
+        _ => execute_recording_undo(action, controller),
+    };
+    TimedExecution { result, duration_ms: started.elapsed().as_millis() as u64, step_durations_ms }
+}
+
+/// Pops the most recent inverse and runs it, returning it, or `None` when there is nothing to undo.
+/// The inverse is not recorded itself, so repeated calls walk further back.
+pub fn undo_last(controller: &WinUiController) -> PlatformResult<Option<Action>> {
+    let inverse = UNDO_STACK.lock().unwrap().pop_back();
+    match inverse {
+        Some(inverse) => {
+            info!("Undoing with {:?}", inverse);
+            execute_action_on_platform(&inverse, controller)?;
+            Ok(Some(inverse))
+        }
+        None => Ok(None),
+    }
+}
 
 /// Executes a given action using the provided WinUiController.
+/// Query actions return the values they read back, which end up in `TaskInfo::details`.
 pub fn execute_action_on_platform(
     action: &Action,
     controller: &WinUiController,
+) -> PlatformResult<Option<serde_json::Value>> {
+    match action {
+        Action::GetCheckboxState { label } => {
+            info!("Executing GetCheckboxState action for label: {}", label);
+            let state = controller.get_checkbox_state(label)?;
+            Ok(Some(json!({ "checked": state == CheckState::Checked, "state": state.as_str() })))
+        }
+        Action::GetSelectedTab { label } => {
+            info!("Executing GetSelectedTab action for label: {}", label);
+            let tab = controller.get_selected_tab(label)?;
+            Ok(Some(json!({ "tab": tab })))
+        }
+        Action::ListViewGetItems { label } => {
+            info!("Executing ListViewGetItems action for label: {}", label);
+            let items = controller.get_listview_items(label)?;
+            Ok(Some(json!({ "items": items })))
+        }
+        Action::GetSelectedText { label } => {
+            info!("Executing GetSelectedText action for label: {:?}", label);
+            let text = controller.get_selected_text(label.as_deref())?;
+            Ok(Some(json!({ "text": text })))
+        }
+        Action::WindowExists { label, class } => {
+            info!("Executing WindowExists action for label: {}, class: {:?}", label, class);
+            let exists = controller.window_exists(label, class.as_deref())?;
+            Ok(Some(json!({ "exists": exists })))
+        }
+        Action::SendMessage { label, msg, wparam, lparam } => {
+            info!("Executing SendMessage action for label: {}, msg: {:#x}", label, msg);
+            let result = controller.send_raw_message(label, *msg, *wparam, *lparam)?;
+            Ok(Some(json!({ "result": result })))
+        }
+        Action::ListWindows => {
+            info!("Executing ListWindows action");
+            let windows = controller.list_windows()?;
+            serde_json::to_value(&windows).map(Some).map_err(|e| e.to_string())
+        }
+        Action::GetWindowDpi { label } => {
+            info!("Executing GetWindowDpi action for label: {:?}", label);
+            let dpi = controller.get_window_dpi(label.as_deref())?;
+            Ok(Some(json!({ "dpi": dpi, "scale": dpi_scale_factor(dpi) })))
+        }
+        Action::GetPixelColor { x, y } => {
+            info!("Executing GetPixelColor action at ({}, {})", x, y);
+            let (r, g, b) = controller.get_pixel_color(*x, *y)?;
+            let hex = format!("#{:02X}{:02X}{:02X}", r, g, b);
+            Ok(Some(json!({ "r": r, "g": g, "b": b, "hex": hex })))
+        }
+        Action::WaitForAnyWindow { labels, timeout_ms } => {
+            info!("Executing WaitForAnyWindow action for labels: {:?}", labels);
+            let window = controller.wait_for_any_window(labels, *timeout_ms)?;
+            Ok(Some(json!({ "window": window })))
+        }
+        Action::WaitForTitle { title, timeout_ms } => {
+            info!("Executing WaitForTitle action for title: {}", title);
+            let title = controller.wait_for_title(title, *timeout_ms)?;
+            Ok(Some(json!({ "title": title })))
+        }
+        Action::StaticGetText { label } => {
+            info!("Executing StaticGetText action for label: {}", label);
+            let text = controller.get_static_text(label)?;
+            Ok(Some(json!({ "text": text })))
+        }
+        Action::MultiStep { steps, continue_on_error } => execute_steps(steps, *continue_on_error, controller, &mut Vec::new()),
+        _ => execute_command_action(action, controller).map(|_| None),
+    }
+}
+
+/// Runs the steps of a `MultiStep`, timing each one into `step_durations_ms`.
+fn execute_steps(
+    steps: &[Action],
+    continue_on_error: bool,
+    controller: &WinUiController,
+    step_durations_ms: &mut Vec<u64>,
+) -> PlatformResult<Option<serde_json::Value>> {
+    info!("Executing MultiStep action with {} steps (continue on error: {})", steps.len(), continue_on_error);
+    let total = steps.len();
+    // What the steps read back so far, for `${prev}` in the steps after them.
+    let mut context: HashMap<String, String> = HashMap::new();
+    if !continue_on_error {
+        // The last step that read something back determines the details.
+        let mut details = None;
+        for (index, step) in steps.iter().enumerate() {
+            let started = Instant::now();
+            let result = execute_step_in_context(step, controller, &mut context);
+            step_durations_ms.push(started.elapsed().as_millis() as u64);
+            details = result
+                .map_err(|e| format!("step {} of {} failed: {}", index + 1, total, e))?
+                .or(details);
+        }
+        return Ok(details);
+    }
+    // Run every step and report each outcome instead of stopping at the first failure.
+    let results: Vec<serde_json::Value> = steps
+        .iter()
+        .enumerate()
+        .map(|(index, step)| {
+            let started = Instant::now();
+            let result = execute_step_in_context(step, controller, &mut context);
+            step_durations_ms.push(started.elapsed().as_millis() as u64);
+            match result {
+                Ok(details) => json!({ "step": index + 1, "ok": true, "details": details }),
+                Err(e) => {
+                    error!("step {} of {} failed: {}", index + 1, total, e);
+                    json!({ "step": index + 1, "ok": false, "error": e })
+                }
+            }
+        })
+        .collect();
+    let failed = results.iter().filter(|result| result["ok"] == false).count();
+    Ok(Some(json!({ "steps": results, "failed": failed })))
+}
+
+/// Runs one `MultiStep` step with its `${name}` placeholders filled from `context`, then stores
+/// what a `StaticGetText` or `GetCheckboxState` step read as `prev` for the steps after it.
+fn execute_step_in_context(
+    step: &Action,
+    controller: &WinUiController,
+    context: &mut HashMap<String, String>,
+) -> PlatformResult<Option<serde_json::Value>> {
+    let step = resolve_context(step, context);
+    // Each reversible step is undone on its own, newest first.
+    let details = execute_recording_undo(&step, controller)?;
+    let output = match (&step, &details) {
+        (Action::StaticGetText { .. }, Some(details)) => details["text"].as_str().map(str::to_string),
+        (Action::GetCheckboxState { .. }, Some(details)) => details["checked"].as_bool().map(|checked| checked.to_string()),
+        _ => None,
+    };
+    if let Some(output) = output {
+        context.insert(PREVIOUS_STEP_PLACEHOLDER.to_string(), output);
+    }
+    Ok(details)
+}
+
+/// Fills `${name}` placeholders in the text a step enters from `context`.
+/// Names missing from `context` are left as written.
+fn resolve_context(step: &Action, context: &HashMap<String, String>) -> Action {
+    let fill = |text: &str| {
+        context.iter().fold(text.to_string(), |text, (name, value)| text.replace(&format!("${{{}}}", name), value))
+    };
+    match step {
+        Action::EditEnterText { label, text, hwnd } => Action::EditEnterText { label: label.clone(), text: fill(text), hwnd: *hwnd },
+        Action::SetText { label, text, hwnd } => Action::SetText { label: label.clone(), text: fill(text), hwnd: *hwnd },
+        Action::EditPasteText { label, text } => Action::EditPasteText { label: label.clone(), text: text.as_deref().map(fill) },
+        Action::TypeText { text, delay_ms } => Action::TypeText { text: fill(text), delay_ms: *delay_ms },
+        _ => step.clone(),
+    }
+}
+
+/// Executes actions that only change UI state.
+fn execute_command_action(
+    action: &Action,
+    controller: &WinUiController,
 ) -> PlatformResult<()> {
     match action {
-        Action::ButtonClick { label } => {
-            info!("Executing ButtonClick action for label: {}", label);
-            controller.click_button(label)
+        Action::ButtonClick { label, hwnd } => {
+            info!("Executing ButtonClick action for label: {}, hwnd: {:?}", label, hwnd);
+            controller.click_button(label, *hwnd)
         }
-        Action::ButtonDoubleClick { label } => {
-            info!("Executing ButtonDoubleClick action for label: {}", label);
-            controller.double_click_button(label)
+        Action::ButtonDoubleClick { label, hwnd, .. } => {
+            info!("Executing ButtonDoubleClick action for label: {}, hwnd: {:?}", label, hwnd);
+            controller.double_click_button(label, *hwnd)
         }
-        Action::EditEnterText { label, text } => {
-            info!("Executing EditEnterText action for label: {}, text: {}", label, text);
-            controller.enter_text(label, text)
+        Action::EditEnterText { label, text, hwnd } => {
+            info!("Executing EditEnterText action for label: {}, text: {}, hwnd: {:?}", label, text, hwnd);
+            controller.enter_text(label, text, *hwnd)
+        }
+        Action::SetText { label, text, hwnd } => {
+            info!("Executing SetText action for label: {}, text: {}, hwnd: {:?}", label, text, hwnd);
+            controller.enter_text(label, text, *hwnd)
         }
         Action::EditSelectText { label, start, end } => {
             info!("Executing EditSelectText action for label: {}, start: {:?}, end: {:?}", label, start, end);
             controller.select_text(label, *start, *end)
         }
         Action::CheckboxSetState { label, state } => {
-            info!("Executing CheckboxSetState action for label: {}, state: {}", label, state);
+            info!("Executing CheckboxSetState action for label: {}, state: {}", label, state.as_str());
             controller.set_checkbox_state(label, *state)
         }
         Action::RadioSelect { label, variant } => {
@@ -73,25 +313,28 @@ pub fn execute_action_on_platform(
              if let Ok(index) = tab.parse::<usize>() {
                  controller.select_tabcontrol_tab(label, index)
              } else {
-                 error!("Invalid tab index format: {}", tab);
-                Err(format!("Invalid tab index format: {}", tab))
+                 controller.select_tabcontrol_tab_by_name(label, tab).map(|_| ())
              }
         }
         Action::WindowResize { width, height } => {
             info!("Executing WindowResize action to {}x{}", width, height);
              controller.resize_window("Main", *width as i32, *height as i32) // Assuming main window
         }
-        Action::WindowMinimize { label } => {
-            info!("Executing WindowMinimize action for label: {}", label);
-            controller.minimize_window(label)
+        Action::WindowMinimize { label, process } => {
+            info!("Executing WindowMinimize action for label: {}, process: {:?}", label, process);
+            controller.minimize_window(label, process.as_deref())
+        }
+        Action::WindowMaximize { label, process } => {
+            info!("Executing WindowMaximize action for label: {}, process: {:?}", label, process);
+            controller.maximize_window(label, process.as_deref())
         }
-        Action::WindowMaximize { label } => {
-            info!("Executing WindowMaximize action for label: {}", label);
-            controller.maximize_window(label)
+        Action::WindowClose { label, process } => {
+            info!("Executing WindowClose action for label: {}, process: {:?}", label, process);
+            controller.close_window(label, process.as_deref())
         }
-        Action::WindowClose { label } => {
-            info!("Executing WindowClose action for label: {}", label);
-            controller.close_window(label)
+        Action::TypeText { text, delay_ms } => {
+            info!("Executing TypeText action, {} characters", text.chars().count());
+            controller.type_text(text, *delay_ms)
         }
         Action::KeyPress { key } => {
              info!("Executing KeyPress action for key: {}", key);
@@ -101,35 +344,42 @@ pub fn execute_action_on_platform(
              info!("Executing Scroll action: {} by {:?}", direction, amount);
              controller.scroll_window(direction, *amount)
         }
+        Action::Open { target, kind } => {
+            info!("Executing Open action for target: {}, kind: {}", target, kind.as_str());
+            controller.open_target(target, *kind)
+        }
        Action::LaunchApplication { app } => {
            info!("Executing LaunchApplication action for app: {}", app);
            controller.launch_application(app)
        }
-        Action::StaticGetText { label } => {
-            // Implement this to get text from static UI element, if possible
-            info!("Executing StaticGetText action for label: {}", label);
-            match controller.get_static_text(label) {
-                Ok(text) => {
-                    info!("Static text: {}", text);
-                    Ok(())
-                }
-                Err(e) => {
-                    error!("Error getting static text: {}", e);
-                    Err(e)
-                }
-            }
+        Action::SystemMenu { label, command } => {
+            info!("Executing SystemMenu action for label: {}, command: {}", label, command);
+            controller.system_menu(label, command)
         }
-        Action::MultiStep { steps } => {
-            info!("Executing MultiStep action with {} steps", steps.len());
-            for step in steps {
-                execute_action_on_platform(step, controller)?;
-            }
-            Ok(())
+        Action::WindowActivate { label, process } => {
+            info!("Executing WindowActivate action for label: {}, process: {:?}", label, process);
+            controller.activate_window(label, process.as_deref())
+        }
+        Action::WindowHide { label } => {
+            info!("Executing WindowHide action for label: {}", label);
+            controller.hide_window(label).map(|_| ())
+        }
+        Action::WindowShow { label } => {
+            info!("Executing WindowShow action for label: {}", label);
+            controller.show_window(label)
+        }
+        Action::WindowSetTopmost { label, topmost } => {
+            info!("Executing WindowSetTopmost action for label: {}, topmost: {}", label, topmost);
+            controller.set_topmost(label, *topmost)
         }
         Action::SetFocus { label } => {
             info!("Executing SetFocus action for label: {}", label);
             controller.set_focus(label)
         }
+        Action::Refused { intent, reason } => {
+            error!("Refusing disabled intent: {}", intent);
+            Err(reason.clone())
+        }
         _ => {
             error!("Unsupported action: {:?}", action);
             Err(format!("Unsupported action: {:?}", action))
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use log::{error, warn};
+
+use crate::task::model::TaskInfo;
+
+/// Append-only JSON-lines log of task status transitions.
+/// Every line is one serialized `TaskInfo`; the last line for a task id is its latest state.
+#[derive(Debug, Clone)]
+pub struct TaskHistory {
+    path: PathBuf,
+}
+
+impl TaskHistory {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        TaskHistory {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Appends a single transition record to the log.
+    pub fn append(&self, record: &TaskInfo) -> Result<(), String> {
+        let line = serde_json::to_string(record)
+            .map_err(|e| format!("Failed to serialize task record: {}", e))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("Failed to open task history '{}': {}", self.path.display(), e))?;
+        writeln!(file, "{}", line)
+            .map_err(|e| format!("Failed to write task history '{}': {}", self.path.display(), e))
+    }
+
+    /// Reads every record in the log in the order they were written.
+    /// Malformed lines are skipped with a warning so a torn write doesn't lose the whole history.
+    pub fn load(&self) -> Vec<TaskInfo> {
+        if !self.path.exists() {
+            return Vec::new();
+        }
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) => {
+                error!("Failed to open task history '{}': {}", self.path.display(), e);
+                return Vec::new();
+            }
+        };
+        BufReader::new(file)
+            .lines()
+            .enumerate()
+            .filter_map(|(index, line)| {
+                let line = line.ok()?;
+                if line.trim().is_empty() {
+                    return None;
+                }
+                match serde_json::from_str::<TaskInfo>(&line) {
+                    Ok(record) => Some(record),
+                    Err(e) => {
+                        warn!("Skipping malformed task history line {}: {}", index + 1, e);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the latest state of every task whose last recorded status is terminal.
+    /// Used on startup to repopulate the task list.
+    pub fn load_finished(&self) -> Vec<TaskInfo> {
+        let mut latest: HashMap<Uuid, TaskInfo> = HashMap::new();
+        let mut order: Vec<Uuid> = Vec::new();
+        for record in self.load() {
+            if !latest.contains_key(&record.id) {
+                order.push(record.id);
+            }
+            latest.insert(record.id, record);
+        }
+        order
+            .into_iter()
+            .filter_map(|id| latest.remove(&id))
+            .filter(|record| record.status.is_terminal())
+            .collect()
+    }
+
+    /// Returns one page of raw transition records together with the total record count.
+    pub fn page(&self, offset: usize, limit: usize) -> (usize, Vec<TaskInfo>) {
+        let records = self.load();
+        let total = records.len();
+        let page = records.into_iter().skip(offset).take(limit).collect();
+        (total, page)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task::model::TaskStatus;
+
+    #[test]
+    fn retrying_records_are_persisted_but_not_restored() {
+        let path = std::env::temp_dir().join(format!("task-history-test-{}.jsonl", Uuid::new_v4()));
+        let history = TaskHistory::new(&path);
+        let finished = TaskInfo::new("finished".to_string());
+        let retrying = TaskInfo::new("retrying".to_string());
+        for (record, status) in [
+            (&finished, TaskStatus::Retrying { attempt: 2, max: 2, last_error: "timeout".to_string() }),
+            (&finished, TaskStatus::Failed("timeout".to_string())),
+            (&retrying, TaskStatus::Retrying { attempt: 2, max: 3, last_error: "busy".to_string() }),
+        ] {
+            history.append(&TaskInfo { status, ..record.clone() }).unwrap();
+        }
+
+        let statuses: Vec<TaskStatus> = history.load().into_iter().map(|record| record.status).collect();
+        assert_eq!(statuses[0], TaskStatus::Retrying { attempt: 2, max: 2, last_error: "timeout".to_string() });
+        assert_eq!(statuses[2], TaskStatus::Retrying { attempt: 2, max: 3, last_error: "busy".to_string() });
+        let restored = history.load_finished();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].id, finished.id);
+
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -9,10 +9,18 @@ pub enum TaskStatus {
     Running,
     Completed,
     Failed(String), // Include the error message if the task failed
+    Retrying { attempt: u32, max: u32, last_error: String }, // Attempt now running or about to run, counting from 1, and why the previous one failed
     Cancelled,
     Stopping
 }
 
+impl TaskStatus {
+    /// Returns true once the task can no longer change state.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, TaskStatus::Completed | TaskStatus::Failed(_) | TaskStatus::Cancelled)
+    }
+}
+
 impl fmt::Display for TaskStatus {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -20,6 +28,9 @@ impl fmt::Display for TaskStatus {
             TaskStatus::Running => write!(f, "Running"),
             TaskStatus::Completed => write!(f, "Completed"),
             TaskStatus::Failed(e) => write!(f, "Failed: {}", e),
+            TaskStatus::Retrying { attempt, max, last_error } => {
+                write!(f, "Retrying (attempt {} of {}): {}", attempt, max, last_error)
+            }
             TaskStatus::Cancelled => write!(f, "Cancelled"),
             TaskStatus::Stopping => write!(f, "Stopping")
         }
@@ -32,6 +43,16 @@ pub struct TaskInfo {
     pub id: Uuid,           // Unique identifier for the task
     pub name: String,         // Name or description of the task
     pub status: TaskStatus, // Current status of the task
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>, // Values read back by query actions, e.g. `{"checked": true}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<f32>, // Fraction of the work done, 0.0 to 1.0, for tasks that report it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_ms: Option<u64>, // Time from scheduling until the worker started the task
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>, // Time the task itself took to run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub step_durations_ms: Vec<u64>, // Per step of a MultiStep, or per command of a batch
     // Add more fields as needed (e.g., start time, end time, etc.)
 }
 
@@ -41,6 +62,25 @@ impl TaskInfo {
             id: Uuid::new_v4(), // Generate a new UUID
             name,
             status: TaskStatus::Queued,
+            details: None,
+            progress: None,
+            queue_ms: None,
+            duration_ms: None,
+            step_durations_ms: Vec::new(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retrying_status_round_trips_through_json() {
+        let status = TaskStatus::Retrying { attempt: 2, max: 3, last_error: "Окно 'Блокнот' не найдено".to_string() };
+        let json = serde_json::to_string(&status).unwrap();
+        assert_eq!(serde_json::from_str::<TaskStatus>(&json).unwrap(), status);
+        assert_eq!(status.to_string(), "Retrying (attempt 2 of 3): Окно 'Блокнот' не найдено");
+        assert!(!status.is_terminal());
+    }
+}
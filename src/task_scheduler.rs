@@ -1,78 +1,245 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
 
-use crate::config::{AppConfig, SharedConfig};
-use crate::language::PATTERNS;
+use crate::config::SharedConfig;
+use crate::language::current_patterns;
+use crate::notification::NotificationSink;
+use crate::task::history::TaskHistory;
+use crate::task::model::{TaskInfo, TaskStatus};
+use tokio::sync::broadcast;
+use log::{info, warn, error, debug};
 
 /// A task that can be scheduled by the TaskScheduler.
-/// Each task has a name for identification and a closure representing the action to execute.
+/// Each task has an id, a name for identification and a closure representing the action to execute.
+/// The closure reports whether the action succeeded; the error is kept as the task's failure reason.
 pub struct Task {
+    pub id: Uuid,
     pub name: String,
-    pub action: Box<dyn FnOnce() + Send + 'static>,
+    pub action: Box<dyn FnMut() -> Result<(), String> + Send + 'static>,
+    pub max_attempts: u32, // Runs of the action in all before the task fails; 1 for tasks built with `new`
+    pub retry_delay: Duration, // Pause between a failed attempt and the next one
+    pub status_events: Option<broadcast::Sender<TaskStatus>>, // Receives the Running and Retrying transitions
+    pub queued_at: Instant, // When the task was created, to tell time spent queued from time spent running
 }
 
 impl Task {
-    /// Creates a new task with the given name and action.
+    /// Creates a new task with the given name and action, under a fresh id. The action runs once.
     pub fn new<F>(name: &str, action: F) -> Self
     where
-        F: FnOnce() + Send + 'static,
+        F: FnOnce() -> Result<(), String> + Send + 'static,
+    {
+        let mut action = Some(action);
+        Task::retrying(name, 1, Duration::ZERO, move || match action.take() {
+            Some(action) => action(),
+            None => Err("Task action has already run".to_string()),
+        })
+    }
+
+    /// Creates a task whose action is run again after `retry_delay` while it fails, up to
+    /// `max_attempts` runs in all. Between attempts the task's status is `Retrying`.
+    pub fn retrying<F>(name: &str, max_attempts: u32, retry_delay: Duration, action: F) -> Self
+    where
+        F: FnMut() -> Result<(), String> + Send + 'static,
     {
         Task {
+            id: Uuid::new_v4(),
             name: name.to_string(),
             action: Box::new(action),
+            max_attempts: max_attempts.max(1),
+            retry_delay,
+            status_events: None,
+            queued_at: Instant::now(),
         }
     }
+
+    /// Runs the task under `id`, for callers that already track it by that id.
+    pub fn with_id(mut self, id: Uuid) -> Self {
+        self.id = id;
+        self
+    }
+
+    /// Publishes the task's status transitions made by the scheduler on `events`.
+    pub fn with_status_events(mut self, events: broadcast::Sender<TaskStatus>) -> Self {
+        self.status_events = Some(events);
+        self
+    }
 }
 
+/// Messages handled by the scheduler worker thread.
+enum SchedulerMessage {
+    /// Execute a task.
+    Run(Task),
+    /// Append a task status transition to the history log.
+    Record(TaskInfo),
+}
+
+/// Status of every scheduled task, by task id, shared by the scheduler and its worker thread.
+type StatusMap = Arc<Mutex<HashMap<Uuid, TaskInfo>>>;
+
 /// TaskScheduler manages a queue of tasks and executes them sequentially on a background thread.
 pub struct TaskScheduler {
-    sender: mpsc::Sender<Task>,
+    sender: mpsc::Sender<SchedulerMessage>,
+    statuses: StatusMap, // Status of every scheduled task, by task id
+    finished: Arc<Mutex<VecDeque<Uuid>>>, // Finished task ids, oldest first, for evicting their statuses
+    accepting: AtomicBool, // Cleared by `shutdown`; later tasks are refused
+    aborting: Arc<AtomicBool>, // Set once the shutdown grace period is over; the worker skips queued tasks
+    history: Option<TaskHistory>, // For the final statuses written on shutdown
+    queue_depth: Arc<AtomicUsize>, // Tasks sent to the worker that it has not started yet
+    rejected: AtomicU64, // Tasks refused because the queue was at `max_queue_depth`
+    config: SharedConfig, // For `max_queue_depth`, read on every schedule so reloads apply
+    worker: thread::JoinHandle<()>, // Finishes only if the worker loop exits or panics
 }
 
+thread_local! {
+    /// The task the worker thread is running, with the status map its progress is written to.
+    static CURRENT_TASK: RefCell<Option<(Uuid, StatusMap)>> = const { RefCell::new(None) };
+}
+
+/// Records the progress of the task running on the calling thread: `fraction` of the work done
+/// and a message such as "copied 3 of 10 files", kept in the task's details until it finishes.
+/// Does nothing when called outside a scheduled task.
+pub fn report_progress(fraction: f32, message: &str) {
+    CURRENT_TASK.with(|current| {
+        if let Some((task_id, statuses)) = current.borrow().as_ref() {
+            if let Some(task_info) = statuses.lock().unwrap().get_mut(task_id) {
+                task_info.progress = Some(fraction.clamp(0.0, 1.0));
+                task_info.details = Some(serde_json::json!({ "progress": message }));
+            }
+        }
+    });
+}
+
+/// How often `shutdown` checks whether the queue has drained.
+const SHUTDOWN_POLL_INTERVAL_MS: u64 = 100;
+
+/// How many finished task statuses are kept; older ones are dropped as new tasks finish.
+const MAX_FINISHED_STATUSES: usize = 1000;
+
 impl TaskScheduler {
     /// Creates a new TaskScheduler and starts a worker thread that processes tasks.
-    /// The scheduler uses the shared configuration to display notifications based on language messages and settings.
-    pub fn new(shared_config: SharedConfig) -> Self {
-        let (tx, rx) = mpsc::channel::<Task>();
+    /// Notifications built from the language messages go to `notifier`; the shared configuration
+    /// supplies the delay and the `notification_enable` switch, which is checked for every task.
+    /// When a history log is given, status records sent via `record` are written from the worker thread.
+    pub fn new(shared_config: SharedConfig, notifier: Arc<dyn NotificationSink>, history: Option<TaskHistory>) -> Self {
+        let (tx, rx) = mpsc::channel::<SchedulerMessage>();
+        let statuses: StatusMap = Arc::new(Mutex::new(HashMap::new()));
+        let worker_statuses = statuses.clone();
+        let finished: Arc<Mutex<VecDeque<Uuid>>> = Arc::new(Mutex::new(VecDeque::new()));
+        let worker_finished = finished.clone();
+        let aborting = Arc::new(AtomicBool::new(false));
+        let worker_aborting = aborting.clone();
+        let shutdown_history = history.clone();
+        let queue_depth = Arc::new(AtomicUsize::new(0));
+        let worker_queue_depth = queue_depth.clone();
+        let config = shared_config.clone();
 
         // Spawn a worker thread that processes tasks.
-        thread::spawn(move || {
+        let worker = thread::spawn(move || {
             loop {
                 match rx.recv() {
-                    Ok(task) => {
-                        // Load current configuration to display notifications.
-                        if let Ok(config_lock) = shared_config.lock() {
-                            if let Some(ref cfg) = *config_lock {
-                                // Notify that the task has been queued.
-                                cfg.show_notification(&format!(
-                                    "{}: {}",
-                                    PATTERNS.msg_task_queued, task.name
-                                ));
-                                
-                                // Wait for the configured notification delay.
-                                thread::sleep(Duration::from_millis(cfg.notifications_delay as u64));
-                                
-                                // Notify that the task is now processing.
-                                cfg.show_notification(&format!(
-                                    "{}: {}",
-                                    PATTERNS.msg_task_processing, task.name
-                                ));
+                    Ok(SchedulerMessage::Record(record)) => {
+                        if let Some(ref history) = history {
+                            if let Err(e) = history.append(&record) {
+                                error!("Error writing task history: {}", e);
                             }
                         }
-                        
+                    }
+                    Ok(SchedulerMessage::Run(mut task)) => {
+                        worker_queue_depth.fetch_sub(1, Ordering::SeqCst);
+                        if worker_aborting.load(Ordering::SeqCst) {
+                            // Shutdown gave up waiting; the task is already marked cancelled.
+                            continue;
+                        }
+                        // Load current configuration to display notifications.
+                        // Copy the settings out so the lock is not held while sleeping.
+                        let settings = shared_config.lock().ok()
+                            .and_then(|config_lock| config_lock.as_ref().map(|cfg| (cfg.notification_enable, cfg.notification_delay)));
+                        if let Some((true, delay)) = settings {
+                            let patterns = current_patterns();
+                            // Notify that the task has been queued.
+                            notifier.notify(&format!(
+                                "{}: {}",
+                                patterns.msg_task_queued, task.name
+                            ));
+
+                            // Wait for the configured notification delay.
+                            thread::sleep(Duration::from_millis(delay as u64));
+
+                            // Notify that the task is now processing.
+                            notifier.notify(&format!(
+                                "{}: {}",
+                                patterns.msg_task_processing, task.name
+                            ));
+                        }
+
                         // Execute the task.
-                        (task.action)();
-                        
-                        // After executing, notify that the task was successfully completed.
-                        if let Ok(config_lock) = shared_config.lock() {
-                            if let Some(ref cfg) = *config_lock {
-                                cfg.show_notification(&format!(
-                                    "{}: {}",
-                                    PATTERNS.msg_task_success, task.name
-                                ));
+                        let queue_ms = task.queued_at.elapsed().as_millis() as u64;
+                        set_status(&worker_statuses, &task.id, TaskStatus::Running);
+                        set_timing(&worker_statuses, &task.id, queue_ms, None);
+                        if let Some(ref events) = task.status_events {
+                            // No subscribers is fine; nobody is watching this task.
+                            let _ = events.send(TaskStatus::Running);
+                        }
+                        CURRENT_TASK.with(|current| *current.borrow_mut() = Some((task.id, worker_statuses.clone())));
+                        let started = Instant::now();
+                        let mut attempt = 1;
+                        let result = loop {
+                            match (task.action)() {
+                                // Shutdown stops retries; the failure then stands.
+                                Err(e) if attempt < task.max_attempts && !worker_aborting.load(Ordering::SeqCst) => {
+                                    warn!("Task {} ({}) failed attempt {} of {}: {}", task.id, task.name, attempt, task.max_attempts, e);
+                                    attempt += 1;
+                                    let status = TaskStatus::Retrying { attempt, max: task.max_attempts, last_error: e };
+                                    set_status(&worker_statuses, &task.id, status.clone());
+                                    if let Some(ref history) = history {
+                                        if let Some(task_info) = worker_statuses.lock().unwrap().get(&task.id) {
+                                            if let Err(e) = history.append(task_info) {
+                                                error!("Error writing task history: {}", e);
+                                            }
+                                        }
+                                    }
+                                    if let Some(ref events) = task.status_events {
+                                        let _ = events.send(status);
+                                    }
+                                    thread::sleep(task.retry_delay);
+                                }
+                                result => break result,
                             }
+                        };
+                        let duration_ms = started.elapsed().as_millis() as u64;
+                        CURRENT_TASK.with(|current| *current.borrow_mut() = None);
+                        set_timing(&worker_statuses, &task.id, queue_ms, Some(duration_ms));
+                        debug!("Task {} waited {} ms in the queue and ran for {} ms", task.id, queue_ms, duration_ms);
+
+                        let status = match result {
+                            Ok(()) => {
+                                info!("Task {} ({}) completed", task.id, task.name);
+                                TaskStatus::Completed
+                            }
+                            Err(e) => {
+                                error!("Task {} ({}) failed: {}", task.id, task.name, e);
+                                TaskStatus::Failed(e)
+                            }
+                        };
+
+                        // After executing, notify how the task ended.
+                        let enabled = shared_config.lock().ok()
+                            .and_then(|config_lock| config_lock.as_ref().map(|cfg| cfg.notification_enable));
+                        if enabled == Some(true) {
+                            let patterns = current_patterns();
+                            let message = match status {
+                                TaskStatus::Completed => &patterns.msg_task_success,
+                                _ => &patterns.msg_task_failure,
+                            };
+                            notifier.notify(&format!("{}: {}", message, task.name));
                         }
+                        set_status(&worker_statuses, &task.id, status);
+                        retire(&worker_statuses, &worker_finished, task.id, MAX_FINISHED_STATUSES);
                     }
                     Err(_) => {
                         // If the channel is disconnected, exit the worker loop.
@@ -82,14 +249,226 @@ impl TaskScheduler {
             }
         });
 
-        TaskScheduler { sender: tx }
+        TaskScheduler {
+            sender: tx,
+            statuses,
+            finished,
+            accepting: AtomicBool::new(true),
+            aborting,
+            history: shutdown_history,
+            queue_depth,
+            rejected: AtomicU64::new(0),
+            config,
+            worker,
+        }
     }
 
     /// Schedules a new task for execution.
-    /// If sending the task fails, an error is logged to the console.
+    /// If sending the task fails, an error is logged and the task is marked failed.
     pub fn schedule(&self, task: Task) {
-        if let Err(e) = self.sender.send(task) {
-            eprintln!("Error scheduling task: {}", e);
+        let task_id = task.id;
+        if !self.accepting.load(Ordering::SeqCst) {
+            error!("Refusing task {} ({}): the scheduler is shutting down", task_id, task.name);
+            self.statuses.lock().unwrap().insert(task_id, TaskInfo {
+                id: task_id,
+                name: task.name,
+                status: TaskStatus::Failed("Server is shutting down".to_string()),
+                details: None,
+                progress: None,
+                queue_ms: None,
+                duration_ms: None,
+                step_durations_ms: Vec::new(),
+            });
+            retire(&self.statuses, &self.finished, task_id, MAX_FINISHED_STATUSES);
+            return;
+        }
+        if self.is_full() {
+            error!("Refusing task {} ({}): the queue is full", task_id, task.name);
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            self.statuses.lock().unwrap().insert(task_id, TaskInfo {
+                id: task_id,
+                name: task.name,
+                status: TaskStatus::Failed("Task queue is full".to_string()),
+                details: None,
+                progress: None,
+                queue_ms: None,
+                duration_ms: None,
+                step_durations_ms: Vec::new(),
+            });
+            retire(&self.statuses, &self.finished, task_id, MAX_FINISHED_STATUSES);
+            return;
+        }
+        self.statuses.lock().unwrap().insert(task_id, TaskInfo {
+            id: task_id,
+            name: task.name.clone(),
+            status: TaskStatus::Queued,
+            details: None,
+            progress: None,
+            queue_ms: None,
+            duration_ms: None,
+            step_durations_ms: Vec::new(),
+        });
+        self.queue_depth.fetch_add(1, Ordering::SeqCst);
+        if let Err(e) = self.sender.send(SchedulerMessage::Run(task)) {
+            self.queue_depth.fetch_sub(1, Ordering::SeqCst);
+            error!("Error scheduling task: {}", e);
+            set_status(&self.statuses, &task_id, TaskStatus::Failed("Scheduler is not running".to_string()));
+            retire(&self.statuses, &self.finished, task_id, MAX_FINISHED_STATUSES);
+        }
+    }
+
+    /// Number of scheduled tasks the worker has not started yet.
+    pub fn queue_depth(&self) -> usize {
+        self.queue_depth.load(Ordering::SeqCst)
+    }
+
+    /// True once `max_queue_depth` tasks are waiting; a limit of 0 never fills.
+    pub fn is_full(&self) -> bool {
+        let limit = self.config.lock().ok()
+            .and_then(|config_lock| config_lock.as_ref().map(|cfg| cfg.max_queue_depth))
+            .unwrap_or(0);
+        limit > 0 && self.queue_depth() >= limit
+    }
+
+    /// Checks for room before a command is accepted, counting the rejection when the queue is full.
+    /// `schedule` checks again, so a task that races past this is still refused.
+    pub fn admit(&self) -> bool {
+        if self.is_full() {
+            warn!("Rejecting a command: {} tasks are already queued", self.queue_depth());
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            false
+        } else {
+            true
         }
     }
-}
\ No newline at end of file
+
+    /// Number of tasks refused because the queue was full.
+    pub fn rejected_count(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    /// Stops accepting tasks and waits up to `grace` for the queued and running ones to finish.
+    /// Whatever is still unfinished afterwards is marked cancelled and skipped by the worker;
+    /// a task already running cannot be interrupted and is left to the process exit.
+    /// Final statuses are appended to the history log, if one is configured.
+    /// Returns how many tasks were drained and how many were force-aborted.
+    pub fn shutdown(&self, grace: Duration) -> (usize, usize) {
+        self.accepting.store(false, Ordering::SeqCst);
+        let unfinished = |statuses: &HashMap<Uuid, TaskInfo>| -> Vec<Uuid> {
+            statuses.values().filter(|info| !info.status.is_terminal()).map(|info| info.id).collect()
+        };
+        let pending = unfinished(&self.statuses.lock().unwrap());
+        info!("Shutting down scheduler: {} unfinished tasks, grace period {:?}", pending.len(), grace);
+
+        let deadline = Instant::now() + grace;
+        while Instant::now() < deadline && !unfinished(&self.statuses.lock().unwrap()).is_empty() {
+            thread::sleep(Duration::from_millis(SHUTDOWN_POLL_INTERVAL_MS));
+        }
+        self.aborting.store(true, Ordering::SeqCst);
+
+        let mut statuses = self.statuses.lock().unwrap();
+        let aborted = unfinished(&statuses);
+        for task_id in &aborted {
+            if let Some(task_info) = statuses.get_mut(task_id) {
+                task_info.status = TaskStatus::Cancelled;
+            }
+        }
+        if let Some(ref history) = self.history {
+            for task_id in &pending {
+                if let Some(task_info) = statuses.get(task_id) {
+                    if let Err(e) = history.append(task_info) {
+                        error!("Error writing task history: {}", e);
+                    }
+                }
+            }
+        }
+        (pending.len().saturating_sub(aborted.len()), aborted.len())
+    }
+
+    /// Whether the worker thread is still running; a dead worker leaves every scheduled task queued.
+    pub fn is_worker_alive(&self) -> bool {
+        !self.worker.is_finished()
+    }
+
+    /// Returns the status the scheduler last recorded for a task, if it was scheduled here.
+    pub fn status(&self, task_id: &Uuid) -> Option<TaskInfo> {
+        self.statuses.lock().unwrap().get(task_id).cloned()
+    }
+
+    /// Queues a task status transition for the history log, keeping file I/O off the request path.
+    pub fn record(&self, record: TaskInfo) {
+        if let Err(e) = self.sender.send(SchedulerMessage::Record(record)) {
+            error!("Error recording task history: {}", e);
+        }
+    }
+}
+
+/// Marks a task as finished and drops the statuses of the oldest finished tasks beyond `keep`,
+/// so a long-running server does not hold every task it has ever run.
+fn retire(statuses: &Mutex<HashMap<Uuid, TaskInfo>>, finished: &Mutex<VecDeque<Uuid>>, task_id: Uuid, keep: usize) {
+    let mut finished = finished.lock().unwrap();
+    finished.push_back(task_id);
+    let mut statuses = statuses.lock().unwrap();
+    while finished.len() > keep {
+        if let Some(oldest) = finished.pop_front() {
+            statuses.remove(&oldest);
+        }
+    }
+}
+
+fn set_status(statuses: &Mutex<HashMap<Uuid, TaskInfo>>, task_id: &Uuid, status: TaskStatus) {
+    if let Some(task_info) = statuses.lock().unwrap().get_mut(task_id) {
+        task_info.status = status;
+    }
+}
+
+/// Records how long a task waited in the queue and, once it has finished, how long it ran.
+fn set_timing(statuses: &Mutex<HashMap<Uuid, TaskInfo>>, task_id: &Uuid, queue_ms: u64, duration_ms: Option<u64>) {
+    if let Some(task_info) = statuses.lock().unwrap().get_mut(task_id) {
+        task_info.queue_ms = Some(queue_ms);
+        task_info.duration_ms = duration_ms;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(id: Uuid) -> TaskInfo {
+        TaskInfo {
+            id,
+            name: "task".to_string(),
+            status: TaskStatus::Queued,
+            details: None,
+            progress: None,
+            queue_ms: None,
+            duration_ms: None,
+            step_durations_ms: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn only_the_newest_finished_statuses_are_kept() {
+        let statuses = Mutex::new(HashMap::new());
+        let finished = Mutex::new(VecDeque::new());
+        let ids: Vec<Uuid> = (0..5).map(|_| Uuid::new_v4()).collect();
+        for id in &ids {
+            statuses.lock().unwrap().insert(*id, queued(*id));
+        }
+        let running = Uuid::new_v4();
+        statuses.lock().unwrap().insert(running, queued(running));
+
+        for id in &ids {
+            set_status(&statuses, id, TaskStatus::Completed);
+            retire(&statuses, &finished, *id, 3);
+        }
+
+        let statuses = statuses.lock().unwrap();
+        assert!(!statuses.contains_key(&ids[0]));
+        assert!(!statuses.contains_key(&ids[1]));
+        assert!(ids[2..].iter().all(|id| statuses.contains_key(id)));
+        // Unfinished tasks are never evicted.
+        assert!(statuses.contains_key(&running));
+        assert_eq!(finished.lock().unwrap().len(), 3);
+    }
+}
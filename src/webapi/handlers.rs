@@ -1,19 +1,26 @@
-use actix_web::{get, put, App, HttpResponse, HttpServer, Responder, web, Result};
+use actix_web::{get, post, put, delete, App, HttpRequest, HttpResponse, HttpServer, Responder, web, Result};
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::{HashMap, VecDeque};
 use tokio::sync::oneshot; // For task cancellation
+use tokio::sync::broadcast; // For task status events
+use futures_util::{stream, StreamExt};
 use tokio::task::JoinHandle;
 use uuid::Uuid; // For generating unique task IDs
 use std::time::Duration;
 use actix_web::http::header::ContentType;
 use std::fs;
-use log::{info, error, debug}; // Import logging macros
+use log::{info, warn, error, debug}; // Import logging macros
 use env_logger::Env;
-use crate::task::model::TaskStatus;
+use regex::Regex;
+use crate::task::model::{TaskInfo, TaskStatus};
+use crate::task::history::TaskHistory;
 
 // Добавьте ваши модули:
 mod config;
+mod file_trust;
+mod notification;
 mod language;
 mod intent_mapper;
 mod nlp;
@@ -23,28 +30,60 @@ mod debug_logger;
 
 mod platform;
 
-use crate::config::{AppConfig, SharedConfig, init_shared_config};
-use crate::nlp::parse_command;
-use crate::intent_mapper::map_intent;
+use crate::config::{AppConfig, AliasConfig, ApiKeyRejection, SharedConfig, init_shared_config};
+use crate::config::{OPERATION_EDIT_ALIASES, OPERATION_EDIT_SETTINGS, OPERATION_STOP_TASK, OPERATION_UNDO};
+use crate::nlp::{normalize_command, parse_command, parse_command_with, pattern_captures, NLPResult};
+use crate::intent_mapper::{is_blocked_in_safe_mode, is_known_intent, map_intent, required_targets, validate, Action, INTENT_SPECS, SAFE_MODE_MESSAGE};
 use crate::task_scheduler::{Task, TaskScheduler};
-use crate::language::PATTERNS; // Import PATTERNS
+use crate::language::{current_patterns, load_patterns, reload_patterns, using_embedded_patterns};
 use crate::webapi::models::*;
+use crate::debug_logger::PipelineTrace;
 
 use crate::platform::windows::controller::WinUiController;
 
 use std::time::Instant;
 
 lazy_static::lazy_static! {
-    static ref LAST_COMMAND_TIME: Mutex<Option<Instant>> = Mutex::new(None);
+    // Recent command times per client address, for the anti-flood sliding window.
+    static ref CLIENT_REQUESTS: Mutex<HashMap<String, VecDeque<Instant>>> = Mutex::new(HashMap::new());
 }
 
-// Task structure (replace with your actual Task structure)
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct TaskInfo {
-    id: Uuid, // Уникальный идентификатор задачи
-    name: String,
-    status: TaskStatus, // e.g., "queued", "running", "completed", "error"
-    // Optional: Add more fields to describe the task
+/// Counters served by `/metrics`, updated without taking any lock.
+#[derive(Default)]
+struct CommandCounters {
+    commands_received: AtomicU64, // Single and batch commands, throttled ones included
+    tasks_completed: AtomicU64,
+    tasks_failed: AtomicU64,
+    tasks_cancelled: AtomicU64,
+    execution_ms_total: AtomicU64, // Summed over completed and failed tasks
+    parse_ema_us: AtomicU64, // Moving average of parse_command + map_intent time, microseconds
+}
+
+/// Weight of the newest command in the parse-time moving average.
+const PARSE_EMA_ALPHA: f64 = 0.1;
+
+/// A command parsing this many times slower than the moving average is logged as slow.
+const SLOW_PARSE_FACTOR: f64 = 5.0;
+
+/// Parse times below this are never reported as slow, however small the average is.
+const SLOW_PARSE_MIN: Duration = Duration::from_millis(5);
+
+/// Folds the time `command` took to parse and map into the moving average, and warns when it was far
+/// above it: that usually means a language-file pattern backtracks badly on this input.
+fn record_parse_time(data: &web::Data<AppState>, command: &str, elapsed: Duration) {
+    let sample = elapsed.as_micros() as u64;
+    let average = data.counters.parse_ema_us
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |average| {
+            if average == 0 {
+                Some(sample)
+            } else {
+                Some((average as f64 + PARSE_EMA_ALPHA * (sample as f64 - average as f64)).round() as u64)
+            }
+        })
+        .unwrap_or(0);
+    if average > 0 && elapsed >= SLOW_PARSE_MIN && sample as f64 > average as f64 * SLOW_PARSE_FACTOR {
+        warn!("Slow command parse: {} us against an average of {} us for '{}'", sample, average, command);
+    }
 }
 
 // State to hold tasks
@@ -54,91 +93,666 @@ struct AppState {
     scheduler: Arc<TaskScheduler>,   // Your TaskScheduler
     controller: Arc<WinUiController>,
     config_path: String, // Store the config file path
+    pending_confirmations: Arc<Mutex<HashMap<Uuid, (String, Action, Instant)>>>, // Commands awaiting confirmation, and when they were held back
+    history: Option<TaskHistory>, // Persisted task transitions, if `task_history_path` is configured
+    task_events: Arc<Mutex<HashMap<Uuid, broadcast::Sender<TaskStatus>>>>, // Status transitions per task, until it finishes
+    last_task: Arc<Mutex<Option<Uuid>>>, // Most recently scheduled task, served by /last
+    intent_metrics: Arc<Mutex<HashMap<String, (u64, u64)>>>, // (succeeded, failed) executions per intent
+    command_history: Arc<Mutex<VecDeque<(Uuid, String, Instant, bool)>>>, // Received commands, newest last; the flag marks throttled ones
+    counters: Arc<CommandCounters>,
+    idempotency_keys: Arc<Mutex<HashMap<String, (Uuid, Instant)>>>, // Idempotency-Key -> task scheduled for it, and when
+    coalescing: Arc<Mutex<HashMap<String, (Uuid, Instant, Arc<Mutex<Vec<Action>>>)>>>, // Normalized command -> its queued task, last arrival and actions
+}
+
+/// Capacity of each task's status channel; a task has only a handful of transitions.
+const TASK_EVENTS_CAPACITY: usize = 16;
+
+/// Rebuilds the task list from the persisted history on startup.
+/// Only finished tasks are restored, and they come back without cancel channels or JoinHandles.
+fn restore_tasks(history: &TaskHistory) -> HashMap<Uuid, (TaskInfo, Option<oneshot::Sender<()>>, Option<JoinHandle<()>>)> {
+    let restored: HashMap<_, _> = history
+        .load_finished()
+        .into_iter()
+        .map(|task_info| (task_info.id, (task_info, None, None)))
+        .collect();
+    info!("Restored {} finished tasks from history", restored.len());
+    restored
+}
+
+/// A user-facing message in the server's language: the `MSG_<KEY>` entry of the language file,
+/// or the English `fallback` when the file has none. See [`crate::language::Patterns::message`].
+fn localized(key: &str, fallback: &str, args: &[&dyn std::fmt::Display]) -> String {
+    current_patterns().message(key, fallback, args)
 }
 
 #[derive(Serialize)]
-struct ErrorResponse {
+struct MessageResponse {
     message: String,
 }
 
 // 1. Handler for command processing
 #[get("/")]
-async fn execute_command(data: web::Data<AppState>, query: web::Query<ExecuteCommandRequest>) -> HttpResponse {
-    let command = &query.query;
-    info!("Received command: {}", command);
+async fn execute_command(req: HttpRequest, data: web::Data<AppState>, query: web::Query<ExecuteCommandRequest>) -> HttpResponse {
+    run_command(&data, &query, api_key(&req), idempotency_key(&req), &client_address(&req))
+}
 
-     let config_lock = data.config.lock().unwrap();
-     let (antiflood, antiflood_delay) = if let Some(ref cfg) = *config_lock {
-        (cfg.antiflood, cfg.notifications_delay)
-    } else {
-        (false, 5) // Default values if config is not loaded
+// Same as `GET /`, but the command comes in a JSON body, so quotes, newlines and Cyrillic need no URL-encoding
+#[post("/")]
+async fn execute_command_body(req: HttpRequest, data: web::Data<AppState>, body: web::Json<ExecuteCommandRequest>) -> HttpResponse {
+    run_command(&data, &body, api_key(&req), idempotency_key(&req), &client_address(&req))
+}
+
+/// Identifies the client for anti-flood accounting by its (proxy-reported) address.
+fn client_address(req: &HttpRequest) -> String {
+    req.connection_info()
+        .realip_remote_addr()
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+/// Header carrying the caller's API key.
+const API_KEY_HEADER: &str = "X-Api-Key";
+
+/// Reads the API key sent with the request, if any.
+fn api_key(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Header a client sets so that retrying the same command does not run it twice.
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// Reads the idempotency key sent with the request, if any.
+fn idempotency_key(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Returns the task already scheduled under `key` within `idempotency_ttl_secs`.
+/// Expired keys are dropped on the way.
+fn idempotent_task(data: &web::Data<AppState>, key: &str) -> Option<TaskInfo> {
+    let ttl_secs = data.config.lock().unwrap()
+        .as_ref()
+        .map_or(300, |cfg| cfg.idempotency_ttl_secs);
+    let ttl = Duration::from_secs(ttl_secs);
+    let task_id = {
+        let mut keys = data.idempotency_keys.lock().unwrap();
+        keys.retain(|_, (_, created)| created.elapsed() < ttl);
+        keys.get(key).map(|(task_id, _)| *task_id)?
     };
+    let task_info = data.tasks.lock().unwrap()
+        .get(&task_id)
+        .map(|(task_info, _, _)| task_info.clone());
+    // A stopped task is gone from the task list, but the scheduler still knows its status.
+    task_info.or_else(|| data.scheduler.status(&task_id))
+}
+
+/// Why a mapped command cannot run: not recognised, refused by the configuration, or missing parameters.
+fn command_rejection(action: &Action) -> Option<(ErrorCode, String)> {
+    match action {
+        Action::Unknown { hint } => Some((ErrorCode::ParseFailed, hint.clone())),
+        Action::Refused { reason, .. } => Some((ErrorCode::IntentDisabled, reason.clone())),
+        _ => validate(action).err().map(|message| (ErrorCode::InvalidParameters, message)),
+    }
+}
+
+/// Error code for a failure reported by the controller; windows that cannot be found get their own code.
+fn controller_error_code(message: &str) -> ErrorCode {
+    if message.starts_with("Window") && message.contains("not found") {
+        ErrorCode::WindowNotFound
+    } else {
+        ErrorCode::ActionFailed
+    }
+}
+
+/// Response for a failure reported by the controller: 404 when the window is missing, 500 otherwise.
+fn controller_error(message: String) -> HttpResponse {
+    let error_code = controller_error_code(&message);
+    let error_response = ErrorResponse { error_code, message };
+    match error_code {
+        ErrorCode::WindowNotFound => HttpResponse::NotFound().json(&error_response),
+        _ => HttpResponse::InternalServerError().json(&error_response),
+    }
+}
+
+/// Checks that the API key may run the parsed intent. Returns the 401/403 response when it may not.
+fn check_api_key(data: &web::Data<AppState>, api_key: Option<&str>, intent: &str) -> Option<HttpResponse> {
+    let config_lock = data.config.lock().unwrap();
+    let cfg = config_lock.as_ref()?;
+    cfg.check_api_key(api_key, intent).err().map(api_key_rejected)
+}
 
-    if antiflood {
-        let mut last_command_time = LAST_COMMAND_TIME.lock().unwrap();
-            let now = Instant::now();
-        if let Some(last_time) = *last_command_time {
-            let elapsed = now.duration_since(last_time);
-              let duration = Duration::from_secs(antiflood_delay as u64);
-             if elapsed < duration {
-                    let message = format!("Too many requests. Please wait before sending another command. Timeout = {:.2?}", duration - elapsed);
-                 let error_response = ErrorResponse { message };
-                return HttpResponse::TooManyRequests().json(&error_response);
+/// Checks that the request's API key may use a non-command endpoint, one of the `OPERATION_*` names.
+/// Returns the 401/403 response when it may not.
+fn check_api_key_operation(data: &web::Data<AppState>, req: &HttpRequest, operation: &str) -> Option<HttpResponse> {
+    let config_lock = data.config.lock().unwrap();
+    let cfg = config_lock.as_ref()?;
+    cfg.check_api_key_operation(api_key(req).as_deref(), operation).err().map(api_key_rejected)
+}
+
+/// Builds the 401/403 response for a request refused by the API key check.
+fn api_key_rejected(rejection: ApiKeyRejection) -> HttpResponse {
+    match rejection {
+        ApiKeyRejection::Unauthorized => {
+            let message = format!("A valid {} header is required", API_KEY_HEADER);
+            let error_response = ErrorResponse { error_code: ErrorCode::Unauthorized, message };
+            HttpResponse::Unauthorized().json(&error_response)
+        }
+        ApiKeyRejection::IntentNotAllowed(denied) => {
+            info!("API key refused intent '{}'", denied);
+            let message = format!("Intent '{}' is not allowed for this API key", denied);
+            let error_response = ErrorResponse { error_code: ErrorCode::IntentNotAllowed, message };
+            HttpResponse::Forbidden().json(&error_response)
+        }
+    }
+}
+
+/// Parses, maps and schedules a single command for both the query-string and JSON-body entry points.
+/// A repeated `idempotency_key` returns the task scheduled the first time instead of running the command again.
+fn run_command(data: &web::Data<AppState>, request: &ExecuteCommandRequest, api_key: Option<String>, idempotency_key: Option<String>, client: &str) -> HttpResponse {
+    let command = &request.query;
+    info!("Received command from {}: {}", client, command);
+
+    if let Some(key) = idempotency_key.as_deref() {
+        if let Some(task_info) = idempotent_task(data, key) {
+            info!("Idempotency key '{}' already scheduled task {}; not running '{}' again", key, task_info.id, command);
+            return HttpResponse::Ok().json(&task_info);
+        }
+    }
+
+    let remaining = check_antiflood(data, client);
+    let command_id = record_command(data, command, remaining.is_err());
+    match remaining {
+        Ok(remaining) => {
+            let mut response = dispatch_command(data, command_id, request, api_key, idempotency_key);
+            if let Some(remaining) = remaining {
+                insert_rate_limit_remaining(&mut response, remaining);
             }
+            response
         }
-        *last_command_time = Some(now);
+        Err(response) => response,
     }
+}
+
+/// Parses `command` with the patterns of `lang`, or the default ones when no language is given.
+fn parse_in_language(command: &str, lang: Option<&str>) -> Result<NLPResult, HttpResponse> {
+    match lang {
+        Some(lang) => match load_patterns(lang) {
+            Ok(patterns) => Ok(parse_command_with(command, &patterns)),
+            Err(e) => {
+                let error_response = ErrorResponse { error_code: ErrorCode::LanguageNotFound, message: e };
+                Err(HttpResponse::BadRequest().json(&error_response))
+            }
+        },
+        None => Ok(parse_command(command)),
+    }
+}
 
-    let nlp_result = parse_command(&command);
+/// The part of [`run_command`] after the anti-flood check. A scheduled task gets `command_id`, the id of the history entry.
+fn dispatch_command(data: &web::Data<AppState>, command_id: Uuid, request: &ExecuteCommandRequest, api_key: Option<String>, idempotency_key: Option<String>) -> HttpResponse {
+    let command = &request.query;
+
+    let parse_started = Instant::now();
+    let nlp_result = match parse_in_language(command, request.lang.as_deref()) {
+        Ok(nlp_result) => nlp_result,
+        Err(response) => return response,
+    };
+    let parse_elapsed = parse_started.elapsed();
     debug!("NLP Result: {:?}", nlp_result);
 
+    if let Some(response) = check_api_key(data, api_key.as_deref(), &nlp_result.intent) {
+        return response;
+    }
+
+    let map_started = Instant::now();
     let action = map_intent(&nlp_result, &data.config);
+    record_parse_time(data, command, parse_elapsed + map_started.elapsed());
     debug!("Mapped Action: {:?}", action);
+    let trace = PipelineTrace::new(command_id, command, &nlp_result.normalized, &nlp_result.intent, nlp_result.confidence, format!("{:?}", action));
+
+    if request.dry_run {
+        let dry_run = DryRunResponse {
+            query: command.clone(),
+            intent: nlp_result.intent.clone(),
+            parameters: nlp_result.parameters.clone(),
+            confidence: nlp_result.confidence,
+            action: format!("{:?}", action),
+        };
+        return HttpResponse::Ok().json(&dry_run);
+    }
+
+    if let Some((error_code, message)) = command_rejection(&action) {
+        info!("Command '{}' rejected: {}", command, message);
+        trace.finish(&format!("rejected: {}", message));
+        let error_response = ErrorResponse { error_code, message };
+        return match error_code {
+            ErrorCode::IntentDisabled => HttpResponse::Forbidden().json(&error_response),
+            _ => HttpResponse::BadRequest().json(&error_response),
+        };
+    }
+
+    if let Some(response) = require_confirmation(data, command, &nlp_result, &action) {
+        return response;
+    }
+
+    let coalesce = coalescing(&action);
+    if coalesce.is_some() {
+        if let Some(task_info) = coalesce_command(data, &nlp_result.normalized, &action) {
+            info!("Command '{}' coalesced into queued task {}", command, task_info.id);
+            return HttpResponse::Ok().json(&task_info);
+        }
+    }
+
+    if let Some(response) = check_queue_capacity(data) {
+        trace.finish("rejected: queue full");
+        return response;
+    }
+
+    let actions = Arc::new(Mutex::new(vec![action]));
+    let task_info = schedule_shared_actions(data, command_id, format!("Task: {}", command), vec![nlp_result.intent.clone()], actions.clone(), Some(trace));
+    if let Some(key) = idempotency_key {
+        data.idempotency_keys.lock().unwrap().insert(key, (task_info.id, Instant::now()));
+    }
+    if coalesce.is_some() && coalesce_window(data).is_some() {
+        data.coalescing.lock().unwrap().insert(nlp_result.normalized.clone(), (task_info.id, Instant::now(), actions));
+    }
+    HttpResponse::Ok().json(&task_info) // Return TaskInfo
+}
+
+/// How a command is folded into an identical one whose task is still queued.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Coalesce {
+    /// Add the new command's amount to the queued one.
+    AddAmount,
+    /// Drop the new command; running it twice changes nothing.
+    Dedupe,
+}
+
+/// Which commands `coalesce_enable` may merge: scrolling by lines or pages adds up the amounts, while
+/// scrolling to the top or bottom, minimizing, maximizing, activating, pinning and arranging windows
+/// are deduplicated. Every other command runs as many times as it is sent.
+fn coalescing(action: &Action) -> Option<Coalesce> {
+    match action {
+        Action::Scroll { direction, .. } if matches!(direction.as_str(), "top" | "bottom") => Some(Coalesce::Dedupe),
+        Action::Scroll { .. } => Some(Coalesce::AddAmount),
+        Action::WindowMinimize { .. }
+        | Action::WindowMaximize { .. }
+        | Action::WindowActivate { .. }
+        | Action::WindowSetTopmost { .. }
+        | Action::WindowMinimizeAll
+        | Action::WindowMaximizeAll
+        | Action::ArrangeWindows { .. }
+        | Action::WindowMoveNamed { .. } => Some(Coalesce::Dedupe),
+        _ => None,
+    }
+}
+
+/// The `coalesce_window_ms` window, or `None` while coalescing is off.
+fn coalesce_window(data: &web::Data<AppState>) -> Option<Duration> {
+    data.config.lock().unwrap()
+        .as_ref()
+        .filter(|cfg| cfg.coalesce_enable)
+        .map(|cfg| Duration::from_millis(cfg.coalesce_window_ms))
+}
+
+/// Folds `action` into the task of the same normalized command if that arrived within
+/// `coalesce_window_ms` and has not started yet. Returns the task it was folded into.
+fn coalesce_command(data: &web::Data<AppState>, normalized: &str, action: &Action) -> Option<TaskInfo> {
+    let window = coalesce_window(data)?;
+    let mut pending = data.coalescing.lock().unwrap();
+    pending.retain(|_, (_, last_arrival, _)| last_arrival.elapsed() < window);
+    let (task_id, last_arrival, actions) = pending.get_mut(normalized)?;
+
+    // The worker marks the task running under the tasks lock before it reads its actions,
+    // so while this lock is held a queued task still runs whatever is changed here.
+    let tasks_lock = data.tasks.lock().unwrap();
+    let task_info = tasks_lock.get(task_id).map(|(task_info, _, _)| task_info.clone())?;
+    if task_info.status != TaskStatus::Queued {
+        return None;
+    }
+    if coalescing(action) == Some(Coalesce::AddAmount) {
+        let mut actions = actions.lock().unwrap();
+        if let (Some(Action::Scroll { amount, .. }), Action::Scroll { amount: extra, .. }) = (actions.first_mut(), action) {
+            *amount = Some(amount.unwrap_or(1) + extra.unwrap_or(1));
+        }
+    }
+    *last_arrival = Instant::now();
+    Some(task_info)
+}
+
+/// Appends a received command to the bounded history served by `/history`.
+/// Returns the entry's id, which the command's task takes if one is scheduled.
+fn record_command(data: &web::Data<AppState>, command: &str, throttled: bool) -> Uuid {
+    data.counters.commands_received.fetch_add(1, Ordering::Relaxed);
+    let limit = data.config.lock().unwrap()
+        .as_ref()
+        .map(|cfg| cfg.history_limit)
+        .unwrap_or(100);
+    let id = Uuid::new_v4();
+    let mut history = data.command_history.lock().unwrap();
+    history.push_back((id, command.to_string(), Instant::now(), throttled));
+    while history.len() > limit {
+        history.pop_front();
+    }
+    id
+}
+
+/// How long a client is told to wait before retrying when the task queue is full.
+const QUEUE_FULL_RETRY_AFTER_SECS: u64 = 5;
+
+/// Returns the 503 response when `max_queue_depth` tasks are already waiting.
+fn check_queue_capacity(data: &web::Data<AppState>) -> Option<HttpResponse> {
+    if data.scheduler.admit() {
+        return None;
+    }
+    let message = format!(
+        "Task queue is full ({} tasks waiting). Retry in {} s",
+        data.scheduler.queue_depth(), QUEUE_FULL_RETRY_AFTER_SECS
+    );
+    let error_response = ErrorResponse { error_code: ErrorCode::QueueFull, message };
+    Some(HttpResponse::ServiceUnavailable()
+        .insert_header(("Retry-After", QUEUE_FULL_RETRY_AFTER_SECS.to_string()))
+        .json(&error_response))
+}
+
+/// Header telling the client how many commands it has left in the anti-flood window.
+/// Lowercase, as `HeaderName::from_static` requires.
+const RATE_LIMIT_REMAINING_HEADER: &str = "x-ratelimit-remaining";
+
+/// Applies the anti-flood limit: at most `antiflood_max_requests` commands per client within
+/// the last `antiflood_window_secs`. Returns how many commands the client has left in the window
+/// (`None` when antiflood is off), or the 429 response when the command must be rejected.
+fn check_antiflood(data: &web::Data<AppState>, client: &str) -> Result<Option<usize>, HttpResponse> {
+     let config_lock = data.config.lock().unwrap();
+     let (antiflood, window_secs, max_requests) = if let Some(ref cfg) = *config_lock {
+        (cfg.antiflood, cfg.antiflood_window_secs, cfg.antiflood_max_requests)
+    } else {
+        (false, 5, 1) // Default values if config is not loaded
+    };
+    drop(config_lock); // map_intent locks the config again
+
+    if !antiflood {
+        return Ok(None);
+    }
+    let window = Duration::from_secs(window_secs);
+    let max_requests = max_requests.max(1);
+    let now = Instant::now();
+
+    let mut clients = CLIENT_REQUESTS.lock().unwrap();
+    // Forget clients whose whole window has expired so the map does not grow without bound.
+    clients.retain(|_, times| times.back().is_some_and(|last| now.duration_since(*last) < window));
+    let times = clients.entry(client.to_string()).or_default();
+    while times.front().is_some_and(|first| now.duration_since(*first) >= window) {
+        times.pop_front();
+    }
+
+    if times.len() >= max_requests {
+        let retry_after = times.front().map_or(window, |first| window - now.duration_since(*first));
+        let retry_after_secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+        let message = format!(
+            "Too many requests: at most {} commands per {} s. Retry in {} s",
+            max_requests, window_secs, retry_after_secs
+        );
+        let error_response = ErrorResponse { error_code: ErrorCode::RateLimited, message };
+        return Err(HttpResponse::TooManyRequests()
+            .insert_header(("Retry-After", retry_after_secs.to_string()))
+            .insert_header((RATE_LIMIT_REMAINING_HEADER, "0"))
+            .json(&error_response));
+    }
+    times.push_back(now);
+    Ok(Some(max_requests - times.len()))
+}
+
+fn insert_rate_limit_remaining(response: &mut HttpResponse, remaining: usize) {
+    if let Ok(value) = actix_web::http::header::HeaderValue::from_str(&remaining.to_string()) {
+        response.headers_mut().insert(
+            actix_web::http::header::HeaderName::from_static(RATE_LIMIT_REMAINING_HEADER),
+            value,
+        );
+    }
+}
 
-    let task_name = format!("Task: {}", command);
-    let task_id = Uuid::new_v4();
+// Handler for a batch of commands sent as a JSON body
+#[post("/execute")]
+async fn execute_batch(req: HttpRequest, data: web::Data<AppState>, body: web::Json<ExecuteBatchRequest>) -> HttpResponse {
+    info!("Received batch of {} commands (sequential: {})", body.commands.len(), body.sequential);
+    data.counters.commands_received.fetch_add(body.commands.len() as u64, Ordering::Relaxed);
+
+    let remaining = match check_antiflood(&data, &client_address(&req)) {
+        Ok(remaining) => remaining,
+        Err(response) => return response,
+    };
+
+    let min_confidence = data.config.lock().unwrap()
+        .as_ref()
+        .map(|cfg| cfg.min_confidence)
+        .unwrap_or(0.0);
+
+    // Map the whole batch up front so a bad command rejects it before anything runs.
+    let mut actions = Vec::with_capacity(body.commands.len());
+    for (index, command) in body.commands.iter().enumerate() {
+        let parse_started = Instant::now();
+        let nlp_result = parse_command(command);
+        let parse_elapsed = parse_started.elapsed();
+        if let Some(response) = check_api_key(&data, api_key(&req).as_deref(), &nlp_result.intent) {
+            return response;
+        }
+        let map_started = Instant::now();
+        let action = map_intent(&nlp_result, &data.config);
+        record_parse_time(&data, command, parse_elapsed + map_started.elapsed());
+        debug!("Batch command #{} '{}' mapped to {:?}", index, command, action);
+
+        let rejection = command_rejection(&action).or_else(|| {
+            (nlp_result.confidence < min_confidence).then(|| (
+                ErrorCode::ParseFailed,
+                format!("Command parsed with low confidence {:.2}; send it alone to confirm", nlp_result.confidence),
+            ))
+        });
+        if let Some((error_code, reason)) = rejection {
+            let message = format!("Command #{} '{}' rejected: {}", index, command, reason);
+            let error_response = ErrorResponse { error_code, message };
+            return HttpResponse::UnprocessableEntity().json(&error_response);
+        }
+        let trace = PipelineTrace::new(Uuid::new_v4(), command, &nlp_result.normalized, &nlp_result.intent, nlp_result.confidence, format!("{:?}", action));
+        actions.push((command.clone(), nlp_result.intent, action, trace));
+    }
+
+    if let Some(response) = check_queue_capacity(&data) {
+        return response;
+    }
+
+    let task_infos: Vec<TaskInfo> = if body.sequential {
+        let task_name = format!("Batch: {}", body.commands.join("; "));
+        let (intents, actions) = actions.into_iter().map(|(_, intent, action, _)| (intent, action)).unzip();
+        vec![schedule_actions(&data, Uuid::new_v4(), task_name, intents, actions, None)]
+    } else {
+        actions
+            .into_iter()
+            .map(|(command, intent, action, trace)| schedule_action(&data, trace.trace_id, &command, &intent, action, Some(trace)))
+            .collect()
+    };
+    let mut response = HttpResponse::Ok().json(&task_infos);
+    if let Some(remaining) = remaining {
+        insert_rate_limit_remaining(&mut response, remaining);
+    }
+    response
+}
+
+/// How long a confirmation token stays valid; unconfirmed commands are forgotten afterwards.
+const CONFIRMATION_TTL: Duration = Duration::from_secs(300);
+
+/// Returns true if `action`, parsed with `confidence`, must be confirmed before it runs.
+/// Unrecognised commands execute nothing, so there is nothing to confirm.
+fn needs_confirmation(action: &Action, confidence: f32, min_confidence: f32) -> bool {
+    !matches!(action, Action::Unknown { .. }) && confidence < min_confidence
+}
+
+/// Drops confirmations held back for longer than `ttl`.
+fn sweep_confirmations(pending: &mut HashMap<Uuid, (String, Action, Instant)>, ttl: Duration) {
+    pending.retain(|_, (_, _, created)| created.elapsed() < ttl);
+}
+
+/// Holds back commands parsed below `min_confidence` until the client confirms them.
+/// Returns the "did you mean" response when confirmation is needed.
+fn require_confirmation(data: &web::Data<AppState>, command: &str, nlp_result: &NLPResult, action: &Action) -> Option<HttpResponse> {
+    let min_confidence = data.config.lock().unwrap()
+        .as_ref()
+        .map(|cfg| cfg.min_confidence)
+        .unwrap_or(0.0);
+    if !needs_confirmation(action, nlp_result.confidence, min_confidence) {
+        return None;
+    }
+
+    let token = Uuid::new_v4();
+    info!("Command '{}' parsed with low confidence {:.2}, confirmation token {}", command, nlp_result.confidence, token);
+    let mut pending = data.pending_confirmations.lock().unwrap();
+    sweep_confirmations(&mut pending, CONFIRMATION_TTL);
+    pending.insert(token, (command.to_string(), action.clone(), Instant::now()));
+    drop(pending);
+
+    let confirmation = ConfirmationResponse {
+        message: format!("Did you mean '{}' ({})? Confirm with /confirm={}", command, nlp_result.intent, token),
+        token,
+        intent: nlp_result.intent.clone(),
+        confidence: nlp_result.confidence,
+    };
+    Some(HttpResponse::Accepted().json(&confirmation))
+}
+
+/// Counts one execution of a command parsed as `intent` as succeeded or failed.
+fn record_action_metrics(metrics: &Mutex<HashMap<String, (u64, u64)>>, intent: &str, succeeded: bool) {
+    let mut metrics = metrics.lock().unwrap();
+    let counts = metrics.entry(intent.to_string()).or_insert((0, 0));
+    if succeeded {
+        counts.0 += 1;
+    } else {
+        counts.1 += 1;
+    }
+}
+
+/// Creates the task `task_id` for the mapped action, registers it in the task list and hands it to the scheduler.
+fn schedule_action(data: &web::Data<AppState>, task_id: Uuid, command: &str, intent: &str, action: Action, trace: Option<PipelineTrace>) -> TaskInfo {
+    schedule_actions(data, task_id, format!("Task: {}", command), vec![intent.to_string()], vec![action], trace)
+}
+
+/// Creates one task that runs `actions` in order and stops on the first failure.
+/// `intents` holds the parsed intent of each action, for the per-intent metrics.
+/// `trace`, if given, is logged under the task id once the task finishes.
+fn schedule_actions(data: &web::Data<AppState>, task_id: Uuid, task_name: String, intents: Vec<String>, actions: Vec<Action>, trace: Option<PipelineTrace>) -> TaskInfo {
+    schedule_shared_actions(data, task_id, task_name, intents, Arc::new(Mutex::new(actions)), trace)
+}
+
+/// Like [`schedule_actions`], but the actions stay shared, so coalescing can change them until the task starts.
+fn schedule_shared_actions(data: &web::Data<AppState>, task_id: Uuid, task_name: String, intents: Vec<String>, actions: Arc<Mutex<Vec<Action>>>, trace: Option<PipelineTrace>) -> TaskInfo {
 
     let (cancel_tx, cancel_rx) = oneshot::channel::<()>();
+    let (events_tx, _) = broadcast::channel::<TaskStatus>(TASK_EVENTS_CAPACITY);
+    data.task_events.lock().unwrap().insert(task_id, events_tx.clone());
     let controller = data.controller.clone();  // Clone the WinUiController
     let task_action = {
         let config = data.config.clone();
         let task_id = task_id.clone();
         let tasks_clone = data.tasks.clone();
         let controller_clone = controller.clone();
-        let action_clone = action.clone();
+        let task_name = task_name.clone();
+        let scheduler = data.scheduler.clone();
+        let task_events = data.task_events.clone();
+        let intent_metrics = data.intent_metrics.clone();
+        let counters = data.counters.clone();
         move || {
             info!("Executing task: {}", task_name);
+            let started = Instant::now();
+            if let Some((task_info, _, _)) = tasks_clone.lock().unwrap().get_mut(&task_id) {
+                task_info.status = TaskStatus::Running;
+                scheduler.record(task_info.clone());
+            }
+            let actions = actions.lock().unwrap().clone();
+            let safe_mode = config.lock().unwrap().as_ref().is_some_and(|cfg| cfg.safe_mode);
 
-                let action_result = crate::task::executor::execute_action_on_platform(&action_clone, &controller_clone);
+                // A lone MultiStep reports its steps; a batch reports each of its commands.
+                let mut step_durations_ms = Vec::new();
+                let action_result = actions
+                    .iter()
+                    .zip(&intents)
+                    .try_fold(None, |details, (action, intent)| {
+                        if safe_mode && is_blocked_in_safe_mode(action) {
+                            warn!("Refusing {:?} in safe mode", action);
+                            record_action_metrics(&intent_metrics, intent, false);
+                            return Err(SAFE_MODE_MESSAGE.to_string());
+                        }
+                        let execution = crate::task::executor::execute_timed(action, &controller_clone);
+                        record_action_metrics(&intent_metrics, intent, execution.result.is_ok());
+                        if actions.len() == 1 {
+                            step_durations_ms = execution.step_durations_ms;
+                        } else {
+                            step_durations_ms.push(execution.duration_ms);
+                        }
+                        execution.result.map(|read| read.or(details))
+                    });
 
             info!("Task completed with result: {:?}", action_result);
+            counters.execution_ms_total.fetch_add(started.elapsed().as_millis() as u64, Ordering::Relaxed);
+            match action_result {
+                Ok(_) => counters.tasks_completed.fetch_add(1, Ordering::Relaxed),
+                Err(_) => counters.tasks_failed.fetch_add(1, Ordering::Relaxed),
+            };
+            if let Some(trace) = trace {
+                let result = match &action_result {
+                    Ok(_) => "completed".to_string(),
+                    Err(e) => format!("failed: {}", e),
+                };
+                trace.finish(&result);
+            }
 
+            let outcome = action_result.as_ref().map(|_| ()).map_err(|e| e.clone());
              let mut tasks_lock = tasks_clone.lock().unwrap();
             if let Some((task_info, _, _)) = tasks_lock.get_mut(&task_id) {
+                task_info.step_durations_ms = step_durations_ms;
                 task_info.status = match action_result {
-                    Ok(_) => TaskStatus::Completed,
+                    Ok(details) => {
+                        task_info.details = details;
+                        TaskStatus::Completed
+                    }
                     Err(e) => TaskStatus::Failed(e),
                 };
+                scheduler.record(task_info.clone());
+                finish_task_events(&task_events, &task_id, task_info.status.clone());
             }
+            outcome
         }
     };
 
-    let task = Task::new(&task_name, task_action);
+    let task = Task::new(&task_name, task_action)
+        .with_id(task_id)
+        .with_status_events(events_tx);
 
     let task_info = TaskInfo {
         id: task_id,
         name: task_name.clone(),
         status: TaskStatus::Queued,
+        details: None,
+        progress: None,
+        queue_ms: None,
+        duration_ms: None,
+        step_durations_ms: Vec::new(),
     };
 
     {
         let mut tasks_lock = data.tasks.lock().unwrap();
         tasks_lock.insert(task_id, (task_info.clone(), Some(cancel_tx), None));
     }
+    *data.last_task.lock().unwrap() = Some(task_id);
+    data.scheduler.record(task_info.clone());
 
     let scheduler_clone = data.scheduler.clone();
     let task_id_clone = task_id.clone();
     let tasks_clone_2 = data.tasks.clone();
+    let task_events_clone = data.task_events.clone();
+    let counters = data.counters.clone();
     let handle: JoinHandle<()> = tokio::spawn(async move {
         // Schedule task
         scheduler_clone.schedule(task);
@@ -147,9 +761,12 @@ async fn execute_command(data: web::Data<AppState>, query: web::Query<ExecuteCom
         tokio::select! {
             _ = cancel_rx => {
                 info!("Task {} cancelled.", task_id_clone);
+                counters.tasks_cancelled.fetch_add(1, Ordering::Relaxed);
                 let mut tasks_lock = tasks_clone_2.lock().unwrap();
                 if let Some((task_info, _, _)) = tasks_lock.get_mut(&task_id_clone) {
                     task_info.status = TaskStatus::Cancelled;
+                    scheduler_clone.record(task_info.clone());
+                    finish_task_events(&task_events_clone, &task_id_clone, TaskStatus::Cancelled);
                 }
             }
         }
@@ -162,52 +779,729 @@ async fn execute_command(data: web::Data<AppState>, query: web::Query<ExecuteCom
             tasks_lock.insert(task_id, (task_info.clone(), Some(cancel_tx), Some(handle)));
         }
     }
-    HttpResponse::Ok().json(&task_info) // Return TaskInfo
+    task_info
+}
+
+/// Publishes a task's terminal status and drops its channel, which ends any open event streams.
+fn finish_task_events(task_events: &Mutex<HashMap<Uuid, broadcast::Sender<TaskStatus>>>, task_id: &Uuid, status: TaskStatus) {
+    if let Some(events) = task_events.lock().unwrap().remove(task_id) {
+        let _ = events.send(status);
+    }
+}
+
+/// Returns a task's current status and a receiver for its later transitions, or `None` for an
+/// unknown task. The receiver is `None` once the task has finished and its channel is gone.
+fn subscribe_task_events(data: &web::Data<AppState>, id: &Uuid) -> Option<(TaskStatus, Option<broadcast::Receiver<TaskStatus>>)> {
+    // Subscribe under the tasks lock so no transition slips in between reading the status and subscribing.
+    let tasks_lock = data.tasks.lock().unwrap();
+    let current = tasks_lock.get(id).map(|(task_info, _, _)| task_info.status.clone())?;
+    let receiver = data.task_events.lock().unwrap().get(id).map(|events| events.subscribe());
+    Some((current, receiver))
+}
+
+// Handler streaming a task's status changes as Server-Sent Events
+#[get("/tasks/{id}/events")]
+async fn task_events(data: web::Data<AppState>, task_id: web::Path<Uuid>) -> HttpResponse {
+    let id = task_id.into_inner();
+    let (current, receiver) = match subscribe_task_events(&data, &id) {
+        Some(subscription) => subscription,
+        None => {
+            let message = localized("task_not_found", "Task with id {} not found", &[&id]);
+            let error_response = ErrorResponse { error_code: ErrorCode::TaskNotFound, message };
+            return HttpResponse::NotFound().json(&error_response);
+        }
+    };
+
+    // State: the status to emit next (the current one first), then whatever the channel delivers.
+    let events = stream::unfold((Some(current), receiver, false), move |(pending, mut receiver, done)| async move {
+        if done {
+            return None;
+        }
+        let status = match pending {
+            Some(status) => status,
+            None => loop {
+                match receiver.as_mut()?.recv().await {
+                    Ok(status) => break status,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            },
+        };
+        // Close after the terminal state, or right away for finished tasks without a channel.
+        let done = status.is_terminal() || receiver.is_none();
+        let event = TaskEvent { id, status };
+        let payload = serde_json::to_string(&event).unwrap_or_default();
+        let chunk = web::Bytes::from(format!("data: {}\n\n", payload));
+        Some((Ok::<_, actix_web::Error>(chunk), (None, receiver, done)))
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(events)
+}
+
+/// Events queued for a `/ws` client before reading further frames waits for it to catch up.
+const SOCKET_OUTBOX_CAPACITY: usize = 64;
+
+// Handler for a persistent command channel: JSON command frames in, parse, scheduling,
+// status and result events out on the same socket
+#[get("/ws")]
+async fn command_socket(req: HttpRequest, body: web::Payload, data: web::Data<AppState>) -> Result<HttpResponse> {
+    let (response, session, messages) = actix_ws::handle(&req, body)?;
+    let client = client_address(&req);
+    info!("WebSocket client {} connected", client);
+    actix_web::rt::spawn(serve_command_socket(data, session, messages, api_key(&req), client));
+    Ok(response)
+}
+
+/// Runs one `/ws` connection until the client disconnects. Every event goes through a bounded
+/// outbox, so a client that stops reading stalls its own commands rather than growing a queue.
+async fn serve_command_socket(
+    data: web::Data<AppState>,
+    mut session: actix_ws::Session,
+    mut messages: actix_ws::MessageStream,
+    api_key: Option<String>,
+    client: String,
+) {
+    let (outbox, mut events) = tokio::sync::mpsc::channel::<SocketEvent>(SOCKET_OUTBOX_CAPACITY);
+    let mut writer_session = session.clone();
+    let writer = actix_web::rt::spawn(async move {
+        while let Some(event) = events.recv().await {
+            let payload = serde_json::to_string(&event).unwrap_or_default();
+            if writer_session.text(payload).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut cancel_on_disconnect = Vec::new();
+    while let Some(message) = messages.next().await {
+        match message {
+            Ok(actix_ws::Message::Text(text)) => {
+                let command = match serde_json::from_str::<SocketCommand>(&text) {
+                    Ok(command) => command,
+                    Err(e) => {
+                        let message = format!("Invalid command frame: {}", e);
+                        let rejected = SocketEvent::Rejected { id: None, error_code: ErrorCode::InvalidParameters, message };
+                        if outbox.send(rejected).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                };
+                if let Some(task_info) = socket_command(&data, &command, api_key.as_deref(), &client, &outbox).await {
+                    if command.cancel_on_disconnect {
+                        cancel_on_disconnect.push(task_info.id);
+                    }
+                    actix_web::rt::spawn(forward_task_events(data.clone(), task_info.id, outbox.clone()));
+                }
+            }
+            Ok(actix_ws::Message::Ping(bytes)) => {
+                if session.pong(&bytes).await.is_err() {
+                    break;
+                }
+            }
+            Ok(actix_ws::Message::Close(reason)) => {
+                debug!("WebSocket client {} closed the socket: {:?}", client, reason);
+                break;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                error!("WebSocket protocol error from {}: {}", client, e);
+                break;
+            }
+        }
+    }
+
+    info!("WebSocket client {} disconnected", client);
+    for task_id in cancel_on_disconnect {
+        let pending = data.tasks.lock().unwrap()
+            .get(&task_id)
+            .is_some_and(|(task_info, _, _)| !task_info.status.is_terminal());
+        if pending && cancel_task(&data, &task_id).is_some() {
+            info!("Cancelled task {} of disconnected WebSocket client {}", task_id, client);
+        }
+    }
+    writer.abort();
+    let _ = session.close(None).await;
 }
+
+/// Parses, maps and schedules one `/ws` command, sending `parsed` and then `scheduled` or `rejected`.
+/// Applies the same anti-flood, API key, policy and validation checks as `GET /`; a command that would
+/// need confirmation is rejected, since there is no token to confirm it with on the socket.
+async fn socket_command(
+    data: &web::Data<AppState>,
+    command: &SocketCommand,
+    api_key: Option<&str>,
+    client: &str,
+    outbox: &tokio::sync::mpsc::Sender<SocketEvent>,
+) -> Option<TaskInfo> {
+    let query = &command.query;
+    info!("Received WebSocket command from {}: {}", client, query);
+    let reject = |error_code: ErrorCode, message: String| SocketEvent::Rejected { id: command.id.clone(), error_code, message };
+
+    let throttled = check_antiflood(data, client).is_err();
+    let command_id = record_command(data, query, throttled);
+    if throttled {
+        let message = "Too many requests, retry later".to_string();
+        let _ = outbox.send(reject(ErrorCode::RateLimited, message)).await;
+        return None;
+    }
+
+    let parse_started = Instant::now();
+    let nlp_result = parse_command(query);
+    let parse_elapsed = parse_started.elapsed();
+    debug!("NLP Result: {:?}", nlp_result);
+    let parsed = SocketEvent::Parsed {
+        id: command.id.clone(),
+        query: query.clone(),
+        intent: nlp_result.intent.clone(),
+        parameters: nlp_result.parameters.clone(),
+        confidence: nlp_result.confidence,
+    };
+    outbox.send(parsed).await.ok()?;
+
+    let key_check = data.config.lock().unwrap()
+        .as_ref()
+        .map_or(Ok(()), |cfg| cfg.check_api_key(api_key, &nlp_result.intent));
+    let min_confidence = data.config.lock().unwrap()
+        .as_ref()
+        .map_or(0.0, |cfg| cfg.min_confidence);
+    let map_started = Instant::now();
+    let action = map_intent(&nlp_result, &data.config);
+    record_parse_time(data, query, parse_elapsed + map_started.elapsed());
+    debug!("Mapped Action: {:?}", action);
+
+    let rejection = match &key_check {
+        Err(ApiKeyRejection::Unauthorized) => Some((ErrorCode::Unauthorized, format!("A valid {} header is required", API_KEY_HEADER))),
+        Err(ApiKeyRejection::IntentNotAllowed(denied)) => {
+            Some((ErrorCode::IntentNotAllowed, format!("Intent '{}' is not allowed for this API key", denied)))
+        }
+        Ok(()) => command_rejection(&action).or_else(|| {
+            (nlp_result.confidence < min_confidence).then(|| (
+                ErrorCode::ParseFailed,
+                format!("Command parsed with low confidence {:.2}; send it to GET / to confirm", nlp_result.confidence),
+            ))
+        }),
+    };
+    if let Some((error_code, message)) = rejection {
+        info!("WebSocket command '{}' rejected: {}", query, message);
+        let _ = outbox.send(reject(error_code, message)).await;
+        return None;
+    }
+
+    if !data.scheduler.admit() {
+        let message = format!("Task queue is full, retry in {} s", QUEUE_FULL_RETRY_AFTER_SECS);
+        let _ = outbox.send(reject(ErrorCode::QueueFull, message)).await;
+        return None;
+    }
+
+    let trace = PipelineTrace::new(command_id, query, &nlp_result.normalized, &nlp_result.intent, nlp_result.confidence, format!("{:?}", action));
+    let task_info = schedule_action(data, command_id, query, &nlp_result.intent, action, Some(trace));
+    outbox.send(SocketEvent::Scheduled { id: command.id.clone(), task: task_info.clone() }).await.ok()?;
+    Some(task_info)
+}
+
+/// Sends a task's status transitions to a `/ws` client, then its final state as a `result` event.
+async fn forward_task_events(data: web::Data<AppState>, task_id: Uuid, outbox: tokio::sync::mpsc::Sender<SocketEvent>) {
+    if let Some((_, Some(mut receiver))) = subscribe_task_events(&data, &task_id) {
+        loop {
+            match receiver.recv().await {
+                Ok(status) => {
+                    let terminal = status.is_terminal();
+                    if outbox.send(SocketEvent::Status { task_id, status }).await.is_err() {
+                        return;
+                    }
+                    if terminal {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+    // A cancelled task is gone from the task list, but the scheduler still knows its status.
+    let task_info = data.tasks.lock().unwrap()
+        .get(&task_id)
+        .map(|(task_info, _, _)| task_info.clone())
+        .or_else(|| data.scheduler.status(&task_id));
+    if let Some(task) = task_info {
+        let _ = outbox.send(SocketEvent::Result { task }).await;
+    }
+}
+
+// Handler to confirm a low-confidence command
+#[get("/confirm={token}")]
+async fn confirm_command(req: HttpRequest, data: web::Data<AppState>, token: web::Path<Uuid>) -> impl Responder {
+    let token = token.into_inner();
+    let pending = {
+        let mut pending = data.pending_confirmations.lock().unwrap();
+        sweep_confirmations(&mut pending, CONFIRMATION_TTL);
+        pending.remove(&token)
+    };
+    if let Some((command, action, created)) = pending {
+        // The token may reach another client than the one that sent the command, so its key is checked again.
+        let intent = parse_command(&command).intent;
+        if let Some(response) = check_api_key(&data, api_key(&req).as_deref(), &intent) {
+            data.pending_confirmations.lock().unwrap().insert(token, (command, action, created));
+            return response;
+        }
+        info!("Command '{}' confirmed with token {}", command, token);
+        if let Some(response) = check_queue_capacity(&data) {
+            // Keep the token so the client can confirm again once the queue drains; it still expires on time.
+            data.pending_confirmations.lock().unwrap().insert(token, (command, action, created));
+            return response;
+        }
+        let task_info = schedule_action(&data, Uuid::new_v4(), &command, &intent, action, None);
+        HttpResponse::Ok().json(&task_info)
+    } else {
+        let message = format!("Confirmation token {} not found", token);
+        let error_response = ErrorResponse { error_code: ErrorCode::ConfirmationNotFound, message };
+        HttpResponse::NotFound().json(&error_response)
+    }
+}
+
 // 2. Handler to get the task list
 #[get("/get=tasksall")]
 async fn get_all_tasks(data: web::Data<AppState>) -> impl Responder {
     let tasks_lock = data.tasks.lock().unwrap();
-    let task_list: Vec<TaskInfo> = tasks_lock.iter().map(|(_, (task_info, _, _))| task_info.clone()).collect();
+    let task_list: Vec<TaskInfo> = tasks_lock.iter().map(|(_, (task_info, _, _))| with_progress(&data, task_info)).collect();
     HttpResponse::Ok().json(task_list)
 }
 
+/// Copies `task_info`, adding the progress a running task reported to the scheduler
+/// and the queue and run times the scheduler measured.
+fn with_progress(data: &web::Data<AppState>, task_info: &TaskInfo) -> TaskInfo {
+    let mut task_info = task_info.clone();
+    if let Some(reported) = data.scheduler.status(&task_info.id) {
+        task_info.queue_ms = reported.queue_ms;
+        task_info.duration_ms = reported.duration_ms;
+        // Retries happen inside the scheduler, so only its status shows the attempt in progress.
+        if task_info.status == TaskStatus::Running && matches!(reported.status, TaskStatus::Retrying { .. }) {
+            task_info.status = reported.status.clone();
+        }
+        if !task_info.status.is_terminal() && reported.progress.is_some() {
+            task_info.progress = reported.progress;
+            task_info.details = reported.details;
+        }
+    }
+    task_info
+}
+
+/// Looks up the task served by `/last`: the most recently scheduled one, while it is still tracked.
+fn last_task_info<A, B>(last_task: Option<Uuid>, tasks: &HashMap<Uuid, (TaskInfo, A, B)>) -> Option<&TaskInfo> {
+    last_task.and_then(|id| tasks.get(&id)).map(|(task_info, _, _)| task_info)
+}
+
+// Handler returning the most recently scheduled task, for clients that do not track task ids
+#[get("/last")]
+async fn get_last_task(data: web::Data<AppState>) -> impl Responder {
+    let last_task = *data.last_task.lock().unwrap();
+    let tasks_lock = data.tasks.lock().unwrap();
+    match last_task_info(last_task, &tasks_lock) {
+        Some(task_info) => HttpResponse::Ok().json(task_info),
+        None => {
+            let message = localized("no_task_scheduled", "No command has been scheduled yet", &[]);
+            let error_response = ErrorResponse { error_code: ErrorCode::TaskNotFound, message };
+            HttpResponse::NotFound().json(&error_response)
+        }
+    }
+}
+
+// Handler to page through the persisted task history
+#[get("/tasks/history")]
+async fn get_task_history(data: web::Data<AppState>, query: web::Query<TaskHistoryQuery>) -> impl Responder {
+    let offset = query.offset.unwrap_or(0);
+    let limit = query.limit.unwrap_or(50);
+    if let Some(ref history) = data.history {
+        let (total, records) = history.page(offset, limit);
+        HttpResponse::Ok().json(TaskHistoryResponse { total, offset, limit, records })
+    } else {
+        let message = localized("task_history_disabled", "Task history is not configured", &[]);
+        let error_response = ErrorResponse { error_code: ErrorCode::HistoryNotConfigured, message };
+        HttpResponse::NotFound().json(&error_response)
+    }
+}
+
+// Handler to check whether a task with the given name is still running
+#[get("/task/running")]
+async fn is_task_running(data: web::Data<AppState>, query: web::Query<TaskRunningQuery>) -> impl Responder {
+    let tasks_lock = data.tasks.lock().unwrap();
+    let task_ids = running_task_ids(tasks_lock.values().map(|(task_info, _, _)| task_info), &query.name);
+    HttpResponse::Ok().json(TaskRunningResponse {
+        name: query.name.clone(),
+        running: !task_ids.is_empty(),
+        task_ids,
+    })
+}
+
+/// Returns the ids of non-terminal tasks whose name is `name` or `Task: {name}`,
+/// so callers can pass either the task name or the original command.
+fn running_task_ids<'a>(tasks: impl Iterator<Item = &'a TaskInfo>, name: &str) -> Vec<Uuid> {
+    let task_name = format!("Task: {}", name);
+    tasks
+        .filter(|task_info| !task_info.status.is_terminal())
+        .filter(|task_info| task_info.name == name || task_info.name == task_name)
+        .map(|task_info| task_info.id)
+        .collect()
+}
+
+// Handler listing the visible top-level windows, for picking a command target
+#[get("/windows")]
+async fn list_windows(data: web::Data<AppState>) -> impl Responder {
+    match data.controller.list_windows() {
+        Ok(windows) => HttpResponse::Ok().json(windows),
+        Err(message) => controller_error(message),
+    }
+}
+
+// Handler checking whether a window exists. Read-only, so it answers directly instead of going through the scheduler
+#[get("/exists")]
+async fn window_exists(data: web::Data<AppState>, query: web::Query<WindowExistsQuery>) -> impl Responder {
+    match data.controller.window_exists(&query.label, query.class.as_deref()) {
+        Ok(exists) => HttpResponse::Ok().json(WindowExistsResponse {
+            label: query.label.clone(),
+            class: query.class.clone(),
+            exists,
+        }),
+        Err(message) => controller_error(message),
+    }
+}
+
+// Handler listing every intent with its parameters, for clients building commands or forms
+#[get("/intents")]
+async fn list_intents() -> impl Responder {
+    let patterns = current_patterns();
+    let intents: Vec<IntentDescription> = INTENT_SPECS
+        .iter()
+        .map(|spec| IntentDescription {
+            intent: spec.intent.to_string(),
+            action: spec.action.to_string(),
+            parameters: spec.params
+                .iter()
+                .map(|param| IntentParameter {
+                    name: param.name.to_string(),
+                    kind: param.kind.to_string(),
+                    required: param.required,
+                })
+                .collect(),
+            example: patterns.examples.get(spec.intent).cloned(),
+        })
+        .collect();
+    HttpResponse::Ok().json(intents)
+}
+
+// Handler reverting the most recent reversible action. Runs directly, like the read-only handlers, so the undo is immediate
+#[post("/undo")]
+async fn undo(req: HttpRequest, data: web::Data<AppState>) -> HttpResponse {
+    if let Some(response) = check_api_key_operation(&data, &req, OPERATION_UNDO) {
+        return response;
+    }
+    match crate::task::executor::undo_last(&data.controller) {
+        Ok(Some(inverse)) => HttpResponse::Ok().json(UndoResponse { undone_with: format!("{:?}", inverse) }),
+        Ok(None) => {
+            let message = "Nothing to undo: only checkbox changes, window minimize/maximize/hide/topmost and text replacement can be undone".to_string();
+            let error_response = ErrorResponse { error_code: ErrorCode::NothingToUndo, message };
+            HttpResponse::Conflict().json(&error_response)
+        }
+        Err(message) => controller_error(message),
+    }
+}
+
+// Handler explaining how a command is parsed and mapped, without executing it
+#[get("/describe")]
+async fn describe_command(data: web::Data<AppState>, query: web::Query<DescribeQuery>) -> HttpResponse {
+    let nlp_result = match parse_in_language(&query.query, query.lang.as_deref()) {
+        Ok(nlp_result) => nlp_result,
+        Err(response) => return response,
+    };
+    // Mapped through the configured aliases, like an executed command.
+    let action = map_intent(&nlp_result, &data.config);
+    let safe_mode = data.config.lock().unwrap().as_ref().is_some_and(|cfg| cfg.safe_mode);
+    HttpResponse::Ok().json(DescribeResponse {
+        would_require: required_targets(&action),
+        blocked: (safe_mode && is_blocked_in_safe_mode(&action)).then(|| SAFE_MODE_MESSAGE.to_string()),
+        resolved_action: format!("{:?}", action),
+        normalized: nlp_result.normalized,
+        tokens: nlp_result.tokens,
+        intent: nlp_result.intent,
+        confidence: nlp_result.confidence,
+        parameters: nlp_result.parameters,
+    })
+}
+
+// Handler re-reading the language file, so pattern edits apply without a restart
+#[post("/reload-language")]
+async fn reload_language(req: HttpRequest, data: web::Data<AppState>) -> HttpResponse {
+    if let Some(response) = check_api_key_operation(&data, &req, OPERATION_EDIT_SETTINGS) {
+        return response;
+    }
+    let Some(lang) = data.config.lock().unwrap().as_ref().map(|cfg| cfg.language.clone()) else {
+        let message = localized("settings_not_initialized", "Settings not initialized", &[]);
+        let error_response = ErrorResponse { error_code: ErrorCode::SettingsNotInitialized, message };
+        return HttpResponse::NotFound().json(error_response);
+    };
+    match reload_patterns(&lang) {
+        Ok(path) => {
+            crate::nlp::set_stemmer_language(&lang);
+            HttpResponse::Ok().json(ReloadLanguageResponse { path: path.display().to_string() })
+        }
+        Err(e) => {
+            error!("Failed to reload language patterns, keeping the current ones: {}", e);
+            let error_response = ErrorResponse { error_code: ErrorCode::LanguageInvalid, message: e };
+            HttpResponse::UnprocessableEntity().json(&error_response)
+        }
+    }
+}
+
+// Handler trying a regex against a command normalized the way the parser sees it, for language authors
+#[post("/lang/test")]
+async fn test_language_pattern(body: web::Json<LangTestRequest>) -> HttpResponse {
+    let request = body.into_inner();
+    if let Some(which) = request.which.as_deref().filter(|which| !is_known_intent(which)) {
+        let error_response = ErrorResponse { error_code: ErrorCode::InvalidParameters, message: format!("Unknown intent '{}'", which) };
+        return HttpResponse::BadRequest().json(&error_response);
+    }
+    let patterns = match request.lang.as_deref() {
+        Some(lang) => match load_patterns(lang) {
+            Ok(patterns) => patterns,
+            Err(e) => {
+                let error_response = ErrorResponse { error_code: ErrorCode::LanguageNotFound, message: e };
+                return HttpResponse::BadRequest().json(&error_response);
+            }
+        },
+        None => current_patterns(),
+    };
+    // Compiled exactly as the language loader does, so the same errors surface here.
+    let regex = match Regex::new(&request.pattern) {
+        Ok(regex) => regex,
+        Err(e) => {
+            let error_response = ErrorResponse { error_code: ErrorCode::PatternInvalid, message: e.to_string() };
+            return HttpResponse::UnprocessableEntity().json(&error_response);
+        }
+    };
+
+    let (tokens, normalized) = normalize_command(&request.text, &patterns);
+    let (captures, named_captures) = pattern_captures(&regex, &normalized);
+    let parsed_intent = parse_command_with(&request.text, &patterns).intent;
+    HttpResponse::Ok().json(LangTestResponse {
+        which_matched: request.which.as_ref().map(|which| which.eq_ignore_ascii_case(&parsed_intent)),
+        matched: !captures.is_empty(),
+        normalized,
+        tokens,
+        captures,
+        named_captures,
+        parsed_intent,
+    })
+}
+
+// Handler reporting what this build can do, so clients can adapt their UI
+#[get("/capabilities")]
+async fn get_capabilities() -> impl Responder {
+    HttpResponse::Ok().json(capabilities())
+}
+
+/// Collects the compiled-in backend and features and the language files present on disk.
+fn capabilities() -> CapabilitiesResponse {
+    let backend = if cfg!(feature = "enable_win32") { "win32" } else { "none" };
+    let mut features = Vec::new();
+    if cfg!(feature = "enable_win32") {
+        features.push("enable_win32".to_string());
+    }
+    if cfg!(feature = "ocr") {
+        features.push("ocr".to_string());
+    }
+    let endpoints = [
+        "GET /", "POST /", "POST /execute", "GET /confirm={token}", "GET /get=tasksall", "GET /last",
+        "GET /tasks/history", "GET /tasks/{id}/events", "GET /ws", "GET /task/running", "GET /stop={task_id}", "GET /status", "GET /ready", "GET /metrics",
+        "GET /history", "POST /history/{id}/replay",
+        "GET /capabilities", "GET /intents", "GET /describe", "POST /undo", "POST /reload-language", "POST /lang/test", "GET /windows", "GET /exists", "GET /get=settings", "GET /config", "POST /aliases", "DELETE /aliases/{alias}", "GET /get=settings.{setting_name}",
+        "PUT /put=settings.{setting_name}",
+    ];
+    CapabilitiesResponse {
+        backend: backend.to_string(),
+        platform: std::env::consts::OS.to_string(),
+        features,
+        endpoints: endpoints.iter().map(|e| e.to_string()).collect(),
+        languages: available_languages(),
+    }
+}
+
+/// Lists the language codes that have a `{code}.lng` file in any language directory.
+fn available_languages() -> Vec<String> {
+    let mut languages: Vec<String> = crate::language::language_dir_candidates()
+        .into_iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lng"))
+        .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+        .collect();
+    languages.sort();
+    languages.dedup();
+    languages
+}
+
 // 3. Handler to stop a task
 #[get("/stop={task_id}")]
-async fn stop_task(data: web::Data<AppState>, task_id: web::Path<Uuid>) -> impl Responder {
+async fn stop_task(req: HttpRequest, data: web::Data<AppState>, task_id: web::Path<Uuid>) -> impl Responder {
+    if let Some(response) = check_api_key_operation(&data, &req, OPERATION_STOP_TASK) {
+        return response;
+    }
     let id = task_id.into_inner();
     info!("Stopping task with id: {}", id);
 
-    let mut tasks_lock = data.tasks.lock().unwrap();
+    if let Some(task_info) = cancel_task(&data, &id) {
+       HttpResponse::Ok().json(task_info)
+    } else {
+          let message = localized("task_not_found", "Task with id {} not found", &[&id]);
+        let error_response = ErrorResponse { error_code: ErrorCode::TaskNotFound, message };
+        HttpResponse::NotFound().json(&error_response)
+    }
+}
+
+/// Removes a task from the task list, signals its cancellation and aborts its tokio handle.
+/// Returns the task as it was, or `None` if there is no such task.
+fn cancel_task(data: &web::Data<AppState>, id: &Uuid) -> Option<TaskInfo> {
+    let (task_info, cancel_tx_opt, join_handle_opt) = data.tasks.lock().unwrap().remove(id)?;
+    if let Some(cancel_tx) = cancel_tx_opt {
+        let _ = cancel_tx.send(()); // Signal cancellation
+        info!("Sent cancellation signal for task {}", id);
+    }
+    if let Some(join_handle) = join_handle_opt {
+        info!("Aborting tokio handle for task {}", id);
+        join_handle.abort(); // Abort the spawned task
+    }
+    Some(task_info)
+}
 
-    if let Some((task_info, cancel_tx_opt, join_handle_opt)) = tasks_lock.remove(&id) {
-        //task_info.status = "stopping".to_string(); // Set status to "stopping"
+// Handler listing the most recent commands received, newest first
+#[get("/history")]
+async fn get_command_history(data: web::Data<AppState>, query: web::Query<CommandHistoryQuery>) -> impl Responder {
+    let limit = query.limit.unwrap_or(50);
+    let history = data.command_history.lock().unwrap();
+    let entries: Vec<CommandHistoryEntry> = history
+        .iter()
+        .rev()
+        .take(limit)
+        .map(|(id, command, received, throttled)| CommandHistoryEntry {
+            id: *id,
+            command: command.clone(),
+            received_secs_ago: received.elapsed().as_secs_f64(),
+            throttled: *throttled,
+        })
+        .collect();
+    HttpResponse::Ok().json(entries)
+}
 
-        if let Some(cancel_tx) = cancel_tx_opt {
-            let _ = cancel_tx.send(()); // Signal cancellation
-            info!("Sent cancellation signal for task {}", id);
+// Handler re-running a recorded command as a new task
+#[post("/history/{id}/replay")]
+async fn replay_command(req: HttpRequest, data: web::Data<AppState>, id: web::Path<Uuid>) -> HttpResponse {
+    let id = id.into_inner();
+    let command = data.command_history.lock().unwrap()
+        .iter()
+        .find(|(entry_id, ..)| *entry_id == id)
+        .map(|(_, command, ..)| command.clone());
+    match command {
+        Some(command) => {
+            info!("Replaying command {}: {}", id, command);
+            let request = ExecuteCommandRequest { query: command, lang: None, dry_run: false };
+            run_command(&data, &request, api_key(&req), None, &client_address(&req))
         }
-
-        if let Some(join_handle) = join_handle_opt {
-             info!("Aborting tokio handle for task {}", id);
-            join_handle.abort(); // Abort the spawned task
+        None => {
+            let message = localized("command_not_found", "Command with id {} not found in history", &[&id]);
+            let error_response = ErrorResponse { error_code: ErrorCode::CommandNotFound, message };
+            HttpResponse::NotFound().json(&error_response)
         }
-       HttpResponse::Ok().json(task_info)
+    }
+}
+
+// Handler serving command, task and per-intent counters in the Prometheus text format
+#[get("/metrics")]
+async fn get_metrics(data: web::Data<AppState>) -> impl Responder {
+    let counters = &data.counters;
+    let completed = counters.tasks_completed.load(Ordering::Relaxed);
+    let failed = counters.tasks_failed.load(Ordering::Relaxed);
+    let finished = completed + failed;
+    let average_ms = if finished == 0 {
+        0.0
     } else {
-          let message = format!("Task with id {} not found", id);
-        let error_response = ErrorResponse { message };
-        HttpResponse::NotFound().json(&error_response)
+        counters.execution_ms_total.load(Ordering::Relaxed) as f64 / finished as f64
+    };
+
+    let mut body = String::new();
+    body.push_str("# HELP winnlp_commands_received_total Commands received, throttled ones included.\n");
+    body.push_str("# TYPE winnlp_commands_received_total counter\n");
+    body.push_str(&format!("winnlp_commands_received_total {}\n", counters.commands_received.load(Ordering::Relaxed)));
+    body.push_str("# HELP winnlp_tasks_total Tasks by terminal status.\n");
+    body.push_str("# TYPE winnlp_tasks_total counter\n");
+    body.push_str(&format!("winnlp_tasks_total{{status=\"completed\"}} {}\n", completed));
+    body.push_str(&format!("winnlp_tasks_total{{status=\"failed\"}} {}\n", failed));
+    body.push_str(&format!("winnlp_tasks_total{{status=\"cancelled\"}} {}\n", counters.tasks_cancelled.load(Ordering::Relaxed)));
+    body.push_str("# HELP winnlp_task_duration_average_ms Average execution time of completed and failed tasks.\n");
+    body.push_str("# TYPE winnlp_task_duration_average_ms gauge\n");
+    body.push_str(&format!("winnlp_task_duration_average_ms {:.3}\n", average_ms));
+    body.push_str("# HELP winnlp_queue_depth Scheduled tasks that have not started yet.\n");
+    body.push_str("# TYPE winnlp_queue_depth gauge\n");
+    body.push_str(&format!("winnlp_queue_depth {}\n", data.scheduler.queue_depth()));
+    body.push_str("# HELP winnlp_tasks_rejected_total Tasks refused because the queue was at max_queue_depth.\n");
+    body.push_str("# TYPE winnlp_tasks_rejected_total counter\n");
+    body.push_str(&format!("winnlp_tasks_rejected_total {}\n", data.scheduler.rejected_count()));
+    body.push_str("# HELP winnlp_parse_duration_ema_ms Moving average of the time to parse a command and map its intent.\n");
+    body.push_str("# TYPE winnlp_parse_duration_ema_ms gauge\n");
+    body.push_str(&format!("winnlp_parse_duration_ema_ms {:.3}\n", counters.parse_ema_us.load(Ordering::Relaxed) as f64 / 1000.0));
+
+    let mut intents: Vec<(String, u64, u64)> = data.intent_metrics.lock().unwrap()
+        .iter()
+        .map(|(intent, (succeeded, failed))| (intent.clone(), *succeeded, *failed))
+        .collect();
+    intents.sort();
+    body.push_str("# HELP winnlp_action_executions_total Action executions by intent and outcome.\n");
+    body.push_str("# TYPE winnlp_action_executions_total counter\n");
+    for (intent, succeeded, failed) in intents {
+        body.push_str(&format!("winnlp_action_executions_total{{intent=\"{}\",result=\"succeeded\"}} {}\n", intent, succeeded));
+        body.push_str(&format!("winnlp_action_executions_total{{intent=\"{}\",result=\"failed\"}} {}\n", intent, failed));
     }
+
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(body)
 }
 
 // 4. Handler to get the status
 #[get("/status")]
 async fn get_status() -> impl Responder {
-     let message = "Status: Running".to_string();
-        let response = ErrorResponse { message };
+     let message = localized("status_running", "Status: Running", &[]);
+        let response = MessageResponse { message };
     HttpResponse::Ok().json(response)
 }
 
+// Readiness: unlike `/status`, fails with 503 until the configuration, language patterns and scheduler are usable
+#[get("/ready")]
+async fn get_ready(data: web::Data<AppState>) -> impl Responder {
+    let check = |name: &str, healthy: bool, detail: &str| ReadinessCheck {
+        name: name.to_string(),
+        healthy,
+        detail: detail.to_string(),
+    };
+    let config_loaded = data.config.lock().unwrap().is_some();
+    let embedded_patterns = using_embedded_patterns();
+    let worker_alive = data.scheduler.is_worker_alive();
+    let checks = vec![
+        check("config", config_loaded, if config_loaded { "loaded" } else { "configuration failed to load" }),
+        check("language", !embedded_patterns, if embedded_patterns { "language file not loaded; using the embedded fallback" } else { "loaded" }),
+        check("scheduler", worker_alive, if worker_alive { "worker running" } else { "worker thread has stopped" }),
+    ];
+    let ready = checks.iter().all(|check| check.healthy);
+    let response = ReadinessResponse { ready, checks };
+    if ready {
+        HttpResponse::Ok().json(response)
+    } else {
+        HttpResponse::ServiceUnavailable().json(response)
+    }
+}
+
 // 5. Handler to get settings
 #[get("/get=settings")]
 async fn get_settings(data: web::Data<AppState>) -> impl Responder {
@@ -222,12 +1516,127 @@ async fn get_settings(data: web::Data<AppState>) -> impl Responder {
           };
         HttpResponse::Ok().json(&settings_response)
     } else {
-          let message = "Settings not initialized".to_string();
-        let error_response = ErrorResponse { message };
+          let message = localized("settings_not_initialized", "Settings not initialized", &[]);
+        let error_response = ErrorResponse { error_code: ErrorCode::SettingsNotInitialized, message };
+        HttpResponse::NotFound().json(error_response)
+    }
+}
+
+// Handler returning the whole configuration, aliases with their steps included.
+// API key values are masked; their allowed intents are shown.
+#[get("/config")]
+async fn get_config(data: web::Data<AppState>) -> impl Responder {
+    let config_lock = data.config.lock().unwrap();
+    if let Some(ref cfg) = *config_lock {
+        let mut config = cfg.clone();
+        for api_key in config.api_keys.iter_mut() {
+            api_key.key = "***".to_string();
+        }
+        HttpResponse::Ok().json(&config)
+    } else {
+        let message = localized("settings_not_initialized", "Settings not initialized", &[]);
+        let error_response = ErrorResponse { error_code: ErrorCode::SettingsNotInitialized, message };
         HttpResponse::NotFound().json(error_response)
     }
 }
 
+// Handler adding an alias at runtime; the config file is rewritten so the alias survives restarts
+#[post("/aliases")]
+async fn create_alias(req: HttpRequest, data: web::Data<AppState>, body: web::Json<AliasConfig>) -> HttpResponse {
+    if let Some(response) = check_api_key_operation(&data, &req, OPERATION_EDIT_ALIASES) {
+        return response;
+    }
+    let alias = body.into_inner();
+    if let Err(message) = validate_alias(&alias) {
+        let error_response = ErrorResponse { error_code: ErrorCode::AliasInvalid, message };
+        return HttpResponse::BadRequest().json(&error_response);
+    }
+
+    {
+        let mut config_lock = data.config.lock().unwrap();
+        let cfg = match config_lock.as_mut() {
+            Some(cfg) => cfg,
+            None => {
+                let message = localized("settings_not_initialized", "Settings not initialized", &[]);
+                let error_response = ErrorResponse { error_code: ErrorCode::SettingsNotInitialized, message };
+                return HttpResponse::NotFound().json(error_response);
+            }
+        };
+        if cfg.aliases.iter().any(|existing| existing.alias.eq_ignore_ascii_case(&alias.alias)) {
+            let message = localized("alias_exists", "Alias '{}' already exists", &[&alias.alias]);
+            let error_response = ErrorResponse { error_code: ErrorCode::AliasExists, message };
+            return HttpResponse::Conflict().json(&error_response);
+        }
+        cfg.aliases.push(alias.clone());
+    } // save_config_to_file locks the config again
+
+    if let Err(e) = save_config_to_file(data.config.clone(), &data.config_path) {
+        error!("Failed to save config to file: {}", e);
+    }
+    info!("Alias '{}' -> '{}' created", alias.alias, alias.intent);
+    HttpResponse::Created().json(&alias)
+}
+
+// Handler removing an alias at runtime
+#[delete("/aliases/{alias}")]
+async fn delete_alias(req: HttpRequest, data: web::Data<AppState>, path: web::Path<String>) -> HttpResponse {
+    if let Some(response) = check_api_key_operation(&data, &req, OPERATION_EDIT_ALIASES) {
+        return response;
+    }
+    let name = path.into_inner();
+    let removed = {
+        let mut config_lock = data.config.lock().unwrap();
+        let cfg = match config_lock.as_mut() {
+            Some(cfg) => cfg,
+            None => {
+                let message = localized("settings_not_initialized", "Settings not initialized", &[]);
+                let error_response = ErrorResponse { error_code: ErrorCode::SettingsNotInitialized, message };
+                return HttpResponse::NotFound().json(error_response);
+            }
+        };
+        let position = cfg.aliases.iter().position(|existing| existing.alias.eq_ignore_ascii_case(&name));
+        position.map(|index| cfg.aliases.remove(index))
+    };
+
+    match removed {
+        Some(alias) => {
+            if let Err(e) = save_config_to_file(data.config.clone(), &data.config_path) {
+                error!("Failed to save config to file: {}", e);
+            }
+            info!("Alias '{}' deleted", alias.alias);
+            HttpResponse::Ok().json(&alias)
+        }
+        None => {
+            let message = localized("alias_not_found", "Alias '{}' not found", &[&name]);
+            let error_response = ErrorResponse { error_code: ErrorCode::AliasNotFound, message };
+            HttpResponse::NotFound().json(&error_response)
+        }
+    }
+}
+
+/// Checks that an alias points at known intents; a `multi` alias needs at least one step.
+fn validate_alias(alias: &AliasConfig) -> Result<(), String> {
+    if alias.alias.trim().is_empty() {
+        return Err("Alias name must not be empty".to_string());
+    }
+    let is_multi = alias.command_type.as_deref().is_some_and(|t| t.eq_ignore_ascii_case("multi"));
+    if !is_multi {
+        return if is_known_intent(&alias.intent) {
+            Ok(())
+        } else {
+            Err(format!("Unknown intent '{}'", alias.intent))
+        };
+    }
+    let steps = alias.steps.as_deref().unwrap_or_default();
+    if steps.is_empty() {
+        return Err(format!("Alias '{}' is 'multi' but has no steps", alias.alias));
+    }
+    match steps.iter().position(|step| !is_known_intent(&step.intent)) {
+        Some(index) => Err(format!("Step #{} has unknown intent '{}'", index, steps[index].intent)),
+        None => Ok(()),
+    }
+}
+
 // 6. Handler to get settings by name
 #[get("/get=settings.{setting_name}")]
 async fn get_setting_by_name(data: web::Data<AppState>, setting_name: web::Path<String>) -> impl Responder {
@@ -240,46 +1649,49 @@ async fn get_setting_by_name(data: web::Data<AppState>, setting_name: web::Path<
             "notification_enable" =>  HttpResponse::Ok().json(&cfg.notification_enable),
             "antiflood" => HttpResponse::Ok().json(&cfg.antiflood),
             _ =>  {
-                  let message = "Settings not found".to_string();
-                  let error_response = ErrorResponse { message };
+                  let message = localized("setting_not_found", "Setting not found", &[]);
+                  let error_response = ErrorResponse { error_code: ErrorCode::SettingNotFound, message };
                   HttpResponse::NotFound().json(&error_response)
             } ,
         }
     } else {
-          let message = "Settings not initialized".to_string();
-                  let error_response = ErrorResponse { message };
+          let message = localized("settings_not_initialized", "Settings not initialized", &[]);
+                  let error_response = ErrorResponse { error_code: ErrorCode::SettingsNotInitialized, message };
                 HttpResponse::NotFound().json(error_response)
     }
 }
 
 // 7. Handler to update settings
 #[put("/put=settings.{setting_name}")]
-async fn update_setting(data: web::Data<AppState>, path: web::Path<String>, query: web::Query<HashMap<String, String>>) -> impl Responder {
+async fn update_setting(req: HttpRequest, data: web::Data<AppState>, path: web::Path<String>, query: web::Query<HashMap<String, String>>) -> impl Responder {
+    if let Some(response) = check_api_key_operation(&data, &req, OPERATION_EDIT_SETTINGS) {
+        return response;
+    }
     let setting_path = path.into_inner();
     let app_state = data.clone();
     if let Some((config_lock, mut json_result)) = update_config(&data.config, &data.config_path, &setting_path, query).await {
            if json_result.is_ok() {
-            let message = json_result.unwrap();
-             let response = ErrorResponse { message };
+            let message = localized("execution_result", "Execution result: {}", &[&json_result.unwrap()]);
+             let response = MessageResponse { message };
              HttpResponse::Ok().json(response)
            } else {
-                 let message = json_result.unwrap_err().to_string();
-                 let response = ErrorResponse { message };
+                 let (error_code, message) = json_result.unwrap_err();
+                 let response = ErrorResponse { error_code, message };
                HttpResponse::BadRequest().json(response)
            }
     } else {
-                 let message = "Settings not initialized".to_string();
-                 let response = ErrorResponse { message };
+                 let message = localized("settings_not_initialized", "Settings not initialized", &[]);
+                 let response = ErrorResponse { error_code: ErrorCode::SettingsNotInitialized, message };
         HttpResponse::NotFound().json(response)
     }
 }
 
 //Helper to perform safe config update
-async fn update_config(config: &SharedConfig, config_path: &str, setting_path: &str, query: web::Query<HashMap<String, String>>) -> Option<(SharedConfig,  Result<String, Box<dyn std::error::Error>>>) {
+async fn update_config(config: &SharedConfig, config_path: &str, setting_path: &str, query: web::Query<HashMap<String, String>>) -> Option<(SharedConfig,  Result<String, (ErrorCode, String)>)> {
      let mut config_lock = config.lock().unwrap();
     if let Some(ref mut cfg) = *config_lock {
         if let Some(value) = query.get("value") {
-            let result: Result<String, Box<dyn std::error::Error>> = match setting_path {
+            let result: Result<String, (ErrorCode, String)> = match setting_path {
                 "notifications_delay" => {
                      match value.parse::<u32>() {
                          Ok(new_delay) => {
@@ -287,13 +1699,17 @@ async fn update_config(config: &SharedConfig, config_path: &str, setting_path: &
                                Ok(format!("Notification delay updated to {}", new_delay))
                          },
                          Err(e) => {
-                              Err(From::from("value is not in the right type, please try again"))
+                              Err((ErrorCode::SettingInvalid, "value is not in the right type, please try again".to_string()))
                          }
                      }
                 },
-                "language" => {
-                    cfg.language = value.clone();
-                    Ok(format!("Language updated to {}", value))
+                "language" => match reload_patterns(value) {
+                    Ok(_) => {
+                        cfg.language = value.clone();
+                        crate::nlp::set_stemmer_language(&cfg.language);
+                        Ok(format!("Language updated to {}", value))
+                    }
+                    Err(e) => Err((ErrorCode::SettingInvalid, e)),
                 },
                  "notification_enable" => {
                     match value.parse::<bool>() {
@@ -301,7 +1717,7 @@ async fn update_config(config: &SharedConfig, config_path: &str, setting_path: &
                             cfg.notification_enable = new_value;
                             Ok(format!("Notification enable updated to {}", new_value))
                         }
-                        Err(_) => Err(From::from("Invalid value for notification_enable. Must be a boolean (true/false)"))
+                        Err(_) => Err((ErrorCode::SettingInvalid, "Invalid value for notification_enable. Must be a boolean (true/false)".to_string()))
                     }
                 }
                 "antiflood" => {
@@ -310,10 +1726,10 @@ async fn update_config(config: &SharedConfig, config_path: &str, setting_path: &
                             cfg.antiflood = new_value;
                             Ok(format!("Anti-flood updated to {}", new_value))
                         }
-                        Err(_) => Err(From::from("Invalid value for antiflood. Must be a boolean (true/false)"))
+                        Err(_) => Err((ErrorCode::SettingInvalid, "Invalid value for antiflood. Must be a boolean (true/false)".to_string()))
                     }
                 }
-                _ =>  Err(From::from("Setting not found"))
+                _ =>  Err((ErrorCode::SettingNotFound, "Setting not found".to_string()))
             };
 
            if result.is_ok() {
@@ -325,7 +1741,7 @@ async fn update_config(config: &SharedConfig, config_path: &str, setting_path: &
 
            Some((config.clone(), result))
         } else {
-             Some((config.clone(), Err(From::from("Missing 'value' parameter"))))
+             Some((config.clone(), Err((ErrorCode::SettingInvalid, "Missing 'value' parameter".to_string()))))
         }
     } else {
         None
@@ -342,6 +1758,6 @@ fn save_config_to_file(config: SharedConfig, file_path: &str) -> Result<(), Stri
             .map_err(|e| format!("Failed to write config to file: {}", e))?;
         Ok(())
     } else {
-        Err("Settings not initialized".to_string())
+        Err(localized("settings_not_initialized", "Settings not initialized", &[]))
     }
   }
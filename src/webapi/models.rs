@@ -2,7 +2,43 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use std::collections::HashMap;
 
-use crate::task::model::TaskStatus;
+use crate::task::model::{TaskInfo, TaskStatus};
+
+/// Machine-readable error codes carried by every API error response.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    ParseFailed,
+    IntentDisabled,
+    RateLimited,
+    SettingsNotInitialized,
+    SettingNotFound,
+    SettingInvalid,
+    TaskNotFound,
+    ConfirmationNotFound,
+    HistoryNotConfigured,
+    LanguageNotFound,
+    ActionFailed,
+    Unauthorized,
+    IntentNotAllowed,
+    InvalidParameters,
+    CommandNotFound,
+    AliasExists,
+    AliasNotFound,
+    AliasInvalid,
+    LanguageInvalid,
+    NothingToUndo,
+    PatternInvalid,
+    QueueFull,
+    WindowNotFound,
+}
+
+/// Body of every API error response.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ErrorResponse {
+    pub error_code: ErrorCode, // Machine-readable, stable across message wording changes
+    pub message: String,
+}
 
 /// Represents a Task for data transfer over the API.
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -43,4 +79,240 @@ pub struct UpdateSettingRequest {
 #[derive(Debug, Deserialize)]
 pub struct ExecuteCommandRequest {
     pub query: String,
+    #[serde(default)]
+    pub lang: Option<String>, // Language file to parse with; the loaded default when omitted
+    #[serde(default)]
+    pub dry_run: bool, // Parse and map only, without scheduling a task
+}
+
+/// What a command would do, returned for `dry_run` requests.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DryRunResponse {
+    pub query: String,
+    pub intent: String,
+    pub parameters: HashMap<String, String>,
+    pub confidence: f32,
+    pub action: String,
+}
+
+/// Query for `/describe`.
+#[derive(Debug, Deserialize)]
+pub struct DescribeQuery {
+    pub query: String,
+    #[serde(default)]
+    pub lang: Option<String>, // Language file to parse with; the loaded default when omitted
+}
+
+/// How a command was parsed and what it would do, returned by `/describe`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DescribeResponse {
+    pub normalized: String,
+    pub tokens: Vec<String>, // Stemmed words left after stop-word removal, to debug patterns that don't match
+    pub intent: String,
+    pub confidence: f32,
+    pub parameters: HashMap<String, String>,
+    pub resolved_action: String,
+    pub would_require: Vec<String>, // Windows and controls the action has to find
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blocked: Option<String>, // Why the action would be refused, e.g. a file operation under safe_mode
+}
+
+/// A pattern to try against a command, for `POST /lang/test`.
+#[derive(Debug, Deserialize)]
+pub struct LangTestRequest {
+    pub pattern: String,
+    pub text: String,
+    #[serde(default)]
+    pub which: Option<String>, // Intent the pattern is meant for; the response tells whether the parser lands on it
+    #[serde(default)]
+    pub lang: Option<String>, // Language whose stop words are used; the loaded default when omitted
+}
+
+/// What a tested pattern captured from the normalized command, returned by `POST /lang/test`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LangTestResponse {
+    pub normalized: String, // The text the pattern was run against, as the parser sees it
+    pub tokens: Vec<String>,
+    pub matched: bool,
+    pub captures: Vec<Option<String>>, // Group 0 is the whole match; groups that did not take part are null
+    pub named_captures: HashMap<String, String>,
+    pub parsed_intent: String, // What the loaded patterns parse the text as; an earlier pattern may win
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub which_matched: Option<bool>, // Whether `parsed_intent` is the requested `which`
+}
+
+/// A batch of commands for `POST /execute`.
+/// With `sequential` the commands share one task and stop on the first failure.
+#[derive(Debug, Deserialize)]
+pub struct ExecuteBatchRequest {
+    pub commands: Vec<String>,
+    #[serde(default)]
+    pub sequential: bool,
+}
+
+/// Returned instead of a task when a command was parsed below `min_confidence`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConfirmationResponse {
+    pub message: String,
+    pub token: Uuid,
+    pub intent: String,
+    pub confidence: f32,
+}
+
+/// Pagination parameters for the task history endpoint.
+#[derive(Debug, Deserialize)]
+pub struct TaskHistoryQuery {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+/// One page of persisted task transitions.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TaskHistoryResponse {
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub records: Vec<TaskInfo>,
+}
+
+/// Query parameters for the command history endpoint.
+#[derive(Debug, Deserialize)]
+pub struct CommandHistoryQuery {
+    pub limit: Option<usize>,
+}
+
+/// A command received by the server, as served by `GET /history`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommandHistoryEntry {
+    pub id: Uuid,
+    pub command: String,
+    pub received_secs_ago: f64,
+    pub throttled: bool, // Rejected by antiflood
+}
+
+/// One parameter of an intent, as listed by `/intents`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IntentParameter {
+    pub name: String,
+    pub kind: String, // "string", "integer", "boolean" or "list"
+    pub required: bool,
+}
+
+/// An intent, its parameters and the action it maps to, as listed by `/intents`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IntentDescription {
+    pub intent: String,
+    pub action: String,
+    pub parameters: Vec<IntentParameter>,
+    pub example: Option<String>, // From the default language file, if it has one
+}
+
+/// Response of `/undo`: the action that reverted the last reversible one.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UndoResponse {
+    pub undone_with: String,
+}
+
+/// Response of `/reload-language`: the language file the patterns were reloaded from.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReloadLanguageResponse {
+    pub path: String,
+}
+
+/// One dependency checked by `/ready`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReadinessCheck {
+    pub name: String,
+    pub healthy: bool,
+    pub detail: String,
+}
+
+/// Response of `/ready`: whether the server can take commands, and the check behind each answer.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReadinessResponse {
+    pub ready: bool,
+    pub checks: Vec<ReadinessCheck>,
+}
+
+/// Backends, features and languages available in this build.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CapabilitiesResponse {
+    pub backend: String,
+    pub platform: String,
+    pub features: Vec<String>,
+    pub endpoints: Vec<String>,
+    pub languages: Vec<String>,
+}
+
+/// One Server-Sent Event on `/tasks/{id}/events`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TaskEvent {
+    pub id: Uuid,
+    pub status: TaskStatus,
+}
+
+/// A command frame received on `/ws`.
+#[derive(Debug, Deserialize)]
+pub struct SocketCommand {
+    pub query: String,
+    #[serde(default)]
+    pub id: Option<String>, // Client's own reference, echoed on the events sent before a task exists
+    #[serde(default)]
+    pub cancel_on_disconnect: bool, // Cancel the task if the socket closes before it finishes
+}
+
+/// An event sent on `/ws`. The events after `scheduled` carry the task id they belong to.
+#[derive(Debug, Serialize, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SocketEvent {
+    Parsed {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        query: String,
+        intent: String,
+        parameters: HashMap<String, String>,
+        confidence: f32,
+    },
+    Rejected {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        error_code: ErrorCode,
+        message: String,
+    },
+    Scheduled {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        task: TaskInfo,
+    },
+    Status { task_id: Uuid, status: TaskStatus },
+    Result { task: TaskInfo },
+}
+
+/// Query for the window existence check.
+#[derive(Debug, Deserialize)]
+pub struct WindowExistsQuery {
+    pub label: String,
+    pub class: Option<String>,
+}
+
+/// Whether the requested window exists.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WindowExistsResponse {
+    pub label: String,
+    pub class: Option<String>,
+    pub exists: bool,
+}
+
+/// Query for the named-task running check.
+#[derive(Debug, Deserialize)]
+pub struct TaskRunningQuery {
+    pub name: String,
+}
+
+/// Whether any non-terminal task with the requested name exists.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TaskRunningResponse {
+    pub name: String,
+    pub running: bool,
+    pub task_ids: Vec<Uuid>,
 }
@@ -1,14 +1,20 @@
-use crate::intent_mapper::Action;
-use crate::debug_logger::{log_info, log_debug};
+use crate::intent_mapper::{
+    is_blocked_in_safe_mode, Action, CheckState, OpenKind, PasteMode, Rect, PREVIOUS_STEP_PLACEHOLDER, SAFE_MODE_MESSAGE,
+};
+use crate::debug_logger::{log_info, log_debug, log_warn};
 use std::ffi::{CString, CStr};
 use std::mem;
 use std::ptr;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use std::fs::File;
 use std::fs::{self, File};
-use std::io::{Write, BufWriter};
-use std::path::Path;
+use std::io::{Read, Write, BufWriter};
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, VecDeque};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use serde::{Deserialize, Serialize};
 
 #[macro_use]
 extern crate lazy_static;
@@ -17,63 +23,364 @@ use std::sync::Mutex;
 lazy_static! {
     // Global store for selected files.
     static ref SELECTED_FILES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    // How the selected files were last marked: `Move` after a cut, so the next paste removes the sources.
+    static ref PENDING_PASTE_MODE: Mutex<PasteMode> = Mutex::new(PasteMode::Copy);
+    // How long actions wait for their target window to appear, from `action_timeout_ms`.
+    static ref ACTION_TIMEOUT: Mutex<Duration> = Mutex::new(Duration::ZERO);
+    // Pause between typed characters, from `keystroke_delay_ms`.
+    static ref KEYSTROKE_DELAY: Mutex<Duration> = Mutex::new(Duration::ZERO);
+    // File the named window layouts are persisted to, from `layouts_path`.
+    static ref LAYOUTS_PATH: Mutex<PathBuf> = Mutex::new(PathBuf::from("layouts.json"));
+    // Directory file selection searches and may not leave, from `file_root`.
+    static ref FILE_ROOT: Mutex<PathBuf> = Mutex::new(PathBuf::from("."));
+    // Windows hidden by `WindowHide`, newest last, with the label they were hidden under.
+    static ref HIDDEN_WINDOWS: Mutex<Vec<(String, isize)>> = Mutex::new(Vec::new());
+    // Inverses of the most recent reversible actions, newest last, for `undo_last`.
+    static ref UNDO_STACK: Mutex<VecDeque<Action>> = Mutex::new(VecDeque::new());
+}
+
+/// How many undoable actions are remembered for `/undo`.
+const UNDO_LIMIT: usize = 50;
+
+// Whether input actions must land in the window that had focus when the command arrived, from `require_focus_lock`.
+static REQUIRE_FOCUS_LOCK: AtomicBool = AtomicBool::new(false);
+
+// Whether file and process operations are refused, from `safe_mode`.
+static SAFE_MODE: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    // Window that had focus when the current task's command arrived, while the focus lock is on.
+    static FOCUS_TARGET: Cell<Option<HWND>> = Cell::new(None);
 }
 
+// How long to wait for a re-activated window to take the foreground back.
+const FOCUS_RESTORE_MS: u64 = 100;
+
+// Smallest width or height a relative resize may shrink a window to, in pixels.
+const MIN_WINDOW_SIZE: i32 = 100;
+
+// Interval between window lookups while waiting for a window to appear.
+const WINDOW_POLL_INTERVAL_MS: u64 = 100;
+
 // Constants for the UpDown (spinner) control messages.
 const UDM_GETPOS: u32 = 0x0400 + 2;   // WM_USER + 2
 const UDM_SETPOS: u32 = 0x0400 + 3;   // WM_USER + 3
 
-use windows::Win32::Foundation::{HWND, LPARAM, WPARAM, HGLOBAL, HANDLE, CloseHandle};
+// Constants for the trackbar (slider) control messages.
+const TBM_GETRANGEMIN: u32 = 0x0400 + 1; // WM_USER + 1
+const TBM_GETRANGEMAX: u32 = 0x0400 + 2; // WM_USER + 2
+const TBM_SETPOS: u32 = 0x0400 + 5;      // WM_USER + 5
+
+// Virtual key codes used by keyboard navigation actions.
+const VK_TAB: u16 = 0x09;
+const VK_SHIFT: u16 = 0x10;
+const VK_RETURN: u16 = 0x0D;
+const VK_ESCAPE: u16 = 0x1B;
+const VK_SPACE: u16 = 0x20;
+const VK_CONTROL: u16 = 0x11;
+const VK_MENU: u16 = 0x12;
+const VK_LWIN: u16 = 0x5B;
+
+// Clipboard format for a packed device-independent bitmap.
+const CF_DIB: u32 = 8;
+
+// Typematic timing used while holding a key: injected key-downs do not auto-repeat,
+// so repeats are sent explicitly after the initial delay.
+const HOLD_REPEAT_DELAY_MS: u64 = 500;
+const HOLD_REPEAT_INTERVAL_MS: u64 = 33;
+
+// WM_SYSCOMMAND codes for the window system menu.
+const SC_SIZE: u32 = 0xF000;
+const SC_MOVE: u32 = 0xF010;
+const SC_MINIMIZE: u32 = 0xF020;
+const SC_MAXIMIZE: u32 = 0xF030;
+const SC_CLOSE: u32 = 0xF060;
+const SC_RESTORE: u32 = 0xF120;
+
+// Tab control messages and the item text flag used to read tab captions.
+const TCM_GETITEMCOUNT: u32 = 0x1300 + 4;  // TCM_FIRST + 4
+const TCM_GETITEMW: u32 = 0x1300 + 60;     // TCM_FIRST + 60
+const TCIF_TEXT: u32 = 0x0001;
+// Longest tab caption read, in UTF-16 units.
+const TAB_TEXT_MAX: usize = 256;
+
+// List view messages used to read row text.
+const LVM_GETITEMCOUNT: u32 = 0x1000 + 4;    // LVM_FIRST + 4
+const LVM_GETITEMTEXTW: u32 = 0x1000 + 115;  // LVM_FIRST + 115
+// Longest list row read, in UTF-16 units.
+const LIST_TEXT_MAX: usize = 512;
+
+// DPI at 100% scaling.
+const USER_DEFAULT_SCREEN_DPI: u32 = 96;
+
+// One notch of the mouse wheel.
+const WHEEL_DELTA: i32 = 120;
+
+// Border drag used by DragResize: pixels inside the window edge to grab,
+// and how many intermediate cursor moves make up the drag.
+const DRAG_EDGE_INSET: i32 = 3;
+const DRAG_STEPS: i32 = 10;
+const DRAG_STEP_DELAY_MS: u64 = 15;
+
+// PrintWindow flag asking DWM-composed windows to render their full content.
+const PW_RENDERFULLCONTENT: u32 = 0x0000_0002;
+
+use windows::Win32::Foundation::{BOOL, HWND, LPARAM, WPARAM, HGLOBAL, HANDLE, CloseHandle, RECT, POINT};
 use windows::Win32::UI::WindowsAndMessaging::{
     EnumWindows, EnumChildWindows, FindWindowA, GetForegroundWindow, GetWindowTextA, GetWindowTextLengthA,
     IsWindowVisible, SendMessageA, ShowWindow, SW_MAXIMIZE, SW_MINIMIZE, SW_SHOWNORMAL, WM_CLOSE,
-    WM_VSCROLL, SB_LINEUP, SB_LINEDOWN,
+    WM_VSCROLL, SB_LINEUP, SB_LINEDOWN, SB_PAGEUP, SB_PAGEDOWN, SB_TOP, SB_BOTTOM, GetShellWindow, GetDesktopWindow,
+    SW_HIDE, SW_SHOW, SetWindowPos, HWND_BOTTOM, HWND_TOP, HWND_TOPMOST, HWND_NOTOPMOST, SWP_NOMOVE, SWP_NOSIZE, SWP_NOACTIVATE, SWP_NOZORDER,
+    SendMessageW, EM_GETSEL, WM_GETTEXT, WM_GETTEXTLENGTH, GetGUIThreadInfo, GUITHREADINFO,
+    GetClassNameA, GetWindowRect, GetClientRect,
+    IsIconic, SW_RESTORE, SetForegroundWindow, BringWindowToTop, PostMessageA, WM_SYSCOMMAND,
+    IsWindow, GetWindowTextW, GetWindowTextLengthW, GetMenu, GetSubMenu, GetMenuItemCount, GetMenuItemID, GetMenuStringW, HMENU, MF_BYPOSITION,
+    WM_COMMAND, SystemParametersInfoW, SPI_GETWORKAREA, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS, IsZoomed,
+    BM_GETCHECK, BM_SETCHECK, BST_CHECKED, BST_INDETERMINATE, BST_UNCHECKED,
+    GetWindowLongA, GWL_EXSTYLE, WS_EX_TOPMOST, GetParent,
 };
 use windows::Win32::UI::Shell::ShellExecuteA;
+use windows::Win32::UI::HiDpi::GetDpiForWindow;
 use windows::Win32::System::Clipboard::{
     OpenClipboard, EmptyClipboard, SetClipboardData, CloseClipboard, CF_UNICODETEXT,
+    GetClipboardData, IsClipboardFormatAvailable,
 };
-use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
-use windows::Win32::System::Threading::{GetWindowThreadProcessId, OpenProcess, TerminateProcess, PROCESS_TERMINATE};
+use windows::Win32::System::Memory::{
+    GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GlobalSize, GMEM_MOVEABLE,
+    VirtualAllocEx, VirtualFreeEx, MEM_COMMIT, MEM_RESERVE, MEM_RELEASE, PAGE_READWRITE,
+};
+use windows::Win32::System::Threading::{
+    GetWindowThreadProcessId, OpenProcess, TerminateProcess, PROCESS_TERMINATE, GetCurrentThreadId, AttachThreadInput,
+    PROCESS_VM_OPERATION, PROCESS_VM_READ, PROCESS_VM_WRITE, PROCESS_QUERY_LIMITED_INFORMATION,
+    QueryFullProcessImageNameW, PROCESS_NAME_WIN32, GetCurrentProcess, IsWow64Process,
+};
+use windows::core::PWSTR;
+use windows::Win32::System::Diagnostics::Debug::{ReadProcessMemory, WriteProcessMemory};
 use windows::Win32::Graphics::Gdi::{
     GetDC, CreateCompatibleDC, CreateCompatibleBitmap, SelectObject, BitBlt, DeleteDC, DeleteObject,
-    SRCCOPY, GetDeviceCaps, HORZRES, VERTRES, BITMAP, GetObjectA,
+    SRCCOPY, GetDeviceCaps, HORZRES, VERTRES, BITMAP, GetObjectA, ReleaseDC, ClientToScreen, GetPixel, CLR_INVALID,
 };
+use windows::Win32::Storage::Xps::{PrintWindow, PRINT_WINDOW_FLAGS, PW_CLIENTONLY};
 
 use windows::Win32::UI::Input::KeyboardAndMouse::{
-    SendInput, INPUT, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP, KEYEVENTF_EXTENDEDKEY,
+    SendInput, INPUT, INPUT_KEYBOARD, KEYBDINPUT, KEYEVENTF_KEYUP, KEYEVENTF_EXTENDEDKEY, KEYEVENTF_UNICODE,
+    INPUT_MOUSE, MOUSEINPUT, MOUSEEVENTF_LEFTDOWN, MOUSEEVENTF_LEFTUP, MOUSEEVENTF_WHEEL,
+    GetDoubleClickTime,
 };
+use windows::Win32::UI::WindowsAndMessaging::SetCursorPos;
 
 /// Представляет результат выполнения действия.
 #[derive(Debug)]
 pub enum ExecutionResult {
     Success(String),
+    Read(String, serde_json::Value), // Действие-запрос: сообщение и прочитанные значения, например `{"checked": true}`
     Failure(String),
 }
 
+/// Результат действия вместе со временем его выполнения.
+#[derive(Debug)]
+pub struct TimedExecution {
+    pub result: ExecutionResult,
+    pub duration_ms: u64,
+}
+
+/// Выполняет действие, как [`execute_recording_undo`], и замеряет, сколько оно заняло.
+pub fn execute_action_timed(action: &Action) -> TimedExecution {
+    let started = Instant::now();
+    let result = execute_recording_undo(action);
+    TimedExecution { result, duration_ms: started.elapsed().as_millis() as u64 }
+}
+
+/// Выполняет действие, как [`execute_action`], и, если оно удалось и его можно отменить,
+/// запоминает обратное действие для [`undo_last`].
+pub fn execute_recording_undo(action: &Action) -> ExecutionResult {
+    let inverse = unsafe { capture_inverse(action) };
+    let result = execute_action(action);
+    if let Some(inverse) = inverse {
+        if !matches!(result, ExecutionResult::Failure(_)) {
+            push_undo(&mut UNDO_STACK.lock().unwrap(), inverse, UNDO_LIMIT);
+        }
+    }
+    result
+}
+
+/// Снимает со стека последнее обратное действие и выполняет его; `None`, если отменять нечего.
+/// Само обратное действие не запоминается, поэтому повторные вызовы уходят всё дальше назад.
+pub fn undo_last() -> Option<(Action, ExecutionResult)> {
+    let inverse = UNDO_STACK.lock().unwrap().pop_back()?;
+    log_info(&format!("Отмена действием {:?}", inverse));
+    let result = execute_action(&inverse);
+    Some((inverse, result))
+}
+
+/// Строит обратное действие по состоянию интерфейса до выполнения `action`.
+/// Возвращает `None` для необратимых действий и для тех, что ничего не изменят.
+unsafe fn capture_inverse(action: &Action) -> Option<Action> {
+    let target = |class: &str, label: &str, hwnd: &Option<isize>| match hwnd {
+        Some(handle) => window_from_handle(*handle),
+        None => Some(find_window(class, label)).filter(|hwnd| hwnd.0 != 0),
+    };
+    match action {
+        Action::CheckboxSetState { label, state } => {
+            let current = checkbox_state(target("Button", label, &None)?);
+            // Setting a box to the state it already has leaves nothing to undo.
+            (current != *state).then(|| Action::CheckboxSetState { label: label.clone(), state: current })
+        }
+        Action::EditEnterText { label, hwnd, .. } => {
+            let text = control_text(target("Edit", label, hwnd)?);
+            Some(Action::EditEnterText { label: label.clone(), text, hwnd: *hwnd })
+        }
+        Action::SetText { label, hwnd, .. } => {
+            let text = control_text(target("Static", label, hwnd)?);
+            Some(Action::SetText { label: label.clone(), text, hwnd: *hwnd })
+        }
+        Action::WindowSetTopmost { label, topmost } => {
+            let hwnd = if label.is_empty() { Some(GetForegroundWindow()).filter(|hwnd| hwnd.0 != 0)? } else { target("", label, &None)? };
+            let was_topmost = (GetWindowLongA(hwnd, GWL_EXSTYLE) as u32 & WS_EX_TOPMOST.0) != 0;
+            // Pinning a window that is already pinned leaves nothing to undo.
+            (was_topmost != *topmost).then(|| Action::WindowSetTopmost { label: label.clone(), topmost: was_topmost })
+        }
+        _ => action.inverse(),
+    }
+}
+
+/// Pushes `inverse` onto the undo stack, dropping the oldest entries beyond `limit`.
+fn push_undo(stack: &mut VecDeque<Action>, inverse: Action, limit: usize) {
+    stack.push_back(inverse);
+    while stack.len() > limit {
+        stack.pop_front();
+    }
+}
+
+/// Выполняет шаги `MultiStep` по порядку; каждый обратимый шаг запоминается для отмены отдельно.
+/// Без `continue_on_error` останавливается на первой ошибке, иначе выполняет все шаги и возвращает сводку.
+/// Прочитанное шагом значение доступно следующим шагам как `${prev}`.
+fn execute_steps(steps: &[Action], continue_on_error: bool) -> ExecutionResult {
+    log_info(&format!("Выполнение {} шагов (продолжать при ошибке: {})", steps.len(), continue_on_error));
+    let total = steps.len();
+    let mut context = HashMap::new();
+    if !continue_on_error {
+        // Значения определяет последний шаг, который что-то прочитал.
+        let mut details = None;
+        for (index, step) in steps.iter().enumerate() {
+            match execute_step_in_context(step, &mut context) {
+                ExecutionResult::Success(_) => {}
+                ExecutionResult::Read(_, read) => details = Some(read),
+                ExecutionResult::Failure(e) => {
+                    return ExecutionResult::Failure(format!("step {} of {} failed: {}", index + 1, total, e));
+                }
+            }
+        }
+        let message = format!("Выполнено шагов: {}", total);
+        return match details {
+            Some(details) => ExecutionResult::Read(message, details),
+            None => ExecutionResult::Success(message),
+        };
+    }
+    let results: Vec<serde_json::Value> = steps
+        .iter()
+        .enumerate()
+        .map(|(index, step)| match execute_step_in_context(step, &mut context) {
+            ExecutionResult::Success(message) => serde_json::json!({ "step": index + 1, "ok": true, "message": message }),
+            ExecutionResult::Read(message, details) => {
+                serde_json::json!({ "step": index + 1, "ok": true, "message": message, "details": details })
+            }
+            ExecutionResult::Failure(e) => {
+                log_warn(&format!("step {} of {} failed: {}", index + 1, total, e));
+                serde_json::json!({ "step": index + 1, "ok": false, "error": e })
+            }
+        })
+        .collect();
+    let failed = results.iter().filter(|result| result["ok"] == false).count();
+    ExecutionResult::Read(
+        format!("Выполнено шагов: {}, с ошибкой: {}", total - failed, failed),
+        serde_json::json!({ "steps": results, "failed": failed }),
+    )
+}
+
+/// Выполняет шаг с подставленными значениями из `context` и запоминает прочитанный им текст
+/// или состояние флажка как `${prev}`.
+fn execute_step_in_context(step: &Action, context: &mut HashMap<String, String>) -> ExecutionResult {
+    let step = resolve_context(step, context);
+    let result = execute_recording_undo(&step);
+    if let ExecutionResult::Read(_, details) = &result {
+        let output = match &step {
+            Action::StaticGetText { .. } => details["text"].as_str().map(str::to_string),
+            Action::GetCheckboxState { .. } => details["checked"].as_bool().map(|checked| checked.to_string()),
+            _ => None,
+        };
+        if let Some(output) = output {
+            context.insert(PREVIOUS_STEP_PLACEHOLDER.to_string(), output);
+        }
+    }
+    result
+}
+
+/// Подставляет `${name}` из `context` в текст, который вводит шаг.
+/// Имена, которых нет в `context`, остаются как есть.
+fn resolve_context(step: &Action, context: &HashMap<String, String>) -> Action {
+    let fill = |text: &str| {
+        context.iter().fold(text.to_string(), |text, (name, value)| text.replace(&format!("${{{}}}", name), value))
+    };
+    match step {
+        Action::EditEnterText { label, text, hwnd } => Action::EditEnterText { label: label.clone(), text: fill(text), hwnd: *hwnd },
+        Action::SetText { label, text, hwnd } => Action::SetText { label: label.clone(), text: fill(text), hwnd: *hwnd },
+        Action::EditPasteText { label, text } => Action::EditPasteText { label: label.clone(), text: text.as_deref().map(fill) },
+        Action::TypeText { text, delay_ms } => Action::TypeText { text: fill(text), delay_ms: *delay_ms },
+        _ => step.clone(),
+    }
+}
+
 /// Выполняет переданное действие с использованием Win32 API.
 pub fn execute_action(action: &Action) -> ExecutionResult {
+    if SAFE_MODE.load(Ordering::Relaxed) && is_blocked_in_safe_mode(action) {
+        log_warn(&format!("Действие {:?} запрещено в безопасном режиме", action));
+        return ExecutionResult::Failure(SAFE_MODE_MESSAGE.to_string());
+    }
     unsafe {
         match action {
-            Action::ButtonClick { label } => {
+            Action::ButtonClick { label, hwnd } => {
                 log_info(&format!("Нажатие кнопки '{}'", label));
-                let hwnd = find_window("Button", label);
-                if hwnd.0 == 0 {
-                    return ExecutionResult::Failure(format!("Кнопка '{}' не найдена", label));
-                }
+                let hwnd = match hwnd {
+                    Some(handle) => match window_from_handle(*handle) {
+                        Some(hwnd) => hwnd,
+                        None => return stale_handle_failure(*handle),
+                    },
+                    None => match wait_for_window("Button", label, action_timeout()) {
+                        Some(hwnd) => hwnd,
+                        None => return window_wait_failure(label, format!("Кнопка '{}' не найдена", label)),
+                    },
+                };
                 SendMessageA(hwnd, BM_CLICK, WPARAM(0), LPARAM(0));
                 ExecutionResult::Success(format!("Нажата кнопка '{}'", label))
             }
-            Action::ButtonDoubleClick { label } => {
-                log_info(&format!("Двойной клик по кнопке '{}'", label));
-                let hwnd = find_window("Button", label);
+            Action::ButtonDoubleClick { label, use_mouse, hwnd } => {
+                log_info(&format!("Двойной клик по элементу '{}'", label));
+                let hwnd = match hwnd {
+                    Some(handle) => match window_from_handle(*handle) {
+                        Some(hwnd) => hwnd,
+                        None => return stale_handle_failure(*handle),
+                    },
+                    None => find_window("", label),
+                };
                 if hwnd.0 == 0 {
-                    return ExecutionResult::Failure(format!("Кнопка '{}' не найдена", label));
+                    return ExecutionResult::Failure(format!("Элемент '{}' не найден", label));
+                }
+                match double_click_method(&window_class_name(hwnd), *use_mouse) {
+                    DoubleClickMethod::ButtonMessage => {
+                        SendMessageA(hwnd, BM_CLICK, WPARAM(0), LPARAM(0));
+                        thread::sleep(Duration::from_millis(100));
+                        SendMessageA(hwnd, BM_CLICK, WPARAM(0), LPARAM(0));
+                        ExecutionResult::Success(format!("Двойной клик по кнопке '{}'", label))
+                    }
+                    DoubleClickMethod::MouseInput => {
+                        if mouse_double_click_center(hwnd) {
+                            ExecutionResult::Success(format!("Двойной клик мышью по элементу '{}'", label))
+                        } else {
+                            ExecutionResult::Failure(format!("Не удалось выполнить двойной клик по элементу '{}'", label))
+                        }
+                    }
                 }
-                SendMessageA(hwnd, BM_CLICK, WPARAM(0), LPARAM(0));
-                thread::sleep(Duration::from_millis(100));
-                SendMessageA(hwnd, BM_CLICK, WPARAM(0), LPARAM(0));
-                ExecutionResult::Success(format!("Двойной клик по кнопке '{}'", label))
             }
             Action::GroupWindows => {
                 log_info("Grouping all visible windows in a grid layout");
@@ -83,9 +390,15 @@ pub fn execute_action(action: &Action) -> ExecutionResult {
                     ExecutionResult::Failure("Failed to group windows".to_string())
                 }
             }
-            Action::EditEnterText { label, text } => {
+            Action::EditEnterText { label, text, hwnd } => {
                 log_info(&format!("Ввод текста '{}' в поле '{}'", text, label));
-                let hwnd = find_window("Edit", label);
+                let hwnd = match hwnd {
+                    Some(handle) => match window_from_handle(*handle) {
+                        Some(hwnd) => hwnd,
+                        None => return stale_handle_failure(*handle),
+                    },
+                    None => find_window("Edit", label),
+                };
                 if hwnd.0 == 0 {
                     return ExecutionResult::Failure(format!("Поле '{}' не найдено", label));
                 }
@@ -194,11 +507,17 @@ pub fn execute_action(action: &Action) -> ExecutionResult {
                 let text = String::from_utf8_lossy(&buffer)
                     .trim_end_matches('\0')
                     .to_string();
-                ExecutionResult::Success(format!("Текст в '{}': {}", label, text))
+                ExecutionResult::Read(format!("Текст в '{}': {}", label, text), serde_json::json!({ "text": text }))
             }
-            Action::SetText { label, text } => {
+            Action::SetText { label, text, hwnd } => {
                 log_info(&format!("Установка текста '{}' в статическом поле '{}'", text, label));
-                let hwnd = find_window("Static", label);
+                let hwnd = match hwnd {
+                    Some(handle) => match window_from_handle(*handle) {
+                        Some(hwnd) => hwnd,
+                        None => return stale_handle_failure(*handle),
+                    },
+                    None => find_window("Static", label),
+                };
                 if hwnd.0 == 0 {
                     return ExecutionResult::Failure(format!("Статическое поле '{}' не найдено", label));
                 }
@@ -209,6 +528,192 @@ pub fn execute_action(action: &Action) -> ExecutionResult {
                     ExecutionResult::Failure(format!("Не удалось установить текст в '{}'", label))
                 }
             }
+            Action::WindowActivate { label, process } => {
+                let name = window_name(label, process.as_deref());
+                log_info(&format!("Активация окна '{}'", name));
+                let found = poll_until(action_timeout(), || {
+                    let hwnd = find_process_window(label, process.as_deref());
+                    if hwnd.0 != 0 { Some(hwnd) } else { None }
+                });
+                let hwnd = match found {
+                    Some(hwnd) => hwnd,
+                    None => return window_wait_failure(&name, format!("Окно '{}' не найдено", name)),
+                };
+                if activate_window(hwnd) {
+                    ExecutionResult::Success(format!("Окно '{}' активировано", name))
+                } else {
+                    ExecutionResult::Failure(format!("Не удалось вывести окно '{}' на передний план", name))
+                }
+            }
+            Action::WindowSetTopmost { label, topmost } => {
+                log_info(&format!("{} окна '{}' поверх остальных", if *topmost { "Закрепление" } else { "Открепление" }, label));
+                let hwnd = if label.is_empty() { GetForegroundWindow() } else { find_window("", label) };
+                if hwnd.0 == 0 {
+                    return ExecutionResult::Failure(format!("Окно '{}' не найдено", label));
+                }
+                let insert_after = if *topmost { HWND_TOPMOST } else { HWND_NOTOPMOST };
+                if !SetWindowPos(hwnd, insert_after, 0, 0, 0, 0, SWP_NOMOVE | SWP_NOSIZE).as_bool() {
+                    return ExecutionResult::Failure(format!("Не удалось изменить закрепление окна '{}'", label));
+                }
+                if *topmost {
+                    ExecutionResult::Success(format!("Окно '{}' закреплено поверх всех окон", label))
+                } else {
+                    ExecutionResult::Success(format!("Окно '{}' откреплено", label))
+                }
+            }
+            Action::WindowHide { label } => {
+                log_info(&format!("Скрытие окна '{}'", label));
+                let hwnd = if label.is_empty() { GetForegroundWindow() } else { find_window("", label) };
+                if hwnd.0 == 0 {
+                    return ExecutionResult::Failure(format!("Окно '{}' не найдено", label));
+                }
+                let title = if label.is_empty() { window_title_w(hwnd) } else { label.clone() };
+                log_warn(&format!(
+                    "Окно '{}' скрыто; если у программы нет значка в трее, вернуть его можно только командой показа окна", title));
+                ShowWindow(hwnd, SW_HIDE);
+                HIDDEN_WINDOWS.lock().unwrap().push((title.clone(), hwnd.0));
+                ExecutionResult::Success(format!("Окно '{}' скрыто", title))
+            }
+            Action::WindowShow { label } => {
+                log_info(&format!("Показ скрытого окна '{}'", label));
+                let hwnd = match take_hidden_window(label) {
+                    Some(hwnd) => hwnd,
+                    None if !label.is_empty() => find_window("", label),
+                    None => return ExecutionResult::Failure("Нет скрытых окон".to_string()),
+                };
+                if hwnd.0 == 0 {
+                    return ExecutionResult::Failure(format!("Окно '{}' не найдено", label));
+                }
+                ShowWindow(hwnd, SW_SHOW);
+                ExecutionResult::Success(format!("Окно '{}' снова показано", window_title_w(hwnd)))
+            }
+            Action::SendMessage { label, msg, wparam, lparam } => {
+                log_warn(&format!(
+                    "Отправка сообщения {:#x} элементу '{}' (wParam={:#x}, lParam={:#x})", msg, label, wparam, lparam));
+                let hwnd = find_window("", label);
+                if hwnd.0 == 0 {
+                    return ExecutionResult::Failure(format!("Окно или элемент '{}' не найден", label));
+                }
+                let result = SendMessageW(hwnd, *msg, WPARAM(*wparam), LPARAM(*lparam)).0;
+                ExecutionResult::Success(format!("Сообщение {:#x} отправлено '{}', результат: {}", msg, label, result))
+            }
+            Action::SaveLayout { name } => {
+                log_info(&format!("Сохранение раскладки окон '{}'", name));
+                let placements = capture_layout();
+                if placements.is_empty() {
+                    return ExecutionResult::Failure("Нет видимых окон для сохранения".to_string());
+                }
+                let mut layouts = match load_layouts() {
+                    Ok(layouts) => layouts,
+                    Err(e) => return ExecutionResult::Failure(format!(
+                        "Файл раскладок не прочитан, раскладка '{}' не сохранена, чтобы не затереть его: {}", name, e)),
+                };
+                let count = placements.len();
+                layouts.insert(name.clone(), placements);
+                match save_layouts(&layouts) {
+                    Ok(()) => ExecutionResult::Success(format!("Раскладка '{}' сохранена: {} окон", name, count)),
+                    Err(e) => ExecutionResult::Failure(format!("Не удалось сохранить раскладку '{}': {}", name, e)),
+                }
+            }
+            Action::RestoreLayout { name } => {
+                log_info(&format!("Восстановление раскладки окон '{}'", name));
+                let layouts = match load_layouts() {
+                    Ok(layouts) => layouts,
+                    Err(e) => return ExecutionResult::Failure(format!("Не удалось прочитать раскладки: {}", e)),
+                };
+                let placements = match layouts.get(name) {
+                    Some(placements) => placements,
+                    None => return ExecutionResult::Failure(format!("Раскладка '{}' не найдена", name)),
+                };
+                let (restored, missing) = restore_layout(placements);
+                if restored == 0 {
+                    ExecutionResult::Failure(format!("Ни одно окно раскладки '{}' не найдено", name))
+                } else if missing.is_empty() {
+                    ExecutionResult::Success(format!("Раскладка '{}' восстановлена: {} окон", name, restored))
+                } else {
+                    ExecutionResult::Success(format!(
+                        "Раскладка '{}' восстановлена: {} окон, не найдены: {}", name, restored, missing.join(", ")))
+                }
+            }
+            Action::Drag { from_label, to_label } => {
+                log_info(&format!("Перетаскивание из '{}' в '{}'", from_label, to_label));
+                // Both ends are resolved before any input is sent, so a missing control
+                // never leaves the mouse button held down.
+                let from = find_window("", from_label);
+                if from.0 == 0 {
+                    return ExecutionResult::Failure(format!("Элемент-источник '{}' не найден", from_label));
+                }
+                let to = find_window("", to_label);
+                if to.0 == 0 {
+                    return ExecutionResult::Failure(format!("Элемент-приёмник '{}' не найден", to_label));
+                }
+                let (from_point, to_point) = match (client_center_on_screen(from), client_center_on_screen(to)) {
+                    (Some(from_point), Some(to_point)) => (from_point, to_point),
+                    _ => return ExecutionResult::Failure("Не удалось определить координаты элементов".to_string()),
+                };
+                if mouse_drag(from_point, to_point) {
+                    ExecutionResult::Success(format!("'{}' перетащен в '{}'", from_label, to_label))
+                } else {
+                    ExecutionResult::Failure(format!("Не удалось перетащить '{}' в '{}'", from_label, to_label))
+                }
+            }
+            Action::SystemMenu { label, command } => {
+                log_info(&format!("Команда системного меню '{}' для окна '{}'", command, label));
+                let code = match system_command_code(command) {
+                    Some(code) => code,
+                    None => return ExecutionResult::Failure(format!(
+                        "Неизвестная команда системного меню '{}'. Используйте restore, minimize, maximize, close, move или size", command)),
+                };
+                let hwnd = find_window("", label);
+                if hwnd.0 == 0 {
+                    return ExecutionResult::Failure(format!("Окно '{}' не найдено", label));
+                }
+                // WM_SYSCOMMAND is posted so SC_MOVE/SC_SIZE, which start a modal loop, do not block the worker.
+                if PostMessageA(hwnd, WM_SYSCOMMAND, WPARAM(code as usize), LPARAM(0)).as_bool() {
+                    ExecutionResult::Success(format!("Команда '{}' отправлена окну '{}'", command, label))
+                } else {
+                    ExecutionResult::Failure(format!("Не удалось отправить команду '{}' окну '{}'", command, label))
+                }
+            }
+            Action::MenuSelect { window, menu_path } => {
+                log_info(&format!("Выбор пункта меню '{}' в окне '{}'", menu_path, window));
+                let hwnd = if window.is_empty() { GetForegroundWindow() } else { find_window("", window) };
+                if hwnd.0 == 0 {
+                    return ExecutionResult::Failure(format!("Окно '{}' не найдено", window));
+                }
+                let menu = GetMenu(hwnd);
+                if menu.0 == 0 {
+                    return ExecutionResult::Failure(format!("У окна '{}' нет меню", window));
+                }
+                let path: Vec<&str> = menu_path.split('>').map(str::trim).filter(|s| !s.is_empty()).collect();
+                match find_menu_command(menu, &path) {
+                    // Posted like a real menu click, so a dialog opened by the command does not block the worker.
+                    Some(id) => if PostMessageA(hwnd, WM_COMMAND, WPARAM(id as usize), LPARAM(0)).as_bool() {
+                        ExecutionResult::Success(format!("Пункт меню '{}' выбран", menu_path))
+                    } else {
+                        ExecutionResult::Failure(format!("Не удалось выбрать пункт меню '{}'", menu_path))
+                    },
+                    None => ExecutionResult::Failure(format!(
+                        "Пункт меню '{}' не найден. Доступные меню: {}", menu_path, menu_item_names(menu).join(", "))),
+                }
+            }
+            Action::WaitForAnyWindow { labels, timeout_ms } => {
+                log_info(&format!("Ожидание одного из окон {:?} до {} мс", labels, timeout_ms));
+                if labels.is_empty() {
+                    return ExecutionResult::Failure("Не указано ни одного окна для ожидания".to_string());
+                }
+                match wait_for_any_window(labels, Duration::from_millis(*timeout_ms)) {
+                    Some(label) => ExecutionResult::Success(format!("Появилось окно '{}'", label)),
+                    None => ExecutionResult::Failure(format!("timed out waiting for '{}'", labels.join("' | '"))),
+                }
+            }
+            Action::WaitForTitle { title, timeout_ms } => {
+                log_info(&format!("Ожидание заголовка '{}' до {} мс", title, timeout_ms));
+                match wait_for_title(title, Duration::from_millis(*timeout_ms)) {
+                    Some(current) => ExecutionResult::Success(format!("Заголовок активного окна: '{}'", current)),
+                    None => ExecutionResult::Failure(format!("timed out waiting for title '{}'", title)),
+                }
+            }
             Action::SetFocus { label } => {
                 log_info(&format!("Установка фокуса на '{}'", label));
                 let hwnd = find_window("", label);
@@ -222,17 +727,46 @@ pub fn execute_action(action: &Action) -> ExecutionResult {
                 }
             }
             Action::CheckboxSetState { label, state } => {
-                log_info(&format!("Установка состояния чекбокса '{}' в {}", label, state));
+                log_info(&format!("Установка состояния чекбокса '{}' в {}", label, state.as_str()));
                 let hwnd = find_window("Button", label);
                 if hwnd.0 == 0 {
                     return ExecutionResult::Failure(format!("Чекбокс '{}' не найден", label));
                 }
                 let current_state = SendMessageA(hwnd, BM_GETCHECK, WPARAM(0), LPARAM(0)).0;
-                let desired_state = if *state { BST_CHECKED } else { BST_UNCHECKED };
+                let desired_state = match state {
+                    CheckState::Checked => BST_CHECKED,
+                    CheckState::Unchecked => BST_UNCHECKED,
+                    CheckState::Indeterminate => BST_INDETERMINATE,
+                };
                 if current_state != desired_state as i32 {
                     SendMessageA(hwnd, BM_SETCHECK, WPARAM(desired_state as usize), LPARAM(0));
                 }
-                ExecutionResult::Success(format!("Чекбокс '{}' установлен в {}", label, state))
+                ExecutionResult::Success(format!("Чекбокс '{}' установлен в {}", label, state.as_str()))
+            }
+            Action::SetSlider { label, percent } => {
+                log_info(&format!("Установка слайдера '{}' на {}%", label, percent));
+                let hwnd = find_window("msctls_trackbar32", label);
+                if hwnd.0 == 0 {
+                    return ExecutionResult::Failure(format!("Слайдер '{}' не найден", label));
+                }
+                let min = SendMessageA(hwnd, TBM_GETRANGEMIN, WPARAM(0), LPARAM(0)).0 as i32;
+                let max = SendMessageA(hwnd, TBM_GETRANGEMAX, WPARAM(0), LPARAM(0)).0 as i32;
+                let position = slider_position(min, max, *percent);
+                // wParam TRUE redraws the thumb at the new position.
+                SendMessageA(hwnd, TBM_SETPOS, WPARAM(1), LPARAM(position as isize));
+                ExecutionResult::Success(format!("Слайдер '{}' установлен в позицию {}", label, position))
+            }
+            Action::GetCheckboxState { label } => {
+                log_info(&format!("Чтение состояния чекбокса '{}'", label));
+                let hwnd = find_window("Button", label);
+                if hwnd.0 == 0 {
+                    return ExecutionResult::Failure(format!("Чекбокс '{}' не найден", label));
+                }
+                let state = checkbox_state(hwnd);
+                ExecutionResult::Read(
+                    format!("Чекбокс '{}': state={}", label, state.as_str()),
+                    serde_json::json!({ "checked": state == CheckState::Checked, "state": state.as_str() }),
+                )
             }
             Action::RadioSelect { label, variant } => {
                 log_info(&format!("Выбор радиокнопки '{}' с вариантом {:?}", label, variant));
@@ -293,18 +827,71 @@ pub fn execute_action(action: &Action) -> ExecutionResult {
                     ExecutionResult::Failure("Выбор по имени не поддерживается; используйте числовой индекс.".to_string())
                 }
             }
-            Action::TabControlSelectTab { label, tab } => {
-                log_info(&format!("Выбор вкладки '{}' в элементе '{}'", tab, label));
+            Action::ListViewGetItems { label } => {
+                log_info(&format!("Чтение строк списка '{}'", label));
+                let hwnd = find_window("SysListView32", label);
+                if hwnd.0 == 0 {
+                    return ExecutionResult::Failure(format!("Список '{}' не найден", label));
+                }
+                match listview_items(hwnd) {
+                    Ok(items) => ExecutionResult::Success(format!("Список '{}': items={:?}", label, items)),
+                    Err(e) => ExecutionResult::Failure(format!("Не удалось прочитать строки списка '{}': {}", label, e)),
+                }
+            }
+            Action::GetSelectedText { label } => {
+                log_info(&format!("Чтение выделенного текста из '{}'", label.as_deref().unwrap_or("фокус")));
+                let hwnd = match label {
+                    Some(lbl) => find_window("Edit", lbl),
+                    None => focused_control(),
+                };
+                if hwnd.0 == 0 {
+                    return ExecutionResult::Failure("Text field not found".to_string());
+                }
+                let mut start: u32 = 0;
+                let mut end: u32 = 0;
+                SendMessageW(hwnd, EM_GETSEL, WPARAM(&mut start as *mut u32 as usize), LPARAM(&mut end as *mut u32 as isize));
+                let len = SendMessageW(hwnd, WM_GETTEXTLENGTH, WPARAM(0), LPARAM(0)).0 as usize;
+                let mut buffer: Vec<u16> = vec![0; len + 1];
+                let copied = SendMessageW(hwnd, WM_GETTEXT, WPARAM(len + 1), LPARAM(buffer.as_mut_ptr() as isize)).0 as usize;
+                let selected = slice_selection(&buffer[..copied.min(len)], start as usize, end as usize);
+                let details = serde_json::json!({ "text": selected });
+                ExecutionResult::Read(selected, details)
+            }
+            Action::GetSelectedTab { label } => {
+                log_info(&format!("Чтение выбранной вкладки в элементе '{}'", label));
                 let hwnd = find_window("SysTabControl32", label);
                 if hwnd.0 == 0 {
                     return ExecutionResult::Failure(format!("Элемент управления вкладками '{}' не найден", label));
                 }
-                if let Ok(index) = tab.parse::<u32>() {
-                    SendMessageA(hwnd, TCM_SETCURSEL, WPARAM(index as usize), LPARAM(0));
-                    ExecutionResult::Success(format!("Вкладка {} выбрана в контроле '{}'", index, label))
+                let index = SendMessageA(hwnd, TCM_GETCURSEL, WPARAM(0), LPARAM(0)).0;
+                if index < 0 {
+                    ExecutionResult::Failure(format!("В элементе '{}' нет выбранной вкладки", label))
                 } else {
-                    ExecutionResult::Failure("Выбор по имени не поддерживается; используйте числовой индекс.".to_string())
+                    ExecutionResult::Read(format!("Элемент '{}': tab={}", label, index), serde_json::json!({ "tab": index }))
+                }
+            }
+            Action::TabControlSelectTab { label, tab } => {
+                log_info(&format!("Выбор вкладки '{}' в элементе '{}'", tab, label));
+                let hwnd = find_window("SysTabControl32", label);
+                if hwnd.0 == 0 {
+                    return ExecutionResult::Failure(format!("Элемент управления вкладками '{}' не найден", label));
                 }
+                let index = match tab.parse::<usize>() {
+                    Ok(index) => index,
+                    Err(_) => {
+                        let captions = match tab_captions(hwnd) {
+                            Ok(captions) => captions,
+                            Err(e) => return ExecutionResult::Failure(format!("Не удалось прочитать вкладки элемента '{}': {}", label, e)),
+                        };
+                        match resolve_tab_index(&captions, tab) {
+                            Some(index) => index,
+                            None => return ExecutionResult::Failure(format!(
+                                "Вкладка '{}' не найдена в элементе '{}'. Доступные вкладки: {}", tab, label, captions.join(", "))),
+                        }
+                    }
+                };
+                SendMessageA(hwnd, TCM_SETCURSEL, WPARAM(index), LPARAM(0));
+                ExecutionResult::Success(format!("Вкладка '{}' ({}) выбрана в контроле '{}'", tab, index, label))
             }
             Action::WindowResize { width, height } => {
                 log_info(&format!("Изменение размера активного окна до {}x{}", width, height));
@@ -318,28 +905,51 @@ pub fn execute_action(action: &Action) -> ExecutionResult {
                     ExecutionResult::Failure("Не удалось изменить размер окна".to_string())
                 }
             }
-            Action::WindowMinimize { label } => {
-                log_info(&format!("Свернуть окно '{}'", label));
-                let hwnd = find_window("", label);
+            Action::WindowResizeRelative { dw, dh } => {
+                log_info(&format!("Изменение размера активного окна на {:+}x{:+}", dw, dh));
+                let hwnd = GetForegroundWindow();
                 if hwnd.0 == 0 {
-                    return ExecutionResult::Failure(format!("Окно '{}' не найдено", label));
+                    return ExecutionResult::Failure("Активное окно не найдено".to_string());
+                }
+                let mut rect = RECT::default();
+                if !GetWindowRect(hwnd, &mut rect).as_bool() {
+                    return ExecutionResult::Failure("Не удалось получить размер активного окна".to_string());
+                }
+                let width = (rect.right - rect.left + dw).max(MIN_WINDOW_SIZE);
+                let height = (rect.bottom - rect.top + dh).max(MIN_WINDOW_SIZE);
+                if SetWindowPos(hwnd, HWND(0), 0, 0, width, height, SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE).as_bool() {
+                    ExecutionResult::Success(format!("Окно изменило размер до {}x{}", width, height))
+                } else {
+                    ExecutionResult::Failure("Не удалось изменить размер окна".to_string())
+                }
+            }
+            Action::WindowMinimize { label, process } => {
+                let name = window_name(label, process.as_deref());
+                log_info(&format!("Свернуть окно '{}'", name));
+                let hwnd = find_process_window(label, process.as_deref());
+                if hwnd.0 == 0 {
+                    return ExecutionResult::Failure(format!("Окно '{}' не найдено", name));
                 }
                 ShowWindow(hwnd, SW_MINIMIZE);
-                ExecutionResult::Success(format!("Окно '{}' свернуто", label))
+                ExecutionResult::Success(format!("Окно '{}' свернуто", name))
             }
-            Action::WindowMaximize { label } => {
-                log_info(&format!("Развернуть окно '{}'", label));
-                let hwnd = find_window("", label);
+            Action::WindowMaximize { label, process } => {
+                let name = window_name(label, process.as_deref());
+                log_info(&format!("Развернуть окно '{}'", name));
+                let hwnd = find_process_window(label, process.as_deref());
                 if hwnd.0 == 0 {
-                    return ExecutionResult::Failure(format!("Окно '{}' не найдено", label));
+                    return ExecutionResult::Failure(format!("Окно '{}' не найдено", name));
                 }
                 ShowWindow(hwnd, SW_MAXIMIZE);
-                ExecutionResult::Success(format!("Окно '{}' развернуто", label))
+                ExecutionResult::Success(format!("Окно '{}' развернуто", name))
             }
             Action::LaunchApplication { app } => {
                 log_info(&format!("Запуск приложения '{}'", app));
                 let operation = CString::new("open").unwrap();
-                let app_c = CString::new(app.clone()).unwrap();
+                let app_c = match shell_target(app) {
+                    Ok(app_c) => app_c,
+                    Err(e) => return ExecutionResult::Failure(e),
+                };
                 let result = ShellExecuteA(None, &operation, &app_c, None, None, SW_SHOWNORMAL);
                 if (result.0 as isize) <= 32 {
                     ExecutionResult::Failure(format!("Не удалось запустить приложение '{}'", app))
@@ -365,10 +975,31 @@ pub fn execute_action(action: &Action) -> ExecutionResult {
                 // Здесь можно реализовать логику группировки окон.
                 ExecutionResult::Success(format!("Окна '{}' сгруппированы в группу '{}'", windows, group))
             }
+            Action::ArrangeWindows { layout } => {
+                log_info(&format!("Расположение окон: {}", layout));
+                match arrange_windows(layout) {
+                    Ok(count) => ExecutionResult::Success(format!("Окна расположены ({}): {}", layout, count)),
+                    Err(e) => ExecutionResult::Failure(e),
+                }
+            }
+            Action::WindowMoveNamed { label, position } => {
+                log_info(&format!("Перемещение окна '{}' в положение {}", label, position));
+                let hwnd = if label.is_empty() { GetForegroundWindow() } else { find_window("", label) };
+                if hwnd.0 == 0 {
+                    return ExecutionResult::Failure(format!("Окно '{}' не найдено", label));
+                }
+                match move_window_to(hwnd, position) {
+                    Ok((x, y)) => ExecutionResult::Success(format!("Окно '{}' перемещено в положение {} ({}, {})", label, position, x, y)),
+                    Err(e) => ExecutionResult::Failure(format!("{} '{}'", e, label)),
+                }
+            }
             Action::LaunchObject { object } => {
                 log_info(&format!("Запуск объекта '{}'", object));
                 let operation = CString::new("open").unwrap();
-                let object_c = CString::new(object.clone()).unwrap();
+                let object_c = match shell_target(object) {
+                    Ok(object_c) => object_c,
+                    Err(e) => return ExecutionResult::Failure(e),
+                };
                 let result = ShellExecuteA(None, &operation, &object_c, None, None, SW_SHOWNORMAL);
                 if (result.0 as isize) <= 32 {
                     ExecutionResult::Failure(format!("Не удалось запустить объект '{}'", object))
@@ -376,6 +1007,35 @@ pub fn execute_action(action: &Action) -> ExecutionResult {
                     ExecutionResult::Success(format!("Объект '{}' запущен", object))
                 }
             }
+            Action::Open { target, kind } => {
+                log_info(&format!("Открытие ({}) '{}'", kind.as_str(), target));
+                // Links go to the default browser and files to their associated program;
+                // folders are explored so they open in Explorer even when "open" is remapped.
+                let verb = match kind {
+                    OpenKind::Folder if !Path::new(target).is_dir() => {
+                        return ExecutionResult::Failure(format!("Папка '{}' не найдена", target));
+                    }
+                    OpenKind::File if !Path::new(target).is_file() => {
+                        return ExecutionResult::Failure(format!("Файл '{}' не найден", target));
+                    }
+                    OpenKind::Folder => "explore",
+                    OpenKind::Url | OpenKind::File | OpenKind::App => "open",
+                };
+                let operation = match shell_target(verb) {
+                    Ok(operation) => operation,
+                    Err(e) => return ExecutionResult::Failure(e),
+                };
+                let target_c = match shell_target(target) {
+                    Ok(target_c) => target_c,
+                    Err(e) => return ExecutionResult::Failure(e),
+                };
+                let result = ShellExecuteA(None, &operation, &target_c, None, None, SW_SHOWNORMAL);
+                if (result.0 as isize) <= 32 {
+                    ExecutionResult::Failure(format!("Не удалось открыть '{}'", target))
+                } else {
+                    ExecutionResult::Success(format!("Открыто: '{}'", target))
+                }
+            }
             Action::FocusObject { object } => {
                 log_info(&format!("Установка фокуса на объект '{}'", object));
                 let object_c = CString::new(object.clone()).unwrap();
@@ -391,18 +1051,85 @@ pub fn execute_action(action: &Action) -> ExecutionResult {
             }
             Action::WindowMinimizeAll => {
                 log_info("Свернуть все окна");
-                // Здесь должна быть реализация сворачивания всех окон.
-                ExecutionResult::Success("Все окна свернуты".to_string())
+                if minimize_all_windows() {
+                    ExecutionResult::Success("Все окна свернуты".to_string())
+                } else {
+                    ExecutionResult::Failure("Не удалось перечислить окна для сворачивания".to_string())
+                }
             }
             Action::WindowMaximizeAll => {
                 log_info("Развернуть все окна");
-                // Здесь должна быть реализация разворачивания всех окон.
-                ExecutionResult::Success("Все окна развернуты".to_string())
+                if maximize_all_windows() {
+                    ExecutionResult::Success("Все окна развернуты".to_string())
+                } else {
+                    ExecutionResult::Failure("Не удалось перечислить окна для разворачивания".to_string())
+                }
             }
             Action::WindowCloseAll => {
                 log_info("Закрыть все окна");
-                // Здесь должна быть реализация закрытия всех окон.
-                ExecutionResult::Success("Все окна закрыты".to_string())
+                if close_all_windows() {
+                    ExecutionResult::Success("Все окна закрыты".to_string())
+                } else {
+                    ExecutionResult::Failure("Не удалось перечислить окна для закрытия".to_string())
+                }
+            }
+            Action::ListWindows => {
+                log_info("Список окон");
+                let windows = visible_window_infos();
+                let titles: Vec<&str> = windows.iter().map(|window| window.title.as_str()).collect();
+                ExecutionResult::Read(titles.join("\n"), serde_json::json!({ "windows": windows }))
+            }
+            Action::WindowExists { label, class } => {
+                log_info(&format!("Проверка наличия окна '{}' (класс {:?})", label, class));
+                let exists = window_exists(label, class.as_deref());
+                ExecutionResult::Read(format!("Окно '{}': exists={}", label, exists), serde_json::json!({ "exists": exists }))
+            }
+            Action::GetWindowDpi { label } => {
+                let name = label.as_deref().unwrap_or("активное");
+                log_info(&format!("Чтение DPI окна '{}'", name));
+                let hwnd = match label {
+                    Some(lbl) => find_window("", lbl),
+                    None => GetForegroundWindow(),
+                };
+                if hwnd.0 == 0 {
+                    return ExecutionResult::Failure(format!("Окно '{}' не найдено", name));
+                }
+                let dpi = GetDpiForWindow(hwnd);
+                if dpi == 0 {
+                    return ExecutionResult::Failure(format!("Не удалось получить DPI окна '{}'", name));
+                }
+                let scale = dpi_scale_factor(dpi);
+                ExecutionResult::Read(
+                    format!("Окно '{}': dpi={}, scale={}", name, dpi, scale),
+                    serde_json::json!({ "dpi": dpi, "scale": scale }),
+                )
+            }
+            Action::GetPixelColor { x, y } => {
+                log_info(&format!("Чтение цвета пикселя ({}, {})", x, y));
+                let hdc_screen = GetDC(HWND(0));
+                if hdc_screen.0 == 0 {
+                    return ExecutionResult::Failure("Не удалось получить контекст экрана".to_string());
+                }
+                let color = GetPixel(hdc_screen, *x, *y);
+                ReleaseDC(HWND(0), hdc_screen);
+                if color.0 == CLR_INVALID {
+                    return ExecutionResult::Failure(format!("Пиксель ({}, {}) вне экрана", x, y));
+                }
+                let details = color_details(color.0);
+                let message = format!("Цвет пикселя ({}, {}): {}", x, y, details["hex"].as_str().unwrap_or_default());
+                ExecutionResult::Read(message, details)
+            }
+            Action::SendToBack { label } => {
+                log_info(&format!("Окно '{}' на задний план", label.as_deref().unwrap_or("активное")));
+                set_z_order(label.as_deref(), true)
+            }
+            Action::BringToFront { label } => {
+                log_info(&format!("Окно '{}' на передний план", label.as_deref().unwrap_or("активное")));
+                set_z_order(label.as_deref(), false)
+            }
+            Action::DragResize { label, edge, delta } => {
+                log_info(&format!("Перетаскивание края '{}' окна '{}' на {} пикселей", edge, label.as_deref().unwrap_or("активное"), delta));
+                drag_resize(label.as_deref(), edge, *delta)
             }
             Action::OpenFileProperties { file } => {
                 log_info(&format!("Opening file properties for '{}'", file));
@@ -457,8 +1184,19 @@ pub fn execute_action(action: &Action) -> ExecutionResult {
                     ExecutionResult::Failure(format!("Item '{}' not found in window '{}'", item, label))
                 }
             }
+            Action::TypeText { text, delay_ms } => {
+                log_info(&format!("Ввод текста '{}' в элемент с фокусом", text));
+                let delay = delay_ms.map(Duration::from_millis).unwrap_or_else(keystroke_delay);
+                match type_unicode_text(text, delay) {
+                    Ok(()) => ExecutionResult::Success(format!("Текст '{}' напечатан", text)),
+                    Err(e) => ExecutionResult::Failure(format!("Не удалось напечатать текст '{}': {}", text, e)),
+                }
+            }
             Action::KeyPress { key } => {
                 log_info(&format!("Sending key press '{}'", key));
+                if let Err(e) = ensure_focus() {
+                    return ExecutionResult::Failure(e);
+                }
                 let key_str = key.trim();
                 let vk = windows::Win32::UI::Input::KeyboardAndMouse::VkKeyScanA(
                     key_str.chars().next().unwrap() as i8
@@ -492,42 +1230,155 @@ pub fn execute_action(action: &Action) -> ExecutionResult {
                     ExecutionResult::Success(format!("Key '{}' pressed successfully", key))
                 }
             }
+            Action::KeyChord { modifiers, key } => {
+                log_info(&format!("Sending key chord '{}+{}'", modifiers.join("+"), key));
+                let mut modifier_keys = Vec::with_capacity(modifiers.len());
+                for modifier in modifiers {
+                    match modifier_vk(modifier) {
+                        Some(vk) => modifier_keys.push(vk),
+                        None => return ExecutionResult::Failure(format!("Unknown modifier '{}'", modifier)),
+                    }
+                }
+                send_key_chord(&modifier_keys, key)
+            }
+            Action::HoldKey { key, duration_ms } => {
+                log_info(&format!("Holding key '{}' for {} ms", key, duration_ms));
+                if GetForegroundWindow().0 == 0 {
+                    return ExecutionResult::Failure(format!("Foreground window not found for '{}'", key));
+                }
+                match chord_key_vk(key) {
+                    Some(vk) => hold_key(vk, *duration_ms, key),
+                    None => ExecutionResult::Failure(format!("Failed to convert '{}' to a virtual key", key)),
+                }
+            }
+            Action::TabTo { count, reverse } => {
+                log_info(&format!("Moving focus {} {} times", if *reverse { "backwards" } else { "forwards" }, count));
+                if GetForegroundWindow().0 == 0 {
+                    return ExecutionResult::Failure("Foreground window not found for tab navigation".to_string());
+                }
+                let modifiers: &[u16] = if *reverse { &[VK_SHIFT] } else { &[] };
+                if send_key_taps(VK_TAB, *count, modifiers) {
+                    ExecutionResult::Success(format!("Focus moved {} times{}", count, if *reverse { " backwards" } else { "" }))
+                } else {
+                    ExecutionResult::Failure("Error sending Tab key presses".to_string())
+                }
+            }
+            Action::SubmitDialog => {
+                log_info("Submitting dialog with Enter");
+                press_on_focused(VK_RETURN, "Enter")
+            }
+            Action::CancelDialog => {
+                log_info("Cancelling dialog with Escape");
+                press_on_focused(VK_ESCAPE, "Escape")
+            }
+            Action::ActivateFocused => {
+                log_info("Activating focused control with Space");
+                press_on_focused(VK_SPACE, "Space")
+            }
             Action::Scroll { direction, amount } => {
                 log_info(&format!("Scrolling '{}' by {:?}", direction, amount));
+                if let Err(e) = ensure_focus() {
+                    return ExecutionResult::Failure(e);
+                }
                 let hwnd = GetForegroundWindow();
                 if hwnd.0 == 0 {
                     return ExecutionResult::Failure("Foreground window not found for scrolling".to_string());
                 }
-                let amt = amount.unwrap_or(1);
-                let wparam = if direction.to_lowercase() == "up" {
-                    WPARAM(SB_LINEUP as usize)
-                } else if direction.to_lowercase() == "down" {
-                    WPARAM(SB_LINEDOWN as usize)
-                } else {
-                    return ExecutionResult::Failure("Invalid scroll direction. Use 'up' or 'down'".to_string());
+                let (code, repeats) = match scroll_code(direction) {
+                    Some(code) => code,
+                    None => return ExecutionResult::Failure(
+                        "Invalid scroll direction. Use 'up', 'down', 'page_up', 'page_down', 'top' or 'bottom'".to_string()),
                 };
+                // Jumping to the top or bottom is a single message; the rest repeat `amount` times.
+                let amt = if repeats { amount.unwrap_or(1) } else { 1 };
+                let wparam = WPARAM(code as usize);
                 for _ in 0..amt {
                     SendMessageA(hwnd, WM_VSCROLL, wparam, LPARAM(0));
                     thread::sleep(Duration::from_millis(50));
                 }
                 ExecutionResult::Success(format!("Scrolled '{}' by {}", direction, amt))
             }
-            Action::Screenshot => {
-                log_info("Taking screenshot as PNG");
-                match take_screenshot_png("screenshot.png") {
-                    Ok(path)  => ExecutionResult::Success(format!("Screenshot saved to '{}'", path)),
-                    Err(e) => ExecutionResult::Failure(format!("Error taking screenshot: {}", e)),
+            Action::MouseDoubleClick { x, y } => {
+                log_info(&format!("Double-clicking at ({}, {})", x, y));
+                if let Err(e) = ensure_focus() {
+                    return ExecutionResult::Failure(e);
                 }
-            }
-            Action::SpinnerAdjust { label, operation, value } => {
-                log_info(&format!("Adjusting spinner '{}' with operation: {} and value: {}", label, operation, value));
-                // Find the spinner control. Here we assume its class is "msctls_updown32".
-                let spinner_hwnd = find_window("msctls_updown32", label);
-                if spinner_hwnd.0 == 0 {
-                    return ExecutionResult::Failure(format!("Spinner control '{}' not found", label));
+                if !SetCursorPos(*x, *y).as_bool() {
+                    return ExecutionResult::Failure(format!("Failed to move cursor to ({}, {})", x, y));
                 }
-                // Retrieve the current position.
-                let current_result = SendMessageA(spinner_hwnd, UDM_GETPOS, WPARAM(0), LPARAM(0));
+                let inputs = [
+                    mouse_input(MOUSEEVENTF_LEFTDOWN, 0),
+                    mouse_input(MOUSEEVENTF_LEFTUP, 0),
+                    mouse_input(MOUSEEVENTF_LEFTDOWN, 0),
+                    mouse_input(MOUSEEVENTF_LEFTUP, 0),
+                ];
+                if SendInput(&inputs, mem::size_of::<INPUT>() as i32) as usize != inputs.len() {
+                    ExecutionResult::Failure(format!("Error sending double click at ({}, {})", x, y))
+                } else {
+                    ExecutionResult::Success(format!("Double-clicked at ({}, {})", x, y))
+                }
+            }
+            Action::ClickText { text } => {
+                log_info(&format!("Клик по тексту '{}' на экране", text));
+                click_text(text)
+            }
+            Action::MouseWheel { direction, amount } => {
+                log_info(&format!("Scrolling mouse wheel '{}' by {} notches", direction, amount));
+                let delta = match direction.to_lowercase().as_str() {
+                    "up" => WHEEL_DELTA * *amount as i32,
+                    "down" => -WHEEL_DELTA * *amount as i32,
+                    _ => return ExecutionResult::Failure("Invalid wheel direction. Use 'up' or 'down'".to_string()),
+                };
+                let inputs = [mouse_input(MOUSEEVENTF_WHEEL, delta)];
+                if SendInput(&inputs, mem::size_of::<INPUT>() as i32) != 1 {
+                    ExecutionResult::Failure("Error sending mouse wheel input".to_string())
+                } else {
+                    ExecutionResult::Success(format!("Mouse wheel scrolled '{}' by {} notches", direction, amount))
+                }
+            }
+            Action::Screenshot { region, target_window, path } => {
+                let file_path = path.clone().unwrap_or_else(default_screenshot_path);
+                let source = match (target_window, region) {
+                    (Some(label), _) => {
+                        let hwnd = find_window("", label);
+                        if hwnd.0 == 0 {
+                            return ExecutionResult::Failure(format!("Окно '{}' не найдено", label));
+                        }
+                        CaptureSource::Window(hwnd)
+                    }
+                    (None, Some(rect)) => CaptureSource::Region(*rect),
+                    (None, None) => CaptureSource::Screen,
+                };
+                log_info(&format!("Taking screenshot of {:?} as PNG to '{}'", source, file_path));
+                match take_screenshot_png(&file_path, source) {
+                    Ok(path)  => ExecutionResult::Success(format!("Screenshot saved to '{}'", path)),
+                    Err(e) => ExecutionResult::Failure(format!("Error taking screenshot: {}", e)),
+                }
+            }
+            Action::GetClipboardImage { path } => {
+                let file_path = path.as_deref().unwrap_or("clipboard.png");
+                log_info(&format!("Saving clipboard image to '{}'", file_path));
+                match save_clipboard_image_png(file_path) {
+                    Ok(path) => ExecutionResult::Success(format!("Clipboard image saved to '{}'", path)),
+                    Err(e) => ExecutionResult::Failure(format!("Error reading clipboard image: {}", e)),
+                }
+            }
+            Action::SetClipboardImage { path } => {
+                log_info(&format!("Placing image '{}' on the clipboard", path));
+                match set_clipboard_image(path) {
+                    Ok(()) => ExecutionResult::Success(format!("Image '{}' copied to clipboard", path)),
+                    Err(e) => ExecutionResult::Failure(format!("Error copying image to clipboard: {}", e)),
+                }
+            }
+            Action::SpinnerAdjust { label, operation, value } => {
+                log_info(&format!("Adjusting spinner '{}' with operation: {} and value: {}", label, operation, value));
+                // Find the spinner control. Here we assume its class is "msctls_updown32".
+                let spinner_hwnd = find_window("msctls_updown32", label);
+                if spinner_hwnd.0 == 0 {
+                    return ExecutionResult::Failure(format!("Spinner control '{}' not found", label));
+                }
+                // Retrieve the current position.
+                let current_result = SendMessageA(spinner_hwnd, UDM_GETPOS, WPARAM(0), LPARAM(0));
                 // Lower word holds the signed position.
                 let mut current_value = (current_result & 0xFFFF) as i32;
                 // Adjust the spinner value according to the operation.
@@ -541,30 +1392,25 @@ pub fn execute_action(action: &Action) -> ExecutionResult {
                 SendMessageA(spinner_hwnd, UDM_SETPOS, WPARAM(0), LPARAM(current_value as isize));
                 ExecutionResult::Success(format!("Spinner '{}' adjusted to {}", label, current_value))
             }
-            Action::SelectFiles { criteria } => {
-                log_info(&format!("Selecting files matching '{}'", criteria));
+            Action::SelectFiles { criteria, root, recursive } => {
+                log_info(&format!("Selecting files matching '{}' (root: {:?}, recursive: {})", criteria, root, recursive));
+                let (base_dir, search_dir) = match resolve_search_root(root.as_deref()) {
+                    Ok(dirs) => dirs,
+                    Err(e) => return ExecutionResult::Failure(e),
+                };
                 let mut matches = Vec::new();
-                // For demonstration, search in the current directory.
-                match fs::read_dir(".") {
-                    Ok(entries) => {
-                        for entry in entries.flatten() {
-                            let path = entry.path();
-                            if let Some(path_str) = path.to_str() {
-                                if path_str.contains(criteria) {
-                                    matches.push(path_str.to_string());
-                                }
-                            }
-                        }
-                    }
-                    Err(e) => return ExecutionResult::Failure(format!("Error reading directory: {}", e)),
+                if let Err(e) = find_files(&base_dir, &search_dir, criteria, *recursive, &mut matches) {
+                    return ExecutionResult::Failure(e);
                 }
                 if matches.is_empty() {
-                    ExecutionResult::Failure(format!("No files matching '{}' found", criteria))
+                    ExecutionResult::Failure(format!("No files matching '{}' found in '{}'", criteria, search_dir.display()))
                 } else {
                     // Save selection globally.
                     let mut selected = SELECTED_FILES.lock().unwrap();
                     selected.clear();
                     selected.extend(matches.clone());
+                    // A new selection starts as a copy; only a cut after it turns the paste into a move.
+                    *PENDING_PASTE_MODE.lock().unwrap() = PasteMode::Copy;
                     ExecutionResult::Success(format!("Files selected: {:?}", matches))
                 }
             }
@@ -591,36 +1437,44 @@ pub fn execute_action(action: &Action) -> ExecutionResult {
                             ExecutionResult::Failure(errors.join("; "))
                         }
                     },
-                    "copy" | "cut" => {
-                        // For copy or cut, we expect the paste operation to supply the destination.
-                        ExecutionResult::Failure(format!("Operation '{}' requires a paste destination. Use Action::PasteFiles", operation))
+                    "copy" | "copy_file" => {
+                        // The paste operation supplies the destination.
+                        *PENDING_PASTE_MODE.lock().unwrap() = PasteMode::Copy;
+                        ExecutionResult::Success(format!("{} files marked for copying; paste them to finish", selected.len()))
+                    },
+                    "cut" | "cut_file" => {
+                        *PENDING_PASTE_MODE.lock().unwrap() = PasteMode::Move;
+                        ExecutionResult::Success(format!("{} files marked for moving; paste them to finish", selected.len()))
                     },
                     _ => ExecutionResult::Failure(format!("Unsupported file operation '{}'", operation)),
                 }
             }
-            Action::PasteFiles { destination } => {
-                log_info(&format!("Pasting files into '{}'", destination));
-                let selected = SELECTED_FILES.lock().unwrap();
+            Action::PasteFiles { destination, mode } => {
+                // A preceding cut turns the paste into a move even if the action asked for a copy.
+                let mode = match *PENDING_PASTE_MODE.lock().unwrap() {
+                    PasteMode::Move => PasteMode::Move,
+                    PasteMode::Copy => *mode,
+                };
+                log_info(&format!("Pasting files into '{}' ({:?})", destination, mode));
+                let mut selected = SELECTED_FILES.lock().unwrap();
                 if selected.is_empty() {
                     return ExecutionResult::Failure("No files are currently selected to paste.".to_string());
                 }
                 if !Path::new(destination).is_dir() {
                     return ExecutionResult::Failure(format!("Destination '{}' is not a valid directory", destination));
                 }
-                let mut errors = Vec::new();
-                for file in selected.iter() {
-                    let path = Path::new(file);
-                    if let Some(filename) = path.file_name() {
-                        let dest_path = Path::new(destination).join(filename);
-                        if let Err(e) = fs::copy(path, &dest_path) {
-                            errors.push(format!("Failed to copy {}: {}", file, e));
-                        }
-                    } else {
-                        errors.push(format!("Invalid file path: {}", file));
+                let failed = paste_files(&selected, Path::new(destination), mode, &mut crate::task_scheduler::report_progress);
+                if mode == PasteMode::Move {
+                    // The moved sources are gone; keep the ones that failed selected so the move can be retried.
+                    selected.retain(|file| failed.iter().any(|(failed_file, _)| failed_file == file));
+                    if selected.is_empty() {
+                        *PENDING_PASTE_MODE.lock().unwrap() = PasteMode::Copy;
                     }
                 }
+                let errors: Vec<String> = failed.into_iter().map(|(_, error)| error).collect();
                 if errors.is_empty() {
-                    ExecutionResult::Success(format!("Files pasted into '{}'", destination))
+                    let verb = if mode == PasteMode::Move { "moved" } else { "pasted" };
+                    ExecutionResult::Success(format!("Files {} into '{}'", verb, destination))
                 } else {
                     ExecutionResult::Failure(errors.join("; "))
                 }
@@ -664,11 +1518,72 @@ pub fn execute_action(action: &Action) -> ExecutionResult {
                     Err(e) => ExecutionResult::Failure(format!("Error deleting file '{}': {}", name, e)),
                 }
             }
+            Action::Refused { intent, reason } => {
+                log_info(&format!("Отказ в выполнении интента '{}'", intent));
+                ExecutionResult::Failure(reason.clone())
+            }
+            Action::MultiStep { steps, continue_on_error } => execute_steps(steps, *continue_on_error),
             _ => ExecutionResult::Failure("Неизвестное действие".to_string()),
         }
     }
 }
 
+/// Видимое окно верхнего уровня, как его возвращают `ListWindows` и `GET /windows`.
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowInfo {
+    hwnd: isize,
+    title: String,
+    class_name: String,
+    pid: u32,
+}
+
+/// Collects the visible top-level windows, skipping untitled ones and the Program Manager.
+/// Titles are read as UTF-16 so non-ASCII titles survive.
+unsafe fn visible_window_infos() -> Vec<WindowInfo> {
+    extern "system" fn enum_proc(hwnd: HWND, lparam: LPARAM) -> i32 {
+        unsafe {
+            if IsWindowVisible(hwnd).as_bool() && hwnd != GetShellWindow() {
+                let title = window_title_w(hwnd);
+                let infos_ptr = lparam.0 as *mut Vec<WindowInfo>;
+                if !title.is_empty() && !infos_ptr.is_null() {
+                    let mut pid: u32 = 0;
+                    GetWindowThreadProcessId(hwnd, Some(&mut pid));
+                    (*infos_ptr).push(WindowInfo { hwnd: hwnd.0, title, class_name: window_class_name(hwnd), pid });
+                }
+            }
+        }
+        1 // continue enumeration
+    }
+    let mut infos: Vec<WindowInfo> = Vec::new();
+    EnumWindows(Some(enum_proc), LPARAM(&mut infos as *mut Vec<WindowInfo> as isize));
+    infos
+}
+
+/// The visible top-level windows, for `GET /windows`, which answers without going through the scheduler.
+pub fn list_windows() -> Vec<WindowInfo> {
+    unsafe { visible_window_infos() }
+}
+
+/// Whether a window titled `label` is open, for `GET /exists`, which also answers directly.
+pub fn window_is_open(label: &str, class: Option<&str>) -> bool {
+    unsafe { window_exists(label, class) }
+}
+
+/// Collects the titles of visible top-level windows, skipping untitled ones and the Program Manager.
+unsafe fn visible_window_titles() -> Vec<String> {
+    visible_window_infos().into_iter().map(|info| info.title).collect()
+}
+
+/// Returns true if a window titled `label` exists: an exact `find_window` match first, then,
+/// when no class is required, any visible window whose title contains `label` (case-insensitive).
+unsafe fn window_exists(label: &str, class: Option<&str>) -> bool {
+    if find_window(class.unwrap_or(""), label).0 != 0 {
+        return true;
+    }
+    let needle = label.to_lowercase();
+    class.is_none() && !needle.is_empty() && visible_window_titles().iter().any(|title| title.to_lowercase().contains(&needle))
+}
+
 /// Helper function to minimize all visible windows.
 unsafe fn minimize_all_windows() -> bool {
     extern "system" fn enum_windows_proc(hwnd: HWND, _lparam: LPARAM) -> i32 {
@@ -696,160 +1611,2229 @@ unsafe fn maximize_all_windows() -> bool {
 }
 
 /// Helper function to close all visible windows.
+/// Untitled windows and the shell/desktop windows are skipped so the desktop itself stays up.
 unsafe fn close_all_windows() -> bool {
     extern "system" fn enum_windows_proc(hwnd: HWND, _lparam: LPARAM) -> i32 {
         unsafe {
-            if IsWindowVisible(hwnd).as_bool() {
-                SendMessageA(hwnd, WM_CLOSE, WPARAM(0), LPARAM(0));
+            if !IsWindowVisible(hwnd).as_bool() || GetWindowTextLengthA(hwnd) == 0 {
+                return 1;
+            }
+            if hwnd == GetShellWindow() || hwnd == GetDesktopWindow() {
+                return 1;
             }
+            SendMessageA(hwnd, WM_CLOSE, WPARAM(0), LPARAM(0));
         }
         1
     }
     EnumWindows(Some(enum_windows_proc), LPARAM(0)).as_bool()
 }
 
-/// Helper function to find a window by class name and title.
-/// If the class name is empty, the search is performed only by title.
-unsafe fn find_window(class_name: &str, window_title: &str) -> HWND {
-    let class = if !class_name.is_empty() {
-        Some(&CString::new(class_name).unwrap())
-    } else {
-        None
+/// Converts a percentage (clamped to 0..=100) to a trackbar position within `min..=max`, rounding to nearest.
+fn slider_position(min: i32, max: i32, percent: u32) -> i32 {
+    let percent = percent.min(100) as i64;
+    let (min, max) = (min.min(max) as i64, min.max(max) as i64);
+    (min + ((max - min) * percent + 50) / 100) as i32
+}
+
+/// Converts a DPI value to the scale factor relative to the 96 DPI baseline (1.0 = 100%).
+fn dpi_scale_factor(dpi: u32) -> f64 {
+    dpi as f64 / USER_DEFAULT_SCREEN_DPI as f64
+}
+
+/// Picks the `SetWindowPos` insert-after handle for a z-order change.
+fn z_order_insert_after(to_back: bool) -> HWND {
+    if to_back { HWND_BOTTOM } else { HWND_TOP }
+}
+
+/// Moves the window with the given title (or the foreground window) to the bottom or top of the z-order
+/// without moving, resizing or activating it.
+unsafe fn set_z_order(label: Option<&str>, to_back: bool) -> ExecutionResult {
+    let hwnd = match label {
+        Some(lbl) => find_window("", lbl),
+        None => GetForegroundWindow(),
     };
-    let title = Some(&CString::new(window_title).unwrap());
-    FindWindowA(class, title)
+    let name = label.unwrap_or("активное");
+    if hwnd.0 == 0 {
+        return ExecutionResult::Failure(format!("Окно '{}' не найдено", name));
+    }
+    let flags = SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE;
+    if SetWindowPos(hwnd, z_order_insert_after(to_back), 0, 0, 0, 0, flags).as_bool() {
+        let place = if to_back { "задний" } else { "передний" };
+        ExecutionResult::Success(format!("Окно '{}' перемещено на {} план", name, place))
+    } else {
+        ExecutionResult::Failure(format!("Не удалось изменить z-порядок окна '{}'", name))
+    }
 }
 
-/// Takes a screenshot of the entire screen and saves it as a PNG file.
-/// This function uses the image crate, so ensure it is added as a dependency in Cargo.toml.
-unsafe fn take_screenshot_png(file_path: &str) -> Result<String, String> {
-    // Get the device context of the entire screen.
-    let hdc_screen = GetDC(HWND(0));
-    if hdc_screen.0 == 0 {
-        return Err("Failed to obtain screen DC".to_string());
+/// Returns the point just inside the given edge of `rect`, at the middle of that edge.
+/// The point is inset by `DRAG_EDGE_INSET` so it lands on the resize border
+/// rather than outside the window.
+fn edge_grab_point(rect: &RECT, edge: &str) -> Option<(i32, i32)> {
+    let center_x = (rect.left + rect.right) / 2;
+    let center_y = (rect.top + rect.bottom) / 2;
+    match edge.to_lowercase().as_str() {
+        "left" => Some((rect.left + DRAG_EDGE_INSET, center_y)),
+        "right" => Some((rect.right - 1 - DRAG_EDGE_INSET, center_y)),
+        "top" => Some((center_x, rect.top + DRAG_EDGE_INSET)),
+        "bottom" => Some((center_x, rect.bottom - 1 - DRAG_EDGE_INSET)),
+        _ => None,
     }
-    let width = GetDeviceCaps(hdc_screen, HORZRES);
-    let height = GetDeviceCaps(hdc_screen, VERTRES);
-    let hdc_mem = CreateCompatibleDC(hdc_screen);
-    if hdc_mem.0 == 0 {
-        return Err("Failed to create compatible DC".to_string());
+}
+
+/// Returns where the grabbed edge must be dropped so the window grows by `delta`
+/// pixels (shrinks when negative).
+fn edge_drop_point(grab: (i32, i32), edge: &str, delta: i32) -> (i32, i32) {
+    let (x, y) = grab;
+    match edge.to_lowercase().as_str() {
+        "left" => (x - delta, y),
+        "right" => (x + delta, y),
+        "top" => (x, y - delta),
+        _ => (x, y + delta),
     }
-    // Create a 32-bit bitmap for the screenshot.
-    let hbitmap = CreateCompatibleBitmap(hdc_screen, width, height);
-    if hbitmap.0 == 0 {
-        return Err("Failed to create compatible bitmap".to_string());
+}
+
+/// Presses the left button at `from`, moves the cursor to `to` in small steps so the
+/// target sees a continuous drag, and releases it there.
+unsafe fn mouse_drag(from: (i32, i32), to: (i32, i32)) -> bool {
+    if !SetCursorPos(from.0, from.1).as_bool() {
+        return false;
     }
-    let old_obj = SelectObject(hdc_mem, hbitmap);
-    if old_obj.0 == 0 {
-        return Err("Failed to select bitmap into DC".to_string());
+    if SendInput(&[mouse_input(MOUSEEVENTF_LEFTDOWN, 0)], mem::size_of::<INPUT>() as i32) != 1 {
+        return false;
+    }
+    let mut moved = true;
+    for step in 1..=DRAG_STEPS {
+        let x = from.0 + (to.0 - from.0) * step / DRAG_STEPS;
+        let y = from.1 + (to.1 - from.1) * step / DRAG_STEPS;
+        thread::sleep(Duration::from_millis(DRAG_STEP_DELAY_MS));
+        if !SetCursorPos(x, y).as_bool() {
+            moved = false;
+            break;
+        }
     }
-    if !BitBlt(hdc_mem, 0, 0, width, height, hdc_screen, 0, 0, SRCCOPY).as_bool() {
-        return Err("BitBlt failed".to_string());
+    // Always release the button, even if the drag was interrupted.
+    let released = SendInput(&[mouse_input(MOUSEEVENTF_LEFTUP, 0)], mem::size_of::<INPUT>() as i32) == 1;
+    moved && released
+}
+
+/// Returns the screen coordinates of the center of `hwnd`'s client area.
+unsafe fn client_center_on_screen(hwnd: HWND) -> Option<(i32, i32)> {
+    let mut rect = RECT::default();
+    if !GetClientRect(hwnd, &mut rect).as_bool() {
+        return None;
     }
-    // Prepare to get bitmap bits in BGRA (32-bit) format.
-    let mut bmi_header = windows::Win32::Graphics::Gdi::BITMAPINFOHEADER {
-        biSize: mem::size_of::<windows::Win32::Graphics::Gdi::BITMAPINFOHEADER>() as u32,
-        biWidth: width,
-        biHeight: -height, // Negative height indicates a top-down bitmap.
-        biPlanes: 1,
-        biBitCount: 32,
-        biCompression: 0, // BI_RGB
-        biSizeImage: 0,
-        biXPelsPerMeter: 0,
-        biYPelsPerMeter: 0,
-        biClrUsed: 0,
-        biClrImportant: 0,
+    let mut center = POINT { x: (rect.left + rect.right) / 2, y: (rect.top + rect.bottom) / 2 };
+    if !ClientToScreen(hwnd, &mut center).as_bool() {
+        return None;
+    }
+    Some((center.x, center.y))
+}
+
+/// Resizes the window with the given title (or the foreground window) by dragging
+/// one of its borders with the mouse. Used for apps that ignore `SetWindowPos`.
+unsafe fn drag_resize(label: Option<&str>, edge: &str, delta: i32) -> ExecutionResult {
+    let hwnd = match label {
+        Some(lbl) => find_window("", lbl),
+        None => GetForegroundWindow(),
     };
-    let row_bytes = ((32 * width + 31) / 32) * 4;
-    let image_size = (row_bytes * height) as usize;
-    let mut pixel_data: Vec<u8> = vec![0; image_size];
-    let ret = windows::Win32::Graphics::Gdi::GetDIBits(
-        hdc_mem,
-        hbitmap,
-        0,
-        height as u32,
-        Some(pixel_data.as_mut_ptr() as *mut _),
-        &mut windows::Win32::Graphics::Gdi::BITMAPINFO {
-            bmiHeader: bmi_header,
-            bmiColors: [Default::default(); 1],
-        },
-        windows::Win32::Graphics::Gdi::DIB_RGB_COLORS,
-    );
-    if ret == 0 {
-        return Err("GetDIBits failed".to_string());
+    let name = label.unwrap_or("активное");
+    if hwnd.0 == 0 {
+        return ExecutionResult::Failure(format!("Окно '{}' не найдено", name));
     }
-    // Clean up GDI objects.
-    SelectObject(hdc_mem, old_obj);
-    DeleteObject(hbitmap);
-    DeleteDC(hdc_mem);
-    ReleaseDC(HWND(0), hdc_screen);
+    let mut rect = RECT::default();
+    if !GetWindowRect(hwnd, &mut rect).as_bool() {
+        return ExecutionResult::Failure(format!("Не удалось получить границы окна '{}'", name));
+    }
+    let grab = match edge_grab_point(&rect, edge) {
+        Some(point) => point,
+        None => return ExecutionResult::Failure(format!("Неизвестный край окна '{}'. Используйте left, right, top или bottom", edge)),
+    };
+    if mouse_drag(grab, edge_drop_point(grab, edge, delta)) {
+        ExecutionResult::Success(format!("Край '{}' окна '{}' перемещён на {} пикселей", edge, name, delta))
+    } else {
+        ExecutionResult::Failure(format!("Не удалось перетащить край '{}' окна '{}'", edge, name))
+    }
+}
 
-    // Convert BGRA to RGBA by swapping blue and red channels.
-    for i in (0..pixel_data.len()).step_by(4) {
-        let b = pixel_data[i];
-        let r = pixel_data[i + 2];
-        pixel_data[i] = r;
-        pixel_data[i + 2] = b;
+/// Builds a keyboard INPUT event for a virtual key press or release.
+unsafe fn keyboard_input(vk: u16, key_up: bool) -> INPUT {
+    let mut input: INPUT = mem::zeroed();
+    input.r#type = INPUT_KEYBOARD;
+    input.Anonymous.ki = KEYBDINPUT {
+        wVk: vk,
+        wScan: 0,
+        dwFlags: if key_up { KEYEVENTF_KEYUP } else { 0 },
+        time: 0,
+        dwExtraInfo: 0,
+    };
+    input
+}
+
+/// Builds a keyboard INPUT event that types one UTF-16 code unit, independent of the keyboard layout.
+unsafe fn unicode_input(code_unit: u16, key_up: bool) -> INPUT {
+    let mut input: INPUT = mem::zeroed();
+    input.r#type = INPUT_KEYBOARD;
+    input.Anonymous.ki = KEYBDINPUT {
+        wVk: 0,
+        wScan: code_unit,
+        dwFlags: if key_up { KEYEVENTF_UNICODE | KEYEVENTF_KEYUP } else { KEYEVENTF_UNICODE },
+        time: 0,
+        dwExtraInfo: 0,
+    };
+    input
+}
+
+/// Types `text` into the focused control like a real keyboard would, one code unit at a time.
+/// With a zero `delay` everything goes out in one `SendInput` call, which is fastest but can
+/// outrun applications that debounce input; otherwise each down/up pair is sent on its own,
+/// with `delay` between pairs, so typing takes `delay` times one less than the code unit count.
+/// The focus lock is checked before every `SendInput`, so typing stops once focus moves elsewhere.
+unsafe fn type_unicode_text(text: &str, delay: Duration) -> Result<(), String> {
+    let code_units: Vec<u16> = text.encode_utf16().collect();
+    let send = |units: &[u16]| {
+        let inputs: Vec<INPUT> = units
+            .iter()
+            .flat_map(|&code_unit| [unicode_input(code_unit, false), unicode_input(code_unit, true)])
+            .collect();
+        SendInput(&inputs, mem::size_of::<INPUT>() as i32) as usize == inputs.len()
+    };
+    send_paced(&code_units, delay, || ensure_focus(), send, thread::sleep)
+}
+
+/// Sends `code_units` through `send`: all at once with a zero `delay`, otherwise one at a time
+/// with `pause(delay)` between them. `check_focus` runs before every send and stops on an error.
+fn send_paced(
+    code_units: &[u16],
+    delay: Duration,
+    mut check_focus: impl FnMut() -> Result<(), String>,
+    mut send: impl FnMut(&[u16]) -> bool,
+    mut pause: impl FnMut(Duration),
+) -> Result<(), String> {
+    let batches: Vec<&[u16]> = if delay.is_zero() { vec![code_units] } else { code_units.chunks(1).collect() };
+    for (index, batch) in batches.into_iter().enumerate() {
+        if index > 0 {
+            pause(delay);
+        }
+        check_focus()?;
+        if !send(batch) {
+            return Err("SendInput failed".to_string());
+        }
     }
-    // Save the PNG using the image crate.
-    match image::save_buffer(file_path, &pixel_data, width as u32, height as u32, image::ColorType::Rgba8) {
-        Ok(_) => Ok(file_path.to_string()),
-        Err(e) => Err(format!("Error saving PNG: {}", e)),
+    Ok(())
+}
+
+/// Builds a mouse INPUT event at the current cursor position.
+/// `data` carries the wheel delta for `MOUSEEVENTF_WHEEL` and is ignored for button events.
+unsafe fn mouse_input(flags: windows::Win32::UI::Input::KeyboardAndMouse::MOUSE_EVENT_FLAGS, data: i32) -> INPUT {
+    let mut input: INPUT = mem::zeroed();
+    input.r#type = INPUT_MOUSE;
+    input.Anonymous.mi = MOUSEINPUT {
+        dx: 0,
+        dy: 0,
+        mouseData: data,
+        dwFlags: flags,
+        time: 0,
+        dwExtraInfo: 0,
+    };
+    input
+}
+
+/// Builds the key event sequence for tapping `vk` `count` times while holding `modifiers`.
+/// Modifiers go down first and are released in reverse order after the last tap.
+fn key_tap_sequence(vk: u16, count: u32, modifiers: &[u16]) -> Vec<(u16, bool)> {
+    let mut sequence = Vec::with_capacity(modifiers.len() * 2 + count as usize * 2);
+    sequence.extend(modifiers.iter().map(|m| (*m, false)));
+    for _ in 0..count {
+        sequence.push((vk, false));
+        sequence.push((vk, true));
     }
+    sequence.extend(modifiers.iter().rev().map(|m| (*m, true)));
+    sequence
 }
 
-/// Groups all visible top-level windows by arranging them in a grid layout across the screen.
-unsafe fn group_windows() -> bool {
-    // Vector to store HWNDs of all visible windows.
-    let mut windows_vec: Vec<HWND> = Vec::new();
-    extern "system" fn enum_proc(hwnd: HWND, lparam: LPARAM) -> i32 {
-        unsafe {
-            // Only include visible windows.
-            if IsWindowVisible(hwnd).as_bool() {
-                // Append the window handle into the Vec<HWND> passed via lparam.
-                let windows_ptr = lparam.0 as *mut Vec<HWND>;
-                if !windows_ptr.is_null() {
-                    (*windows_ptr).push(hwnd);
-                }
-            }
+/// Sends `vk` `count` times to the foreground window while holding `modifiers`.
+unsafe fn send_key_taps(vk: u16, count: u32, modifiers: &[u16]) -> bool {
+    let inputs: Vec<INPUT> = key_tap_sequence(vk, count, modifiers)
+        .into_iter()
+        .map(|(key, key_up)| keyboard_input(key, key_up))
+        .collect();
+    SendInput(&inputs, mem::size_of::<INPUT>() as i32) as usize == inputs.len()
+}
+
+/// Maps a modifier name from a chord ("ctrl", "alt", ...) to its virtual key.
+fn modifier_vk(name: &str) -> Option<u16> {
+    match name.trim().to_lowercase().as_str() {
+        "ctrl" | "control" | "ктрл" => Some(VK_CONTROL),
+        "alt" | "альт" => Some(VK_MENU),
+        "shift" | "шифт" => Some(VK_SHIFT),
+        "win" | "вин" => Some(VK_LWIN),
+        _ => None,
+    }
+}
+
+/// Maps the main key of a chord to its virtual key: named keys and F1-F24 first, then single characters.
+unsafe fn chord_key_vk(key: &str) -> Option<u16> {
+    let key = key.trim().to_lowercase();
+    let named = match key.as_str() {
+        "enter" | "ввод" => Some(VK_RETURN),
+        "esc" | "escape" => Some(VK_ESCAPE),
+        "tab" | "таб" => Some(VK_TAB),
+        "space" | "пробел" => Some(VK_SPACE),
+        "delete" | "del" => Some(0x2E),
+        "backspace" => Some(0x08),
+        "home" => Some(0x24),
+        "end" => Some(0x23),
+        "left" | "влево" => Some(0x25),
+        "up" | "вверх" => Some(0x26),
+        "right" | "вправо" => Some(0x27),
+        "down" | "вниз" => Some(0x28),
+        _ => None,
+    };
+    if named.is_some() {
+        return named;
+    }
+    if let Some(number) = key.strip_prefix('f').and_then(|n| n.parse::<u16>().ok()) {
+        // VK_F1 is 0x70 and the function keys are contiguous up to VK_F24.
+        return (1..=24).contains(&number).then(|| 0x70 + number - 1);
+    }
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii() => {
+            let scan = windows::Win32::UI::Input::KeyboardAndMouse::VkKeyScanA(c as i8) as u16;
+            // The low byte is the virtual key; 0xFF means the character has no key on this layout.
+            (scan & 0xFF != 0xFF).then(|| scan & 0xFF)
         }
-        1 // continue enumeration
+        _ => None,
     }
-    
-    // Enumerate all top-level windows.
-    EnumWindows(Some(enum_proc), LPARAM(&mut windows_vec as *mut _ as isize));
-    if windows_vec.is_empty() {
-        return false;
+}
+
+/// Holds `modifiers` down, taps `key`, and releases the modifiers in reverse order.
+/// The modifiers are released even if the key cannot be converted or sent, so none stay stuck.
+unsafe fn send_key_chord(modifiers: &[u16], key: &str) -> ExecutionResult {
+    if GetForegroundWindow().0 == 0 {
+        return ExecutionResult::Failure(format!("Foreground window not found for '{}'", key));
     }
-    
-    // Determine grid layout dimensions.
-    let count = windows_vec.len();
-    let cols = (count as f64).sqrt().ceil() as usize;
-    let rows = (count + cols - 1) / cols;
-    
-    // Get screen dimensions.
-    let screen_dc = GetDC(HWND(0));
-    let screen_width = GetDeviceCaps(screen_dc, HORZRES);
-    let screen_height = GetDeviceCaps(screen_dc, VERTRES);
-    ReleaseDC(HWND(0), screen_dc);
-    
-    let window_width = screen_width / cols as i32;
-    let window_height = screen_height / rows as i32;
-    
-    // Arrange each window in the grid.
-    for (index, hwnd) in windows_vec.iter().enumerate() {
-        let col = index % cols;
-        let row = index / cols;
-        let x = col as i32 * window_width;
-        let y = row as i32 * window_height;
-        // Move each window to its grid position.
-        SetWindowPos(*hwnd, HWND(0), x, y, window_width, window_height, SWP_NOZORDER | SWP_NOACTIVATE);
-    }
-    
-    true
+    let presses: Vec<INPUT> = modifiers.iter().map(|vk| keyboard_input(*vk, false)).collect();
+    let releases: Vec<INPUT> = modifiers.iter().rev().map(|vk| keyboard_input(*vk, true)).collect();
+    let pressed = SendInput(&presses, mem::size_of::<INPUT>() as i32) as usize == presses.len();
+
+    let result = if !pressed {
+        ExecutionResult::Failure("Error pressing chord modifiers".to_string())
+    } else {
+        match chord_key_vk(key) {
+            Some(vk) if send_key_taps(vk, 1, &[]) => ExecutionResult::Success(format!("Key chord with '{}' sent", key)),
+            Some(_) => ExecutionResult::Failure(format!("Error sending key press for '{}'", key)),
+            None => ExecutionResult::Failure(format!("Failed to convert '{}' to a virtual key", key)),
+        }
+    };
+
+    if !releases.is_empty() {
+        SendInput(&releases, mem::size_of::<INPUT>() as i32);
+    }
+    result
 }
 
-/// Releases the device context.
-unsafe fn ReleaseDC(hWnd: HWND, hDC: windows::Win32::Graphics::Gdi::HDC) {
-    windows::Win32::Graphics::Gdi::ReleaseDC(hWnd, hDC);
+/// Offsets (ms after the initial key-down) at which repeat key-downs are sent while a key is held.
+fn hold_repeat_offsets(duration_ms: u64) -> Vec<u64> {
+    (HOLD_REPEAT_DELAY_MS..duration_ms).step_by(HOLD_REPEAT_INTERVAL_MS as usize).collect()
+}
+
+/// Presses `vk`, keeps it down for `duration_ms` with typematic repeats, then releases it.
+/// The key-up is always sent, even if a repeat fails, so the key does not stay stuck.
+unsafe fn hold_key(vk: u16, duration_ms: u64, key_name: &str) -> ExecutionResult {
+    let size = mem::size_of::<INPUT>() as i32;
+    if let Err(e) = ensure_focus() {
+        return ExecutionResult::Failure(e);
+    }
+    if SendInput(&[keyboard_input(vk, false)], size) != 1 {
+        return ExecutionResult::Failure(format!("Error pressing key '{}'", key_name));
+    }
+    let mut elapsed = 0;
+    for offset in hold_repeat_offsets(duration_ms) {
+        thread::sleep(Duration::from_millis(offset - elapsed));
+        elapsed = offset;
+        // Focus moved while holding: stop repeating into the wrong window, but still release the key.
+        if let Err(e) = ensure_focus() {
+            SendInput(&[keyboard_input(vk, true)], size);
+            return ExecutionResult::Failure(e);
+        }
+        SendInput(&[keyboard_input(vk, false)], size);
+    }
+    thread::sleep(Duration::from_millis(duration_ms.saturating_sub(elapsed)));
+    if SendInput(&[keyboard_input(vk, true)], size) != 1 {
+        return ExecutionResult::Failure(format!("Error releasing key '{}'", key_name));
+    }
+    ExecutionResult::Success(format!("Key '{}' held for {} ms", key_name, duration_ms))
+}
+
+/// Presses a single key on whatever control has focus in the foreground window.
+unsafe fn press_on_focused(vk: u16, key_name: &str) -> ExecutionResult {
+    if GetForegroundWindow().0 == 0 {
+        return ExecutionResult::Failure(format!("Foreground window not found for '{}'", key_name));
+    }
+    if send_key_taps(vk, 1, &[]) {
+        ExecutionResult::Success(format!("Key '{}' pressed on focused control", key_name))
+    } else {
+        ExecutionResult::Failure(format!("Error sending key press for '{}'", key_name))
+    }
+}
+
+/// Maps a scroll direction to its `WM_VSCROLL` code, and whether `amount` repeats it.
+fn scroll_code(direction: &str) -> Option<(u32, bool)> {
+    match direction.to_lowercase().as_str() {
+        "up" => Some((SB_LINEUP.0 as u32, true)),
+        "down" => Some((SB_LINEDOWN.0 as u32, true)),
+        "page_up" => Some((SB_PAGEUP.0 as u32, true)),
+        "page_down" => Some((SB_PAGEDOWN.0 as u32, true)),
+        "top" => Some((SB_TOP.0 as u32, false)),
+        "bottom" => Some((SB_BOTTOM.0 as u32, false)),
+        _ => None,
+    }
+}
+
+/// Maps a system menu command word to its `WM_SYSCOMMAND` code.
+fn system_command_code(command: &str) -> Option<u32> {
+    match command.to_lowercase().as_str() {
+        "restore" => Some(SC_RESTORE),
+        "minimize" => Some(SC_MINIMIZE),
+        "maximize" => Some(SC_MAXIMIZE),
+        "close" => Some(SC_CLOSE),
+        "move" => Some(SC_MOVE),
+        "size" => Some(SC_SIZE),
+        _ => None,
+    }
+}
+
+/// Converts a path, URL or program name from user input for `ShellExecuteA`.
+/// An interior NUL is reported as an error rather than panicking the worker.
+fn shell_target(target: &str) -> Result<CString, String> {
+    CString::new(target).map_err(|_| format!("Недопустимый символ NUL в '{}'", target.escape_default()))
+}
+
+/// Splits a `COLORREF` (0x00BBGGRR) into the `{r, g, b, hex}` a pixel read returns.
+fn color_details(colorref: u32) -> serde_json::Value {
+    let (r, g, b) = (colorref & 0xFF, (colorref >> 8) & 0xFF, (colorref >> 16) & 0xFF);
+    serde_json::json!({ "r": r, "g": g, "b": b, "hex": format!("#{:02X}{:02X}{:02X}", r, g, b) })
+}
+
+/// Restores `hwnd` if minimized and makes it the foreground window.
+/// Windows only lets the thread owning the foreground window change it, so our input
+/// queue is attached to that thread for the duration of the call.
+/// Returns true if `hwnd` is the foreground window afterwards.
+unsafe fn activate_window(hwnd: HWND) -> bool {
+    if IsIconic(hwnd).as_bool() {
+        ShowWindow(hwnd, SW_RESTORE);
+    }
+    let foreground_thread = GetWindowThreadProcessId(GetForegroundWindow(), None);
+    let current_thread = GetCurrentThreadId();
+    let attached = foreground_thread != 0
+        && foreground_thread != current_thread
+        && AttachThreadInput(current_thread, foreground_thread, true).as_bool();
+    SetForegroundWindow(hwnd);
+    BringWindowToTop(hwnd);
+    if attached {
+        AttachThreadInput(current_thread, foreground_thread, false);
+    }
+    GetForegroundWindow() == hwnd
+}
+
+/// Turns a raw handle from `/windows` into an `HWND`, or `None` if it no longer names a window.
+unsafe fn window_from_handle(handle: isize) -> Option<HWND> {
+    let hwnd = HWND(handle);
+    if IsWindow(hwnd).as_bool() {
+        Some(hwnd)
+    } else {
+        None
+    }
+}
+
+/// Takes the window hidden under `label` (ignoring case), or the most recently hidden one when
+/// `label` is empty. The handle is kept rather than the title, which may change while hidden;
+/// windows closed in the meantime are forgotten on the way.
+unsafe fn take_hidden_window(label: &str) -> Option<HWND> {
+    let mut hidden = HIDDEN_WINDOWS.lock().unwrap();
+    hidden.retain(|(_, handle)| IsWindow(HWND(*handle)).as_bool());
+    let index = if label.is_empty() {
+        hidden.len().checked_sub(1)?
+    } else {
+        let label = label.to_lowercase();
+        hidden.iter().rposition(|(hidden_label, _)| hidden_label.to_lowercase() == label)?
+    };
+    Some(HWND(hidden.remove(index).1))
+}
+
+fn stale_handle_failure(handle: isize) -> ExecutionResult {
+    ExecutionResult::Failure(format!("Окно с дескриптором {:#x} больше не существует", handle))
+}
+
+/// Returns the control that has keyboard focus in the foreground thread.
+unsafe fn focused_control() -> HWND {
+    let mut info: GUITHREADINFO = mem::zeroed();
+    info.cbSize = mem::size_of::<GUITHREADINFO>() as u32;
+    if GetGUIThreadInfo(0, &mut info).is_err() {
+        return HWND(0);
+    }
+    info.hwndFocus
+}
+
+/// Slices the `start..end` selection (UTF-16 indices, as reported by `EM_GETSEL`) out of the control text.
+/// Out-of-range or reversed bounds are clamped instead of failing.
+fn slice_selection(text: &[u16], start: usize, end: usize) -> String {
+    let (start, end) = (start.min(end).min(text.len()), start.max(end).min(text.len()));
+    String::from_utf16_lossy(&text[start..end])
+}
+
+/// Finds the tab whose caption matches `name`: an exact (case-insensitive) match wins,
+/// otherwise the first caption containing `name`. Mnemonic ampersands are ignored.
+fn resolve_tab_index(captions: &[String], name: &str) -> Option<usize> {
+    let name = name.trim().to_lowercase();
+    if name.is_empty() {
+        return None;
+    }
+    let captions: Vec<String> = captions.iter().map(|c| c.replace('&', "").trim().to_lowercase()).collect();
+    captions.iter().position(|c| *c == name)
+        .or_else(|| captions.iter().position(|c| c.contains(&name)))
+}
+
+/// `TCITEMW` as laid out by comctl32.
+#[repr(C)]
+struct TabItem {
+    mask: u32,
+    state: u32,
+    state_mask: u32,
+    text: *mut u16,
+    text_max: i32,
+    image: i32,
+    lparam: isize,
+}
+
+/// Returns true if `process` has the bitness of this process. `TCITEMW` and `LVITEMW` hold
+/// pointers, so their layout differs between 32- and 64-bit processes.
+unsafe fn same_bitness(process: HANDLE) -> bool {
+    let (mut target, mut own) = (BOOL(0), BOOL(0));
+    IsWow64Process(process, &mut target).as_bool()
+        && IsWow64Process(GetCurrentProcess(), &mut own).as_bool()
+        && target == own
+}
+
+/// Reads every tab caption of a `SysTabControl32`. The control lives in another process,
+/// so `TCM_GETITEMW` needs its item and text buffer allocated in that process's memory.
+unsafe fn tab_captions(hwnd: HWND) -> Result<Vec<String>, String> {
+    let count = SendMessageW(hwnd, TCM_GETITEMCOUNT, WPARAM(0), LPARAM(0)).0;
+    if count <= 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut process_id: u32 = 0;
+    GetWindowThreadProcessId(hwnd, Some(&mut process_id));
+    let access = PROCESS_VM_OPERATION | PROCESS_VM_READ | PROCESS_VM_WRITE | PROCESS_QUERY_LIMITED_INFORMATION;
+    let process = OpenProcess(access, false, process_id);
+    if process.0 == 0 {
+        return Err(format!("OpenProcess failed for process {}", process_id));
+    }
+    if !same_bitness(process) {
+        CloseHandle(process);
+        return Err(format!("Cannot read tabs of process {}: it is not of this process's bitness (32/64-bit)", process_id));
+    }
+
+    let item_size = std::mem::size_of::<TabItem>();
+    let buffer_size = item_size + TAB_TEXT_MAX * std::mem::size_of::<u16>();
+    let remote = VirtualAllocEx(process, None, buffer_size, MEM_COMMIT | MEM_RESERVE, PAGE_READWRITE);
+    if remote.is_null() {
+        CloseHandle(process);
+        return Err("VirtualAllocEx failed".to_string());
+    }
+    let remote_text = (remote as *mut u8).add(item_size) as *mut u16;
+
+    let mut captions = Vec::with_capacity(count as usize);
+    let mut result = Ok(());
+    for index in 0..count as usize {
+        let item = TabItem {
+            mask: TCIF_TEXT,
+            state: 0,
+            state_mask: 0,
+            text: remote_text,
+            text_max: TAB_TEXT_MAX as i32,
+            image: 0,
+            lparam: 0,
+        };
+        if !WriteProcessMemory(process, remote, &item as *const TabItem as *const _, item_size, None).as_bool() {
+            result = Err(format!("WriteProcessMemory failed for tab {}", index));
+            break;
+        }
+        if SendMessageW(hwnd, TCM_GETITEMW, WPARAM(index), LPARAM(remote as isize)).0 == 0 {
+            result = Err(format!("TCM_GETITEM failed for tab {}", index));
+            break;
+        }
+        // The control may point pszText at its own copy of the caption instead of filling our buffer.
+        let mut filled = std::mem::MaybeUninit::<TabItem>::uninit();
+        if !ReadProcessMemory(process, remote as *const _, filled.as_mut_ptr() as *mut _, item_size, None).as_bool() {
+            result = Err(format!("ReadProcessMemory failed for tab {}", index));
+            break;
+        }
+        let caption = filled.assume_init().text;
+        let mut text = [0u16; TAB_TEXT_MAX];
+        if !caption.is_null()
+            && !ReadProcessMemory(process, caption as *const _, text.as_mut_ptr() as *mut _, TAB_TEXT_MAX * 2, None).as_bool()
+        {
+            result = Err(format!("ReadProcessMemory failed for tab {}", index));
+            break;
+        }
+        let len = text.iter().position(|&c| c == 0).unwrap_or(TAB_TEXT_MAX);
+        captions.push(String::from_utf16_lossy(&text[..len]));
+    }
+
+    VirtualFreeEx(process, remote, 0, MEM_RELEASE);
+    CloseHandle(process);
+    result.map(|_| captions)
+}
+
+/// `LVITEMW` as laid out by comctl32.
+#[repr(C)]
+struct ListViewItem {
+    mask: u32,
+    item: i32,
+    sub_item: i32,
+    state: u32,
+    state_mask: u32,
+    text: *mut u16,
+    text_max: i32,
+    image: i32,
+    lparam: isize,
+    indent: i32,
+    group_id: i32,
+    columns: u32,
+    column_ids: *mut u32,
+    column_formats: *mut i32,
+    group: i32,
+}
+
+/// Reads the first-column text of every row in a `SysListView32`. As with [`tab_captions`],
+/// `LVM_GETITEMTEXTW` needs its item and text buffer in the control's process.
+unsafe fn listview_items(hwnd: HWND) -> Result<Vec<String>, String> {
+    let count = SendMessageW(hwnd, LVM_GETITEMCOUNT, WPARAM(0), LPARAM(0)).0;
+    if count <= 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut process_id: u32 = 0;
+    GetWindowThreadProcessId(hwnd, Some(&mut process_id));
+    let access = PROCESS_VM_OPERATION | PROCESS_VM_READ | PROCESS_VM_WRITE | PROCESS_QUERY_LIMITED_INFORMATION;
+    let process = OpenProcess(access, false, process_id);
+    if process.0 == 0 {
+        return Err(format!("OpenProcess failed for process {}", process_id));
+    }
+    if !same_bitness(process) {
+        CloseHandle(process);
+        return Err(format!("Cannot read list items of process {}: it is not of this process's bitness (32/64-bit)", process_id));
+    }
+
+    let item_size = std::mem::size_of::<ListViewItem>();
+    let buffer_size = item_size + LIST_TEXT_MAX * std::mem::size_of::<u16>();
+    let remote = VirtualAllocEx(process, None, buffer_size, MEM_COMMIT | MEM_RESERVE, PAGE_READWRITE);
+    if remote.is_null() {
+        CloseHandle(process);
+        return Err("VirtualAllocEx failed".to_string());
+    }
+    let remote_text = (remote as *mut u8).add(item_size) as *mut u16;
+
+    let mut rows = Vec::with_capacity(count as usize);
+    let mut result = Ok(());
+    for index in 0..count as usize {
+        let item = ListViewItem {
+            mask: 0,
+            item: index as i32,
+            sub_item: 0,
+            state: 0,
+            state_mask: 0,
+            text: remote_text,
+            text_max: LIST_TEXT_MAX as i32,
+            image: 0,
+            lparam: 0,
+            indent: 0,
+            group_id: 0,
+            columns: 0,
+            column_ids: ptr::null_mut(),
+            column_formats: ptr::null_mut(),
+            group: 0,
+        };
+        if !WriteProcessMemory(process, remote, &item as *const ListViewItem as *const _, item_size, None).as_bool() {
+            result = Err(format!("WriteProcessMemory failed for row {}", index));
+            break;
+        }
+        // Returns the text length; an empty cell is a valid row, not a failure.
+        let len = SendMessageW(hwnd, LVM_GETITEMTEXTW, WPARAM(index), LPARAM(remote as isize)).0.clamp(0, LIST_TEXT_MAX as isize) as usize;
+        let mut text = [0u16; LIST_TEXT_MAX];
+        if len > 0 && !ReadProcessMemory(process, remote_text as *const _, text.as_mut_ptr() as *mut _, len * 2, None).as_bool() {
+            result = Err(format!("ReadProcessMemory failed for row {}", index));
+            break;
+        }
+        rows.push(String::from_utf16_lossy(&text[..len]));
+    }
+
+    VirtualFreeEx(process, remote, 0, MEM_RELEASE);
+    CloseHandle(process);
+    result.map(|_| rows)
+}
+
+/// How a double-click is delivered to a control.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DoubleClickMethod {
+    /// Two `BM_CLICK` messages; enough for push buttons.
+    ButtonMessage,
+    /// A real mouse double-click at the control's center via `SendInput`.
+    MouseInput,
+}
+
+/// Picks the double-click method: buttons get `BM_CLICK` unless the caller asks
+/// for real mouse input; every other class (list views, tree views, list boxes)
+/// only reacts to `WM_LBUTTONDBLCLK`, which only real input produces.
+fn double_click_method(class_name: &str, use_mouse: bool) -> DoubleClickMethod {
+    if !use_mouse && class_name.eq_ignore_ascii_case("Button") {
+        DoubleClickMethod::ButtonMessage
+    } else {
+        DoubleClickMethod::MouseInput
+    }
+}
+
+/// Returns the window class name of `hwnd`, or an empty string on failure.
+unsafe fn window_class_name(hwnd: HWND) -> String {
+    let mut buffer = [0u8; 256];
+    let len = GetClassNameA(hwnd, &mut buffer);
+    String::from_utf8_lossy(&buffer[..len.max(0) as usize]).into_owned()
+}
+
+/// Moves the cursor to the center of `hwnd` and sends two clicks, the second one
+/// well within the system double-click time so it is recognised as a double-click.
+unsafe fn mouse_double_click_center(hwnd: HWND) -> bool {
+    let mut rect = RECT::default();
+    if !GetWindowRect(hwnd, &mut rect).as_bool() {
+        return false;
+    }
+    let (x, y) = ((rect.left + rect.right) / 2, (rect.top + rect.bottom) / 2);
+    if !SetCursorPos(x, y).as_bool() {
+        return false;
+    }
+    let click = [mouse_input(MOUSEEVENTF_LEFTDOWN, 0), mouse_input(MOUSEEVENTF_LEFTUP, 0)];
+    if SendInput(&click, mem::size_of::<INPUT>() as i32) as usize != click.len() {
+        return false;
+    }
+    thread::sleep(Duration::from_millis((GetDoubleClickTime() / 5).min(50) as u64));
+    SendInput(&click, mem::size_of::<INPUT>() as i32) as usize == click.len()
+}
+
+/// Sets how long actions wait for their target window to appear.
+pub fn set_action_timeout(timeout: Duration) {
+    *ACTION_TIMEOUT.lock().unwrap() = timeout;
+}
+
+fn action_timeout() -> Duration {
+    *ACTION_TIMEOUT.lock().unwrap()
+}
+
+/// Sets the default pause between typed characters; a `type_text` command can override it.
+pub fn set_keystroke_delay(delay: Duration) {
+    *KEYSTROKE_DELAY.lock().unwrap() = delay;
+}
+
+fn keystroke_delay() -> Duration {
+    *KEYSTROKE_DELAY.lock().unwrap()
+}
+
+/// Makes keyboard and mouse input fail with "focus changed" when the window that had focus at
+/// task start is no longer in the foreground and cannot be re-activated.
+pub fn set_require_focus_lock(enabled: bool) {
+    REQUIRE_FOCUS_LOCK.store(enabled, Ordering::Relaxed);
+}
+
+/// Refuses file and process operations (deleting, creating, copying, pasting, launching) while enabled.
+pub fn set_safe_mode(enabled: bool) {
+    SAFE_MODE.store(enabled, Ordering::Relaxed);
+}
+
+/// The foreground window, as the input target of a command that just arrived, when the focus lock
+/// is on. Captured on arrival, so a focus change while the task waits in the queue is caught too.
+pub fn capture_focus_target() -> Option<isize> {
+    if !REQUIRE_FOCUS_LOCK.load(Ordering::Relaxed) {
+        return None;
+    }
+    Some(unsafe { GetForegroundWindow() }.0).filter(|&hwnd| hwnd != 0)
+}
+
+/// Makes `target`, from [`capture_focus_target`], the input target of the task on this thread.
+pub fn begin_focus_lock(target: Option<isize>) {
+    FOCUS_TARGET.with(|cell| cell.set(target.map(HWND)));
+}
+
+/// Forgets the input target recorded by [`begin_focus_lock`].
+pub fn end_focus_lock() {
+    FOCUS_TARGET.with(|cell| cell.set(None));
+}
+
+/// Checks that the task's input target still has focus, re-activating it once if it lost it.
+/// Always passes when no target was recorded.
+unsafe fn ensure_focus() -> Result<(), String> {
+    let target = match FOCUS_TARGET.with(Cell::get) {
+        Some(target) => target,
+        None => return Ok(()),
+    };
+    if GetForegroundWindow() == target {
+        return Ok(());
+    }
+    if IsWindow(target).as_bool() {
+        SetForegroundWindow(target);
+        thread::sleep(Duration::from_millis(FOCUS_RESTORE_MS));
+        if GetForegroundWindow() == target {
+            return Ok(());
+        }
+    }
+    Err(format!("focus changed: window '{}' is no longer active", window_title_w(target)))
+}
+
+/// Calls `probe` every `WINDOW_POLL_INTERVAL_MS` until it returns a value or `timeout` elapses.
+/// A zero timeout makes a single attempt.
+fn poll_until<T>(timeout: Duration, mut probe: impl FnMut() -> Option<T>) -> Option<T> {
+    let start = Instant::now();
+    loop {
+        if let Some(found) = probe() {
+            return Some(found);
+        }
+        if start.elapsed() >= timeout {
+            return None;
+        }
+        thread::sleep(Duration::from_millis(WINDOW_POLL_INTERVAL_MS));
+    }
+}
+
+/// Retries `find_window` until the window exists or `timeout` elapses.
+unsafe fn wait_for_window(class_name: &str, window_title: &str, timeout: Duration) -> Option<HWND> {
+    poll_until(timeout, || {
+        let hwnd = find_window(class_name, window_title);
+        if hwnd.0 != 0 { Some(hwnd) } else { None }
+    })
+}
+
+/// Returns the first of `labels` whose window exists, checked in order on each poll.
+fn first_present<'a>(labels: &'a [String], exists: impl Fn(&str) -> bool) -> Option<&'a str> {
+    labels.iter().map(String::as_str).find(|label| exists(label))
+}
+
+/// Waits until any of the windows titled `labels` appears and returns its title.
+unsafe fn wait_for_any_window(labels: &[String], timeout: Duration) -> Option<String> {
+    poll_until(timeout, || {
+        first_present(labels, |label| find_window("", label).0 != 0).map(str::to_string)
+    })
+}
+
+/// Title of `hwnd`, read as UTF-16 so non-ASCII titles survive.
+unsafe fn window_title_w(hwnd: HWND) -> String {
+    let len = GetWindowTextLengthW(hwnd);
+    if len <= 0 {
+        return String::new();
+    }
+    let mut buffer = vec![0u16; len as usize + 1];
+    let copied = GetWindowTextW(hwnd, &mut buffer).max(0) as usize;
+    String::from_utf16_lossy(&buffer[..copied])
+}
+
+/// Reads a control's text with `WM_GETTEXT`, which, unlike `GetWindowText`, also works for
+/// controls of other processes.
+unsafe fn control_text(hwnd: HWND) -> String {
+    let len = SendMessageW(hwnd, WM_GETTEXTLENGTH, WPARAM(0), LPARAM(0)).0 as usize;
+    let mut buffer: Vec<u16> = vec![0; len + 1];
+    let copied = SendMessageW(hwnd, WM_GETTEXT, WPARAM(len + 1), LPARAM(buffer.as_mut_ptr() as isize)).0 as usize;
+    String::from_utf16_lossy(&buffer[..copied.min(len)])
+}
+
+/// Reads the check state of a checkbox with `BM_GETCHECK`.
+unsafe fn checkbox_state(hwnd: HWND) -> CheckState {
+    let raw_state = SendMessageA(hwnd, BM_GETCHECK, WPARAM(0), LPARAM(0)).0;
+    if raw_state == BST_CHECKED as isize {
+        CheckState::Checked
+    } else if raw_state == BST_INDETERMINATE as isize {
+        CheckState::Indeterminate
+    } else {
+        CheckState::Unchecked
+    }
+}
+
+/// Polls until the foreground window's title contains `expected` (ignoring case) and returns that title.
+unsafe fn wait_for_title(expected: &str, timeout: Duration) -> Option<String> {
+    let expected = expected.to_lowercase();
+    poll_until(timeout, || {
+        let title = window_title_w(GetForegroundWindow());
+        if title.to_lowercase().contains(&expected) { Some(title) } else { None }
+    })
+}
+
+/// Failure for a window that never appeared: the timeout message when the action waited,
+/// otherwise the action's own "not found" message.
+fn window_wait_failure(label: &str, not_found: String) -> ExecutionResult {
+    if action_timeout().is_zero() {
+        ExecutionResult::Failure(not_found)
+    } else {
+        ExecutionResult::Failure(format!("timed out waiting for '{}'", label))
+    }
+}
+
+/// Helper function to find a window by class name and title.
+/// If the class name is empty, the search is performed only by title.
+/// A name with an interior NUL cannot match any window, so it yields a null `HWND`.
+unsafe fn find_window(class_name: &str, window_title: &str) -> HWND {
+    let (Ok(class), Ok(title)) = (CString::new(class_name), CString::new(window_title)) else {
+        log_debug(&format!("Недопустимый символ NUL в '{}' / '{}'", class_name.escape_default(), window_title.escape_default()));
+        return HWND(0);
+    };
+    let class = if !class_name.is_empty() { Some(&class) } else { None };
+    let hwnd = FindWindowA(class, Some(&title));
+    if hwnd.0 != 0 || !window_title.contains('/') {
+        return hwnd;
+    }
+    // No window has this title, so it may be a control path.
+    resolve_control_path(window_title).unwrap_or_else(|e| {
+        log_debug(&e);
+        HWND(0)
+    })
+}
+
+/// Resolves a `/`-delimited control path such as "MainWindow/ToolbarPanel/SaveButton".
+/// The first segment names a top-level window and each following one a direct child of the
+/// previous match. A segment matches a window's text or class name, ignoring case, and a
+/// `[n]` suffix picks the n-th match counting from 0. The error names the segment that failed.
+unsafe fn resolve_control_path(path: &str) -> Result<HWND, String> {
+    let segments: Vec<&str> = path.split('/').map(str::trim).collect();
+    let mut current: Option<HWND> = None;
+    for (depth, segment) in segments.iter().enumerate() {
+        let (name, index) = split_segment_index(segment);
+        match matching_windows(current, name).get(index) {
+            Some(hwnd) => current = Some(*hwnd),
+            None => {
+                let parent = if depth == 0 {
+                    "the desktop".to_string()
+                } else {
+                    format!("'{}'", segments[..depth].join("/"))
+                };
+                return Err(format!("Control path '{}': segment {} '{}' not found under {}", path, depth + 1, segment, parent));
+            }
+        }
+    }
+    current.ok_or_else(|| format!("Control path '{}' is empty", path))
+}
+
+/// Splits a trailing `[n]` index off a path segment; segments without one select the first match.
+fn split_segment_index(segment: &str) -> (&str, usize) {
+    if let Some(open) = segment.rfind('[') {
+        if let Some(index) = segment[open + 1..].strip_suffix(']').and_then(|n| n.trim().parse().ok()) {
+            return (segment[..open].trim(), index);
+        }
+    }
+    (segment, 0)
+}
+
+/// Lists the windows whose text or class name is `name`, ignoring case and mnemonic ampersands:
+/// top-level windows when `parent` is `None`, otherwise the direct children of `parent`, in Z order.
+unsafe fn matching_windows(parent: Option<HWND>, name: &str) -> Vec<HWND> {
+    extern "system" fn enum_proc(hwnd: HWND, lparam: LPARAM) -> i32 {
+        unsafe {
+            let windows_ptr = lparam.0 as *mut Vec<HWND>;
+            if !windows_ptr.is_null() {
+                (*windows_ptr).push(hwnd);
+            }
+        }
+        1 // continue enumeration
+    }
+    let name = name.to_lowercase();
+    if name.is_empty() {
+        return Vec::new();
+    }
+    let mut windows: Vec<HWND> = Vec::new();
+    let lparam = LPARAM(&mut windows as *mut Vec<HWND> as isize);
+    if let Some(parent) = parent {
+        EnumChildWindows(parent, Some(enum_proc), lparam);
+    } else {
+        EnumWindows(Some(enum_proc), lparam);
+    }
+    windows.retain(|hwnd| {
+        if let Some(parent) = parent {
+            if GetParent(*hwnd).0 != parent.0 {
+                return false; // A grandchild; it is matched by a later segment
+            }
+        }
+        // Controls of other processes only answer WM_GETTEXT.
+        let text = if parent.is_some() { control_text(*hwnd) } else { window_title_w(*hwnd) };
+        text.replace('&', "").to_lowercase() == name || window_class_name(*hwnd).to_lowercase() == name
+    });
+    windows
+}
+
+/// File name of the executable behind process `pid` (e.g. `notepad.exe`), or `None` if it cannot be opened.
+unsafe fn process_image_name(pid: u32) -> Option<String> {
+    let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid);
+    if process.0 == 0 {
+        return None;
+    }
+    let mut buffer = vec![0u16; 1024];
+    let mut size = buffer.len() as u32;
+    let ok = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut size).as_bool();
+    CloseHandle(process);
+    if !ok {
+        return None;
+    }
+    let path = String::from_utf16_lossy(&buffer[..size as usize]);
+    Path::new(&path).file_name().map(|name| name.to_string_lossy().into_owned())
+}
+
+/// Returns true if `image` is the executable `process`, compared case-insensitively with or without `.exe`.
+fn is_process_image(image: &str, process: &str) -> bool {
+    let image = image.to_lowercase();
+    let process = process.trim().to_lowercase();
+    image == process || image.strip_suffix(".exe") == Some(process.as_str())
+}
+
+/// Visible top-level windows owned by the executable `process`, in Z order.
+unsafe fn process_windows(process: &str) -> Vec<HWND> {
+    extern "system" fn enum_proc(hwnd: HWND, lparam: LPARAM) -> i32 {
+        unsafe {
+            if IsWindowVisible(hwnd).as_bool() {
+                let windows_ptr = lparam.0 as *mut Vec<HWND>;
+                if !windows_ptr.is_null() {
+                    (*windows_ptr).push(hwnd);
+                }
+            }
+        }
+        1 // continue enumeration
+    }
+    let mut windows: Vec<HWND> = Vec::new();
+    EnumWindows(Some(enum_proc), LPARAM(&mut windows as *mut Vec<HWND> as isize));
+    // Several windows usually share a process, so each PID is resolved once.
+    let mut images: HashMap<u32, Option<String>> = HashMap::new();
+    windows.retain(|hwnd| {
+        let mut pid: u32 = 0;
+        GetWindowThreadProcessId(*hwnd, Some(&mut pid));
+        images
+            .entry(pid)
+            .or_insert_with(|| process_image_name(pid))
+            .as_deref()
+            .is_some_and(|image| is_process_image(image, process))
+    });
+    windows
+}
+
+/// Names a window for messages: its title, qualified by the process it was looked up in.
+fn window_name(label: &str, process: Option<&str>) -> String {
+    match process {
+        Some(process) if label.is_empty() => process.to_string(),
+        Some(process) => format!("{} ({})", label, process),
+        None => label.to_string(),
+    }
+}
+
+/// Finds a window of `process` titled `label` (case-insensitive), or its first window for an empty label.
+/// Without a process this is a plain [`find_window`] by title.
+unsafe fn find_process_window(label: &str, process: Option<&str>) -> HWND {
+    let process = match process {
+        Some(process) if !process.trim().is_empty() => process,
+        _ => return find_window("", label),
+    };
+    let label = label.to_lowercase();
+    process_windows(process)
+        .into_iter()
+        .find(|hwnd| label.is_empty() || window_title_w(*hwnd).to_lowercase() == label)
+        .unwrap_or(HWND(0))
+}
+
+/// What a screenshot captures.
+#[derive(Debug, Clone, Copy)]
+enum CaptureSource {
+    /// The whole primary screen.
+    Screen,
+    /// A rectangle of the screen, in screen coordinates.
+    Region(Rect),
+    /// The client area of a window, rendered even when it is covered by other windows.
+    Window(HWND),
+}
+
+/// Default screenshot file name, timestamped so earlier screenshots are not overwritten.
+fn default_screenshot_path() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!("screenshot_{}.png", millis)
+}
+
+/// Takes a screenshot of the given source and saves it as a PNG file.
+/// This function uses the image crate, so ensure it is added as a dependency in Cargo.toml.
+unsafe fn take_screenshot_png(file_path: &str, source: CaptureSource) -> Result<String, String> {
+    let (width, height, pixel_data) = capture_rgba(source)?;
+    // Save the PNG using the image crate.
+    match image::save_buffer(file_path, &pixel_data, width, height, image::ColorType::Rgba8) {
+        Ok(_) => Ok(file_path.to_string()),
+        Err(e) => Err(format!("Error saving PNG: {}", e)),
+    }
+}
+
+/// Captures the given source into memory as top-down RGBA pixels, returning width, height and pixels.
+unsafe fn capture_rgba(source: CaptureSource) -> Result<(u32, u32, Vec<u8>), String> {
+    // Get the device context of the entire screen.
+    let hdc_screen = GetDC(HWND(0));
+    if hdc_screen.0 == 0 {
+        return Err("Failed to obtain screen DC".to_string());
+    }
+    let (left, top, width, height) = match source {
+        CaptureSource::Screen => (0, 0, GetDeviceCaps(hdc_screen, HORZRES), GetDeviceCaps(hdc_screen, VERTRES)),
+        CaptureSource::Region(rect) => (rect.x, rect.y, rect.width as i32, rect.height as i32),
+        CaptureSource::Window(hwnd) => {
+            let mut client = RECT::default();
+            if !GetClientRect(hwnd, &mut client).as_bool() {
+                ReleaseDC(HWND(0), hdc_screen);
+                return Err("GetClientRect failed".to_string());
+            }
+            (0, 0, client.right - client.left, client.bottom - client.top)
+        }
+    };
+    if width <= 0 || height <= 0 {
+        ReleaseDC(HWND(0), hdc_screen);
+        return Err(format!("Nothing to capture: {}x{}", width, height));
+    }
+    let hdc_mem = CreateCompatibleDC(hdc_screen);
+    if hdc_mem.0 == 0 {
+        return Err("Failed to create compatible DC".to_string());
+    }
+    // Create a 32-bit bitmap for the screenshot.
+    let hbitmap = CreateCompatibleBitmap(hdc_screen, width, height);
+    if hbitmap.0 == 0 {
+        return Err("Failed to create compatible bitmap".to_string());
+    }
+    let old_obj = SelectObject(hdc_mem, hbitmap);
+    if old_obj.0 == 0 {
+        return Err("Failed to select bitmap into DC".to_string());
+    }
+    match source {
+        CaptureSource::Window(hwnd) => {
+            // PrintWindow asks the window to render itself, so occluded windows come out intact.
+            let flags = PRINT_WINDOW_FLAGS(PW_CLIENTONLY.0 | PW_RENDERFULLCONTENT);
+            if !PrintWindow(hwnd, hdc_mem, flags).as_bool() {
+                return Err("PrintWindow failed".to_string());
+            }
+        }
+        _ => {
+            if !BitBlt(hdc_mem, 0, 0, width, height, hdc_screen, left, top, SRCCOPY).as_bool() {
+                return Err("BitBlt failed".to_string());
+            }
+        }
+    }
+    // Prepare to get bitmap bits in BGRA (32-bit) format.
+    let mut bmi_header = windows::Win32::Graphics::Gdi::BITMAPINFOHEADER {
+        biSize: mem::size_of::<windows::Win32::Graphics::Gdi::BITMAPINFOHEADER>() as u32,
+        biWidth: width,
+        biHeight: -height, // Negative height indicates a top-down bitmap.
+        biPlanes: 1,
+        biBitCount: 32,
+        biCompression: 0, // BI_RGB
+        biSizeImage: 0,
+        biXPelsPerMeter: 0,
+        biYPelsPerMeter: 0,
+        biClrUsed: 0,
+        biClrImportant: 0,
+    };
+    let row_bytes = ((32 * width + 31) / 32) * 4;
+    let image_size = (row_bytes * height) as usize;
+    let mut pixel_data: Vec<u8> = vec![0; image_size];
+    let ret = windows::Win32::Graphics::Gdi::GetDIBits(
+        hdc_mem,
+        hbitmap,
+        0,
+        height as u32,
+        Some(pixel_data.as_mut_ptr() as *mut _),
+        &mut windows::Win32::Graphics::Gdi::BITMAPINFO {
+            bmiHeader: bmi_header,
+            bmiColors: [Default::default(); 1],
+        },
+        windows::Win32::Graphics::Gdi::DIB_RGB_COLORS,
+    );
+    if ret == 0 {
+        return Err("GetDIBits failed".to_string());
+    }
+    // Clean up GDI objects.
+    SelectObject(hdc_mem, old_obj);
+    DeleteObject(hbitmap);
+    DeleteDC(hdc_mem);
+    ReleaseDC(HWND(0), hdc_screen);
+
+    // Convert BGRA to RGBA by swapping blue and red channels.
+    for i in (0..pixel_data.len()).step_by(4) {
+        let b = pixel_data[i];
+        let r = pixel_data[i + 2];
+        pixel_data[i] = r;
+        pixel_data[i + 2] = b;
+    }
+    Ok((width as u32, height as u32, pixel_data))
+}
+
+/// Tesseract languages used to recognize on-screen text.
+#[cfg(feature = "ocr")]
+const OCR_LANGUAGES: &str = "rus+eng";
+
+/// A word recognized on screen, with its bounding box in screen coordinates.
+#[cfg(feature = "ocr")]
+struct OcrWord {
+    text: String,
+    left: i32,
+    top: i32,
+    right: i32,
+    bottom: i32,
+}
+
+/// Clicks the center of `text` as recognized on the primary screen.
+/// When no exact match is found, the failure names the closest recognized string.
+#[cfg(feature = "ocr")]
+unsafe fn click_text(text: &str) -> ExecutionResult {
+    let (width, height, rgba) = match capture_rgba(CaptureSource::Screen) {
+        Ok(capture) => capture,
+        Err(e) => return ExecutionResult::Failure(format!("Ошибка снимка экрана: {}", e)),
+    };
+    let words = match recognize_words(width, height, &rgba) {
+        Ok(words) => words,
+        Err(e) => return ExecutionResult::Failure(format!("Ошибка распознавания текста: {}", e)),
+    };
+    let (x, y) = match locate_text(&words, text) {
+        Ok(center) => center,
+        Err(Some(nearest)) => return ExecutionResult::Failure(format!("Текст '{}' не найден на экране; ближайшее совпадение: '{}'", text, nearest)),
+        Err(None) => return ExecutionResult::Failure(format!("Текст '{}' не найден на экране: текст не распознан", text)),
+    };
+    if !SetCursorPos(x, y).as_bool() {
+        return ExecutionResult::Failure(format!("Не удалось переместить курсор в ({}, {})", x, y));
+    }
+    let inputs = [mouse_input(MOUSEEVENTF_LEFTDOWN, 0), mouse_input(MOUSEEVENTF_LEFTUP, 0)];
+    if SendInput(&inputs, mem::size_of::<INPUT>() as i32) as usize != inputs.len() {
+        ExecutionResult::Failure(format!("Ошибка отправки клика в ({}, {})", x, y))
+    } else {
+        ExecutionResult::Success(format!("Клик по тексту '{}' в ({}, {})", text, x, y))
+    }
+}
+
+/// Without the `ocr` feature there is nothing to recognize the text with.
+#[cfg(not(feature = "ocr"))]
+unsafe fn click_text(text: &str) -> ExecutionResult {
+    ExecutionResult::Failure(format!("Нельзя найти текст '{}': сервер собран без функции 'ocr'", text))
+}
+
+/// Runs Tesseract over an RGBA capture and returns the recognized words in reading order.
+#[cfg(feature = "ocr")]
+fn recognize_words(width: u32, height: u32, rgba: &[u8]) -> Result<Vec<OcrWord>, String> {
+    let capture = image::RgbaImage::from_raw(width, height, rgba.to_vec())
+        .ok_or_else(|| "capture does not match its size".to_string())?;
+    let mut png = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::ImageRgba8(capture)
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .map_err(|e| format!("error encoding capture: {}", e))?;
+    let mut tesseract = leptess::LepTess::new(None, OCR_LANGUAGES)
+        .map_err(|e| format!("error starting Tesseract: {}", e))?;
+    tesseract.set_image_from_mem(png.get_ref())
+        .map_err(|e| format!("error loading capture: {}", e))?;
+    let boxes = match tesseract.get_component_boxes(leptess::capi::TessPageIteratorLevel_RIL_WORD, true) {
+        Some(boxes) => boxes,
+        None => return Ok(Vec::new()),
+    };
+    let mut words = Vec::new();
+    for word_box in &boxes {
+        let geometry = word_box.get_geometry();
+        tesseract.set_rectangle(geometry.x, geometry.y, geometry.w, geometry.h);
+        let text = tesseract.get_utf8_text().unwrap_or_default().trim().to_string();
+        if !text.is_empty() {
+            words.push(OcrWord {
+                text,
+                left: geometry.x,
+                top: geometry.y,
+                right: geometry.x + geometry.w,
+                bottom: geometry.y + geometry.h,
+            });
+        }
+    }
+    Ok(words)
+}
+
+/// Finds `text` (ignoring case and extra spaces) among the recognized words, trying runs of
+/// adjacent words for multi-word text, and returns the center of the match.
+/// On failure returns the closest candidate by edit distance, if anything was recognized.
+#[cfg(feature = "ocr")]
+fn locate_text(words: &[OcrWord], text: &str) -> Result<(i32, i32), Option<String>> {
+    let target = text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let run = target.split(' ').count();
+    let mut nearest: Option<(usize, String)> = None;
+    for candidate_words in words.windows(run) {
+        let candidate = candidate_words.iter().map(|word| word.text.as_str()).collect::<Vec<_>>().join(" ");
+        let lowered = candidate.to_lowercase();
+        if lowered == target {
+            let left = candidate_words.iter().map(|word| word.left).min().unwrap_or(0);
+            let top = candidate_words.iter().map(|word| word.top).min().unwrap_or(0);
+            let right = candidate_words.iter().map(|word| word.right).max().unwrap_or(0);
+            let bottom = candidate_words.iter().map(|word| word.bottom).max().unwrap_or(0);
+            return Ok(((left + right) / 2, (top + bottom) / 2));
+        }
+        let distance = crate::nlp::levenshtein(&lowered, &target);
+        if nearest.as_ref().is_none_or(|(best, _)| distance < *best) {
+            nearest = Some((distance, candidate));
+        }
+    }
+    Err(nearest.map(|(_, candidate)| candidate))
+}
+
+/// Reads the device-independent bitmap from the clipboard and saves it as a PNG file.
+/// Windows synthesizes `CF_DIB` from `CF_BITMAP`, so this also covers applications that only put a bitmap there.
+unsafe fn save_clipboard_image_png(file_path: &str) -> Result<String, String> {
+    if !IsClipboardFormatAvailable(CF_DIB).as_bool() {
+        return Err("Clipboard does not contain an image".to_string());
+    }
+    if !OpenClipboard(HWND(0)).as_bool() {
+        return Err("Failed to open clipboard".to_string());
+    }
+    let hglobal = match GetClipboardData(CF_DIB) {
+        Ok(handle) if handle.0 != 0 => HGLOBAL(handle.0),
+        _ => {
+            CloseClipboard();
+            return Err("Failed to get clipboard image data".to_string());
+        }
+    };
+    let size = GlobalSize(hglobal);
+    let data_ptr = GlobalLock(hglobal) as *const u8;
+    if data_ptr.is_null() || size == 0 {
+        CloseClipboard();
+        return Err("Failed to lock clipboard image data".to_string());
+    }
+    // Copy out so the clipboard can be released before the conversion.
+    let dib = std::slice::from_raw_parts(data_ptr, size).to_vec();
+    GlobalUnlock(hglobal);
+    CloseClipboard(); // The handle is owned by the clipboard and must not be freed here.
+
+    let (width, height, rgba) = dib_to_rgba(&dib)?;
+    match image::save_buffer(file_path, &rgba, width, height, image::ColorType::Rgba8) {
+        Ok(_) => Ok(file_path.to_string()),
+        Err(e) => Err(format!("Error saving PNG: {}", e)),
+    }
+}
+
+/// Converts a packed DIB (BITMAPINFOHEADER followed by pixels) into top-down RGBA rows.
+/// Only uncompressed 24- and 32-bit images are supported, which is what screenshot tools put on the clipboard.
+fn dib_to_rgba(dib: &[u8]) -> Result<(u32, u32, Vec<u8>), String> {
+    const BI_RGB: u32 = 0;
+    const BI_BITFIELDS: u32 = 3;
+    let read_u32 = |offset: usize| -> Result<u32, String> {
+        dib.get(offset..offset + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| "DIB header is truncated".to_string())
+    };
+
+    let header_size = read_u32(0)? as usize;
+    let width = read_u32(4)? as i32;
+    let height = read_u32(8)? as i32;
+    let bit_count = dib.get(14..16).map(|b| u16::from_le_bytes([b[0], b[1]])).ok_or("DIB header is truncated")?;
+    let compression = read_u32(16)?;
+    if header_size < 40 || width <= 0 || height == 0 {
+        return Err("Invalid DIB header".to_string());
+    }
+    if bit_count != 24 && bit_count != 32 {
+        return Err(format!("Unsupported DIB bit depth: {}", bit_count));
+    }
+    if compression != BI_RGB && compression != BI_BITFIELDS {
+        return Err(format!("Unsupported DIB compression: {}", compression));
+    }
+
+    // BITFIELDS with a plain BITMAPINFOHEADER is followed by three color masks.
+    let masks_size = if compression == BI_BITFIELDS && header_size == 40 { 12 } else { 0 };
+    let pixels_offset = header_size + masks_size;
+    let width = width as usize;
+    let rows = height.unsigned_abs() as usize;
+    let bytes_per_pixel = bit_count as usize / 8;
+    let stride = ((bit_count as usize * width + 31) / 32) * 4;
+    if dib.len() < pixels_offset + stride * rows {
+        return Err("DIB pixel data is truncated".to_string());
+    }
+
+    let mut rgba = Vec::with_capacity(width * rows * 4);
+    for row in 0..rows {
+        // A positive height means the rows are stored bottom-up.
+        let source_row = if height > 0 { rows - 1 - row } else { row };
+        let start = pixels_offset + source_row * stride;
+        for pixel in dib[start..start + width * bytes_per_pixel].chunks_exact(bytes_per_pixel) {
+            let alpha = if bytes_per_pixel == 4 { pixel[3] } else { 255 };
+            rgba.extend_from_slice(&[pixel[2], pixel[1], pixel[0], alpha]);
+        }
+    }
+    // Most 32-bit clipboard images leave alpha at zero; treat those as opaque.
+    if bytes_per_pixel == 4 && rgba.chunks_exact(4).all(|p| p[3] == 0) {
+        rgba.chunks_exact_mut(4).for_each(|p| p[3] = 255);
+    }
+    Ok((width as u32, rows as u32, rgba))
+}
+
+/// Loads an image file and places it on the clipboard as `CF_DIB`.
+/// The source must live under the working directory, the same rule the config and language loaders apply.
+unsafe fn set_clipboard_image(path: &str) -> Result<(), String> {
+    let base_dir = std::env::current_dir()
+        .and_then(|dir| dir.canonicalize())
+        .map_err(|e| format!("Failed to resolve current directory: {}", e))?;
+    let image_path = Path::new(path).canonicalize()
+        .map_err(|e| format!("Failed to resolve image path '{}': {}", path, e))?;
+    if !image_path.starts_with(&base_dir) {
+        return Err(format!("Path injection vulnerability detected: '{}' is not in '{}'", image_path.display(), base_dir.display()));
+    }
+
+    let rgba = image::open(&image_path)
+        .map_err(|e| format!("Failed to load image '{}': {}", image_path.display(), e))?
+        .to_rgba8();
+    let dib = rgba_to_dib(rgba.width(), rgba.height(), rgba.as_raw());
+
+    if !OpenClipboard(HWND(0)).as_bool() {
+        return Err("Failed to open clipboard".to_string());
+    }
+    EmptyClipboard();
+
+    let hglobal = GlobalAlloc(GMEM_MOVEABLE, dib.len());
+    if hglobal.0 == 0 {
+        CloseClipboard();
+        return Err("GlobalAlloc failed".to_string());
+    }
+    let global_ptr = GlobalLock(hglobal) as *mut u8;
+    if global_ptr.is_null() {
+        GlobalFree(hglobal);
+        CloseClipboard();
+        return Err("GlobalLock failed".to_string());
+    }
+    ptr::copy_nonoverlapping(dib.as_ptr(), global_ptr, dib.len());
+    GlobalUnlock(hglobal);
+
+    // On success the clipboard owns the memory; otherwise it is still ours to free.
+    let result = SetClipboardData(CF_DIB, HANDLE(hglobal.0));
+    let placed = matches!(result, Ok(handle) if handle.0 != 0);
+    if !placed {
+        GlobalFree(hglobal);
+    }
+    CloseClipboard(); // Always close the clipboard
+    if placed { Ok(()) } else { Err("SetClipboardData failed".to_string()) }
+}
+
+/// Builds a packed 32-bit bottom-up DIB (BITMAPINFOHEADER followed by BGRA pixels) from top-down RGBA rows.
+fn rgba_to_dib(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    const HEADER_SIZE: u32 = 40;
+    let image_size = width * height * 4;
+    let mut dib = Vec::with_capacity((HEADER_SIZE + image_size) as usize);
+    dib.extend_from_slice(&HEADER_SIZE.to_le_bytes()); // biSize
+    dib.extend_from_slice(&(width as i32).to_le_bytes()); // biWidth
+    dib.extend_from_slice(&(height as i32).to_le_bytes()); // biHeight, positive = bottom-up
+    dib.extend_from_slice(&1u16.to_le_bytes()); // biPlanes
+    dib.extend_from_slice(&32u16.to_le_bytes()); // biBitCount
+    dib.extend_from_slice(&0u32.to_le_bytes()); // biCompression = BI_RGB
+    dib.extend_from_slice(&image_size.to_le_bytes()); // biSizeImage
+    dib.extend_from_slice(&[0u8; 16]); // biXPelsPerMeter, biYPelsPerMeter, biClrUsed, biClrImportant
+
+    // 32-bit rows are already DWORD-aligned, so no padding is needed.
+    let row_bytes = width as usize * 4;
+    for row in rgba.chunks_exact(row_bytes).rev() {
+        for pixel in row.chunks_exact(4) {
+            dib.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+        }
+    }
+    dib
+}
+
+/// Groups all visible top-level windows by arranging them in a grid layout across the screen.
+unsafe fn group_windows() -> bool {
+    arrange_windows("grid").is_ok()
+}
+
+/// Offset between successive windows of the cascade layout, in pixels.
+const CASCADE_STEP: i32 = 30;
+
+/// Visible top-level windows, topmost first.
+unsafe fn visible_windows() -> Vec<HWND> {
+    let mut windows_vec: Vec<HWND> = Vec::new();
+    extern "system" fn enum_proc(hwnd: HWND, lparam: LPARAM) -> i32 {
+        unsafe {
+            // Only include visible windows.
+            if IsWindowVisible(hwnd).as_bool() {
+                // Append the window handle into the Vec<HWND> passed via lparam.
+                let windows_ptr = lparam.0 as *mut Vec<HWND>;
+                if !windows_ptr.is_null() {
+                    (*windows_ptr).push(hwnd);
+                }
+            }
+        }
+        1 // continue enumeration
+    }
+    EnumWindows(Some(enum_proc), LPARAM(&mut windows_vec as *mut _ as isize));
+    windows_vec
+}
+
+/// The part of the primary screen not covered by the taskbar, or the whole screen if it cannot be read.
+unsafe fn work_area() -> RECT {
+    let mut area = RECT::default();
+    if SystemParametersInfoW(SPI_GETWORKAREA, 0, Some(&mut area as *mut RECT as *mut _), SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0)).as_bool() {
+        return area;
+    }
+    let screen_dc = GetDC(HWND(0));
+    area.right = GetDeviceCaps(screen_dc, HORZRES);
+    area.bottom = GetDeviceCaps(screen_dc, VERTRES);
+    ReleaseDC(HWND(0), screen_dc);
+    area
+}
+
+/// Arranges windows in the work area by one of the `WINDOW_LAYOUTS`. The snap layouts move the
+/// foreground window to one half; the others arrange every visible window. Returns how many moved.
+unsafe fn arrange_windows(layout: &str) -> Result<usize, String> {
+    let area = work_area();
+    let (left, top) = (area.left, area.top);
+    let (width, height) = (area.right - area.left, area.bottom - area.top);
+
+    let snap_right = match layout {
+        "snap_left" => Some(false),
+        "snap_right" => Some(true),
+        _ => None,
+    };
+    if let Some(snap_right) = snap_right {
+        let hwnd = GetForegroundWindow();
+        if hwnd.0 == 0 {
+            return Err("Нет активного окна".to_string());
+        }
+        // A maximized window keeps its maximized size until it is restored.
+        if IsIconic(hwnd).as_bool() || IsZoomed(hwnd).as_bool() {
+            ShowWindow(hwnd, SW_RESTORE);
+        }
+        let half = width / 2;
+        let x = if snap_right { left + half } else { left };
+        if !SetWindowPos(hwnd, HWND(0), x, top, half, height, SWP_NOZORDER | SWP_NOACTIVATE).as_bool() {
+            return Err("Не удалось переместить активное окно".to_string());
+        }
+        return Ok(1);
+    }
+
+    let windows = visible_windows();
+    if windows.is_empty() {
+        return Err("Нет видимых окон".to_string());
+    }
+    let count = windows.len() as i32;
+    for (index, hwnd) in windows.iter().enumerate() {
+        let index = index as i32;
+        let (x, y, w, h) = match layout {
+            "grid" => {
+                let cols = (count as f64).sqrt().ceil() as i32;
+                let rows = (count + cols - 1) / cols;
+                let (w, h) = (width / cols, height / rows);
+                (left + index % cols * w, top + index / cols * h, w, h)
+            }
+            "cascade" => {
+                let (w, h) = (width * 2 / 3, height * 2 / 3);
+                let steps = ((width - w).min(height - h) / CASCADE_STEP).max(1);
+                // The topmost window goes last, so every title bar behind it stays visible.
+                let offset = (count - 1 - index) % steps * CASCADE_STEP;
+                (left + offset, top + offset, w, h)
+            }
+            "tile_vertical" => {
+                let w = width / count;
+                (left + index * w, top, w, height)
+            }
+            "tile_horizontal" => {
+                let h = height / count;
+                (left, top + index * h, width, h)
+            }
+            _ => return Err(format!("Неизвестная раскладка окон '{}'", layout)),
+        };
+        SetWindowPos(*hwnd, HWND(0), x, y, w, h, SWP_NOZORDER | SWP_NOACTIVATE);
+    }
+    Ok(windows.len())
+}
+
+/// Moves `hwnd` to one of the `WINDOW_POSITIONS` in the work area, keeping its size.
+/// Returns the new top-left corner.
+unsafe fn move_window_to(hwnd: HWND, position: &str) -> Result<(i32, i32), String> {
+    if IsIconic(hwnd).as_bool() || IsZoomed(hwnd).as_bool() {
+        ShowWindow(hwnd, SW_RESTORE);
+    }
+    let mut rect = RECT::default();
+    if !GetWindowRect(hwnd, &mut rect).as_bool() {
+        return Err("Не удалось получить размер окна".to_string());
+    }
+    let (width, height) = (rect.right - rect.left, rect.bottom - rect.top);
+    let area = work_area();
+    let (x, y) = match position {
+        "top_left" => (area.left, area.top),
+        "top_right" => (area.right - width, area.top),
+        "bottom_left" => (area.left, area.bottom - height),
+        "bottom_right" => (area.right - width, area.bottom - height),
+        "center" => ((area.left + area.right - width) / 2, (area.top + area.bottom - height) / 2),
+        _ => return Err(format!("Неизвестное положение окна '{}'", position)),
+    };
+    if SetWindowPos(hwnd, HWND(0), x, y, 0, 0, SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE).as_bool() {
+        Ok((x, y))
+    } else {
+        Err("Не удалось переместить окно".to_string())
+    }
+}
+
+/// Position and size of one window in a saved layout, matched back by title on restore.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct WindowPlacement {
+    title: String,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+pub fn set_layouts_path(path: PathBuf) {
+    *LAYOUTS_PATH.lock().unwrap() = path;
+}
+
+/// Longest menu item caption read, in UTF-16 units.
+const MENU_TEXT_MAX: usize = 256;
+
+/// Caption of the menu item at `position`, without `&` mnemonic markers and the `\t`-separated shortcut.
+unsafe fn menu_item_text(menu: HMENU, position: u32) -> String {
+    let mut buffer = [0u16; MENU_TEXT_MAX];
+    let len = GetMenuStringW(menu, position, Some(&mut buffer), MF_BYPOSITION).max(0) as usize;
+    let text = String::from_utf16_lossy(&buffer[..len]);
+    text.split('\t').next().unwrap_or_default().replace('&', "").trim().to_string()
+}
+
+/// Captions of the items in `menu`, separators skipped.
+unsafe fn menu_item_names(menu: HMENU) -> Vec<String> {
+    (0..GetMenuItemCount(menu).max(0) as u32)
+        .map(|position| menu_item_text(menu, position))
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Walks `menu` along `path`, matching captions case-insensitively, and returns the command id of the last item.
+/// Returns `None` if a caption is missing or the path ends on a submenu.
+unsafe fn find_menu_command(menu: HMENU, path: &[&str]) -> Option<u32> {
+    let (name, rest) = path.split_first()?;
+    let name = name.to_lowercase();
+    let position = (0..GetMenuItemCount(menu).max(0) as u32)
+        .find(|&position| menu_item_text(menu, position).to_lowercase() == name)?;
+    if rest.is_empty() {
+        // Submenus and separators report -1 instead of a command id.
+        let id = GetMenuItemID(menu, position as i32);
+        if id == u32::MAX { None } else { Some(id) }
+    } else {
+        let submenu = GetSubMenu(menu, position as i32);
+        if submenu.0 == 0 { None } else { find_menu_command(submenu, rest) }
+    }
+}
+
+/// `ERROR_NOT_SAME_DEVICE`: `fs::rename` cannot move a file to another volume.
+const ERROR_NOT_SAME_DEVICE: i32 = 17;
+
+/// Files at least this large are copied in chunks so progress is reported while they are copied.
+const LARGE_FILE_BYTES: u64 = 16 * 1024 * 1024;
+/// Chunk size for copying large files.
+const COPY_CHUNK_BYTES: usize = 1024 * 1024;
+
+/// Copies or moves `files` into `destination`, calling `progress` with the fraction done and a
+/// "copied X of Y files" message after each file and after each chunk of a large file.
+/// Returns the files that could not be pasted, each with its error; the rest are still processed.
+fn paste_files(files: &[String], destination: &Path, mode: PasteMode, progress: &mut dyn FnMut(f32, &str)) -> Vec<(String, String)> {
+    let total = files.len();
+    let mut errors = Vec::new();
+    for (done, file) in files.iter().enumerate() {
+        let path = Path::new(file);
+        let filename = match path.file_name() {
+            Some(filename) => filename,
+            None => {
+                errors.push((file.clone(), format!("Invalid file path: {}", file)));
+                continue;
+            }
+        };
+        let dest_path = destination.join(filename);
+        let mut on_chunk = |copied: u64, size: u64| {
+            let fraction = (done as f32 + copied as f32 / size as f32) / total as f32;
+            progress(fraction, &format!("copied {} of {} files", done, total));
+        };
+        let result = match mode {
+            PasteMode::Copy => copy_file(path, &dest_path, &mut on_chunk),
+            PasteMode::Move => move_file(path, &dest_path, &mut on_chunk),
+        };
+        if let Err(e) = result {
+            errors.push((file.clone(), e));
+        }
+        progress((done + 1) as f32 / total as f32, &format!("copied {} of {} files", done + 1, total));
+    }
+    errors
+}
+
+/// Copies `source` to `dest`. Files of `LARGE_FILE_BYTES` or more are copied in chunks,
+/// calling `on_chunk` with the bytes copied so far and the file size after each one.
+fn copy_file(source: &Path, dest: &Path, on_chunk: &mut dyn FnMut(u64, u64)) -> Result<(), String> {
+    let error = |e: std::io::Error| format!("Failed to copy {}: {}", source.display(), e);
+    let metadata = fs::metadata(source).map_err(error)?;
+    if metadata.len() < LARGE_FILE_BYTES {
+        return fs::copy(source, dest).map(|_| ()).map_err(error);
+    }
+    let mut reader = File::open(source).map_err(error)?;
+    let writer = File::create(dest).map_err(error)?;
+    if let Err(e) = copy_chunks(&mut reader, writer, &metadata, on_chunk) {
+        // A truncated copy must not be left behind looking like a complete file.
+        let _ = fs::remove_file(dest);
+        return Err(error(e));
+    }
+    Ok(())
+}
+
+/// Copies `reader` into `dest` in `COPY_CHUNK_BYTES` chunks, then gives `dest` the permissions
+/// and access/modification times in `metadata`, as `fs::copy` does for small files.
+fn copy_chunks(reader: &mut impl Read, dest: File, metadata: &fs::Metadata, on_chunk: &mut dyn FnMut(u64, u64)) -> std::io::Result<()> {
+    let size = metadata.len();
+    let mut writer = BufWriter::new(dest);
+    let mut buffer = vec![0u8; COPY_CHUNK_BYTES];
+    let mut copied = 0u64;
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..read])?;
+        copied += read as u64;
+        on_chunk(copied, size);
+    }
+    let dest = writer.into_inner().map_err(|e| e.into_error())?;
+    dest.set_permissions(metadata.permissions())?;
+    dest.set_times(fs::FileTimes::new().set_accessed(metadata.accessed()?).set_modified(metadata.modified()?))
+}
+
+/// Moves `source` to `dest`, renaming on the same volume and copying then removing the source across volumes.
+fn move_file(source: &Path, dest: &Path, on_chunk: &mut dyn FnMut(u64, u64)) -> Result<(), String> {
+    match fs::rename(source, dest) {
+        Ok(()) => Ok(()),
+        Err(e) if e.raw_os_error() == Some(ERROR_NOT_SAME_DEVICE) => {
+            copy_file(source, dest, on_chunk)?;
+            fs::remove_file(source)
+                .map_err(|e| format!("Copied {} but failed to remove it: {}", source.display(), e))
+        }
+        Err(e) => Err(format!("Failed to move {}: {}", source.display(), e)),
+    }
+}
+
+/// Sets the directory file selection is confined to.
+pub fn set_file_root(path: PathBuf) {
+    *FILE_ROOT.lock().unwrap() = path;
+}
+
+/// Resolves the directory to search: `root` if given (relative to the configured root), else the root itself.
+/// Returns the canonical configured root and the canonical search directory, which must lie inside it,
+/// the same check the config and language loaders apply.
+fn resolve_search_root(root: Option<&str>) -> Result<(PathBuf, PathBuf), String> {
+    let configured = FILE_ROOT.lock().unwrap().clone();
+    let base_dir = configured.canonicalize()
+        .map_err(|e| format!("Failed to resolve file root '{}': {}", configured.display(), e))?;
+    let search_dir = match root {
+        Some(root) => base_dir.join(root).canonicalize()
+            .map_err(|e| format!("Failed to resolve directory '{}': {}", root, e))?,
+        None => base_dir.clone(),
+    };
+    if !search_dir.starts_with(&base_dir) {
+        return Err(format!("Path injection vulnerability detected: '{}' is not in '{}'", search_dir.display(), base_dir.display()));
+    }
+    if !search_dir.is_dir() {
+        return Err(format!("'{}' is not a directory", search_dir.display()));
+    }
+    Ok((base_dir, search_dir))
+}
+
+/// Collects the files in `dir` whose names match `criteria`, descending into subdirectories if `recursive`.
+/// Symbolic links are skipped, so a link cannot send the search in a cycle, and so are entries
+/// that resolve outside `base_dir`.
+fn find_files(base_dir: &Path, dir: &Path, criteria: &str, recursive: bool, matches: &mut Vec<String>) -> Result<(), String> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Error reading directory '{}': {}", dir.display(), e))?;
+    for entry in entries.flatten() {
+        if !entry.file_type().is_ok_and(|file_type| !file_type.is_symlink()) {
+            continue;
+        }
+        let path = match entry.path().canonicalize() {
+            Ok(path) if path.starts_with(base_dir) => path,
+            _ => continue,
+        };
+        if path.is_dir() {
+            if recursive {
+                // An unreadable subdirectory should not abort the whole search.
+                if let Err(e) = find_files(base_dir, &path, criteria, recursive, matches) {
+                    log_info(&e);
+                }
+            }
+        } else if file_name_matches(&entry.file_name().to_string_lossy(), criteria) {
+            matches.push(path.to_string_lossy().into_owned());
+        }
+    }
+    Ok(())
+}
+
+/// Matches a file name against `criteria`, ignoring case: a glob with `*` and `?` if it has either,
+/// otherwise a substring.
+fn file_name_matches(name: &str, criteria: &str) -> bool {
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let criteria = criteria.to_lowercase();
+    if !criteria.contains(['*', '?']) {
+        return name.iter().collect::<String>().contains(&criteria);
+    }
+    let pattern: Vec<char> = criteria.chars().collect();
+    // Greedy wildcard matching with backtracking to the last `*`.
+    let (mut n, mut p) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            n += 1;
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Reads the saved layouts from the configured layouts file.
+fn load_layouts() -> Result<HashMap<String, Vec<WindowPlacement>>, String> {
+    let path = LAYOUTS_PATH.lock().unwrap().clone();
+    load_layouts_from(&path)
+}
+
+/// Reads the saved layouts. A missing file means none are saved yet; an unreadable or corrupt
+/// one is an error, so saving does not overwrite the layouts it may still hold.
+fn load_layouts_from(path: &Path) -> Result<HashMap<String, Vec<WindowPlacement>>, String> {
+    match fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+fn save_layouts(layouts: &HashMap<String, Vec<WindowPlacement>>) -> Result<(), String> {
+    let path = LAYOUTS_PATH.lock().unwrap().clone();
+    let json = serde_json::to_string_pretty(layouts).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Records the rect of every visible, titled, non-minimized top-level window.
+unsafe fn capture_layout() -> Vec<WindowPlacement> {
+    let mut placements = Vec::new();
+    for title in visible_window_titles() {
+        let hwnd = find_window("", &title);
+        if hwnd.0 == 0 || IsIconic(hwnd).as_bool() {
+            continue;
+        }
+        let mut rect = RECT::default();
+        if GetWindowRect(hwnd, &mut rect).as_bool() {
+            placements.push(WindowPlacement {
+                title,
+                x: rect.left,
+                y: rect.top,
+                width: rect.right - rect.left,
+                height: rect.bottom - rect.top,
+            });
+        }
+    }
+    placements
+}
+
+/// Splits a saved layout into the placements whose window is open now and the titles that are gone.
+fn match_layout<'a>(placements: &'a [WindowPlacement], open_titles: &[String]) -> (Vec<&'a WindowPlacement>, Vec<String>) {
+    let (present, missing): (Vec<_>, Vec<_>) = placements
+        .iter()
+        .partition(|placement| open_titles.iter().any(|title| *title == placement.title));
+    (present, missing.into_iter().map(|placement| placement.title.clone()).collect())
+}
+
+/// Moves every window of the layout that still exists back into place.
+/// Returns how many were moved and the titles that were not found.
+unsafe fn restore_layout(placements: &[WindowPlacement]) -> (usize, Vec<String>) {
+    let (present, mut missing) = match_layout(placements, &visible_window_titles());
+    let mut restored = 0;
+    for placement in present {
+        let hwnd = find_window("", &placement.title);
+        if hwnd.0 == 0 {
+            missing.push(placement.title.clone());
+            continue;
+        }
+        if IsIconic(hwnd).as_bool() {
+            ShowWindow(hwnd, SW_RESTORE);
+        }
+        if SetWindowPos(hwnd, HWND(0), placement.x, placement.y, placement.width, placement.height, SWP_NOZORDER | SWP_NOACTIVATE).as_bool() {
+            restored += 1;
+        } else {
+            missing.push(placement.title.clone());
+        }
+    }
+    (restored, missing)
+}
+
+/// Releases the device context.
+unsafe fn ReleaseDC(hWnd: HWND, hDC: windows::Win32::Graphics::Gdi::HDC) {
+    windows::Win32::Graphics::Gdi::ReleaseDC(hWnd, hDC);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_taps_press_and_release_once_per_count() {
+        let sequence = key_tap_sequence(VK_TAB, 3, &[]);
+        assert_eq!(sequence.len(), 6);
+        assert_eq!(sequence.iter().filter(|(vk, key_up)| *vk == VK_TAB && !key_up).count(), 3);
+        assert_eq!(sequence.iter().filter(|(vk, key_up)| *vk == VK_TAB && *key_up).count(), 3);
+    }
+
+    #[test]
+    fn reverse_tabs_hold_shift_around_every_tap() {
+        let sequence = key_tap_sequence(VK_TAB, 2, &[VK_SHIFT]);
+        assert_eq!(sequence.first(), Some(&(VK_SHIFT, false)));
+        assert_eq!(sequence.last(), Some(&(VK_SHIFT, true)));
+        assert_eq!(&sequence[1..5], &[(VK_TAB, false), (VK_TAB, true), (VK_TAB, false), (VK_TAB, true)]);
+    }
+
+    #[test]
+    fn z_order_flag_follows_the_direction() {
+        assert_eq!(z_order_insert_after(true), HWND_BOTTOM);
+        assert_eq!(z_order_insert_after(false), HWND_TOP);
+    }
+
+    /// A BITMAPINFOHEADER for an uncompressed image of the given size and depth.
+    fn dib_header(width: i32, height: i32, bit_count: u16) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend_from_slice(&40u32.to_le_bytes());
+        header.extend_from_slice(&width.to_le_bytes());
+        header.extend_from_slice(&height.to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes());
+        header.extend_from_slice(&bit_count.to_le_bytes());
+        header.extend_from_slice(&[0u8; 24]);
+        header
+    }
+
+    #[test]
+    fn bottom_up_24_bit_dib_converts_to_png() {
+        // 2x2, rows bottom-up in BGR, each padded to 8 bytes.
+        let mut dib = dib_header(2, 2, 24);
+        dib.extend_from_slice(&[255, 0, 0, 255, 255, 255, 0, 0]); // blue, white
+        dib.extend_from_slice(&[0, 0, 255, 0, 255, 0, 0, 0]); // red, green
+
+        let (width, height, rgba) = dib_to_rgba(&dib).unwrap();
+        assert_eq!((width, height), (2, 2));
+        assert_eq!(rgba, vec![
+            255, 0, 0, 255, 0, 255, 0, 255,
+            0, 0, 255, 255, 255, 255, 255, 255,
+        ]);
+
+        let image = image::RgbaImage::from_raw(width, height, rgba.clone()).unwrap();
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(image)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png)
+            .unwrap();
+        let decoded = image::load_from_memory(&png).unwrap().to_rgba8();
+        assert_eq!(decoded.into_raw(), rgba);
+    }
+
+    #[test]
+    fn transparent_32_bit_dib_is_made_opaque() {
+        let mut dib = dib_header(1, -1, 32);
+        dib.extend_from_slice(&[10, 20, 30, 0]);
+        let (_, _, rgba) = dib_to_rgba(&dib).unwrap();
+        assert_eq!(rgba, vec![30, 20, 10, 255]);
+    }
+
+    #[test]
+    fn unsupported_or_truncated_dibs_are_rejected() {
+        assert!(dib_to_rgba(&dib_header(2, 2, 8)).is_err());
+        assert!(dib_to_rgba(&dib_header(2, 2, 24)).is_err());
+        assert!(dib_to_rgba(&[0u8; 10]).is_err());
+    }
+
+    #[test]
+    fn rgba_image_becomes_a_bottom_up_32_bit_dib() {
+        // 1x2: red on top, semi-transparent blue below.
+        let rgba = [255, 0, 0, 255, 0, 0, 255, 128];
+        let dib = rgba_to_dib(1, 2, &rgba);
+
+        assert_eq!(dib.len(), 40 + 8);
+        assert_eq!(&dib[0..4], &40u32.to_le_bytes());
+        assert_eq!(&dib[4..8], &1i32.to_le_bytes());
+        assert_eq!(&dib[8..12], &2i32.to_le_bytes());
+        assert_eq!(&dib[14..16], &32u16.to_le_bytes());
+        assert_eq!(&dib[20..24], &8u32.to_le_bytes());
+        // Bottom row first, in BGRA.
+        assert_eq!(&dib[40..], &[255, 0, 0, 128, 0, 0, 255, 255]);
+
+        let (width, height, round_trip) = dib_to_rgba(&dib).unwrap();
+        assert_eq!((width, height), (1, 2));
+        assert_eq!(round_trip, rgba);
+    }
+
+    #[test]
+    fn slider_percent_maps_onto_the_range() {
+        assert_eq!(slider_position(0, 100, 50), 50);
+        assert_eq!(slider_position(0, 10, 33), 3);
+        assert_eq!(slider_position(0, 10, 35), 4);
+        assert_eq!(slider_position(-10, 10, 50), 0);
+        assert_eq!(slider_position(20, 40, 0), 20);
+        // Out-of-range percentages are clamped, and a reversed range is reordered.
+        assert_eq!(slider_position(0, 255, 150), 255);
+        assert_eq!(slider_position(100, 0, 25), 25);
+    }
+
+    #[test]
+    fn held_key_repeats_only_after_the_initial_delay() {
+        assert!(hold_repeat_offsets(HOLD_REPEAT_DELAY_MS).is_empty());
+        let offsets = hold_repeat_offsets(600);
+        assert_eq!(offsets, vec![500, 533, 566, 599]);
+        // Every repeat falls inside the hold, before the key-up at `duration_ms`.
+        assert!(offsets.windows(2).all(|pair| pair[1] - pair[0] == HOLD_REPEAT_INTERVAL_MS));
+        assert!(hold_repeat_offsets(2000).iter().all(|offset| *offset < 2000));
+    }
+
+    #[test]
+    fn selection_is_sliced_by_utf16_index() {
+        let text: Vec<u16> = "Привет, 😀 мир".encode_utf16().collect();
+        assert_eq!(slice_selection(&text, 0, 6), "Привет");
+        // The emoji takes two UTF-16 units.
+        assert_eq!(slice_selection(&text, 8, 10), "😀");
+        assert_eq!(slice_selection(&text, 11, 14), "мир");
+    }
+
+    #[test]
+    fn reversed_or_out_of_range_selection_is_clamped() {
+        let text: Vec<u16> = "abcdef".encode_utf16().collect();
+        assert_eq!(slice_selection(&text, 4, 1), "bcd");
+        assert_eq!(slice_selection(&text, 3, 100), "def");
+        assert_eq!(slice_selection(&text, 2, 2), "");
+        assert_eq!(slice_selection(&[], 0, 5), "");
+    }
+
+    #[test]
+    fn double_click_method_follows_class_and_flag() {
+        assert_eq!(double_click_method("Button", false), DoubleClickMethod::ButtonMessage);
+        assert_eq!(double_click_method("BUTTON", false), DoubleClickMethod::ButtonMessage);
+        assert_eq!(double_click_method("Button", true), DoubleClickMethod::MouseInput);
+        assert_eq!(double_click_method("SysListView32", false), DoubleClickMethod::MouseInput);
+        assert_eq!(double_click_method("SysTreeView32", false), DoubleClickMethod::MouseInput);
+    }
+
+    #[test]
+    fn edges_are_grabbed_just_inside_the_border() {
+        let rect = RECT { left: 100, top: 50, right: 500, bottom: 350 };
+        assert_eq!(edge_grab_point(&rect, "left"), Some((103, 200)));
+        assert_eq!(edge_grab_point(&rect, "Right"), Some((496, 200)));
+        assert_eq!(edge_grab_point(&rect, "top"), Some((300, 53)));
+        assert_eq!(edge_grab_point(&rect, "bottom"), Some((300, 346)));
+        assert_eq!(edge_grab_point(&rect, "corner"), None);
+    }
+
+    #[test]
+    fn positive_delta_drags_the_edge_outwards() {
+        assert_eq!(edge_drop_point((103, 200), "left", 50), (53, 200));
+        assert_eq!(edge_drop_point((496, 200), "right", 50), (546, 200));
+        assert_eq!(edge_drop_point((300, 53), "top", -20), (300, 73));
+        assert_eq!(edge_drop_point((300, 346), "bottom", 10), (300, 356));
+    }
+
+    #[test]
+    fn dpi_scale_is_relative_to_96() {
+        assert_eq!(dpi_scale_factor(96), 1.0);
+        assert_eq!(dpi_scale_factor(120), 1.25);
+        assert_eq!(dpi_scale_factor(144), 1.5);
+        assert_eq!(dpi_scale_factor(192), 2.0);
+    }
+
+    #[test]
+    fn first_present_window_is_taken_in_order() {
+        let labels = vec!["Блокнот".to_string(), "Калькулятор".to_string(), "Paint".to_string()];
+        assert_eq!(first_present(&labels, |label| label != "Блокнот"), Some("Калькулятор"));
+        assert_eq!(first_present(&labels, |_| true), Some("Блокнот"));
+        assert_eq!(first_present(&labels, |_| false), None);
+        assert_eq!(first_present(&[], |_| true), None);
+    }
+
+    #[test]
+    fn system_menu_commands_map_to_their_codes() {
+        assert_eq!(system_command_code("restore"), Some(SC_RESTORE));
+        assert_eq!(system_command_code("Minimize"), Some(SC_MINIMIZE));
+        assert_eq!(system_command_code("maximize"), Some(SC_MAXIMIZE));
+        assert_eq!(system_command_code("close"), Some(SC_CLOSE));
+        assert_eq!(system_command_code("move"), Some(SC_MOVE));
+        assert_eq!(system_command_code("size"), Some(SC_SIZE));
+        assert_eq!(system_command_code("shutdown"), None);
+    }
+
+    #[test]
+    fn tabs_resolve_by_exact_caption_before_substring() {
+        let captions = vec!["&Общие".to_string(), "Дополнительно".to_string(), "Доп".to_string()];
+        assert_eq!(resolve_tab_index(&captions, "общие"), Some(0));
+        assert_eq!(resolve_tab_index(&captions, " ДОП "), Some(2));
+        assert_eq!(resolve_tab_index(&captions, "полнит"), Some(1));
+        assert_eq!(resolve_tab_index(&captions, "Безопасность"), None);
+        assert_eq!(resolve_tab_index(&captions, ""), None);
+    }
+
+    fn placement(title: &str) -> WindowPlacement {
+        WindowPlacement { title: title.to_string(), x: 10, y: 20, width: 800, height: 600 }
+    }
+
+    #[test]
+    fn layout_splits_into_open_and_missing_windows() {
+        let placements = vec![placement("Блокнот"), placement("Калькулятор"), placement("Paint")];
+        let open = vec!["Paint".to_string(), "Блокнот".to_string(), "Проводник".to_string()];
+        let (present, missing) = match_layout(&placements, &open);
+        let present: Vec<&str> = present.iter().map(|placement| placement.title.as_str()).collect();
+        assert_eq!(present, ["Блокнот", "Paint"]);
+        assert_eq!(missing, ["Калькулятор"]);
+    }
+
+    #[test]
+    fn corrupt_layouts_file_is_an_error_not_an_empty_set() {
+        let dir = std::env::temp_dir().join(format!("layouts-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("layouts.json");
+
+        assert!(load_layouts_from(&path).unwrap().is_empty());
+
+        let layouts = HashMap::from([("работа".to_string(), vec![placement("Блокнот")])]);
+        fs::write(&path, serde_json::to_string(&layouts).unwrap()).unwrap();
+        assert_eq!(load_layouts_from(&path).unwrap(), layouts);
+
+        fs::write(&path, "{\"работа\": [").unwrap();
+        assert!(load_layouts_from(&path).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn file_names_match_globs_or_substrings_ignoring_case() {
+        assert!(file_name_matches("Report.TXT", "report"));
+        assert!(file_name_matches("report.txt", "*.TXT"));
+        assert!(file_name_matches("report1.txt", "report?.txt"));
+        assert!(file_name_matches("a.b.c", "*.*"));
+        assert!(!file_name_matches("report.txt", "*.doc"));
+        assert!(!file_name_matches("report10.txt", "report?.txt"));
+        assert!(!file_name_matches("notes.md", "report"));
+    }
+
+    #[test]
+    fn a_move_reports_only_the_files_that_failed() {
+        let dir = std::env::temp_dir().join(format!("paste-test-{}", uuid::Uuid::new_v4()));
+        let destination = dir.join("dest");
+        fs::create_dir_all(&destination).unwrap();
+        let present = dir.join("present.txt");
+        fs::write(&present, "data").unwrap();
+        let missing = dir.join("missing.txt");
+        let files = vec![present.to_string_lossy().into_owned(), missing.to_string_lossy().into_owned()];
+
+        let failed = paste_files(&files, &destination, PasteMode::Move, &mut |_, _| {});
+
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, files[1]);
+        assert!(destination.join("present.txt").exists());
+        assert!(!present.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn the_undo_stack_keeps_only_the_newest_inverses() {
+        let mut stack = VecDeque::new();
+        for label in ["a", "b", "c"] {
+            push_undo(&mut stack, Action::WindowShow { label: label.to_string() }, 2);
+        }
+        let labels: Vec<&str> = stack.iter().map(|action| match action {
+            Action::WindowShow { label } => label.as_str(),
+            other => panic!("unexpected {:?}", other),
+        }).collect();
+        assert_eq!(labels, ["b", "c"]);
+    }
+
+    #[test]
+    fn a_chunked_copy_keeps_the_contents_and_modification_time() {
+        let dir = std::env::temp_dir().join(format!("copy-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("source.bin");
+        let data = vec![7u8; COPY_CHUNK_BYTES + 10];
+        fs::write(&source, &data).unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        File::options().write(true).open(&source).unwrap()
+            .set_times(fs::FileTimes::new().set_modified(modified)).unwrap();
+        let metadata = fs::metadata(&source).unwrap();
+        let dest = dir.join("dest.bin");
+
+        let mut chunks = 0;
+        copy_chunks(&mut File::open(&source).unwrap(), File::create(&dest).unwrap(), &metadata, &mut |_, _| chunks += 1).unwrap();
+
+        assert_eq!(chunks, 2);
+        assert_eq!(fs::read(&dest).unwrap(), data);
+        assert_eq!(fs::metadata(&dest).unwrap().modified().unwrap(), modified);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn colorref_splits_into_rgb_and_hex() {
+        assert_eq!(color_details(0x00FF8010), serde_json::json!({ "r": 0x10, "g": 0x80, "b": 0xFF, "hex": "#1080FF" }));
+    }
+
+    #[test]
+    fn paced_typing_pauses_between_code_units_and_checks_focus_before_each_send() {
+        let run = |text: &str, delay: Duration| {
+            let code_units: Vec<u16> = text.encode_utf16().collect();
+            let (mut checks, mut sends, mut pauses) = (0, 0, 0);
+            send_paced(&code_units, delay, || { checks += 1; Ok(()) }, |_| { sends += 1; true }, |_| pauses += 1).unwrap();
+            (checks, sends, pauses)
+        };
+        assert_eq!(run("привет", Duration::from_millis(20)), (6, 6, 5));
+        // A character outside the BMP is two UTF-16 code units.
+        assert_eq!(run("a😀", Duration::from_millis(20)), (3, 3, 2));
+        assert_eq!(run("привет", Duration::ZERO), (1, 1, 0));
+    }
+
+    #[test]
+    fn paced_typing_stops_when_focus_is_lost() {
+        let code_units: Vec<u16> = "abc".encode_utf16().collect();
+        let mut sends = 0;
+        let mut checks = 0;
+        let result = send_paced(
+            &code_units,
+            Duration::from_millis(1),
+            || { checks += 1; if checks < 2 { Ok(()) } else { Err("focus changed".to_string()) } },
+            |_| { sends += 1; true },
+            |_| {},
+        );
+        assert_eq!(result, Err("focus changed".to_string()));
+        assert_eq!(sends, 1);
+    }
+
+    #[test]
+    fn path_segments_take_an_optional_index() {
+        assert_eq!(split_segment_index("SaveButton"), ("SaveButton", 0));
+        assert_eq!(split_segment_index("Button[2]"), ("Button", 2));
+        assert_eq!(split_segment_index("Button [ 1 ]"), ("Button", 1));
+        assert_eq!(split_segment_index("List[x]"), ("List[x]", 0));
+    }
+
+    #[test]
+    fn step_context_fills_entered_text() {
+        let context = HashMap::from([(PREVIOUS_STEP_PLACEHOLDER.to_string(), "42".to_string())]);
+        let step = Action::EditEnterText { label: "Итог".to_string(), text: "Сумма: ${prev}, ${other}".to_string(), hwnd: None };
+        match resolve_context(&step, &context) {
+            Action::EditEnterText { text, .. } => assert_eq!(text, "Сумма: 42, ${other}"),
+            other => panic!("unexpected step {:?}", other),
+        }
+        let click = Action::ButtonClick { label: "${prev}".to_string(), hwnd: None };
+        assert!(matches!(resolve_context(&click, &context), Action::ButtonClick { label, .. } if label == "${prev}"));
+    }
+
+    #[test]
+    fn shell_targets_with_a_nul_are_refused() {
+        assert_eq!(shell_target(r"C:\Windows\notepad.exe").unwrap().as_bytes(), br"C:\Windows\notepad.exe");
+        let error = shell_target("calc\0.exe").unwrap_err();
+        assert!(error.contains(r"calc\u{0}.exe"), "{}", error);
+    }
 }